@@ -0,0 +1,201 @@
+use crate::detail::{Float64, IValueImpl};
+use crate::{IValue, Jinterners, KeyPath};
+use ordered_float::OrderedFloat;
+use std::collections::HashMap;
+
+/// Summary statistics over the numeric values of a field.
+///
+/// This struct is created by the [`aggregate()`](Jinterners::aggregate) and
+/// [`aggregate_by()`](Jinterners::aggregate_by) methods on [`Jinterners`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Aggregate {
+    /// Number of aggregated documents, including those where the field is
+    /// missing or isn't a number.
+    pub count: usize,
+    /// Number of documents where the field is a number.
+    pub numbers: usize,
+    /// Sum of the numeric values.
+    pub sum: f64,
+    /// Minimum of the numeric values, or [`None`] if there are none.
+    pub min: Option<f64>,
+    /// Maximum of the numeric values, or [`None`] if there are none.
+    pub max: Option<f64>,
+}
+
+impl Aggregate {
+    /// Returns the mean of the numeric values, or [`None`] if there are none.
+    pub fn mean(&self) -> Option<f64> {
+        (self.numbers != 0).then(|| self.sum / self.numbers as f64)
+    }
+
+    fn add(&mut self, value: Option<f64>) {
+        self.count += 1;
+        if let Some(x) = value {
+            self.numbers += 1;
+            self.sum += x;
+            self.min = Some(self.min.map_or(x, |min| min.min(x)));
+            self.max = Some(self.max.map_or(x, |max| max.max(x)));
+        }
+    }
+}
+
+impl Jinterners {
+    /// Counts the given documents by value of the field at the given path.
+    ///
+    /// Groups are keyed by interned value, so that grouping never hashes or
+    /// compares the contents of strings, arrays or objects. Documents that
+    /// don't contain the field are skipped.
+    ///
+    /// ```
+    /// use jinterner::{Jinterners, KeyPath};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let docs = interners.intern_many(vec![
+    ///     json!({"level": "info"}),
+    ///     json!({"level": "error"}),
+    ///     json!({"level": "info"}),
+    /// ]);
+    /// let counts = interners.count_by(docs, &KeyPath::new(&interners, "level"));
+    /// assert_eq!(counts[&interners.intern_str("info")], 2);
+    /// assert_eq!(counts[&interners.intern_str("error")], 1);
+    /// ```
+    pub fn count_by(
+        &self,
+        docs: impl IntoIterator<Item = IValue>,
+        group: &KeyPath,
+    ) -> HashMap<IValue, usize> {
+        let mut counts = HashMap::new();
+        for doc in docs {
+            if let Some(key) = group.get(self, doc) {
+                *counts.entry(key).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Groups the given documents by value of the field at the given path,
+    /// preserving their order within each group.
+    ///
+    /// Like for [`count_by()`](Self::count_by), documents that don't contain
+    /// the field are skipped.
+    pub fn group_by(
+        &self,
+        docs: impl IntoIterator<Item = IValue>,
+        group: &KeyPath,
+    ) -> HashMap<IValue, Vec<IValue>> {
+        let mut groups: HashMap<IValue, Vec<IValue>> = HashMap::new();
+        for doc in docs {
+            if let Some(key) = group.get(self, doc) {
+                groups.entry(key).or_default().push(doc);
+            }
+        }
+        groups
+    }
+
+    /// Computes summary statistics of the numeric field at the given path over
+    /// the given documents.
+    pub fn aggregate(&self, docs: impl IntoIterator<Item = IValue>, field: &KeyPath) -> Aggregate {
+        let mut aggregate = Aggregate::default();
+        for doc in docs {
+            aggregate.add(self.number_at(doc, field));
+        }
+        aggregate
+    }
+
+    /// Computes summary statistics of the numeric field at the path `field`,
+    /// for each group of documents with the same value at the path `group`.
+    ///
+    /// Like for [`count_by()`](Self::count_by), documents that don't contain
+    /// the `group` field are skipped.
+    ///
+    /// ```
+    /// use jinterner::{Jinterners, KeyPath};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let docs = interners.intern_many(vec![
+    ///     json!({"service": "api", "ms": 10}),
+    ///     json!({"service": "auth", "ms": 3.5}),
+    ///     json!({"service": "api", "ms": 30}),
+    /// ]);
+    /// let stats = interners.aggregate_by(
+    ///     docs,
+    ///     &KeyPath::new(&interners, "service"),
+    ///     &KeyPath::new(&interners, "ms"),
+    /// );
+    /// let api = stats[&interners.intern_str("api")];
+    /// assert_eq!((api.sum, api.min, api.max), (40.0, Some(10.0), Some(30.0)));
+    /// assert_eq!(api.mean(), Some(20.0));
+    /// ```
+    pub fn aggregate_by(
+        &self,
+        docs: impl IntoIterator<Item = IValue>,
+        group: &KeyPath,
+        field: &KeyPath,
+    ) -> HashMap<IValue, Aggregate> {
+        let mut aggregates: HashMap<IValue, Aggregate> = HashMap::new();
+        for doc in docs {
+            if let Some(key) = group.get(self, doc) {
+                aggregates
+                    .entry(key)
+                    .or_default()
+                    .add(self.number_at(doc, field));
+            }
+        }
+        aggregates
+    }
+
+    fn number_at(&self, doc: IValue, field: &KeyPath) -> Option<f64> {
+        match field.get(self, doc)?.0 {
+            IValueImpl::U64(x) => Some(x as f64),
+            IValueImpl::I64(x) => Some(x as f64),
+            IValueImpl::F64(Float64(OrderedFloat(x))) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn aggregate() {
+        let interners = Jinterners::default();
+        let docs = interners.intern_many(vec![
+            json!({"tag": ["a"], "n": 1}),
+            json!({"tag": ["b"], "n": -2.5}),
+            json!({"tag": ["a"], "n": "3"}),
+            json!({"tag": ["a"], "n": 4}),
+            json!({"n": 100}),
+        ]);
+        let tag = KeyPath::new(&interners, "tag");
+        let n = KeyPath::new(&interners, "n");
+        let a = interners.intern(json!(["a"]));
+        let b = interners.intern(json!(["b"]));
+
+        let counts = interners.count_by(docs.iter().copied(), &tag);
+        assert_eq!(counts, HashMap::from([(a, 3), (b, 1)]));
+        let groups = interners.group_by(docs.iter().copied(), &tag);
+        assert_eq!(groups[&a], [docs[0], docs[2], docs[3]]);
+
+        let total = interners.aggregate(docs.iter().copied(), &n);
+        assert_eq!(
+            total,
+            Aggregate {
+                count: 5,
+                numbers: 4,
+                sum: 102.5,
+                min: Some(-2.5),
+                max: Some(100.0),
+            }
+        );
+        let by_tag = interners.aggregate_by(docs.iter().copied(), &tag, &n);
+        assert_eq!(by_tag[&a].count, 3);
+        assert_eq!(by_tag[&a].mean(), Some(2.5));
+        assert_eq!(by_tag[&b].min, Some(-2.5));
+        assert_eq!(Aggregate::default().mean(), None);
+    }
+}
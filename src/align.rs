@@ -0,0 +1,170 @@
+use crate::detail::mapping::IdMap;
+use crate::translate::Translator;
+use crate::{ArenaKind, IValue, InternedStrKey, Jinterners, Mapping};
+
+/// A [`Mapping`] between two independently built [`Jinterners`] arenas,
+/// created by [`Mapping::align()`], which only maps the entries that exist in
+/// both arenas.
+pub struct PartialMapping {
+    mapping: Mapping,
+    /// Indices of the unmatched entries of the source arena, for each arena
+    /// kind.
+    unmatched_source: [Vec<u32>; 3],
+    /// Indices of the unmatched entries of the destination arena, for each
+    /// arena kind.
+    unmatched_destination: [Vec<u32>; 3],
+}
+
+fn arena_index(arena: ArenaKind) -> usize {
+    match arena {
+        ArenaKind::String => 0,
+        ArenaKind::Array => 1,
+        ArenaKind::Object => 2,
+    }
+}
+
+impl Mapping {
+    /// Matches the entries of two independently built arenas by content, to
+    /// translate values between systems that never shared a dictionary.
+    ///
+    /// A string matches an identical string. An array or object matches an
+    /// array or object whose items match, so a value is mapped if and only if
+    /// an equal value is interned in the destination arena.
+    ///
+    /// ```
+    /// use jinterner::{ArenaKind, Jinterners, Mapping};
+    /// use serde_json::json;
+    ///
+    /// let a = Jinterners::default();
+    /// let b = Jinterners::default();
+    /// b.intern(json!(["unrelated"]));
+    /// let shared = a.intern(json!({"name": "John"}));
+    /// let only_a = a.intern(json!({"name": "Jane"}));
+    /// let expected = b.intern(json!({"name": "John"}));
+    ///
+    /// let alignment = Mapping::align(&a, &b);
+    /// assert_eq!(alignment.map(shared), Some(expected));
+    /// assert_eq!(alignment.map(only_a), None);
+    /// assert_eq!(alignment.unmatched_source(ArenaKind::String).len(), 1); // "Jane"
+    /// assert_eq!(alignment.unmatched_destination(ArenaKind::Array).len(), 1);
+    /// ```
+    pub fn align(a: &Jinterners, b: &Jinterners) -> PartialMapping {
+        let tables = Translator::find(a, b).translate_all();
+        let sizes = [b.string.strings(), b.iarray.slices(), b.iobject.slices()];
+        let mut unmatched_source: [Vec<u32>; 3] = Default::default();
+        let mut unmatched_destination: [Vec<u32>; 3] = Default::default();
+        let [string, iarray, iobject] = [0, 1, 2].map(|i| {
+            // Entries are interned once in each arena, so the matched
+            // entries of the destination are exactly the translated ones.
+            let mut matched = vec![false; sizes[i]];
+            let table = tables[i]
+                .iter()
+                .enumerate()
+                .map(|(id, translated)| match translated {
+                    Some(translated) => {
+                        matched[*translated as usize] = true;
+                        *translated
+                    }
+                    None => {
+                        unmatched_source[i].push(id as u32);
+                        u32::MAX
+                    }
+                })
+                .collect();
+            unmatched_destination[i] = (0..sizes[i] as u32)
+                .filter(|id| !matched[*id as usize])
+                .collect();
+            IdMap::Table(table)
+        });
+        PartialMapping {
+            mapping: Mapping {
+                string,
+                iarray,
+                iobject,
+            },
+            unmatched_source,
+            unmatched_destination,
+        }
+    }
+}
+
+impl PartialMapping {
+    /// Maps the given value from the source arena to the destination arena,
+    /// or returns [`None`] if no equal value is interned in the destination
+    /// arena.
+    pub fn map(&self, v: IValue) -> Option<IValue> {
+        self.mapping.try_map(v)
+    }
+
+    /// Maps the given object key from the source arena to the destination
+    /// arena, or returns [`None`] if the destination arena doesn't contain
+    /// this string.
+    pub fn map_str_key(&self, k: InternedStrKey) -> Option<InternedStrKey> {
+        let mapped = self.mapping.map_str_key(k);
+        (mapped.0.id() != u32::MAX).then_some(mapped)
+    }
+
+    /// Returns whether all the entries of the source arena have a match in
+    /// the destination arena.
+    pub fn is_complete(&self) -> bool {
+        self.unmatched_source.iter().all(Vec::is_empty)
+    }
+
+    /// Returns the indices of the entries of the given kind in the source
+    /// arena that have no match in the destination arena, in increasing
+    /// order.
+    pub fn unmatched_source(&self, arena: ArenaKind) -> &[u32] {
+        &self.unmatched_source[arena_index(arena)]
+    }
+
+    /// Returns the indices of the entries of the given kind in the
+    /// destination arena that have no match in the source arena, in
+    /// increasing order.
+    pub fn unmatched_destination(&self, arena: ArenaKind) -> &[u32] {
+        &self.unmatched_destination[arena_index(arena)]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn align() {
+        let a = Jinterners::default();
+        let b = Jinterners::default();
+        // Intern in different orders so that the indices differ.
+        let values = [
+            json!({"a": [1, "x"], "b": {"c": null}}),
+            json!([{"b": {"c": null}}, "y"]),
+        ];
+        let ia: Vec<IValue> = values.iter().map(|v| a.intern_ref(v)).collect();
+        b.intern(json!({"only_b": ["x"]}));
+        let ib: Vec<IValue> = values.iter().rev().map(|v| b.intern_ref(v)).collect();
+
+        let alignment = Mapping::align(&a, &b);
+        assert!(alignment.is_complete());
+        assert_eq!(alignment.map(ia[0]), Some(ib[1]));
+        assert_eq!(alignment.map(ia[1]), Some(ib[0]));
+        assert_eq!(
+            alignment.map_str_key(a.find_key("c").unwrap()),
+            b.find_key("c")
+        );
+        assert!(alignment.unmatched_source(ArenaKind::Object).is_empty());
+        assert_eq!(alignment.unmatched_destination(ArenaKind::String), [0]);
+        assert_eq!(alignment.unmatched_destination(ArenaKind::Array).len(), 1);
+        assert_eq!(alignment.unmatched_destination(ArenaKind::Object).len(), 1);
+
+        // An object only matches if all its entries match.
+        let reverse = Mapping::align(&b, &a);
+        assert!(!reverse.is_complete());
+        let only_b = b.find(&json!({"only_b": ["x"]})).unwrap();
+        assert_eq!(reverse.map(only_b), None);
+        let array = b.find(&json!(["x"])).unwrap();
+        assert_eq!(reverse.map(array), None);
+        let string = b.find(&json!("x")).unwrap();
+        assert_eq!(reverse.map(string), a.find(&json!("x")));
+        assert_eq!(reverse.map(b.find(&json!(1)).unwrap()), a.find(&json!(1)));
+    }
+}
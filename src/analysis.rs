@@ -0,0 +1,1313 @@
+use crate::detail::IValueImpl;
+use crate::pointer::push_token;
+use crate::{IValue, InternedStrKey, Jinterners};
+use blazinterner::{InternedSlice, InternedStr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Statistics about how often the entries of a [`Jinterners`] arena are
+/// referenced by other entries.
+///
+/// This struct is created by the
+/// [`reference_report()`](Jinterners::reference_report) method on
+/// [`Jinterners`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReferenceReport {
+    /// Statistics for the string arena, counting references both as object
+    /// keys and as string values.
+    pub strings: ReferenceStats,
+    /// Statistics for the array arena.
+    pub arrays: ReferenceStats,
+    /// Statistics for the object arena.
+    pub objects: ReferenceStats,
+}
+
+/// Statistics about how often the entries of one of the underlying arenas of a
+/// [`Jinterners`] are referenced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReferenceStats {
+    /// Number of entries by number of references, in buckets of increasing
+    /// powers of two. Empty buckets are omitted.
+    pub histogram: Vec<HistogramBucket>,
+    /// The most referenced entries, sorted by decreasing number of references.
+    pub most_referenced: Vec<ReferencedEntry>,
+}
+
+/// A bucket of a [`ReferenceStats::histogram`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HistogramBucket {
+    /// Minimal number of references (inclusive) in this bucket.
+    pub min_references: usize,
+    /// Maximal number of references (inclusive) in this bucket.
+    pub max_references: usize,
+    /// Number of entries in this bucket.
+    pub entries: usize,
+}
+
+/// An entry listed in [`ReferenceStats::most_referenced`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReferencedEntry {
+    /// Index of the entry in its arena.
+    pub index: u32,
+    /// Number of references to this entry from other entries.
+    pub references: usize,
+    /// Length of the entry, i.e. the number of bytes for a string, or the
+    /// number of elements for an array or object.
+    pub len: usize,
+}
+
+/// The largest entries of a [`Jinterners`] arena.
+///
+/// This struct is created by the
+/// [`largest_entries()`](Jinterners::largest_entries) method on
+/// [`Jinterners`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LargestEntries {
+    /// The largest strings, sorted by decreasing size.
+    pub strings: Vec<LargeEntry>,
+    /// The largest arrays, sorted by decreasing retained size.
+    pub arrays: Vec<LargeEntry>,
+    /// The largest objects, sorted by decreasing retained size.
+    pub objects: Vec<LargeEntry>,
+}
+
+/// An entry listed in [`LargestEntries`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LargeEntry {
+    /// Index of the entry in its arena.
+    pub index: u32,
+    /// Number of bytes used by this entry and the entries it references,
+    /// transitively. Shared entries are counted once per reference.
+    pub retained_bytes: usize,
+    /// A preview of the entry as JSON, truncated after a given number of
+    /// characters.
+    pub preview: String,
+}
+
+/// A group of objects with the same keys that occur repeatedly in a set of
+/// documents, listed by
+/// [`normalization_candidates()`](Jinterners::normalization_candidates).
+///
+/// Such objects are candidates to be factored out of the documents upstream,
+/// for example into a separate table referenced by identifier.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NormalizationCandidate {
+    /// The keys of the objects in this group, sorted lexicographically.
+    pub keys: Vec<String>,
+    /// Number of distinct objects in this group. A single variant means that
+    /// all the occurrences are identical.
+    pub variants: usize,
+    /// Number of times that an object of this group occurs in the documents.
+    pub occurrences: usize,
+    /// Number of distinct arrays and objects that contain an object of this
+    /// group, i.e. different parents under which the group occurs.
+    pub parents: usize,
+    /// Some of the occurrences, as the index of a document and the JSON pointer
+    /// of the object in this document.
+    pub locations: Vec<(usize, String)>,
+}
+
+/// Statistics about whether the strings of a [`Jinterners`] arena are used
+/// as object keys, as string values, or both.
+///
+/// This struct is created by the [`string_usage()`](Jinterners::string_usage)
+/// method on [`Jinterners`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StringUsageReport {
+    /// Strings only used as object keys.
+    pub keys: StringUsageStats,
+    /// Strings only used as string values, in arrays or objects.
+    pub values: StringUsageStats,
+    /// Strings used both as object keys and as string values.
+    pub both: StringUsageStats,
+    /// Strings not referenced by any array or object, for example strings
+    /// interned as top-level values.
+    pub unreferenced: StringUsageStats,
+    /// The strings used both as keys and as values, sorted by decreasing
+    /// total number of references.
+    pub most_shared: Vec<SharedString>,
+}
+
+/// Statistics about a category of strings in a [`StringUsageReport`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StringUsageStats {
+    /// Number of strings in this category.
+    pub strings: usize,
+    /// Total length of these strings, in bytes.
+    pub bytes: usize,
+    /// Number of references to these strings from arrays and objects, as
+    /// keys or values.
+    pub references: usize,
+}
+
+/// A string listed in [`StringUsageReport::most_shared`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SharedString {
+    /// Index of the string in the string arena.
+    pub index: u32,
+    /// Number of object entries that have this string as key.
+    pub key_references: usize,
+    /// Number of array items and object values that are this string.
+    pub value_references: usize,
+}
+
+/// The retained size of a value, broken down by subtree, returned by
+/// [`IValue::size_breakdown()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SizeBreakdown {
+    /// Number of bytes used by this value and the entries it references,
+    /// transitively. Shared entries are counted once per reference, so that
+    /// this is the sum of [`own_bytes`](Self::own_bytes) and of the retained
+    /// bytes of all the children.
+    pub retained_bytes: usize,
+    /// Number of bytes used by the entry of this value itself, i.e. the bytes
+    /// of a string, or the elements of an array or object together with the
+    /// object keys.
+    pub own_bytes: usize,
+    /// The children of this value, as their array index or object key
+    /// together with their breakdown, sorted by decreasing retained size.
+    /// This is empty for scalars and strings, and beyond the requested depth.
+    pub children: Vec<(String, SizeBreakdown)>,
+}
+
+/// How to attribute the size of entries shared between several values, see
+/// [`IValue::retained_size()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetentionPolicy {
+    /// Count each entry reachable from the value in full, even if it's shared
+    /// with other values.
+    Full,
+    /// Divide the size of each entry equally among all the entries that
+    /// reference it.
+    Divided,
+    /// Only count the entries that are exclusively reachable from the value,
+    /// i.e. that would be freed if the value was removed.
+    ExclusiveOnly,
+}
+
+impl IValue {
+    /// Returns the number of bytes that this value accounts for in the given
+    /// [`Jinterners`] arena, attributing shared entries according to the given
+    /// `policy`.
+    ///
+    /// The caller is responsible for ensuring that the same arena was used to
+    /// intern this value, otherwise an arbitrary value will be returned or
+    /// a panic will happen.
+    ///
+    /// The [`Divided`](RetentionPolicy::Divided) and
+    /// [`ExclusiveOnly`](RetentionPolicy::ExclusiveOnly) policies need to count
+    /// references across the whole arena, which is proportional to the arena
+    /// size rather than to the value size.
+    pub fn retained_size(&self, interners: &Jinterners, policy: RetentionPolicy) -> usize {
+        let Some(root) = Entry::new(self) else {
+            return 0;
+        };
+        match policy {
+            RetentionPolicy::Full => {
+                let mut visited = HashSet::from([root]);
+                let mut queue = vec![root];
+                let mut size = 0;
+                while let Some(entry) = queue.pop() {
+                    size += entry.bytes(interners);
+                    entry.for_each_child(interners, |child| {
+                        if visited.insert(child) {
+                            queue.push(child);
+                        }
+                    });
+                }
+                size
+            }
+            RetentionPolicy::Divided => {
+                let counts = ReferenceCounts::new(interners);
+                let mut memo = HashMap::new();
+                root.divided_size(interners, &counts, &mut memo).round() as usize
+            }
+            RetentionPolicy::ExclusiveOnly => {
+                let counts = ReferenceCounts::new(interners);
+                let mut references = HashMap::new();
+                let mut queue = vec![root];
+                let mut size = 0;
+                while let Some(entry) = queue.pop() {
+                    size += entry.bytes(interners);
+                    entry.for_each_child(interners, |child| {
+                        let count = references.entry(child).or_insert(0);
+                        *count += 1;
+                        // All the references to this child come from exclusive entries.
+                        if *count == counts.get(child) {
+                            queue.push(child);
+                        }
+                    });
+                }
+                size
+            }
+        }
+    }
+}
+
+impl IValue {
+    /// Returns the retained size of this value in the given [`Jinterners`]
+    /// arena, broken down by array item and object field down to the given
+    /// `depth`, to find out which parts of a value are responsible for its
+    /// size.
+    ///
+    /// Shared entries are counted once per reference, as with
+    /// [`RetentionPolicy::Full`] applied to each subtree separately. A `depth`
+    /// of zero only returns the size of the value itself.
+    ///
+    /// The caller is responsible for ensuring that the same arena was used to
+    /// intern this value, otherwise an arbitrary value will be returned or
+    /// a panic will happen.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.intern(json!({
+    ///     "id": 1,
+    ///     "payload": {"data": "x".repeat(1000), "kind": "blob"},
+    /// }));
+    ///
+    /// let breakdown = ivalue.size_breakdown(&interners, 2);
+    /// let (key, payload) = &breakdown.children[0];
+    /// assert_eq!(key, "payload");
+    /// assert_eq!(payload.children[0].0, "data");
+    /// assert_eq!(payload.children[0].1.retained_bytes, 1000);
+    /// assert!(payload.children[0].1.children.is_empty());
+    /// ```
+    pub fn size_breakdown(&self, interners: &Jinterners, depth: usize) -> SizeBreakdown {
+        let mut sizes = RetainedSizes {
+            jinterners: interners,
+            arrays: vec![None; interners.iarray.slices()],
+            objects: vec![None; interners.iobject.slices()],
+        };
+        sizes.breakdown(self, depth)
+    }
+}
+
+/// An entry in one of the underlying arenas of a [`Jinterners`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Entry {
+    String(InternedStr),
+    Array(InternedSlice<IValue>),
+    Object(InternedSlice<(InternedStrKey, IValue)>),
+}
+
+impl Entry {
+    fn new(value: &IValue) -> Option<Self> {
+        match value.0 {
+            IValueImpl::Null
+            | IValueImpl::Bool(_)
+            | IValueImpl::U64(_)
+            | IValueImpl::I64(_)
+            | IValueImpl::F64(_) => None,
+            IValueImpl::String(s) => Some(Entry::String(s)),
+            IValueImpl::Array(a) => Some(Entry::Array(a)),
+            IValueImpl::Object(o) => Some(Entry::Object(o)),
+        }
+    }
+
+    fn bytes(&self, jinterners: &Jinterners) -> usize {
+        match self {
+            Entry::String(s) => jinterners.string.lookup(*s).len(),
+            Entry::Array(a) => size_of_val(jinterners.iarray.lookup(*a)),
+            Entry::Object(o) => size_of_val(jinterners.iobject.lookup(*o)),
+        }
+    }
+
+    /// Calls the given function on each reference from this entry to another
+    /// entry.
+    fn for_each_child(&self, jinterners: &Jinterners, mut f: impl FnMut(Entry)) {
+        match self {
+            Entry::String(_) => (),
+            Entry::Array(a) => {
+                for v in jinterners.iarray.lookup(*a) {
+                    if let Some(child) = Entry::new(v) {
+                        f(child);
+                    }
+                }
+            }
+            Entry::Object(o) => {
+                for (k, v) in jinterners.iobject.lookup(*o) {
+                    f(Entry::String(k.0));
+                    if let Some(child) = Entry::new(v) {
+                        f(child);
+                    }
+                }
+            }
+        }
+    }
+
+    fn divided_size(
+        &self,
+        jinterners: &Jinterners,
+        counts: &ReferenceCounts,
+        memo: &mut HashMap<Entry, f64>,
+    ) -> f64 {
+        if let Some(size) = memo.get(self) {
+            return *size;
+        }
+        let mut size = self.bytes(jinterners) as f64;
+        self.for_each_child(jinterners, |child| {
+            size += child.divided_size(jinterners, counts, memo) / counts.get(child) as f64;
+        });
+        memo.insert(*self, size);
+        size
+    }
+}
+
+/// Number of references to each entry of a [`Jinterners`] arena, indexed by
+/// entry.
+pub(crate) struct ReferenceCounts {
+    pub(crate) strings: Vec<usize>,
+    pub(crate) arrays: Vec<usize>,
+    pub(crate) objects: Vec<usize>,
+}
+
+impl ReferenceCounts {
+    pub(crate) fn new(jinterners: &Jinterners) -> Self {
+        let mut counts = Self {
+            strings: vec![0; jinterners.string.strings()],
+            arrays: vec![0; jinterners.iarray.slices()],
+            objects: vec![0; jinterners.iobject.slices()],
+        };
+        for array in jinterners.iarray.iter() {
+            for v in array {
+                counts.insert(v);
+            }
+        }
+        for object in jinterners.iobject.iter() {
+            for (k, v) in object {
+                counts.strings[k.0.id() as usize] += 1;
+                counts.insert(v);
+            }
+        }
+        counts
+    }
+
+    /// Returns the number of references to the given entry.
+    fn get(&self, entry: Entry) -> usize {
+        match entry {
+            Entry::String(s) => self.strings[s.id() as usize],
+            Entry::Array(a) => self.arrays[a.id() as usize],
+            Entry::Object(o) => self.objects[o.id() as usize],
+        }
+    }
+
+    fn insert(&mut self, value: &IValue) {
+        match value.0 {
+            IValueImpl::Null
+            | IValueImpl::Bool(_)
+            | IValueImpl::U64(_)
+            | IValueImpl::I64(_)
+            | IValueImpl::F64(_) => (),
+            IValueImpl::String(s) => self.strings[s.id() as usize] += 1,
+            IValueImpl::Array(a) => self.arrays[a.id() as usize] += 1,
+            IValueImpl::Object(o) => self.objects[o.id() as usize] += 1,
+        }
+    }
+}
+
+impl Jinterners {
+    /// Returns statistics about how often the entries of this arena are
+    /// referenced by other entries, including the `top` most referenced
+    /// entries of each underlying arena.
+    ///
+    /// This shows which keys and values benefit the most from deduplication.
+    pub fn reference_report(&self, top: usize) -> ReferenceReport {
+        let counts = ReferenceCounts::new(self);
+        ReferenceReport {
+            strings: ReferenceStats::new(&counts.strings, self.string.iter().map(str::len), top),
+            arrays: ReferenceStats::new(&counts.arrays, self.iarray.iter().map(<[_]>::len), top),
+            objects: ReferenceStats::new(&counts.objects, self.iobject.iter().map(<[_]>::len), top),
+        }
+    }
+}
+
+impl Jinterners {
+    /// Returns the `top` largest strings, arrays and objects of this arena, by
+    /// number of retained bytes, together with a JSON preview of at most
+    /// `preview_chars` characters.
+    ///
+    /// This helps finding which values are responsible for a large arena.
+    pub fn largest_entries(&self, top: usize, preview_chars: usize) -> LargestEntries {
+        let mut sizes = RetainedSizes {
+            jinterners: self,
+            arrays: vec![None; self.iarray.slices()],
+            objects: vec![None; self.iobject.slices()],
+        };
+        let arrays: Vec<usize> = (0..self.iarray.slices() as u32)
+            .map(|i| sizes.array(InternedSlice::from_id(i)))
+            .collect();
+        let objects: Vec<usize> = (0..self.iobject.slices() as u32)
+            .map(|i| sizes.object(InternedSlice::from_id(i)))
+            .collect();
+
+        let largest = |sizes: &mut dyn Iterator<Item = usize>, value: fn(u32) -> IValueImpl| {
+            top_entries(sizes, top)
+                .into_iter()
+                .map(|(index, retained_bytes)| LargeEntry {
+                    index,
+                    retained_bytes,
+                    preview: IValue(value(index)).preview(self, preview_chars),
+                })
+                .collect()
+        };
+        LargestEntries {
+            strings: largest(&mut self.string.iter().map(str::len), |i| {
+                IValueImpl::String(InternedStr::from_id(i))
+            }),
+            arrays: largest(&mut arrays.into_iter(), |i| {
+                IValueImpl::Array(InternedSlice::from_id(i))
+            }),
+            objects: largest(&mut objects.into_iter(), |i| {
+                IValueImpl::Object(InternedSlice::from_id(i))
+            }),
+        }
+    }
+}
+
+impl Jinterners {
+    /// Returns how many interned objects contain each key, sorted by
+    /// decreasing frequency.
+    ///
+    /// Objects are counted once each, regardless of how many times they are
+    /// referenced. Strings that are never used as keys aren't listed.
+    pub fn key_frequencies(&self) -> Vec<(InternedStrKey, usize)> {
+        let mut counts = vec![0; self.string.strings()];
+        for object in self.iobject.iter() {
+            for (k, _) in object {
+                counts[k.0.id() as usize] += 1;
+            }
+        }
+
+        let mut frequencies: Vec<(InternedStrKey, usize)> = counts
+            .into_iter()
+            .enumerate()
+            .filter(|(_, count)| *count != 0)
+            .map(|(i, count)| (InternedStrKey(InternedStr::from_id(i as u32)), count))
+            .collect();
+        frequencies.sort_by_key(|(k, count)| (Reverse(*count), *k));
+        frequencies
+    }
+}
+
+impl Jinterners {
+    /// Returns statistics about which strings of this arena are used as object
+    /// keys, as string values, or both, including the `top` most referenced
+    /// strings used both ways.
+    ///
+    /// References are counted from the arrays and objects of this arena, so
+    /// an array or object shared by several values counts once. This tells
+    /// whether keys and values would benefit from separate string arenas.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// interners.intern(json!([
+    ///     {"name": "id", "id": 1},
+    ///     {"name": "name", "id": 2},
+    ///     {"type": "user"},
+    /// ]));
+    ///
+    /// let usage = interners.string_usage(10);
+    /// assert_eq!(usage.keys.strings, 1); // "type"
+    /// assert_eq!(usage.values.strings, 1); // "user"
+    /// assert_eq!(usage.both.strings, 2); // "id" and "name"
+    /// assert_eq!(usage.both.references, 6);
+    /// assert_eq!(usage.most_shared.len(), 2);
+    /// ```
+    pub fn string_usage(&self, top: usize) -> StringUsageReport {
+        let mut keys = vec![0; self.string.strings()];
+        let mut values = vec![0; self.string.strings()];
+        let mut insert = |value: &IValue| {
+            if let IValueImpl::String(s) = value.0 {
+                values[s.id() as usize] += 1;
+            }
+        };
+        for array in self.iarray.iter() {
+            array.iter().for_each(&mut insert);
+        }
+        for object in self.iobject.iter() {
+            for (k, v) in object {
+                keys[k.0.id() as usize] += 1;
+                insert(v);
+            }
+        }
+
+        let mut report = StringUsageReport {
+            keys: StringUsageStats::default(),
+            values: StringUsageStats::default(),
+            both: StringUsageStats::default(),
+            unreferenced: StringUsageStats::default(),
+            most_shared: Vec::new(),
+        };
+        let mut shared = BinaryHeap::new();
+        for (i, string) in self.string.iter().enumerate() {
+            let stats = match (keys[i], values[i]) {
+                (0, 0) => &mut report.unreferenced,
+                (_, 0) => &mut report.keys,
+                (0, _) => &mut report.values,
+                (key_references, value_references) => {
+                    shared.push(Reverse((key_references + value_references, Reverse(i))));
+                    if shared.len() > top {
+                        shared.pop();
+                    }
+                    &mut report.both
+                }
+            };
+            stats.strings += 1;
+            stats.bytes += string.len();
+            stats.references += keys[i] + values[i];
+        }
+        report.most_shared = shared
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((_, Reverse(i)))| SharedString {
+                index: i as u32,
+                key_references: keys[i],
+                value_references: values[i],
+            })
+            .collect();
+        report
+    }
+}
+
+impl Jinterners {
+    /// Finds groups of identical or near-identical objects that occur several
+    /// times under different parents in the given documents, and returns the
+    /// `top` groups with the most occurrences.
+    ///
+    /// Objects are near-identical if they have the same keys. Each group lists
+    /// at most `max_locations` of its occurrences.
+    ///
+    /// Occurrences are counted in the documents as written, i.e. an object
+    /// shared by several documents or repeated within a document counts once
+    /// per occurrence, even though it's interned once. Only groups that occur
+    /// at least twice under at least two distinct parents are listed.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let address = json!({"city": "Paris", "zip": "75001"});
+    /// let roots = [
+    ///     interners.intern(json!({"billing": address, "shipping": address})),
+    ///     interners.intern(json!({"billing": {"city": "Lyon", "zip": "69001"}, "id": 2})),
+    /// ];
+    ///
+    /// let candidates = interners.normalization_candidates(&roots, 10, 5);
+    /// assert_eq!(candidates.len(), 1);
+    /// assert_eq!(candidates[0].keys, ["city", "zip"]);
+    /// assert_eq!(candidates[0].variants, 2);
+    /// assert_eq!(candidates[0].occurrences, 3);
+    /// assert_eq!(candidates[0].parents, 2);
+    /// assert_eq!(
+    ///     candidates[0].locations,
+    ///     [(0, "/billing".into()), (0, "/shipping".into()), (1, "/billing".into())]
+    /// );
+    /// ```
+    pub fn normalization_candidates(
+        &self,
+        roots: &[IValue],
+        top: usize,
+        max_locations: usize,
+    ) -> Vec<NormalizationCandidate> {
+        // Arrays and objects reachable from the roots, children first.
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        for root in roots {
+            if let Some(root) = Entry::new(root) {
+                postorder(self, root, &mut visited, &mut order);
+            }
+        }
+
+        // Propagate the number of occurrences from parents to children, and
+        // record the distinct parents of each entry.
+        let mut occurrences: HashMap<Entry, usize> = HashMap::new();
+        for root in roots.iter().filter_map(Entry::new) {
+            *occurrences.entry(root).or_default() += 1;
+        }
+        let mut parents: HashMap<Entry, Vec<Entry>> = HashMap::new();
+        for entry in order.iter().rev() {
+            let count = occurrences.get(entry).copied().unwrap_or(0);
+            let mut children = HashSet::new();
+            entry.for_each_child(self, |child| {
+                if !matches!(child, Entry::String(_)) {
+                    let occ = occurrences.entry(child).or_default();
+                    *occ = occ.saturating_add(count);
+                    if children.insert(child) {
+                        parents.entry(child).or_default().push(*entry);
+                    }
+                }
+            });
+        }
+
+        struct Group {
+            variants: Vec<Entry>,
+            occurrences: usize,
+            parents: HashSet<Entry>,
+        }
+        let mut groups: HashMap<Vec<InternedStrKey>, Group> = HashMap::new();
+        for entry in &order {
+            let Entry::Object(o) = entry else {
+                continue;
+            };
+            let keys = self.iobject.lookup(*o).iter().map(|(k, _)| *k).collect();
+            let group = groups.entry(keys).or_insert_with(|| Group {
+                variants: Vec::new(),
+                occurrences: 0,
+                parents: HashSet::new(),
+            });
+            group.variants.push(*entry);
+            group.occurrences = group.occurrences.saturating_add(occurrences[entry]);
+            group
+                .parents
+                .extend(parents.get(entry).into_iter().flatten().copied());
+        }
+
+        let mut groups: Vec<(Vec<String>, Group)> = groups
+            .into_iter()
+            .filter(|(keys, group)| {
+                !keys.is_empty() && group.occurrences >= 2 && group.parents.len() >= 2
+            })
+            .map(|(keys, group)| {
+                let mut keys: Vec<String> = keys
+                    .iter()
+                    .map(|k| self.string.lookup(k.0).to_owned())
+                    .collect();
+                keys.sort_unstable();
+                (keys, group)
+            })
+            .collect();
+        groups.sort_by(|(a_keys, a), (b_keys, b)| {
+            (Reverse(a.occurrences), Reverse(a_keys.len()), a_keys).cmp(&(
+                Reverse(b.occurrences),
+                Reverse(b_keys.len()),
+                b_keys,
+            ))
+        });
+        groups.truncate(top);
+
+        let mut locations = Locations {
+            jinterners: self,
+            candidates: HashMap::new(),
+            reachable: HashMap::new(),
+            locations: vec![Vec::new(); groups.len()],
+            max_locations,
+        };
+        for (i, (_, group)) in groups.iter().enumerate() {
+            for variant in &group.variants {
+                locations.candidates.insert(*variant, i);
+            }
+        }
+        if max_locations != 0 {
+            for entry in &order {
+                let mut reachable: Vec<usize> = locations
+                    .candidates
+                    .get(entry)
+                    .copied()
+                    .into_iter()
+                    .collect();
+                entry.for_each_child(self, |child| {
+                    if let Some(r) = locations.reachable.get(&child) {
+                        reachable.extend(r);
+                    }
+                });
+                reachable.sort_unstable();
+                reachable.dedup();
+                locations.reachable.insert(*entry, reachable);
+            }
+            let mut pointer = String::new();
+            for (index, root) in roots.iter().enumerate() {
+                if let Some(root) = Entry::new(root) {
+                    locations.walk(index, root, &mut pointer);
+                }
+            }
+        }
+
+        groups
+            .into_iter()
+            .zip(locations.locations)
+            .map(|((keys, group), locations)| NormalizationCandidate {
+                keys,
+                variants: group.variants.len(),
+                occurrences: group.occurrences,
+                parents: group.parents.len(),
+                locations,
+            })
+            .collect()
+    }
+}
+
+/// Appends the arrays and objects reachable from the given entry to `order`,
+/// children first.
+fn postorder(
+    jinterners: &Jinterners,
+    entry: Entry,
+    visited: &mut HashSet<Entry>,
+    order: &mut Vec<Entry>,
+) {
+    if matches!(entry, Entry::String(_)) || !visited.insert(entry) {
+        return;
+    }
+    entry.for_each_child(jinterners, |child| {
+        postorder(jinterners, child, visited, order)
+    });
+    order.push(entry);
+}
+
+/// Collection of example locations of the normalization candidates.
+struct Locations<'a> {
+    jinterners: &'a Jinterners,
+    /// Index of the candidate of each object.
+    candidates: HashMap<Entry, usize>,
+    /// Indices of the candidates reachable from each entry.
+    reachable: HashMap<Entry, Vec<usize>>,
+    locations: Vec<Vec<(usize, String)>>,
+    max_locations: usize,
+}
+
+impl Locations<'_> {
+    /// Walks the given entry of the given document, only descending into
+    /// children from which a candidate that still lacks locations is
+    /// reachable.
+    fn walk(&mut self, root: usize, entry: Entry, pointer: &mut String) {
+        if let Some(&i) = self.candidates.get(&entry)
+            && self.locations[i].len() < self.max_locations
+        {
+            self.locations[i].push((root, pointer.clone()));
+        }
+        let mut visit = |this: &mut Self, token: &str, value: &IValue| {
+            let Some(child) = Entry::new(value) else {
+                return;
+            };
+            let wanted = this.reachable.get(&child).is_some_and(|reachable| {
+                reachable
+                    .iter()
+                    .any(|&i| this.locations[i].len() < this.max_locations)
+            });
+            if wanted {
+                let len = pointer.len();
+                push_token(pointer, token);
+                this.walk(root, child, pointer);
+                pointer.truncate(len);
+            }
+        };
+        match entry {
+            Entry::String(_) => (),
+            Entry::Array(a) => {
+                for (i, v) in self.jinterners.iarray.lookup(a).iter().enumerate() {
+                    visit(self, &i.to_string(), v);
+                }
+            }
+            Entry::Object(o) => {
+                for (k, v) in self.jinterners.iobject.lookup(o) {
+                    visit(self, self.jinterners.string.lookup(k.0), v);
+                }
+            }
+        }
+    }
+}
+
+/// Memoized computation of the number of bytes retained by each entry of a
+/// [`Jinterners`] arena, counting shared entries once per reference.
+struct RetainedSizes<'a> {
+    jinterners: &'a Jinterners,
+    arrays: Vec<Option<usize>>,
+    objects: Vec<Option<usize>>,
+}
+
+impl RetainedSizes<'_> {
+    fn value(&mut self, value: &IValue) -> usize {
+        match value.0 {
+            IValueImpl::Null
+            | IValueImpl::Bool(_)
+            | IValueImpl::U64(_)
+            | IValueImpl::I64(_)
+            | IValueImpl::F64(_) => 0,
+            IValueImpl::String(s) => self.jinterners.string.lookup(s).len(),
+            IValueImpl::Array(a) => self.array(a),
+            IValueImpl::Object(o) => self.object(o),
+        }
+    }
+
+    fn breakdown(&mut self, value: &IValue, depth: usize) -> SizeBreakdown {
+        let jinterners = self.jinterners;
+        let (own_bytes, mut children) = match value.0 {
+            IValueImpl::Null
+            | IValueImpl::Bool(_)
+            | IValueImpl::U64(_)
+            | IValueImpl::I64(_)
+            | IValueImpl::F64(_) => (0, Vec::new()),
+            IValueImpl::String(s) => (jinterners.string.lookup(s).len(), Vec::new()),
+            IValueImpl::Array(a) => {
+                let items = jinterners.iarray.lookup(a);
+                let children = match depth {
+                    0 => Vec::new(),
+                    _ => items
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| (i.to_string(), self.breakdown(v, depth - 1)))
+                        .collect(),
+                };
+                (size_of_val(items), children)
+            }
+            IValueImpl::Object(o) => {
+                let entries = jinterners.iobject.lookup(o);
+                let keys = entries.iter().map(|(k, _)| jinterners.string.lookup(k.0));
+                let children = match depth {
+                    0 => Vec::new(),
+                    _ => keys
+                        .clone()
+                        .zip(entries)
+                        .map(|(k, (_, v))| (k.to_owned(), self.breakdown(v, depth - 1)))
+                        .collect(),
+                };
+                (
+                    size_of_val(entries) + keys.map(str::len).sum::<usize>(),
+                    children,
+                )
+            }
+        };
+        children.sort_by_key(|(_, child)| Reverse(child.retained_bytes));
+        SizeBreakdown {
+            retained_bytes: self.value(value),
+            own_bytes,
+            children,
+        }
+    }
+
+    fn array(&mut self, array: InternedSlice<IValue>) -> usize {
+        if let Some(size) = self.arrays[array.id() as usize] {
+            return size;
+        }
+        let items = self.jinterners.iarray.lookup(array);
+        let size = size_of_val(items) + items.iter().map(|v| self.value(v)).sum::<usize>();
+        self.arrays[array.id() as usize] = Some(size);
+        size
+    }
+
+    fn object(&mut self, object: InternedSlice<(InternedStrKey, IValue)>) -> usize {
+        if let Some(size) = self.objects[object.id() as usize] {
+            return size;
+        }
+        let entries = self.jinterners.iobject.lookup(object);
+        let size = size_of_val(entries)
+            + entries
+                .iter()
+                .map(|(k, v)| self.jinterners.string.lookup(k.0).len() + self.value(v))
+                .sum::<usize>();
+        self.objects[object.id() as usize] = Some(size);
+        size
+    }
+}
+
+/// Returns the indices and sizes of the `top` largest entries, sorted by
+/// decreasing size.
+fn top_entries(sizes: impl Iterator<Item = usize>, top: usize) -> Vec<(u32, usize)> {
+    let mut largest = BinaryHeap::with_capacity(top + 1);
+    for (index, size) in sizes.enumerate() {
+        largest.push(Reverse((size, Reverse(index as u32))));
+        if largest.len() > top {
+            largest.pop();
+        }
+    }
+    largest
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((size, Reverse(index)))| (index, size))
+        .collect()
+}
+
+impl ReferenceStats {
+    fn new(counts: &[usize], lens: impl Iterator<Item = usize>, top: usize) -> Self {
+        let mut histogram: Vec<HistogramBucket> = Vec::new();
+        let mut most_referenced = BinaryHeap::with_capacity(top + 1);
+        for (index, (&references, len)) in counts.iter().zip(lens).enumerate() {
+            let (min_references, max_references) = match references {
+                0 => (0, 0),
+                _ => {
+                    let min = 1 << references.ilog2();
+                    (min, 2 * min - 1)
+                }
+            };
+            match histogram
+                .iter_mut()
+                .find(|b| b.min_references == min_references)
+            {
+                Some(bucket) => bucket.entries += 1,
+                None => histogram.push(HistogramBucket {
+                    min_references,
+                    max_references,
+                    entries: 1,
+                }),
+            }
+
+            most_referenced.push(Reverse((references, Reverse(index as u32), len)));
+            if most_referenced.len() > top {
+                most_referenced.pop();
+            }
+        }
+        histogram.sort_unstable_by_key(|b| b.min_references);
+
+        Self {
+            histogram,
+            most_referenced: most_referenced
+                .into_sorted_vec()
+                .into_iter()
+                .map(
+                    |Reverse((references, Reverse(index), len))| ReferencedEntry {
+                        index,
+                        references,
+                        len,
+                    },
+                )
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn largest_entries() {
+        let interners = Jinterners::default();
+        let tags = json!(["a", "b", "c"]);
+        interners.intern(json!([
+            {"id": 1, "tags": tags},
+            {"id": 2, "tags": tags},
+            {"id": 3, "blob": "x".repeat(1000)},
+        ]));
+
+        let largest = interners.largest_entries(2, 20);
+        assert_eq!(largest.strings.len(), 2);
+        assert_eq!(largest.strings[0].retained_bytes, 1000);
+        assert_eq!(largest.strings[0].preview, format!("\"{}…", "x".repeat(19)));
+
+        let array_size = size_of::<IValue>();
+        let entry_size = size_of::<(InternedStrKey, IValue)>();
+        let tags_size = 3 * array_size + 3;
+        let blob_size = 2 * entry_size + 4 + 2 + 1000;
+        let top_size = 3 * array_size + 2 * (2 * entry_size + 2 + 4 + tags_size) + blob_size;
+        assert_eq!(
+            largest.arrays,
+            [
+                LargeEntry {
+                    index: 1,
+                    retained_bytes: top_size,
+                    preview: r#"[{"id":1,"tags":["a"…"#.into(),
+                },
+                LargeEntry {
+                    index: 0,
+                    retained_bytes: tags_size,
+                    preview: r#"["a","b","c"]"#.into(),
+                },
+            ]
+        );
+        assert_eq!(largest.objects[0].retained_bytes, blob_size);
+    }
+
+    #[test]
+    fn key_frequencies() {
+        let interners = Jinterners::default();
+        interners.intern(json!([
+            {"id": 1, "name": "id"},
+            {"id": 2, "tags": [{"id": 3}]},
+            {"id": 2, "tags": [{"id": 3}]},
+        ]));
+
+        let key = |k| interners.find_key(k).unwrap();
+        assert_eq!(
+            interners.key_frequencies(),
+            [(key("id"), 3), (key("name"), 1), (key("tags"), 1)]
+        );
+    }
+
+    #[test]
+    fn string_usage() {
+        let interners = Jinterners::default();
+        interners.intern(json!("root"));
+        interners.intern(json!([
+            {"a": "b", "b": ["a", "c"]},
+            {"a": "b", "b": ["a", "c"]},
+            {"c": 1, "d": ["a"]},
+        ]));
+
+        let usage = interners.string_usage(2);
+        assert_eq!(
+            usage.keys,
+            StringUsageStats {
+                strings: 1,
+                bytes: 1,
+                references: 1
+            }
+        );
+        assert_eq!(
+            usage.both,
+            StringUsageStats {
+                strings: 3,
+                bytes: 3,
+                references: 7
+            }
+        );
+        assert_eq!(usage.values, StringUsageStats::default());
+        assert_eq!(
+            usage.unreferenced,
+            StringUsageStats {
+                strings: 1,
+                bytes: 4,
+                references: 0
+            }
+        );
+
+        let index = |s| interners.find_key(s).unwrap().0.id();
+        assert_eq!(
+            usage.most_shared,
+            [
+                SharedString {
+                    index: index("a"),
+                    key_references: 1,
+                    value_references: 2
+                },
+                SharedString {
+                    index: index("b"),
+                    key_references: 1,
+                    value_references: 1
+                },
+            ]
+        );
+        assert!(interners.string_usage(0).most_shared.is_empty());
+    }
+
+    #[test]
+    fn retained_size() {
+        let interners = Jinterners::default();
+        let shared = json!(["x", "y"]);
+        let a = interners.intern(json!({"a": shared, "ab": ["ab"]}));
+        let b = interners.intern(json!({"b": shared}));
+
+        let array_size = size_of::<IValue>();
+        let entry_size = size_of::<(InternedStrKey, IValue)>();
+        let shared_size = 2 * array_size + 2;
+
+        assert_eq!(
+            a.retained_size(&interners, RetentionPolicy::Full),
+            2 * entry_size + 3 + shared_size + array_size
+        );
+        assert_eq!(
+            b.retained_size(&interners, RetentionPolicy::Full),
+            entry_size + 1 + shared_size
+        );
+
+        // The string "ab" is used both as a key and as an array item.
+        assert_eq!(
+            a.retained_size(&interners, RetentionPolicy::ExclusiveOnly),
+            2 * entry_size + 3 + array_size
+        );
+        assert_eq!(
+            b.retained_size(&interners, RetentionPolicy::ExclusiveOnly),
+            entry_size + 1
+        );
+
+        assert_eq!(
+            a.retained_size(&interners, RetentionPolicy::Divided),
+            2 * entry_size + 3 + shared_size / 2 + array_size
+        );
+        assert_eq!(
+            b.retained_size(&interners, RetentionPolicy::Divided),
+            entry_size + 1 + shared_size / 2
+        );
+
+        assert_eq!(
+            interners
+                .intern(json!(1))
+                .retained_size(&interners, RetentionPolicy::Full),
+            0
+        );
+    }
+
+    #[test]
+    fn size_breakdown() {
+        let interners = Jinterners::default();
+        let tags = json!(["a", "bb"]);
+        let ivalue = interners.intern(json!({"x": tags, "yy": [tags, 1], "z": null}));
+        assert_eq!(ivalue.size_breakdown(&interners, 0).children, []);
+
+        let array_size = size_of::<IValue>();
+        let entry_size = size_of::<(InternedStrKey, IValue)>();
+        let leaf = |own_bytes| SizeBreakdown {
+            retained_bytes: own_bytes,
+            own_bytes,
+            children: vec![],
+        };
+        let tags_breakdown = |depth| SizeBreakdown {
+            retained_bytes: 2 * array_size + 3,
+            own_bytes: 2 * array_size,
+            children: match depth {
+                0 => vec![],
+                _ => vec![("1".into(), leaf(2)), ("0".into(), leaf(1))],
+            },
+        };
+
+        let breakdown = ivalue.size_breakdown(&interners, 2);
+        assert_eq!(breakdown.own_bytes, 3 * entry_size + 4);
+        assert_eq!(
+            breakdown.children,
+            [
+                (
+                    "yy".into(),
+                    SizeBreakdown {
+                        retained_bytes: 4 * array_size + 3,
+                        own_bytes: 2 * array_size,
+                        children: vec![("0".into(), tags_breakdown(0)), ("1".into(), leaf(0))],
+                    }
+                ),
+                ("x".into(), tags_breakdown(1)),
+                ("z".into(), leaf(0)),
+            ]
+        );
+        assert_eq!(
+            breakdown.retained_bytes,
+            breakdown.own_bytes
+                + breakdown
+                    .children
+                    .iter()
+                    .map(|(_, child)| child.retained_bytes)
+                    .sum::<usize>()
+        );
+        assert_eq!(
+            breakdown.retained_bytes,
+            ivalue.retained_size(&interners, RetentionPolicy::Full)
+                + tags_breakdown(0).retained_bytes
+        );
+    }
+
+    #[test]
+    fn normalization_candidates() {
+        let interners = Jinterners::default();
+        let point = json!({"x": 1, "y": 2});
+        let first = json!({
+            "a": point,
+            "b": [point, point, {"x": 3, "y": 4}],
+            "c": {"id": 1},
+        });
+        let roots = [
+            interners.intern_ref(&first),
+            interners.intern(json!({"a": point, "c": {"id": 2}})),
+            // Repeated under a single parent.
+            interners.intern(json!([{"z": 0}, {"z": 0}])),
+        ];
+
+        let candidates = interners.normalization_candidates(&roots, 10, 3);
+        assert_eq!(
+            candidates,
+            [
+                NormalizationCandidate {
+                    keys: vec!["x".into(), "y".into()],
+                    variants: 2,
+                    occurrences: 5,
+                    parents: 3,
+                    locations: vec![(0, "/a".into()), (0, "/b/0".into()), (0, "/b/1".into())],
+                },
+                NormalizationCandidate {
+                    keys: vec!["id".into()],
+                    variants: 2,
+                    occurrences: 2,
+                    parents: 2,
+                    locations: vec![(0, "/c".into()), (1, "/c".into())],
+                },
+            ]
+        );
+
+        // The same document twice counts twice, under different parents only
+        // if it's nested.
+        let nested = interners.intern(json!([first, first]));
+        let candidates = interners.normalization_candidates(&[nested], 1, 0);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].occurrences, 8);
+        assert_eq!(candidates[0].parents, 2);
+        assert!(candidates[0].locations.is_empty());
+    }
+
+    #[test]
+    fn reference_report() {
+        let interners = Jinterners::default();
+        interners.intern(json!([
+            {"id": 1, "tags": ["a", "b"]},
+            {"id": 2, "tags": ["a", "b"]},
+            {"id": 3, "tags": ["b"]},
+            "a",
+        ]));
+
+        let report = interners.reference_report(3);
+        assert_eq!(
+            report.strings.most_referenced,
+            [
+                ReferencedEntry {
+                    index: 0,
+                    references: 3,
+                    len: 2,
+                },
+                ReferencedEntry {
+                    index: 1,
+                    references: 3,
+                    len: 4,
+                },
+                // "a" is referenced by one array and the top-level array.
+                ReferencedEntry {
+                    index: 2,
+                    references: 2,
+                    len: 1,
+                },
+            ]
+        );
+        assert_eq!(
+            report.arrays.histogram,
+            [
+                HistogramBucket {
+                    min_references: 0,
+                    max_references: 0,
+                    entries: 1,
+                },
+                HistogramBucket {
+                    min_references: 1,
+                    max_references: 1,
+                    entries: 1,
+                },
+                HistogramBucket {
+                    min_references: 2,
+                    max_references: 3,
+                    entries: 1,
+                },
+            ]
+        );
+        assert_eq!(report.objects.most_referenced.len(), 3);
+        assert!(
+            report
+                .objects
+                .most_referenced
+                .iter()
+                .all(|e| e.references == 1 && e.len == 2)
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Generation of random JSON values with the [`arbitrary`] crate,
+//! for property testing and fuzzing.
+
+use crate::{IValue, Jinterners};
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+use serde_json::{Map, Number, Value};
+
+/// Maximal nesting depth of generated values.
+const MAX_DEPTH: usize = 6;
+/// Maximal number of elements of generated arrays and objects.
+const MAX_LEN: usize = 8;
+/// Strings picked preferably, so that generated values share strings and
+/// object keys like real-world data does.
+const COMMON_STRINGS: &[&str] = &["", "a", "b", "id", "name", "type", "~/", "é"];
+
+/// A random JSON value.
+///
+/// Strings are frequently picked from a small set, and arrays and objects are
+/// kept small and shallow, so that generated values exercise deduplication.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArbitraryValue(pub Value);
+
+impl ArbitraryValue {
+    /// Interns this value into the given arena.
+    pub fn intern(&self, interners: &Jinterners) -> IValue {
+        interners.intern_ref(&self.0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryValue {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        arbitrary_value(u, MAX_DEPTH).map(ArbitraryValue)
+    }
+}
+
+/// A [`Jinterners`] arena filled with random JSON values.
+#[derive(Clone, Debug)]
+pub struct ArbitraryArena {
+    /// The arena.
+    pub interners: Jinterners,
+    /// The values interned in the arena, in order.
+    pub values: Vec<Value>,
+    /// The interned values, in the same order as [`values`](Self::values).
+    pub roots: Vec<IValue>,
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryArena {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let values = u
+            .arbitrary_iter::<ArbitraryValue>()?
+            .map(|v| v.map(|v| v.0))
+            .collect::<Result<Vec<_>>>()?;
+        let interners = Jinterners::default();
+        let roots = interners.intern_many_ref(&values);
+        Ok(Self {
+            interners,
+            values,
+            roots,
+        })
+    }
+}
+
+fn arbitrary_value(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    let max_kind = if depth == 0 { 5 } else { 7 };
+    Ok(match u.int_in_range(0..=max_kind)? {
+        0 => Value::Null,
+        1 => Value::Bool(u.arbitrary()?),
+        2 => Value::from(u.arbitrary::<u64>()?),
+        3 => Value::from(u.int_in_range(i64::MIN..=-1)?),
+        4 => Number::from_f64(u.arbitrary()?).map_or(Value::Null, Value::Number),
+        5 => Value::String(arbitrary_string(u)?),
+        6 => {
+            let len = u.int_in_range(0..=MAX_LEN)?;
+            Value::Array(
+                (0..len)
+                    .map(|_| arbitrary_value(u, depth - 1))
+                    .collect::<Result<_>>()?,
+            )
+        }
+        _ => {
+            let len = u.int_in_range(0..=MAX_LEN)?;
+            let mut map = Map::new();
+            for _ in 0..len {
+                map.insert(arbitrary_string(u)?, arbitrary_value(u, depth - 1)?);
+            }
+            Value::Object(map)
+        }
+    })
+}
+
+fn arbitrary_string(u: &mut Unstructured<'_>) -> Result<String> {
+    if u.ratio(3, 4)? {
+        Ok((*u.choose(COMMON_STRINGS)?).to_owned())
+    } else {
+        u.arbitrary()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Rng;
+
+    /// Runs the given check on arenas generated from pseudo-random inputs.
+    fn check_arenas(check: impl Fn(ArbitraryArena)) {
+        let mut rng = Rng::new(0);
+        for _ in 0..200 {
+            let data: Vec<u8> = (0..1024).map(|_| rng.next() as u8).collect();
+            check(ArbitraryArena::arbitrary(&mut Unstructured::new(&data)).unwrap());
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        check_arenas(|arena| {
+            assert_eq!(arena.interners.validate(), Ok(()));
+            for (value, root) in arena.values.iter().zip(&arena.roots) {
+                assert_eq!(arena.interners.lookup(root), *value);
+            }
+        });
+    }
+
+    #[test]
+    fn optimize() {
+        check_arenas(|arena| {
+            let Some((optimized, mapping)) = arena.interners.optimize(None) else {
+                return;
+            };
+            assert_eq!(optimized.validate(), Ok(()));
+            for (value, root) in arena.values.iter().zip(&arena.roots) {
+                assert_eq!(optimized.lookup(&mapping.map(*root)), *value);
+            }
+        });
+    }
+
+    #[cfg(all(feature = "delta", feature = "serde"))]
+    #[test]
+    fn delta_round_trip() {
+        use crate::DeltaEncoding;
+
+        check_arenas(|arena| {
+            let delta = DeltaEncoding::new(arena.interners);
+            let encoded = serde_json::to_string(&delta).unwrap();
+            let decoded: DeltaEncoding<Jinterners> = serde_json::from_str(&encoded).unwrap();
+            assert_eq!(*decoded, *delta);
+            assert_eq!(decoded.validate(), Ok(()));
+            for (value, root) in arena.values.iter().zip(&arena.roots) {
+                assert_eq!(decoded.lookup(root), *value);
+            }
+        });
+    }
+}
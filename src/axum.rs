@@ -0,0 +1,210 @@
+use crate::detail::deserialize_slice;
+use crate::{IValue, InternedSeed, Jinterners};
+use axum_core::extract::rejection::BytesRejection;
+use axum_core::extract::{FromRef, FromRequest, Request};
+use axum_core::response::{IntoResponse, Response};
+use bytes::Bytes;
+use http::{StatusCode, header};
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+/// An [axum](https://docs.rs/axum) extractor parsing a JSON request body
+/// directly into an application-shared [`Jinterners`] arena.
+///
+/// The arena is obtained from the router state, which must provide an
+/// `Arc<Jinterners>` via [`FromRef`]. The request must have a JSON content
+/// type, i.e. `application/json` or `application/*+json`.
+///
+/// By default the body is extracted as an [`IValue`]. Other types can be
+/// extracted by implementing [`FromInternedJson`].
+///
+/// ```
+/// use jinterner::{IValue, InternedJson, Jinterners};
+/// use std::sync::Arc;
+///
+/// // Registered with `Router::new().route("/", post(handler)).with_state(interners)`.
+/// async fn handler(InternedJson(body): InternedJson) -> String {
+///     format!("{:?}", body.kind())
+/// }
+///
+/// let interners = Arc::new(Jinterners::default());
+/// # let _ = (interners, handler);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct InternedJson<T = IValue>(pub T);
+
+/// A type that can be extracted by [`InternedJson`].
+///
+/// ```
+/// use jinterner::{FromInternedJson, IValue, Jinterners};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Event {
+///     id: u64,
+///     #[serde(with = "jinterner::scoped")]
+///     meta: IValue,
+/// }
+///
+/// impl FromInternedJson for Event {
+///     fn from_interned_json(
+///         interners: &Jinterners,
+///         body: &[u8],
+///     ) -> Result<Self, serde_json::Error> {
+///         interners.scope(|| serde_json::from_slice(body))
+///     }
+/// }
+/// ```
+pub trait FromInternedJson: Sized {
+    /// Parses the given JSON body, interning values into the given arena.
+    fn from_interned_json(interners: &Jinterners, body: &[u8]) -> Result<Self, serde_json::Error>;
+}
+
+impl FromInternedJson for IValue {
+    fn from_interned_json(interners: &Jinterners, body: &[u8]) -> Result<Self, serde_json::Error> {
+        deserialize_slice(InternedSeed::new(interners), body)
+    }
+}
+
+impl<S, T> FromRequest<S> for InternedJson<T>
+where
+    Arc<Jinterners>: FromRef<S>,
+    S: Send + Sync,
+    T: FromInternedJson,
+{
+    type Rejection = InternedJsonRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !has_json_content_type(&req) {
+            return Err(InternedJsonRejection::MissingJsonContentType);
+        }
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(InternedJsonRejection::Body)?;
+        let interners = Arc::<Jinterners>::from_ref(state);
+        T::from_interned_json(&interners, &body)
+            .map(InternedJson)
+            .map_err(InternedJsonRejection::Parse)
+    }
+}
+
+fn has_json_content_type(req: &Request) -> bool {
+    let Some(content_type) = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    let Some((kind, subtype)) = essence.split_once('/') else {
+        return false;
+    };
+    kind.eq_ignore_ascii_case("application")
+        && (subtype.eq_ignore_ascii_case("json")
+            || subtype.len() > 5 && subtype[subtype.len() - 5..].eq_ignore_ascii_case("+json"))
+}
+
+/// The rejection returned when the [`InternedJson`] extractor fails.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum InternedJsonRejection {
+    /// The request doesn't have a JSON content type.
+    MissingJsonContentType,
+    /// The request body couldn't be read.
+    Body(BytesRejection),
+    /// The request body isn't valid JSON, or doesn't match the extracted type.
+    Parse(serde_json::Error),
+}
+
+impl Display for InternedJsonRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InternedJsonRejection::MissingJsonContentType => {
+                f.write_str("expected request with `Content-Type: application/json`")
+            }
+            InternedJsonRejection::Body(e) => e.fmt(f),
+            InternedJsonRejection::Parse(e) => write!(f, "failed to parse the request body: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for InternedJsonRejection {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InternedJsonRejection::MissingJsonContentType => None,
+            InternedJsonRejection::Body(e) => Some(e),
+            InternedJsonRejection::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl IntoResponse for InternedJsonRejection {
+    fn into_response(self) -> Response {
+        match self {
+            InternedJsonRejection::MissingJsonContentType => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, self.to_string()).into_response()
+            }
+            InternedJsonRejection::Body(e) => e.into_response(),
+            InternedJsonRejection::Parse(_) => {
+                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum_core::body::Body;
+    use serde_json::json;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    fn extract(
+        state: &Arc<Jinterners>,
+        content_type: &str,
+        body: &'static str,
+    ) -> Result<InternedJson, InternedJsonRejection> {
+        let req = Request::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap();
+        // The body is already in memory, so the extraction completes at once.
+        let future = pin!(InternedJson::from_request(req, state));
+        match future.poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(result) => result,
+            Poll::Pending => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn interned_json() {
+        let state = Arc::new(Jinterners::default());
+        let InternedJson(ivalue) = extract(&state, "application/json", r#"{"a": [1]}"#).unwrap();
+        assert_eq!(state.lookup(&ivalue), json!({"a": [1]}));
+        let InternedJson(other) = extract(
+            &state,
+            "application/vnd.api+json; charset=utf-8",
+            r#"{"a":[1]}"#,
+        )
+        .unwrap();
+        assert_eq!(ivalue, other);
+
+        let rejection = extract(&state, "text/plain", "{}").unwrap_err();
+        assert!(matches!(
+            rejection,
+            InternedJsonRejection::MissingJsonContentType
+        ));
+        assert_eq!(
+            rejection.into_response().status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+        let rejection = extract(&state, "application/json", "{").unwrap_err();
+        assert_eq!(
+            rejection.to_string(),
+            "failed to parse the request body: EOF while parsing an object at line 1 column 1"
+        );
+        assert_eq!(rejection.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+}
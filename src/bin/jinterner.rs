@@ -0,0 +1,142 @@
+//! Command-line tool to build, inspect and maintain archives of interned JSON
+//! documents.
+
+use jinterner::{Archive, ArenaReport, DocId, JDocStore};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: jinterner <COMMAND> [ARGS...]
+
+Commands:
+  intern <INPUT> <OUTPUT>     Interns a newline-delimited JSON file (or - for
+                              stdin) into a new archive
+  stats <ARCHIVE>             Prints statistics about an archive
+  optimize <ARCHIVE> <OUTPUT> Rewrites an archive with an optimized arena
+  compact <ARCHIVE> <OUTPUT>  Rewrites an archive without unreachable values
+  convert <ARCHIVE> <OUTPUT>  Rewrites an archive in the current format
+  dump <ARCHIVE> [ID...]      Prints the given documents (or all of them) as
+                              newline-delimited JSON
+";
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args[..] {
+        ["intern", input, output] => intern(input, output),
+        ["stats", archive] => stats(archive),
+        ["optimize", archive, output] => rewrite(archive, output, |store| {
+            eprintln!("optimized: {}", store.optimize(None));
+        }),
+        ["compact", archive, output] => rewrite(archive, output, |store| {
+            eprintln!("compacted: {}", store.compact());
+        }),
+        ["convert", archive, output] => rewrite(archive, output, |_| ()),
+        ["dump", archive, ref ids @ ..] => dump(archive, ids),
+        _ => {
+            eprint!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn intern(input: &str, output: &str) -> Result<()> {
+    let reader: Box<dyn BufRead> = match input {
+        "-" => Box::new(io::stdin().lock()),
+        path => Box::new(BufReader::new(File::open(path)?)),
+    };
+    let mut store = JDocStore::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let document: Value =
+            serde_json::from_str(&line).map_err(|e| format!("line {}: {e}", i + 1))?;
+        store.insert(document);
+    }
+    eprintln!("interned {} documents", store.len());
+    write_store(&store, output)
+}
+
+fn stats(path: &str) -> Result<()> {
+    let bytes = read(path)?;
+    let archive = Archive::open(&bytes[..])?;
+    let interners = archive.load_interners()?;
+    let report = interners.storage_report();
+    println!("archive bytes: {}", bytes.len());
+    println!("format version: {}", archive.format_version());
+    println!("documents: {}", archive.len());
+    println!("dictionary strings: {}", archive.dictionary_len());
+    print_arena("strings", &report.strings);
+    print_arena("arrays", &report.arrays);
+    print_arena("objects", &report.objects);
+    Ok(())
+}
+
+fn print_arena(name: &str, report: &ArenaReport) {
+    println!(
+        "{name}: {} entries, total length {}, {} payload bytes",
+        report.entries, report.total_len, report.payload_bytes
+    );
+}
+
+fn rewrite(path: &str, output: &str, f: impl FnOnce(&mut JDocStore)) -> Result<()> {
+    let mut store = Archive::open(read(path)?)?.load_store()?;
+    f(&mut store);
+    write_store(&store, output)
+}
+
+fn dump(path: &str, ids: &[&str]) -> Result<()> {
+    let archive = Archive::open(read(path)?)?;
+    let ids: Vec<DocId> = if ids.is_empty() {
+        archive.ids().collect()
+    } else {
+        let known: HashMap<u64, DocId> = archive.ids().map(|id| (id.get(), id)).collect();
+        ids.iter()
+            .map(|id| {
+                let id: u64 = id
+                    .parse()
+                    .map_err(|_| format!("invalid document id: {id}"))?;
+                known
+                    .get(&id)
+                    .copied()
+                    .ok_or_else(|| format!("no such document: {id}"))
+            })
+            .collect::<std::result::Result<_, _>>()?
+    };
+    let mut stdout = io::stdout().lock();
+    for id in ids {
+        let text = archive.text(id).ok_or("invalid document text")?;
+        writeln!(stdout, "{text}")?;
+    }
+    Ok(())
+}
+
+fn read(path: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|e| format!("{path}: {e}"))?;
+    Ok(bytes)
+}
+
+fn write_store(store: &JDocStore, output: &str) -> Result<()> {
+    let mut w = BufWriter::new(File::create(output)?);
+    store.write_archive(&mut w)?;
+    w.flush()?;
+    Ok(())
+}
@@ -0,0 +1,392 @@
+#[cfg(feature = "serde")]
+use crate::detail::Content;
+use crate::detail::{Float64, IValueImpl};
+use crate::{IValue, Jinterners, ValueRef, WriteOptions};
+use ordered_float::OrderedFloat;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display};
+
+/// An interned value together with the [`Jinterners`] arena it was interned
+/// in.
+///
+/// This allows to navigate, print, compare or serialize the value without
+/// passing the arena around.
+///
+/// This struct is created by the [`bind()`](Jinterners::bind) method on
+/// [`Jinterners`].
+#[derive(Clone, Copy)]
+pub struct Bound<'a> {
+    value: IValue,
+    interners: &'a Jinterners,
+}
+
+impl Jinterners {
+    /// Pairs the given interned value with this arena.
+    ///
+    /// The caller is responsible for ensuring that the same arena was used to
+    /// intern this value, otherwise an arbitrary value will be returned or
+    /// a panic will happen when using the result.
+    pub fn bind(&self, value: IValue) -> Bound<'_> {
+        Bound {
+            value,
+            interners: self,
+        }
+    }
+}
+
+impl<'a> Bound<'a> {
+    /// Returns the underlying interned value.
+    pub fn value(&self) -> IValue {
+        self.value
+    }
+
+    /// Returns the arena that this value was interned in.
+    pub fn interners(&self) -> &'a Jinterners {
+        self.interners
+    }
+
+    /// Returns a deep copy of this value as a [`serde_json::Value`].
+    pub fn to_json(&self) -> Value {
+        self.interners.lookup(&self.value)
+    }
+
+    /// Returns a shallow view of this value.
+    pub fn lookup_ref(&self) -> ValueRef<'a> {
+        self.interners.lookup_ref(&self.value)
+    }
+
+    /// Returns the value associated to the given key if this value is an object
+    /// containing that key, or [`None`] otherwise.
+    pub fn get(&self, key: &str) -> Option<Bound<'a>> {
+        match self.lookup_ref() {
+            ValueRef::Object(map) => map.get(key).map(|v| self.interners.bind(*v)),
+            _ => None,
+        }
+    }
+
+    /// Returns the element at the given index if this value is an array long
+    /// enough, or [`None`] otherwise.
+    pub fn get_index(&self, index: usize) -> Option<Bound<'a>> {
+        match self.lookup_ref() {
+            ValueRef::Array(array) => array.get(index).map(|v| self.interners.bind(*v)),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of elements if this value is an array or an object,
+    /// or [`None`] otherwise.
+    pub fn len(&self) -> Option<usize> {
+        match self.lookup_ref() {
+            ValueRef::Array(array) => Some(array.len()),
+            ValueRef::Object(map) => Some(map.iter().len()),
+            _ => None,
+        }
+    }
+
+    /// Checks whether this value is an empty array or object, or returns
+    /// [`None`] if it's neither an array nor an object.
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Checks whether this value is null.
+    pub fn is_null(&self) -> bool {
+        self.value.0 == IValueImpl::Null
+    }
+
+    /// Returns this value if it's a boolean, or [`None`] otherwise.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.value.0 {
+            IValueImpl::Bool(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Returns this value if it's an integer that fits in a [`u64`], or
+    /// [`None`] otherwise.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.value.0 {
+            IValueImpl::U64(x) => Some(x),
+            IValueImpl::I64(x) => x.try_into().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns this value if it's an integer that fits in a [`i64`], or
+    /// [`None`] otherwise.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.value.0 {
+            IValueImpl::U64(x) => x.try_into().ok(),
+            IValueImpl::I64(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Returns this value as a [`f64`] if it's a number, or [`None`]
+    /// otherwise.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.value.0 {
+            IValueImpl::U64(x) => Some(x as f64),
+            IValueImpl::I64(x) => Some(x as f64),
+            IValueImpl::F64(Float64(OrderedFloat(x))) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Returns this value if it's a string, or [`None`] otherwise.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self.value.0 {
+            IValueImpl::String(s) => Some(self.interners.string.lookup(s)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Bound<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.write_json(self.interners, f)
+    }
+}
+
+impl<'a> Bound<'a> {
+    /// Returns an object that displays this value as compact JSON, with the
+    /// keys of all nested objects sorted by the given comparison function.
+    ///
+    /// By contrast, the [`Display`] implementation of [`Bound`] writes keys in
+    /// the order in which they are stored in the arena.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.intern(json!({"item10": 1, "item9": {"b": 2, "a": 3}}));
+    /// let bound = interners.bind(ivalue);
+    /// assert_eq!(
+    ///     bound.display_sorted_by(str::cmp).to_string(),
+    ///     r#"{"item10":1,"item9":{"a":3,"b":2}}"#
+    /// );
+    /// assert_eq!(
+    ///     bound
+    ///         .display_sorted_by(|a, b| (a.len(), a).cmp(&(b.len(), b)))
+    ///         .to_string(),
+    ///     r#"{"item9":{"a":3,"b":2},"item10":1}"#
+    /// );
+    /// ```
+    pub fn display_sorted_by<F>(&self, compare: F) -> SortedDisplay<'a, F>
+    where
+        F: Fn(&str, &str) -> Ordering,
+    {
+        SortedDisplay {
+            bound: *self,
+            compare,
+        }
+    }
+}
+
+/// Displays a [`Bound`] value as compact JSON, with object keys sorted by a
+/// custom comparison function.
+///
+/// This struct is created by the
+/// [`display_sorted_by()`](Bound::display_sorted_by) method on [`Bound`].
+#[derive(Clone, Copy)]
+pub struct SortedDisplay<'a, F> {
+    bound: Bound<'a>,
+    compare: F,
+}
+
+impl<F> Display for SortedDisplay<'_, F>
+where
+    F: Fn(&str, &str) -> Ordering,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.bound
+            .value
+            .write_json_sorted_by(self.bound.interners, f, Some(&self.compare))
+    }
+}
+
+impl<'a> Bound<'a> {
+    /// Returns an object that displays this value as compact JSON, with
+    /// strings escaped and object keys ordered according to the given options.
+    ///
+    /// ```
+    /// use jinterner::{Jinterners, WriteOptions};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let bound = interners.bind(interners.intern(json!(["café/😀", "\n"])));
+    /// let options = WriteOptions::default()
+    ///     .ascii_only(true)
+    ///     .escape_slash(true)
+    ///     .short_escapes(false);
+    /// assert_eq!(
+    ///     bound.display_with(options).to_string(),
+    ///     r#"["caf\u00e9\/\ud83d\ude00","\u000a"]"#
+    /// );
+    /// ```
+    pub fn display_with(&self, options: WriteOptions) -> DisplayWith<'a> {
+        DisplayWith {
+            bound: *self,
+            options,
+        }
+    }
+}
+
+/// Displays a [`Bound`] value as compact JSON, with custom
+/// [`WriteOptions`].
+///
+/// This struct is created by the [`display_with()`](Bound::display_with)
+/// method on [`Bound`].
+#[derive(Clone, Copy)]
+pub struct DisplayWith<'a> {
+    bound: Bound<'a>,
+    options: WriteOptions,
+}
+
+impl Display for DisplayWith<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.bound.value.write_json_with(
+            self.bound.interners,
+            f,
+            self.options.key_compare(),
+            self.options,
+        )
+    }
+}
+
+impl Debug for Bound<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+/// Two bound values are equal if they represent the same JSON value, even if
+/// they are interned in different arenas.
+impl PartialEq for Bound<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value
+            .eq_content(self.interners, &other.value, other.interners)
+    }
+}
+
+impl Eq for Bound<'_> {}
+
+#[cfg(feature = "serde")]
+impl Serialize for Bound<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Content {
+            value: &self.value,
+            interners: self.interners,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn bound() {
+        let interners = Jinterners::default();
+        let value = json!({"name": "John", "age": 42, "tags": ["a", -1, 1.5, null]});
+        let bound = interners.bind(interners.intern_ref(&value));
+
+        assert_eq!(bound.to_json(), value);
+        assert_eq!(bound.len(), Some(3));
+        assert_eq!(bound.is_empty(), Some(false));
+        assert_eq!(bound.get("name").unwrap().as_str(), Some("John"));
+        assert_eq!(bound.get("age").unwrap().as_u64(), Some(42));
+        assert_eq!(bound.get("missing"), None);
+
+        let tags = bound.get("tags").unwrap();
+        assert_eq!(tags.get_index(0).unwrap().as_str(), Some("a"));
+        assert_eq!(tags.get_index(1).unwrap().as_i64(), Some(-1));
+        assert_eq!(tags.get_index(1).unwrap().as_u64(), None);
+        assert_eq!(tags.get_index(2).unwrap().as_f64(), Some(1.5));
+        assert!(tags.get_index(3).unwrap().is_null());
+        assert_eq!(tags.get_index(4), None);
+        assert_eq!(tags.get("name"), None);
+
+        assert_eq!(tags.to_string(), r#"["a",-1,1.5,null]"#);
+        assert_eq!(format!("{tags:?}"), r#"["a",-1,1.5,null]"#);
+
+        // Values are compared by content, across arenas.
+        let other = Jinterners::default();
+        other.intern(json!(["x", "tags", {"age": 1}]));
+        assert_eq!(other.bind(other.intern_ref(&value)), bound);
+        assert_ne!(other.bind(other.intern(json!({"age": 42}))), bound);
+    }
+
+    #[test]
+    fn display_with() {
+        let interners = Jinterners::default();
+        let value = json!({"k/é": "tab\t quote\" nul\u{0} 𝄞"});
+        let bound = interners.bind(interners.intern_ref(&value));
+        let display = |options| bound.display_with(options).to_string();
+
+        assert_eq!(display(WriteOptions::default()), bound.to_string());
+        assert_eq!(
+            display(WriteOptions::default()),
+            serde_json::to_string(&value).unwrap()
+        );
+        assert_eq!(
+            display(WriteOptions::default().ascii_only(true)),
+            r#"{"k/\u00e9":"tab\t quote\" nul\u0000 \ud834\udd1e"}"#
+        );
+        assert_eq!(
+            display(WriteOptions::default().escape_slash(true)),
+            r#"{"k\/é":"tab\t quote\" nul\u0000 𝄞"}"#
+        );
+        let options = WriteOptions::default()
+            .ascii_only(true)
+            .short_escapes(false)
+            .uppercase_hex(true);
+        let text = display(options);
+        assert_eq!(
+            text,
+            r#"{"k/\u00E9":"tab\u0009 quote\" nul\u0000 \uD834\uDD1E"}"#
+        );
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn display_sort_keys() {
+        let value = json!({"b": [{"y": 1, "x": 2}], "a": null, "c": "é"});
+        let first = Jinterners::default();
+        let second = Jinterners::default();
+        // Intern the keys in different orders.
+        first.intern(json!(["a", "b", "c", "x", "y"]));
+        second.intern(json!(["y", "x", "c", "b", "a"]));
+        let first = first.bind(first.intern_ref(&value));
+        let second = second.bind(second.intern_ref(&value));
+        assert_ne!(first.to_string(), second.to_string());
+
+        let options = WriteOptions::default().sort_keys(true);
+        let expected = r#"{"a":null,"b":[{"x":2,"y":1}],"c":"é"}"#;
+        assert_eq!(first.display_with(options).to_string(), expected);
+        assert_eq!(second.display_with(options).to_string(), expected);
+        assert_eq!(second.display_sorted_by(str::cmp).to_string(), expected);
+        assert_eq!(
+            first.display_with(options.ascii_only(true)).to_string(),
+            r#"{"a":null,"b":[{"x":2,"y":1}],"c":"\u00e9"}"#
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize() {
+        let interners = Jinterners::default();
+        let value = json!({"name": "John", "tags": ["a", {"b": [1.5, -1, true]}]});
+        let bound = interners.bind(interners.intern_ref(&value));
+        assert_eq!(serde_json::to_value(bound).unwrap(), value);
+    }
+}
@@ -0,0 +1,442 @@
+use crate::{IValue, Jinterners};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// An optional bounded memo of materialized values, populated by
+/// [`Jinterners::lookup_cached()`].
+#[derive(Default)]
+pub(crate) struct LookupCache(Option<Mutex<Memo>>);
+
+struct Memo {
+    capacity: usize,
+    values: HashMap<IValue, Arc<Value>>,
+}
+
+impl LookupCache {
+    fn new(capacity: usize) -> Self {
+        Self(Some(Mutex::new(Memo {
+            capacity,
+            values: HashMap::new(),
+        })))
+    }
+
+    /// Creates an empty cache with the same capacity as this one, or a
+    /// disabled cache if this one is disabled.
+    ///
+    /// This is used for arenas derived from this one, in which the cached
+    /// handles may refer to other values.
+    pub(crate) fn emptied(&self) -> Self {
+        match &self.0 {
+            None => Self(None),
+            Some(memo) => Self::new(memo.lock().unwrap_or_else(PoisonError::into_inner).capacity),
+        }
+    }
+}
+
+impl Clone for LookupCache {
+    fn clone(&self) -> Self {
+        Self(self.0.as_ref().map(|memo| {
+            let memo = memo.lock().unwrap_or_else(PoisonError::into_inner);
+            Mutex::new(Memo {
+                capacity: memo.capacity,
+                values: memo.values.clone(),
+            })
+        }))
+    }
+}
+
+impl Debug for LookupCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LookupCache")
+            .field(
+                &self
+                    .0
+                    .as_ref()
+                    .map(|memo| memo.lock().unwrap_or_else(PoisonError::into_inner).capacity),
+            )
+            .finish()
+    }
+}
+
+impl PartialEq for LookupCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for LookupCache {}
+
+impl Jinterners {
+    /// Enables a cache of values materialized by
+    /// [`lookup_cached()`](Self::lookup_cached), holding up to the given
+    /// number of values, or disables it if [`None`] is passed.
+    ///
+    /// Once the cache is full, it's cleared before caching the next value, so
+    /// that it keeps track of the values that are currently hot. Calling this
+    /// method again resizes and clears it.
+    ///
+    /// The cache is preserved in clones of this arena, and emptied in arenas
+    /// derived from it (for example with [`optimize()`](Self::optimize)). It
+    /// isn't serialized.
+    pub fn set_lookup_cache(&mut self, capacity: Option<usize>) {
+        self.lookup_cache = match capacity {
+            None => LookupCache::default(),
+            Some(capacity) => LookupCache::new(capacity),
+        };
+    }
+
+    /// Same as [`lookup()`](Self::lookup), but shares the materialized value
+    /// with previous and subsequent calls for the same handle, if the cache
+    /// is enabled with [`set_lookup_cache()`](Self::set_lookup_cache).
+    ///
+    /// This is useful to repeatedly serve the same few hot values without
+    /// re-building them each time. Without a cache, this allocates a new
+    /// value on each call.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    /// use std::sync::Arc;
+    ///
+    /// let mut interners = Jinterners::default();
+    /// interners.set_lookup_cache(Some(100));
+    ///
+    /// let ivalue = interners.intern(json!({"name": "John", "tags": ["a", "b"]}));
+    /// let first = interners.lookup_cached(&ivalue);
+    /// let second = interners.lookup_cached(&ivalue);
+    /// assert_eq!(*first, json!({"name": "John", "tags": ["a", "b"]}));
+    /// assert!(Arc::ptr_eq(&first, &second));
+    /// ```
+    pub fn lookup_cached(&self, value: &IValue) -> Arc<Value> {
+        let Some(memo) = &self.lookup_cache.0 else {
+            return Arc::new(self.lookup(value));
+        };
+        if let Some(cached) = memo
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values
+            .get(value)
+        {
+            return cached.clone();
+        }
+
+        // Materialize the value without holding the lock, as this is the
+        // expensive part.
+        let materialized = Arc::new(self.lookup(value));
+        let mut memo = memo.lock().unwrap_or_else(PoisonError::into_inner);
+        if memo.capacity == 0 {
+            return materialized;
+        }
+        if memo.values.len() >= memo.capacity && !memo.values.contains_key(value) {
+            memo.values.clear();
+        }
+        memo.values.entry(*value).or_insert(materialized).clone()
+    }
+}
+
+/// A least-recently-used cache of rendered JSON text, bounded by the total
+/// size of the cached text.
+///
+/// This is meant for read-heavy services that serve interned documents
+/// verbatim: rendering each document once and sharing the text avoids walking
+/// the arena on every request. Entries are keyed by [`IValue`] by default, but
+/// any identifier can be used, for example a [`DocId`](crate::DocId) or a
+/// [`RootId`](crate::RootId), so that an entry can be invalidated when the
+/// document it identifies changes.
+///
+/// The cache can be shared across threads, for example in an
+/// [`Arc`](std::sync::Arc). Text is rendered outside of the internal lock.
+///
+/// ```
+/// use jinterner::{Jinterners, TextCache};
+/// use serde_json::json;
+///
+/// let interners = Jinterners::default();
+/// let cache = TextCache::new(1 << 20);
+///
+/// let ivalue = interners.intern(json!({"name": "John"}));
+/// assert_eq!(&*cache.render(&interners, ivalue), r#"{"name":"John"}"#);
+/// assert_eq!(cache.len(), 1);
+/// assert_eq!(cache.size_bytes(), 15);
+///
+/// assert!(cache.invalidate(&ivalue));
+/// assert!(cache.is_empty());
+/// ```
+pub struct TextCache<K = IValue> {
+    capacity_bytes: usize,
+    lru: Mutex<Lru<K>>,
+}
+
+struct Lru<K> {
+    /// Cached text and last access tick of each key.
+    entries: HashMap<K, (Arc<str>, u64)>,
+    /// Keys by last access tick, from the least recently used.
+    order: BTreeMap<u64, K>,
+    size_bytes: usize,
+    tick: u64,
+    /// Incremented on each invalidation, so that text rendered concurrently
+    /// with an invalidation isn't cached.
+    epoch: u64,
+}
+
+impl<K: Hash + Eq + Clone> TextCache<K> {
+    /// Creates an empty cache holding up to the given number of bytes of
+    /// text.
+    pub fn new(capacity_bytes: usize) -> Self {
+        Self {
+            capacity_bytes,
+            lru: Mutex::new(Lru {
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+                size_bytes: 0,
+                tick: 0,
+                epoch: 0,
+            }),
+        }
+    }
+
+    /// Returns the maximal number of bytes of text held by this cache.
+    pub fn capacity_bytes(&self) -> usize {
+        self.capacity_bytes
+    }
+
+    /// Returns the number of cached entries.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Checks whether this cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.lock().entries.is_empty()
+    }
+
+    /// Returns the number of bytes of cached text.
+    pub fn size_bytes(&self) -> usize {
+        self.lock().size_bytes
+    }
+
+    /// Returns the cached text for the given key, marking it as the most
+    /// recently used.
+    pub fn get(&self, key: &K) -> Option<Arc<str>> {
+        self.lock().get(key)
+    }
+
+    /// Returns the cached text for the given key, or renders it with the
+    /// given function and caches it.
+    ///
+    /// The least recently used entries are evicted to make room for the new
+    /// text. Text larger than the capacity of this cache is returned without
+    /// being cached, and so is text whose rendering overlapped with a call to
+    /// [`invalidate()`](Self::invalidate) or [`clear()`](Self::clear), as it
+    /// may be stale.
+    pub fn get_or_insert_with(&self, key: K, render: impl FnOnce() -> String) -> Arc<str> {
+        let epoch = {
+            let mut lru = self.lock();
+            if let Some(text) = lru.get(&key) {
+                return text;
+            }
+            lru.epoch
+        };
+        let text: Arc<str> = render().into();
+        if text.len() <= self.capacity_bytes {
+            let mut lru = self.lock();
+            if lru.epoch != epoch {
+                return text;
+            }
+            lru.remove(&key);
+            while lru.size_bytes + text.len() > self.capacity_bytes {
+                let (_, evicted) = lru.order.pop_first().expect("the cache isn't empty");
+                lru.remove(&evicted);
+            }
+            lru.tick += 1;
+            let tick = lru.tick;
+            lru.size_bytes += text.len();
+            lru.order.insert(tick, key.clone());
+            lru.entries.insert(key, (text.clone(), tick));
+        }
+        text
+    }
+
+    /// Removes the entry of the given key, returning whether it was cached.
+    ///
+    /// Text for this key that is being rendered concurrently by
+    /// [`get_or_insert_with()`](Self::get_or_insert_with) won't be cached.
+    pub fn invalidate(&self, key: &K) -> bool {
+        let mut lru = self.lock();
+        lru.epoch += 1;
+        lru.remove(key)
+    }
+
+    /// Removes all the entries.
+    pub fn clear(&self) {
+        let mut lru = self.lock();
+        lru.epoch += 1;
+        lru.entries.clear();
+        lru.order.clear();
+        lru.size_bytes = 0;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru<K>> {
+        self.lru.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl TextCache<IValue> {
+    /// Returns the compact JSON text of the given value, rendering it from
+    /// the given arena if it isn't cached.
+    ///
+    /// All the values cached by this function must come from the same arena.
+    /// The cache must be cleared after switching to a rebuilt arena, for
+    /// example with [`Jinterners::optimize()`].
+    pub fn render(&self, interners: &Jinterners, value: IValue) -> Arc<str> {
+        self.get_or_insert_with(value, || interners.bind(value).to_string())
+    }
+}
+
+impl<K: Hash + Eq> Lru<K> {
+    fn get(&mut self, key: &K) -> Option<Arc<str>> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (text, last) = self.entries.get_mut(key)?;
+        let key = self.order.remove(last).expect("the key is ordered");
+        *last = tick;
+        let text = text.clone();
+        self.order.insert(tick, key);
+        Some(text)
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        match self.entries.remove(key) {
+            None => false,
+            Some((text, tick)) => {
+                self.order.remove(&tick);
+                self.size_bytes -= text.len();
+                true
+            }
+        }
+    }
+}
+
+impl<K> Debug for TextCache<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lru = self.lru.lock().unwrap_or_else(PoisonError::into_inner);
+        f.debug_struct("TextCache")
+            .field("capacity_bytes", &self.capacity_bytes)
+            .field("len", &lru.entries.len())
+            .field("size_bytes", &lru.size_bytes)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn lookup_cached() {
+        let mut interners = Jinterners::default();
+        // Intern the values in reverse order, so that optimizing the arena
+        // re-orders them.
+        let mut values: Vec<_> = (0..5).rev().map(|i| interners.intern(json!([i]))).collect();
+        values.reverse();
+        assert!(!Arc::ptr_eq(
+            &interners.lookup_cached(&values[0]),
+            &interners.lookup_cached(&values[0])
+        ));
+
+        interners.set_lookup_cache(Some(3));
+        let first = interners.lookup_cached(&values[0]);
+        assert_eq!(*first, json!([0]));
+        assert!(Arc::ptr_eq(&first, &interners.lookup_cached(&values[0])));
+        assert!(Arc::ptr_eq(
+            &first,
+            &interners.clone().lookup_cached(&values[0])
+        ));
+
+        // Filling the cache evicts the previous values.
+        for (i, value) in values.iter().enumerate() {
+            assert_eq!(*interners.lookup_cached(value), json!([i]));
+        }
+        assert!(!Arc::ptr_eq(&first, &interners.lookup_cached(&values[0])));
+
+        let (optimized, mapping) = interners.optimize(None).unwrap();
+        let memo = optimized.lookup_cache.0.as_ref().unwrap().lock().unwrap();
+        assert_eq!(memo.capacity, 3);
+        assert!(memo.values.is_empty());
+        drop(memo);
+        assert_eq!(
+            *optimized.lookup_cached(&mapping.map(values[4])),
+            json!([4])
+        );
+
+        interners.set_lookup_cache(Some(0));
+        let value = interners.lookup_cached(&values[1]);
+        assert!(!Arc::ptr_eq(&value, &interners.lookup_cached(&values[1])));
+    }
+
+    #[test]
+    fn text_cache() {
+        let interners = Jinterners::default();
+        let values: Vec<_> = (0..4).map(|i| interners.intern(json!({"id": i}))).collect();
+        // Each text is 8 bytes long, so that 3 of them fit in the cache.
+        let cache = TextCache::new(24);
+        for value in &values[..3] {
+            cache.render(&interners, *value);
+        }
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.size_bytes(), 24);
+
+        // Touching the first value makes the second one least recently used.
+        assert_eq!(cache.get(&values[0]).as_deref(), Some(r#"{"id":0}"#));
+        assert_eq!(&*cache.render(&interners, values[3]), r#"{"id":3}"#);
+        assert_eq!(cache.len(), 3);
+        assert!(cache.get(&values[1]).is_none());
+        assert!(cache.get(&values[0]).is_some());
+
+        assert!(cache.invalidate(&values[0]));
+        assert!(!cache.invalidate(&values[0]));
+        assert_eq!(cache.size_bytes(), 16);
+
+        // Text that doesn't fit is rendered but not cached.
+        let large = interners.intern(json!({"name": "a long name that doesn't fit"}));
+        assert_eq!(
+            &*cache.render(&interners, large),
+            r#"{"name":"a long name that doesn't fit"}"#
+        );
+        assert!(cache.get(&large).is_none());
+        assert_eq!(cache.len(), 2);
+
+        let by_id: TextCache<u64> = TextCache::new(10);
+        assert_eq!(&*by_id.get_or_insert_with(1, || "first".into()), "first");
+        assert_eq!(&*by_id.get_or_insert_with(1, || unreachable!()), "first");
+        assert_eq!(&*by_id.get_or_insert_with(2, || "second".into()), "second");
+        assert!(by_id.get(&1).is_none());
+        by_id.clear();
+        assert!(by_id.is_empty());
+        assert_eq!(by_id.size_bytes(), 0);
+    }
+
+    #[test]
+    fn text_cache_invalidated_while_rendering() {
+        let cache: TextCache<u64> = TextCache::new(100);
+        let text = cache.get_or_insert_with(1, || {
+            // The document changes while its previous version is rendered.
+            assert!(!cache.invalidate(&1));
+            "stale".into()
+        });
+        assert_eq!(&*text, "stale");
+        assert!(cache.get(&1).is_none());
+        assert_eq!(&*cache.get_or_insert_with(1, || "fresh".into()), "fresh");
+
+        let text = cache.get_or_insert_with(2, || {
+            cache.clear();
+            "stale".into()
+        });
+        assert_eq!(&*text, "stale");
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,293 @@
+use super::seed::deserialize_slice;
+use super::{Float64, IValue, IValueImpl, InternedStrKey};
+use crate::{Error, Jinterners};
+use blazinterner::InternedStr;
+use serde::Deserializer;
+use serde::de::{DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::Value;
+use std::fmt;
+
+impl IValue {
+    /// Checks whether the given JSON text represents this value.
+    ///
+    /// The text is compared to this value while it's being parsed, without
+    /// interning it nor materializing a [`serde_json::Value`]. Once a
+    /// difference is found, the rest of the text is only checked to be valid
+    /// JSON. Values are compared the same way as interned values, i.e. object
+    /// keys can be in any order and `1` differs from `1.0`.
+    ///
+    /// Returns an error if the text isn't valid JSON, even if a difference
+    /// was found before the invalid part.
+    ///
+    /// If an [`InternHook`](crate::InternHook) is installed, the text is
+    /// instead parsed into a [`serde_json::Value`] and found in the arena, so
+    /// that its fields are rewritten the same way as when interning it.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.intern(json!({"name": "John", "tags": ["a", "b"]}));
+    /// assert!(ivalue.eq_json_str(&interners, r#"{"tags": ["a", "b"], "name": "John"}"#)?);
+    /// assert!(!ivalue.eq_json_str(&interners, r#"{"name": "Jane", "tags": ["a", "b"]}"#)?);
+    /// assert!(ivalue.eq_json_str(&interners, r#"{"name": "#).is_err());
+    /// # Ok::<(), jinterner::Error>(())
+    /// ```
+    pub fn eq_json_str(&self, interners: &Jinterners, text: &str) -> Result<bool, Error> {
+        if interners.intern_hook.is_installed() {
+            let value: Value = serde_json::from_str(text)?;
+            return Ok(interners.find(&value) == Some(*self));
+        }
+        let seed = CompareSeed {
+            expected: *self,
+            interners,
+        };
+        Ok(deserialize_slice(seed, text.as_bytes())?)
+    }
+}
+
+/// A [`DeserializeSeed`] that checks whether the deserialized JSON value is
+/// equal to an interned value.
+#[derive(Clone, Copy)]
+struct CompareSeed<'a> {
+    expected: IValue,
+    interners: &'a Jinterners,
+}
+
+impl<'de> DeserializeSeed<'de> for CompareSeed<'_> {
+    type Value = bool;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for CompareSeed<'_> {
+    type Value = bool;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any valid JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(self.expected.0 == IValueImpl::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+        // Non-negative integers are always interned as unsigned.
+        Ok(self.expected.0
+            == match u64::try_from(value) {
+                Ok(x) => IValueImpl::U64(x),
+                Err(_) => IValueImpl::I64(value),
+            })
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(self.expected.0 == IValueImpl::U64(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+        // A float rejected by the FloatPolicy can't be interned, so it isn't
+        // equal to any interned value.
+        Ok(match Float64::new(value, self.interners.float_policy) {
+            None => false,
+            Some(_) if !value.is_finite() => self.expected.0 == IValueImpl::Null,
+            Some(x) => self.expected.0 == IValueImpl::F64(x),
+        })
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(match self.expected.0 {
+            IValueImpl::String(s) => self.interners.find_string(value) == Some(s),
+            _ => false,
+        })
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(self.expected.0 == IValueImpl::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(self.expected.0 == IValueImpl::Null)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let items = match self.expected.0 {
+            IValueImpl::Array(a) => self.interners.iarray.lookup(a),
+            _ => &[],
+        };
+        let mut eq = matches!(self.expected.0, IValueImpl::Array(_));
+        let mut len = 0;
+        loop {
+            let next = match items.get(len).filter(|_| eq) {
+                Some(item) => seq.next_element_seed(CompareSeed {
+                    expected: *item,
+                    interners: self.interners,
+                })?,
+                None => seq.next_element::<IgnoredAny>()?.map(|_| false),
+            };
+            match next {
+                Some(item_eq) => eq &= item_eq,
+                None => break,
+            }
+            len += 1;
+        }
+        Ok(eq && len == items.len())
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let entries = match self.expected.0 {
+            IValueImpl::Object(o) => self.interners.iobject.lookup(o),
+            _ => &[],
+        };
+        let mut eq = matches!(self.expected.0, IValueImpl::Object(_));
+        // Result of the comparison of each expected entry. The last value of
+        // duplicate keys wins, like when interning.
+        let mut matched = vec![false; entries.len()];
+        while let Some(key) = map.next_key_seed(FindKeySeed {
+            interners: self.interners,
+        })? {
+            let index = key.filter(|_| eq).and_then(|key| {
+                entries
+                    .binary_search_by_key(&InternedStrKey(key), |(k, _)| *k)
+                    .ok()
+            });
+            match index {
+                Some(i) => {
+                    matched[i] = map.next_value_seed(CompareSeed {
+                        expected: entries[i].1,
+                        interners: self.interners,
+                    })?;
+                }
+                None => {
+                    eq = false;
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(eq && matched.iter().all(|m| *m))
+    }
+}
+
+/// A [`DeserializeSeed`] that finds an object key in an arena, without
+/// interning it.
+#[derive(Clone, Copy)]
+struct FindKeySeed<'a> {
+    interners: &'a Jinterners,
+}
+
+impl<'de> DeserializeSeed<'de> for FindKeySeed<'_> {
+    type Value = Option<InternedStr>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FindKeySeed<'_> {
+    type Value = Option<InternedStr>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string key")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(self.interners.find_string(value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn eq_json_str() {
+        let interners = Jinterners::default();
+        let value = json!({"b": [1, -2, 3.5, null, true, "x"], "a": {"c": {}}, "d": []});
+        let ivalue = interners.intern_ref(&value);
+        let strings = interners.string.strings();
+
+        let eq = |text: &str| ivalue.eq_json_str(&interners, text).unwrap();
+        assert!(eq(&value.to_string()));
+        assert!(eq(
+            r#" {"d": [], "a": {"c": {}}, "b": [1, -2, 3.5, null, true, "x"]} "#
+        ));
+        // Duplicate keys keep the last value.
+        assert!(eq(
+            r#"{"d": [], "a": {"c": {}}, "b": [], "b": [1, -2, 3.5, null, true, "x"]}"#
+        ));
+        assert!(!eq(
+            r#"{"d": [], "a": {"c": {}}, "b": [1, -2, 3.5, null, true, "x"], "b": []}"#
+        ));
+
+        for text in [
+            r#"{"d": [], "a": {"c": {}}, "b": [1, -2, 3.5, null, true, "y"]}"#,
+            r#"{"d": [], "a": {"c": {}}, "b": [1.0, -2, 3.5, null, true, "x"]}"#,
+            r#"{"d": [], "a": {"c": {}}, "b": [1, -2, 3.5, null, true]}"#,
+            r#"{"d": [], "a": {"c": {}}, "b": [1, -2, 3.5, null, true, "x", 0]}"#,
+            r#"{"d": [], "a": {"c": {}}}"#,
+            r#"{"d": [], "a": {"c": {}}, "b": [1, -2, 3.5, null, true, "x"], "e": 0}"#,
+            r#"{"d": {}, "a": {"c": {}}, "b": [1, -2, 3.5, null, true, "x"]}"#,
+            r#"[{"d": [], "a": {"c": {}}, "b": [1, -2, 3.5, null, true, "x"]}]"#,
+            r#""new string""#,
+            "null",
+        ] {
+            assert!(!eq(text), "{text}");
+        }
+        // Nothing was interned.
+        assert_eq!(interners.string.strings(), strings);
+
+        // Invalid JSON is an error, even after a difference.
+        assert!(matches!(
+            ivalue.eq_json_str(&interners, r#"{"e": 0, "a": }"#),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            ivalue.eq_json_str(&interners, &format!("{value} 0")),
+            Err(Error::Parse(_))
+        ));
+
+        let scalars = [
+            json!(null),
+            json!(false),
+            json!(0),
+            json!(-1),
+            json!(0.5),
+            json!(""),
+        ];
+        for (i, a) in scalars.iter().enumerate() {
+            let ia = interners.intern_ref(a);
+            for (j, b) in scalars.iter().enumerate() {
+                assert_eq!(ia.eq_json_str(&interners, &b.to_string()).unwrap(), i == j);
+            }
+        }
+    }
+}
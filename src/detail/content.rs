@@ -0,0 +1,217 @@
+#[cfg(feature = "serde")]
+use super::{ArrayRef, MapRef};
+use super::{Float64, FloatPolicy, IValue, IValueImpl, InternedStrKey, ValueRef};
+use crate::Jinterners;
+use ordered_float::OrderedFloat;
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde_json::Value;
+
+impl IValue {
+    /// Checks whether this value in the given arena represents the same JSON
+    /// value as the other value in the other arena.
+    pub(crate) fn eq_content(
+        &self,
+        interners: &Jinterners,
+        other: &IValue,
+        other_interners: &Jinterners,
+    ) -> bool {
+        if std::ptr::eq(interners, other_interners) {
+            return self == other;
+        }
+        match (&self.0, &other.0) {
+            (IValueImpl::String(a), IValueImpl::String(b)) => {
+                interners.string.lookup(*a) == other_interners.string.lookup(*b)
+            }
+            (IValueImpl::Array(a), IValueImpl::Array(b)) => {
+                let a = interners.iarray.lookup(*a);
+                let b = other_interners.iarray.lookup(*b);
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|(a, b)| a.eq_content(interners, b, other_interners))
+            }
+            (IValueImpl::Object(a), IValueImpl::Object(b)) => {
+                let a = interners.iobject.lookup(*a);
+                let b = other_interners.iobject.lookup(*b);
+                a.len() == b.len()
+                    && a.iter().all(|(k, v)| {
+                        let Some(k) = other_interners.string.find(interners.string.lookup(k.0))
+                        else {
+                            return false;
+                        };
+                        match b.binary_search_by_key(&InternedStrKey(k), |entry| entry.0) {
+                            Ok(i) => v.eq_content(interners, &b[i].1, other_interners),
+                            Err(_) => false,
+                        }
+                    })
+            }
+            (a, b) => a == b,
+        }
+    }
+}
+
+/// A shallow reference is equal to a [`Value`] if it represents the same JSON
+/// value. Nested values are compared without materializing them.
+impl PartialEq<Value> for ValueRef<'_> {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (ValueRef::Null, Value::Null) => true,
+            (ValueRef::Bool(a), Value::Bool(b)) => a == b,
+            (ValueRef::U64(x), Value::Number(n)) => {
+                IValueImpl::from_number(n, FloatPolicy::Preserve) == IValueImpl::U64(*x)
+            }
+            (ValueRef::I64(x), Value::Number(n)) => {
+                IValueImpl::from_number(n, FloatPolicy::Preserve) == IValueImpl::I64(*x)
+            }
+            (ValueRef::F64(x), Value::Number(n)) => {
+                IValueImpl::from_number(n, FloatPolicy::Preserve)
+                    == IValueImpl::F64(Float64(OrderedFloat(*x)))
+            }
+            (ValueRef::String(a), Value::String(b)) => a == b,
+            (ValueRef::Array(a), Value::Array(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|(v, w)| v.0.lookup_ref(a.interners) == *w)
+            }
+            (ValueRef::Object(a), Value::Object(b)) => {
+                a.map.len() == b.len()
+                    && a.iter()
+                        .all(|(k, v)| b.get(k).is_some_and(|w| v.0.lookup_ref(a.interners) == *w))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq<ValueRef<'_>> for Value {
+    fn eq(&self, other: &ValueRef<'_>) -> bool {
+        other == self
+    }
+}
+
+/// An interned value together with its arena, serialized as the JSON value it
+/// represents.
+#[cfg(feature = "serde")]
+pub(crate) struct Content<'a> {
+    pub(crate) value: &'a IValue,
+    pub(crate) interners: &'a Jinterners,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Content<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.value
+            .0
+            .lookup_ref(self.interners)
+            .serialize(serializer)
+    }
+}
+
+/// Serializes the JSON value that this reference represents, resolving nested
+/// values in the arena it was looked up from.
+#[cfg(feature = "serde")]
+impl Serialize for ValueRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            ValueRef::Null => serializer.serialize_unit(),
+            ValueRef::Bool(x) => serializer.serialize_bool(*x),
+            ValueRef::U64(x) => serializer.serialize_u64(*x),
+            ValueRef::I64(x) => serializer.serialize_i64(*x),
+            ValueRef::F64(x) => serializer.serialize_f64(*x),
+            ValueRef::String(s) => serializer.serialize_str(s),
+            ValueRef::Array(a) => a.serialize(serializer),
+            ValueRef::Object(o) => o.serialize(serializer),
+        }
+    }
+}
+
+/// Serializes this array as a sequence, resolving nested values in the arena
+/// it was looked up from.
+#[cfg(feature = "serde")]
+impl Serialize for ArrayRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let interners = self.interners;
+        let mut seq = serializer.serialize_seq(Some(self.array.len()))?;
+        for value in self.array {
+            seq.serialize_element(&Content { value, interners })?;
+        }
+        seq.end()
+    }
+}
+
+/// Serializes this map, resolving nested values in the arena it was looked up
+/// from.
+#[cfg(feature = "serde")]
+impl Serialize for MapRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let interners = self.interners;
+        let mut map = serializer.serialize_map(Some(self.map.len()))?;
+        for (key, value) in self.iter() {
+            map.serialize_entry(key, &Content { value, interners })?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn eq_json() {
+        let interners = Jinterners::default();
+        let value = json!({"name": "John", "age": 42, "tags": ["a", -1, 1.5, null, true]});
+        let ivalue = interners.intern_ref(&value);
+        assert_eq!(interners.lookup_ref(&ivalue), value);
+        assert_eq!(value, interners.lookup_ref(&ivalue));
+
+        let others = [
+            json!(null),
+            json!({"name": "John", "age": 42}),
+            json!({"name": "John", "age": 42, "tags": ["a", -1, 1.5, null, false]}),
+            json!({"name": "John", "age": 42.0, "tags": ["a", -1, 1.5, null, true]}),
+            json!({"name": "John", "age": 42, "tags": ["a", -1, 1.5, null]}),
+            json!({"name": "John", "age": 42, "other": ["a", -1, 1.5, null, true]}),
+        ];
+        for other in others {
+            assert_ne!(interners.lookup_ref(&ivalue), other);
+            assert_ne!(other, interners.lookup_ref(&ivalue));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_value_ref() {
+        let interners = Jinterners::default();
+        let values = [
+            json!(null),
+            json!(false),
+            json!(u64::MAX),
+            json!(i64::MIN),
+            json!(1.5),
+            json!("a string"),
+            json!([1, "a", [null], {"b": false}]),
+            json!({"name": "John", "tags": ["a", {"b": [1.5, -1, true]}]}),
+        ];
+        for value in values {
+            let ivalue = interners.intern_ref(&value);
+            let value_ref = interners.lookup_ref(&ivalue);
+            assert_eq!(serde_json::to_value(value_ref).unwrap(), value);
+        }
+    }
+}
@@ -1,18 +1,26 @@
-use super::{Float64, IValue, IValueImpl, InternedStrKey};
+use super::path::{Path, PathRef, Segment};
+use super::{
+    BytesEncoding, Float64, IValue, IValueImpl, InternedStrKey, LossyNumbers, SerdeOptions,
+    ValueRef, base64,
+};
 use crate::Jinterners;
 use blazinterner::{InternedSlice, InternedStr};
 use ordered_float::OrderedFloat;
+use serde::Deserializer;
+use serde::de::value::BorrowedStrDeserializer;
 use serde::de::{
-    DeserializeSeed, EnumAccess, Error, Expected, MapAccess, SeqAccess, Unexpected, VariantAccess,
-    Visitor,
+    DeserializeSeed, EnumAccess, Error, Expected, IntoDeserializer, MapAccess, SeqAccess,
+    Unexpected, VariantAccess, Visitor,
 };
-use serde::{Deserializer, forward_to_deserialize_any};
+use serde_json::Value;
 use serde_json::error::Error as JsonError;
 
 fn deserialize_array<'de, V>(
     visitor: V,
     array: InternedSlice<IValue>,
     interners: &'de Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'_>,
 ) -> Result<V::Value, JsonError>
 where
     V: Visitor<'de>,
@@ -23,6 +31,8 @@ where
         array,
         index: 0,
         interners,
+        options,
+        path,
     };
     let value = visitor.visit_seq(&mut array_access)?;
     if array_access.is_fully_scanned() {
@@ -36,6 +46,8 @@ fn deserialize_array_expected_len<'de, V>(
     visitor: V,
     array: InternedSlice<IValue>,
     interners: &'de Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'_>,
     expected_len: usize,
     make_error_msg: impl FnOnce() -> String,
 ) -> Result<V::Value, JsonError>
@@ -52,6 +64,8 @@ where
         array,
         index: 0,
         interners,
+        options,
+        path,
     };
     let value = visitor.visit_seq(&mut array_access)?;
     if array_access.is_fully_scanned() {
@@ -65,6 +79,8 @@ fn deserialize_object<'de, V>(
     visitor: V,
     object: InternedSlice<(InternedStrKey, IValue)>,
     interners: &'de Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'_>,
 ) -> Result<V::Value, JsonError>
 where
     V: Visitor<'de>,
@@ -75,6 +91,8 @@ where
         object,
         index: 0,
         interners,
+        options,
+        path,
     };
     let value = visitor.visit_map(&mut object_access)?;
     if object_access.is_fully_scanned() {
@@ -84,9 +102,49 @@ where
     }
 }
 
+/// Checks that the given number converts to a float without loss of precision,
+/// unless the options allow lossy conversions.
+fn check_float(value: &IValueImpl, options: SerdeOptions, f32: bool) -> Result<(), JsonError> {
+    if options.lossy_numbers == LossyNumbers::Allow {
+        return Ok(());
+    }
+    let (unexpected, lossless) = match *value {
+        IValueImpl::U64(x) => (
+            Unexpected::Unsigned(x),
+            if f32 {
+                x as f32 as u128 == x as u128
+            } else {
+                x as f64 as u128 == x as u128
+            },
+        ),
+        IValueImpl::I64(x) => (
+            Unexpected::Signed(x),
+            if f32 {
+                x as f32 as i128 == x as i128
+            } else {
+                x as f64 as i128 == x as i128
+            },
+        ),
+        IValueImpl::F64(Float64(OrderedFloat(x))) => (
+            Unexpected::Float(x),
+            !f32 || x as f32 as f64 == x || x.is_nan(),
+        ),
+        _ => return Ok(()),
+    };
+    if lossless {
+        Ok(())
+    } else if f32 {
+        Err(Error::invalid_value(unexpected, &"an exact f32"))
+    } else {
+        Err(Error::invalid_value(unexpected, &"an exact f64"))
+    }
+}
+
 pub(super) struct ValueDeserializer<'a, 'b> {
     pub value: &'a IValueImpl,
     pub interners: &'b Jinterners,
+    pub options: SerdeOptions,
+    pub path: PathRef<'a>,
 }
 
 impl<'de> ValueDeserializer<'_, 'de> {
@@ -121,10 +179,11 @@ impl<'de> ValueDeserializer<'_, 'de> {
         }
     }
 
-    fn deserialize_float<V>(self, visitor: V) -> Result<V::Value, JsonError>
+    fn deserialize_float<V>(self, visitor: V, f32: bool) -> Result<V::Value, JsonError>
     where
         V: Visitor<'de>,
     {
+        check_float(self.value, self.options, f32)?;
         match self.value {
             IValueImpl::U64(x) => visitor.visit_u64(*x),
             IValueImpl::I64(x) => visitor.visit_i64(*x),
@@ -137,6 +196,10 @@ impl<'de> ValueDeserializer<'_, 'de> {
 impl<'de> Deserializer<'de> for ValueDeserializer<'_, 'de> {
     type Error = JsonError;
 
+    fn is_human_readable(&self) -> bool {
+        self.options.human_readable
+    }
+
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
@@ -148,8 +211,12 @@ impl<'de> Deserializer<'de> for ValueDeserializer<'_, 'de> {
             IValueImpl::I64(x) => visitor.visit_i64(*x),
             IValueImpl::F64(Float64(OrderedFloat(x))) => visitor.visit_f64(*x),
             IValueImpl::String(s) => visitor.visit_borrowed_str(self.interners.string.lookup(*s)),
-            IValueImpl::Array(a) => deserialize_array(visitor, *a, self.interners),
-            IValueImpl::Object(o) => deserialize_object(visitor, *o, self.interners),
+            IValueImpl::Array(a) => {
+                deserialize_array(visitor, *a, self.interners, self.options, self.path)
+            }
+            IValueImpl::Object(o) => {
+                deserialize_object(visitor, *o, self.interners, self.options, self.path)
+            }
         }
     }
 
@@ -237,14 +304,14 @@ impl<'de> Deserializer<'de> for ValueDeserializer<'_, 'de> {
     where
         V: Visitor<'de>,
     {
-        self.deserialize_float(visitor)
+        self.deserialize_float(visitor, true)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_float(visitor)
+        self.deserialize_float(visitor, false)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -318,7 +385,9 @@ impl<'de> Deserializer<'de> for ValueDeserializer<'_, 'de> {
         V: Visitor<'de>,
     {
         match self.value {
-            IValueImpl::Array(a) => deserialize_array(visitor, *a, self.interners),
+            IValueImpl::Array(a) => {
+                deserialize_array(visitor, *a, self.interners, self.options, self.path)
+            }
             _ => Err(self.invalid_type(&visitor)),
         }
     }
@@ -328,11 +397,15 @@ impl<'de> Deserializer<'de> for ValueDeserializer<'_, 'de> {
         V: Visitor<'de>,
     {
         match self.value {
-            IValueImpl::Array(a) => {
-                deserialize_array_expected_len(visitor, *a, self.interners, len, || {
-                    format!("tuple with {len} elements")
-                })
-            }
+            IValueImpl::Array(a) => deserialize_array_expected_len(
+                visitor,
+                *a,
+                self.interners,
+                self.options,
+                self.path,
+                len,
+                || format!("tuple with {len} elements"),
+            ),
             _ => Err(self.invalid_type(&visitor)),
         }
     }
@@ -354,7 +427,9 @@ impl<'de> Deserializer<'de> for ValueDeserializer<'_, 'de> {
         V: Visitor<'de>,
     {
         match self.value {
-            IValueImpl::Object(o) => deserialize_object(visitor, *o, self.interners),
+            IValueImpl::Object(o) => {
+                deserialize_object(visitor, *o, self.interners, self.options, self.path)
+            }
             _ => Err(self.invalid_type(&visitor)),
         }
     }
@@ -369,8 +444,12 @@ impl<'de> Deserializer<'de> for ValueDeserializer<'_, 'de> {
         V: Visitor<'de>,
     {
         match self.value {
-            IValueImpl::Array(a) => deserialize_array(visitor, *a, self.interners),
-            IValueImpl::Object(o) => deserialize_object(visitor, *o, self.interners),
+            IValueImpl::Array(a) => {
+                deserialize_array(visitor, *a, self.interners, self.options, self.path)
+            }
+            IValueImpl::Object(o) => {
+                deserialize_object(visitor, *o, self.interners, self.options, self.path)
+            }
             _ => Err(self.invalid_type(&visitor)),
         }
     }
@@ -389,6 +468,8 @@ impl<'de> Deserializer<'de> for ValueDeserializer<'_, 'de> {
                 variant: *s,
                 value: None,
                 interners: self.interners,
+                options: self.options,
+                path: self.path,
             }),
             IValueImpl::Object(o) => {
                 let object = self.interners.iobject.lookup(*o);
@@ -399,10 +480,14 @@ impl<'de> Deserializer<'de> for ValueDeserializer<'_, 'de> {
                     ))
                 } else {
                     let (variant, value) = &object[0];
-                    visitor.visit_enum(EnumAccessor {
-                        variant: variant.0,
-                        value: Some(&value.0),
-                        interners: self.interners,
+                    self.path.child(Segment::Key(variant.0), |path| {
+                        visitor.visit_enum(EnumAccessor {
+                            variant: variant.0,
+                            value: Some(&value.0),
+                            interners: self.interners,
+                            options: self.options,
+                            path,
+                        })
                     })
                 }
             }
@@ -424,8 +509,61 @@ impl<'de> Deserializer<'de> for ValueDeserializer<'_, 'de> {
         visitor.visit_unit()
     }
 
-    forward_to_deserialize_any! {
-        bytes byte_buf
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            IValueImpl::String(s) => match self.options.bytes {
+                BytesEncoding::Base64 => {
+                    let s = self.interners.string.lookup(*s);
+                    match base64::decode(s) {
+                        Some(bytes) => visitor.visit_byte_buf(bytes),
+                        None => Err(Error::invalid_value(Unexpected::Str(s), &"a base64 string")),
+                    }
+                }
+                // Like serde_json, a plain string is deserialized as its UTF-8 bytes.
+                BytesEncoding::Array => {
+                    visitor.visit_borrowed_bytes(self.interners.string.lookup_bytes(*s))
+                }
+            },
+            // Collect the bytes directly rather than visiting a sequence of
+            // elements one by one.
+            IValueImpl::Array(a) => {
+                let array = self.interners.iarray.lookup(*a);
+                let mut bytes = Vec::with_capacity(array.len());
+                for (i, item) in array.iter().enumerate() {
+                    match item.0 {
+                        IValueImpl::U64(x) if x <= u8::MAX as u64 => bytes.push(x as u8),
+                        _ => {
+                            return self.path.child(Segment::Index(i), |_| {
+                                Err(match item.0 {
+                                    IValueImpl::U64(x) => {
+                                        Error::invalid_value(Unexpected::Unsigned(x), &"a byte")
+                                    }
+                                    _ => ValueDeserializer {
+                                        value: &item.0,
+                                        interners: self.interners,
+                                        options: self.options,
+                                        path: self.path,
+                                    }
+                                    .invalid_type(&"a byte"),
+                                })
+                            });
+                        }
+                    }
+                }
+                visitor.visit_byte_buf(bytes)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
     }
 }
 
@@ -433,6 +571,8 @@ struct ArrayAccess<'a, 'b> {
     array: &'a [IValue],
     index: usize,
     interners: &'b Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
 }
 
 impl ArrayAccess<'_, '_> {
@@ -450,12 +590,18 @@ impl<'de> SeqAccess<'de> for ArrayAccess<'_, 'de> {
     {
         if self.index < self.array.len() {
             let next = self.array[self.index];
+            let index = self.index;
             self.index += 1;
-            seed.deserialize(ValueDeserializer {
-                value: &next.0,
-                interners: self.interners,
-            })
-            .map(Some)
+            self.path
+                .child(Segment::Index(index), |path| {
+                    seed.deserialize(ValueDeserializer {
+                        value: &next.0,
+                        interners: self.interners,
+                        options: self.options,
+                        path,
+                    })
+                })
+                .map(Some)
         } else {
             Ok(None)
         }
@@ -470,6 +616,8 @@ struct ObjectAccess<'a, 'b> {
     object: &'a [(InternedStrKey, IValue)],
     index: usize,
     interners: &'b Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
 }
 
 impl ObjectAccess<'_, '_> {
@@ -491,6 +639,8 @@ impl<'de> MapAccess<'de> for ObjectAccess<'_, 'de> {
             seed.deserialize(StringDeserializer {
                 istring: next.0.0,
                 interners: self.interners,
+                options: self.options,
+                path: self.path,
             })
             .map(Some)
         } else {
@@ -502,9 +652,14 @@ impl<'de> MapAccess<'de> for ObjectAccess<'_, 'de> {
     where
         T: DeserializeSeed<'de>,
     {
-        seed.deserialize(ValueDeserializer {
-            value: &self.object[self.index - 1].1.0,
-            interners: self.interners,
+        let (key, value) = &self.object[self.index - 1];
+        self.path.child(Segment::Key(key.0), |path| {
+            seed.deserialize(ValueDeserializer {
+                value: &value.0,
+                interners: self.interners,
+                options: self.options,
+                path,
+            })
         })
     }
 
@@ -517,6 +672,8 @@ struct EnumAccessor<'a, 'b> {
     variant: InternedStr,
     value: Option<&'a IValueImpl>,
     interners: &'b Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
 }
 
 impl<'a, 'de> EnumAccess<'de> for EnumAccessor<'a, 'de> {
@@ -530,6 +687,8 @@ impl<'a, 'de> EnumAccess<'de> for EnumAccessor<'a, 'de> {
         seed.deserialize(StringDeserializer {
             istring: self.variant,
             interners: self.interners,
+            options: self.options,
+            path: self.path,
         })
         .map(|value| {
             (
@@ -537,6 +696,8 @@ impl<'a, 'de> EnumAccess<'de> for EnumAccessor<'a, 'de> {
                 VariantAccessor {
                     value: self.value,
                     interners: self.interners,
+                    options: self.options,
+                    path: self.path,
                 },
             )
         })
@@ -546,6 +707,8 @@ impl<'a, 'de> EnumAccess<'de> for EnumAccessor<'a, 'de> {
 struct VariantAccessor<'a, 'b> {
     value: Option<&'a IValueImpl>,
     interners: &'b Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
 }
 
 impl<'de> VariantAccess<'de> for VariantAccessor<'_, 'de> {
@@ -557,6 +720,8 @@ impl<'de> VariantAccess<'de> for VariantAccessor<'_, 'de> {
             Some(value) => Err(ValueDeserializer {
                 value,
                 interners: self.interners,
+                options: self.options,
+                path: self.path,
             }
             .invalid_type(&"unit variant")),
         }
@@ -570,6 +735,8 @@ impl<'de> VariantAccess<'de> for VariantAccessor<'_, 'de> {
             Some(value) => seed.deserialize(ValueDeserializer {
                 value,
                 interners: self.interners,
+                options: self.options,
+                path: self.path,
             }),
             None => Err(Error::invalid_type(
                 Unexpected::UnitVariant,
@@ -583,14 +750,20 @@ impl<'de> VariantAccess<'de> for VariantAccessor<'_, 'de> {
         V: Visitor<'de>,
     {
         match self.value {
-            Some(IValueImpl::Array(a)) => {
-                deserialize_array_expected_len(visitor, *a, self.interners, len, || {
-                    format!("tuple with {len} elements")
-                })
-            }
+            Some(IValueImpl::Array(a)) => deserialize_array_expected_len(
+                visitor,
+                *a,
+                self.interners,
+                self.options,
+                self.path,
+                len,
+                || format!("tuple with {len} elements"),
+            ),
             Some(value) => Err(ValueDeserializer {
                 value,
                 interners: self.interners,
+                options: self.options,
+                path: self.path,
             }
             .invalid_type(&"tuple variant")),
             None => Err(Error::invalid_type(
@@ -611,14 +784,24 @@ impl<'de> VariantAccess<'de> for VariantAccessor<'_, 'de> {
         match self.value {
             Some(IValueImpl::Array(a)) => {
                 let len = fields.len();
-                deserialize_array_expected_len(visitor, *a, self.interners, len, || {
-                    format!("struct with {len} fields")
-                })
+                deserialize_array_expected_len(
+                    visitor,
+                    *a,
+                    self.interners,
+                    self.options,
+                    self.path,
+                    len,
+                    || format!("struct with {len} fields"),
+                )
+            }
+            Some(IValueImpl::Object(o)) => {
+                deserialize_object(visitor, *o, self.interners, self.options, self.path)
             }
-            Some(IValueImpl::Object(o)) => deserialize_object(visitor, *o, self.interners),
             Some(value) => Err(ValueDeserializer {
                 value,
                 interners: self.interners,
+                options: self.options,
+                path: self.path,
             }
             .invalid_type(&"struct variant")),
             None => Err(Error::invalid_type(
@@ -629,12 +812,14 @@ impl<'de> VariantAccess<'de> for VariantAccessor<'_, 'de> {
     }
 }
 
-struct StringDeserializer<'b> {
+struct StringDeserializer<'a, 'b> {
     istring: InternedStr,
     interners: &'b Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
 }
 
-impl<'de> StringDeserializer<'de> {
+impl<'de> StringDeserializer<'_, 'de> {
     fn invalid_type<E>(self, exp: &dyn Expected) -> E
     where
         E: Error,
@@ -646,9 +831,13 @@ impl<'de> StringDeserializer<'de> {
     }
 }
 
-impl<'de> Deserializer<'de> for StringDeserializer<'de> {
+impl<'de> Deserializer<'de> for StringDeserializer<'_, 'de> {
     type Error = JsonError;
 
+    fn is_human_readable(&self) -> bool {
+        self.options.human_readable
+    }
+
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
@@ -876,6 +1065,8 @@ impl<'de> Deserializer<'de> for StringDeserializer<'de> {
             variant: self.istring,
             value: None,
             interners: self.interners,
+            options: self.options,
+            path: self.path,
         })
     }
 
@@ -893,3 +1084,323 @@ impl<'de> Deserializer<'de> for StringDeserializer<'de> {
         visitor.visit_unit()
     }
 }
+
+/// A [`Deserializer`] for a shallow [`ValueRef`].
+///
+/// This allows to deserialize a sub-value obtained by navigation into an
+/// arbitrary type, without going back to its [`IValue`].
+///
+/// ```
+/// use jinterner::{Jinterners, ValueRef, ValueRefDeserializer};
+/// use serde::Deserialize;
+/// use serde_json::json;
+///
+/// #[derive(Deserialize)]
+/// struct User<'a> {
+///     name: &'a str,
+/// }
+///
+/// let interners = Jinterners::default();
+/// let ivalue = interners.intern(json!({"users": [{"name": "John"}]}));
+/// let ValueRef::Object(map) = interners.lookup_ref(&ivalue) else {
+///     unreachable!()
+/// };
+/// let ValueRef::Array(users) = interners.lookup_ref(map.get("users").unwrap()) else {
+///     unreachable!()
+/// };
+/// let users = Vec::<User>::deserialize(ValueRefDeserializer::new(ValueRef::Array(users))).unwrap();
+/// assert_eq!(users[0].name, "John");
+/// ```
+#[derive(Clone, Copy)]
+pub struct ValueRefDeserializer<'a> {
+    value: ValueRef<'a>,
+    options: SerdeOptions,
+}
+
+impl<'a> ValueRefDeserializer<'a> {
+    /// Creates a deserializer for the given value.
+    pub fn new(value: ValueRef<'a>) -> Self {
+        Self::with_options(value, SerdeOptions::default())
+    }
+
+    /// Creates a deserializer for the given value, with the given options.
+    pub fn with_options(value: ValueRef<'a>, options: SerdeOptions) -> Self {
+        Self { value, options }
+    }
+}
+
+impl<'a> IntoDeserializer<'a, JsonError> for ValueRef<'a> {
+    type Deserializer = ValueRefDeserializer<'a>;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        ValueRefDeserializer::new(self)
+    }
+}
+
+/// Deserializes an array or object, reporting the path of the failing value on
+/// error.
+fn deserialize_container<'de, T>(
+    value: IValueImpl,
+    interners: &'de Jinterners,
+    options: SerdeOptions,
+    f: impl FnOnce(ValueDeserializer<'_, 'de>) -> Result<T, JsonError>,
+) -> Result<T, JsonError> {
+    let path = Path::default();
+    f(ValueDeserializer {
+        value: &value,
+        interners,
+        options,
+        path: path.root(),
+    })
+    .map_err(|e| path.wrap_error(e, interners))
+}
+
+/// Evaluates the given expression with a deserializer for the value.
+///
+/// Scalars are deserialized like the equivalent [`serde_json::Value`], strings
+/// are borrowed, and arrays and objects are deserialized from the arena.
+macro_rules! with_deserializer {
+    ($self:ident, $d:ident => $body:expr) => {
+        match $self.value {
+            ValueRef::Null => {
+                let $d = Value::Null;
+                $body
+            }
+            ValueRef::Bool(x) => {
+                let $d = Value::Bool(x);
+                $body
+            }
+            ValueRef::U64(x) => {
+                let $d = Value::from(x);
+                $body
+            }
+            ValueRef::I64(x) => {
+                let $d = Value::from(x);
+                $body
+            }
+            ValueRef::F64(x) => {
+                let $d = Value::from(x);
+                $body
+            }
+            ValueRef::String(s) => {
+                let $d = BorrowedStrDeserializer::<JsonError>::new(s);
+                $body
+            }
+            ValueRef::Array(a) => deserialize_container(
+                IValueImpl::Array(a.handle),
+                a.interners,
+                $self.options,
+                |$d| $body,
+            ),
+            ValueRef::Object(o) => deserialize_container(
+                IValueImpl::Object(o.handle),
+                o.interners,
+                $self.options,
+                |$d| $body,
+            ),
+        }
+    };
+}
+
+macro_rules! forward_to_deserializer {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                with_deserializer!(self, d => d.$method($($arg,)* visitor))
+            }
+        )*
+    };
+}
+
+impl ValueRefDeserializer<'_> {
+    fn check_float(&self, f32: bool) -> Result<(), JsonError> {
+        let value = match self.value {
+            ValueRef::U64(x) => IValueImpl::U64(x),
+            ValueRef::I64(x) => IValueImpl::I64(x),
+            ValueRef::F64(x) => IValueImpl::F64(Float64(OrderedFloat(x))),
+            _ => return Ok(()),
+        };
+        check_float(&value, self.options, f32)
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueRefDeserializer<'de> {
+    type Error = JsonError;
+
+    fn is_human_readable(&self) -> bool {
+        self.options.human_readable
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.check_float(true)?;
+        with_deserializer!(self, d => d.deserialize_f32(visitor))
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.check_float(false)?;
+        with_deserializer!(self, d => d.deserialize_f64(visitor))
+    }
+
+    forward_to_deserializer! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            ValueRef::String(_) => visitor.visit_some(self),
+            _ => with_deserializer!(self, d => d.deserialize_option(visitor)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            ValueRef::String(_) => visitor.visit_newtype_struct(self),
+            _ => with_deserializer!(self, d => d.deserialize_newtype_struct(name, visitor)),
+        }
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match (self.value, self.options.bytes) {
+            (ValueRef::String(s), BytesEncoding::Base64) => match base64::decode(s) {
+                Some(bytes) => visitor.visit_byte_buf(bytes),
+                None => Err(Error::invalid_value(Unexpected::Str(s), &"a base64 string")),
+            },
+            (ValueRef::String(s), BytesEncoding::Array) => {
+                visitor.visit_borrowed_bytes(s.as_bytes())
+            }
+            _ => with_deserializer!(self, d => d.deserialize_bytes(visitor)),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct User<'a> {
+        name: &'a str,
+        nick: Option<&'a str>,
+        age: u8,
+        role: Role,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Role {
+        Admin,
+        Guest { since: u32 },
+    }
+
+    #[test]
+    fn value_ref_deserializer() {
+        let interners = Jinterners::default();
+        let ivalue = interners.intern(json!({
+            "users": [
+                {"name": "John", "nick": "jj", "age": 42, "role": "Admin"},
+                {"name": "Jane", "nick": null, "age": 300, "role": {"Guest": {"since": 2020}}},
+            ]
+        }));
+        let ValueRef::Object(map) = interners.lookup_ref(&ivalue) else {
+            panic!("expected an object")
+        };
+        let ValueRef::Array(users) = interners.lookup_ref(map.get("users").unwrap()) else {
+            panic!("expected an array")
+        };
+
+        let john = interners.lookup_ref(users.get(0).unwrap());
+        assert_eq!(
+            User::deserialize(john.into_deserializer()).unwrap(),
+            User {
+                name: "John",
+                nick: Some("jj"),
+                age: 42,
+                role: Role::Admin,
+            }
+        );
+
+        // Errors in nested values report their path relative to the value.
+        let error = Vec::<User>::deserialize(ValueRef::Array(users).into_deserializer());
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "invalid value: integer `300`, expected u8 at /1/age"
+        );
+
+        // Scalars and strings can be deserialized too.
+        let ValueRef::Object(john) = john else {
+            panic!("expected an object")
+        };
+        let name = interners.lookup_ref(john.get("name").unwrap());
+        assert_eq!(
+            <&str>::deserialize(name.into_deserializer()).unwrap(),
+            "John"
+        );
+        assert_eq!(
+            Option::<&str>::deserialize(name.into_deserializer()).unwrap(),
+            Some("John")
+        );
+        assert_eq!(
+            Role::deserialize(ValueRef::String("Admin").into_deserializer()).unwrap(),
+            Role::Admin
+        );
+        assert_eq!(
+            u8::deserialize(ValueRef::U64(42).into_deserializer()).unwrap(),
+            42
+        );
+        assert!(u8::deserialize(ValueRef::I64(-1).into_deserializer()).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use super::{IValue, InternedStrKey};
+#[cfg(feature = "metrics")]
+use crate::ArenaKind;
+use crate::Jinterners;
+#[cfg(feature = "unicode-normalization")]
+use crate::StringNormalization;
+use blazinterner::{InternedSlice, InternedStr};
+use std::borrow::Cow;
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::{IsNormalized, UnicodeNormalization, is_nfc_quick};
+
+// All entries are added to the underlying arenas through these functions, so
+// that hooks are applied, metrics recorded and watchers notified in one place.
+impl Jinterners {
+    /// Applies the installed [`InternHook`](crate::InternHook) and the
+    /// configured [`StringNormalization`] to the given string.
+    fn normalize<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let value = self.intern_hook.rewrite_string(value);
+        #[cfg(feature = "unicode-normalization")]
+        if self.string_normalization == StringNormalization::Nfc
+            && is_nfc_quick(value.chars()) != IsNormalized::Yes
+        {
+            return Cow::Owned(value.nfc().collect());
+        }
+        value
+    }
+
+    /// Finds the given string in this arena, after normalizing it.
+    pub(crate) fn find_string(&self, value: &str) -> Option<InternedStr> {
+        let value = &*self.normalize(value);
+        if !self.string_filter.may_contain(value) {
+            return None;
+        }
+        self.string.find(value)
+    }
+
+    pub(crate) fn intern_string(&self, value: &str) -> InternedStr {
+        let value = &*self.normalize(value);
+        // The string is added to the filter before it's interned, so that any
+        // thread obtaining its id also finds it in the filter. Adding it again
+        // is harmless.
+        self.string_filter.insert(value);
+        #[cfg(feature = "metrics")]
+        let before = self.string.strings();
+        let id = self.string.intern(value);
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_intern(ArenaKind::String, before, id.id(), value.len());
+        id
+    }
+
+    pub(crate) fn intern_string_mut(&mut self, value: &str) -> InternedStr {
+        let value = &*self.normalize(value);
+        self.string_filter.insert(value);
+        #[cfg(feature = "metrics")]
+        let before = self.string.strings();
+        let id = self.string.intern_mut(value);
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_intern(ArenaKind::String, before, id.id(), value.len());
+        id
+    }
+
+    pub(crate) fn intern_array_slice(&self, values: &[IValue]) -> InternedSlice<IValue> {
+        #[cfg(feature = "metrics")]
+        let before = self.iarray.slices();
+        let id = self.iarray.intern_copy(values);
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_intern(ArenaKind::Array, before, id.id(), size_of_val(values));
+        id
+    }
+
+    pub(crate) fn intern_array_slice_mut(&mut self, values: &[IValue]) -> InternedSlice<IValue> {
+        #[cfg(feature = "metrics")]
+        let before = self.iarray.slices();
+        let id = self.iarray.intern_copy_mut(values);
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_intern(ArenaKind::Array, before, id.id(), size_of_val(values));
+        id
+    }
+
+    /// Interns the array yielded by the given iterator.
+    ///
+    /// # Safety
+    ///
+    /// The iterator's [`len()`](ExactSizeIterator::len) must match the number
+    /// of items it yields.
+    #[cfg(feature = "serde")]
+    pub(crate) unsafe fn intern_array_iter(
+        &self,
+        iter: impl ExactSizeIterator<Item = IValue> + Clone,
+    ) -> InternedSlice<IValue> {
+        #[cfg(feature = "metrics")]
+        let (before, bytes) = (self.iarray.slices(), iter.len() * size_of::<IValue>());
+        // SAFETY: The iterator length is trusted, as guaranteed by the caller.
+        let id = unsafe { self.iarray.intern_iter(iter) };
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_intern(ArenaKind::Array, before, id.id(), bytes);
+        id
+    }
+
+    /// Interns the array yielded by the given iterator.
+    ///
+    /// # Safety
+    ///
+    /// The iterator's [`len()`](ExactSizeIterator::len) must match the number
+    /// of items it yields.
+    #[cfg(feature = "serde")]
+    pub(crate) unsafe fn intern_array_iter_mut(
+        &mut self,
+        iter: impl ExactSizeIterator<Item = IValue> + Clone,
+    ) -> InternedSlice<IValue> {
+        #[cfg(feature = "metrics")]
+        let (before, bytes) = (self.iarray.slices(), iter.len() * size_of::<IValue>());
+        // SAFETY: The iterator length is trusted, as guaranteed by the caller.
+        let id = unsafe { self.iarray.intern_iter_mut(iter) };
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_intern(ArenaKind::Array, before, id.id(), bytes);
+        id
+    }
+
+    /// Interns the given object entries, which must already be sorted by key.
+    pub(crate) fn intern_object_slice(
+        &self,
+        entries: &[(InternedStrKey, IValue)],
+    ) -> InternedSlice<(InternedStrKey, IValue)> {
+        let entries = self.dedup_keys(entries);
+        let entries = self.intern_hook.rewrite_fields(self, entries);
+        #[cfg(feature = "metrics")]
+        let before = self.iobject.slices();
+        let id = self.iobject.intern_copy(&entries);
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_intern(ArenaKind::Object, before, id.id(), size_of_val(&*entries));
+        self.watchers.notify(self, id, &entries);
+        id
+    }
+
+    /// Interns the given object entries, which must already be sorted by key.
+    pub(crate) fn intern_object_slice_mut(
+        &mut self,
+        entries: &[(InternedStrKey, IValue)],
+    ) -> InternedSlice<(InternedStrKey, IValue)> {
+        let entries = self.dedup_keys(entries);
+        let entries = self.intern_hook.rewrite_fields(self, entries);
+        #[cfg(feature = "metrics")]
+        let before = self.iobject.slices();
+        let id = self.iobject.intern_copy_mut(&entries);
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_intern(ArenaKind::Object, before, id.id(), size_of_val(&*entries));
+        self.watchers.notify(self, id, &entries);
+        id
+    }
+
+    /// Finds the given object entries, which must already be sorted by key,
+    /// after applying the same rewrites as when interning them.
+    pub(super) fn find_object_slice(
+        &self,
+        entries: &[(InternedStrKey, IValue)],
+    ) -> Option<InternedSlice<(InternedStrKey, IValue)>> {
+        let entries = self.dedup_keys(entries);
+        let entries = self.intern_hook.rewrite_fields(self, entries);
+        self.iobject.find(&entries)
+    }
+
+    /// Removes entries with duplicate keys from the given sorted object
+    /// entries, which can only happen if distinct keys were normalized or
+    /// rewritten to the same string.
+    fn dedup_keys<'a>(
+        &self,
+        entries: &'a [(InternedStrKey, IValue)],
+    ) -> Cow<'a, [(InternedStrKey, IValue)]> {
+        let may_merge_keys = self.intern_hook.is_installed();
+        #[cfg(feature = "unicode-normalization")]
+        let may_merge_keys =
+            may_merge_keys || self.string_normalization != StringNormalization::Preserve;
+        if may_merge_keys && entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            let mut entries = entries.to_vec();
+            entries.dedup_by_key(|(k, _)| *k);
+            return Cow::Owned(entries);
+        }
+        Cow::Borrowed(entries)
+    }
+}
+
+#[cfg(all(test, feature = "unicode-normalization"))]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn string_normalization() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+
+        let interners = Jinterners::default();
+        let a = interners.intern(json!({ composed: 1 }));
+        let b = interners.intern(json!({ decomposed: 1 }));
+        assert_ne!(a, b);
+        assert!(interners.bind(b).get(composed).is_none());
+
+        let mut interners = Jinterners::default();
+        interners.set_string_normalization(StringNormalization::Nfc);
+        let a = interners.intern(json!({ composed: 1 }));
+        let b = interners.intern_mut(json!({ decomposed: 1 }));
+        assert_eq!(a, b);
+        assert_eq!(interners.lookup(&b), json!({ composed: 1 }));
+        let value = interners.bind(a).get(decomposed).map(|v| v.to_json());
+        assert_eq!(value, Some(json!(1)));
+        assert_eq!(interners.find_key(decomposed), interners.find_key(composed));
+
+        // Keys equal after normalization are merged.
+        let c = interners.intern(json!({ composed: 1, decomposed: 1 }));
+        assert_eq!(c, a);
+        assert_eq!(interners.validate(), Ok(()));
+    }
+}
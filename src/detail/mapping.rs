@@ -1,12 +1,80 @@
 use super::{IValue, IValueImpl, InternedStrKey};
-use blazinterner::ForwardMapping;
+use blazinterner::{ForwardMapping, InternedSlice, InternedStr};
 
 /// Mapping to convert values from one [`Jinterners`](crate::Jinterners)
 /// instance to another.
 pub struct Mapping {
-    pub(crate) string: ForwardMapping,
-    pub(crate) iarray: ForwardMapping,
-    pub(crate) iobject: ForwardMapping,
+    pub(crate) string: IdMap,
+    pub(crate) iarray: IdMap,
+    pub(crate) iobject: IdMap,
+}
+
+/// Conversion of the indices of one arena to another.
+pub(crate) enum IdMap {
+    /// A mapping computed when re-ordering or retaining the items of an arena.
+    Arena(ForwardMapping),
+    /// An explicit table of destination indices, for example to merge several
+    /// arenas into one.
+    Table(Box<[u32]>),
+}
+
+impl From<ForwardMapping> for IdMap {
+    fn from(mapping: ForwardMapping) -> Self {
+        IdMap::Arena(mapping)
+    }
+}
+
+impl IdMap {
+    fn is_identity(&self) -> bool {
+        match self {
+            IdMap::Arena(mapping) => mapping.is_identity(),
+            IdMap::Table(table) => table.iter().enumerate().all(|(i, j)| i == *j as usize),
+        }
+    }
+
+    /// Maps the given index.
+    pub(crate) fn at(&self, id: u32) -> u32 {
+        match self {
+            IdMap::Arena(mapping) => mapping.map_str(InternedStr::from_id(id)).id(),
+            IdMap::Table(table) => table[id as usize],
+        }
+    }
+
+    fn map_str(&self, s: InternedStr) -> InternedStr {
+        InternedStr::from_id(self.at(s.id()))
+    }
+
+    fn map_slice<T>(&self, s: InternedSlice<T>) -> InternedSlice<T> {
+        InternedSlice::from_id(self.at(s.id()))
+    }
+
+    /// Returns a mapping that applies this mapping followed by the other
+    /// mapping.
+    fn compose(self, other: IdMap) -> Self {
+        match (self, other) {
+            (IdMap::Arena(this), IdMap::Arena(other)) => IdMap::Arena(this.compose(other)),
+            (IdMap::Table(table), other) => {
+                IdMap::Table(table.iter().map(|id| other.at(*id)).collect())
+            }
+            // Tables are only produced by merges and alignments, whose mappings
+            // are never composed after another mapping.
+            (IdMap::Arena(_), IdMap::Table(_)) => {
+                unreachable!("a merge mapping can't follow another mapping")
+            }
+        }
+    }
+
+    #[cfg(feature = "debug")]
+    fn count_remapped(&self) -> usize {
+        match self {
+            IdMap::Arena(mapping) => mapping.count_remapped(),
+            IdMap::Table(table) => table
+                .iter()
+                .enumerate()
+                .filter(|(i, j)| *i != **j as usize)
+                .count(),
+        }
+    }
 }
 
 impl Mapping {
@@ -15,6 +83,20 @@ impl Mapping {
     pub(crate) fn compose(self, other: MappingNoStrings) -> Self {
         Self {
             string: self.string,
+            iarray: self.iarray.compose(other.iarray.into()),
+            iobject: self.iobject.compose(other.iobject.into()),
+        }
+    }
+
+    /// Returns a mapping that applies this mapping followed by the other
+    /// mapping.
+    ///
+    /// This mapping must not remove any item, i.e. its source and destination
+    /// must have the same number of items.
+    #[cfg(feature = "retain")]
+    pub(crate) fn then(self, other: Mapping) -> Self {
+        Self {
+            string: self.string.compose(other.string),
             iarray: self.iarray.compose(other.iarray),
             iobject: self.iobject.compose(other.iobject),
         }
@@ -43,7 +125,10 @@ impl Mapping {
         self.iobject.count_remapped()
     }
 
-    pub(crate) fn map_str_key(&self, s: InternedStrKey) -> InternedStrKey {
+    /// Maps the given object key from the source
+    /// [`Jinterners`](crate::Jinterners) to the destination
+    /// [`Jinterners`](crate::Jinterners) of this mapping.
+    pub fn map_str_key(&self, s: InternedStrKey) -> InternedStrKey {
         InternedStrKey(self.string.map_str(s.0))
     }
 
@@ -61,6 +146,19 @@ impl Mapping {
             IValueImpl::Object(x) => IValueImpl::Object(self.iobject.map_slice(x)),
         })
     }
+
+    /// Same as [`map()`](Self::map), but returns [`None`] if the value was
+    /// removed from the destination arena.
+    pub(crate) fn try_map(&self, v: IValue) -> Option<IValue> {
+        let mapped = self.map(v);
+        let removed = match mapped.0 {
+            IValueImpl::String(x) => x.id() == u32::MAX,
+            IValueImpl::Array(x) => x.id() == u32::MAX,
+            IValueImpl::Object(x) => x.id() == u32::MAX,
+            _ => false,
+        };
+        (!removed).then_some(mapped)
+    }
 }
 
 /// Mapping to convert values from one [`Jinterners`](crate::Jinterners)
@@ -72,9 +170,9 @@ pub(crate) struct MappingStrings {
 impl MappingStrings {
     pub fn promote(self, num_arrays: u32, num_objects: u32) -> Mapping {
         Mapping {
-            string: self.string,
-            iarray: ForwardMapping::identity(num_arrays),
-            iobject: ForwardMapping::identity(num_objects),
+            string: self.string.into(),
+            iarray: ForwardMapping::identity(num_arrays).into(),
+            iobject: ForwardMapping::identity(num_objects).into(),
         }
     }
 
@@ -113,9 +211,9 @@ pub(crate) struct MappingNoStrings {
 impl MappingNoStrings {
     pub fn promote(self, num_strings: u32) -> Mapping {
         Mapping {
-            string: ForwardMapping::identity(num_strings),
-            iarray: self.iarray,
-            iobject: self.iobject,
+            string: ForwardMapping::identity(num_strings).into(),
+            iarray: self.iarray.into(),
+            iobject: self.iobject.into(),
         }
     }
 
@@ -1,24 +1,57 @@
 #[cfg(feature = "serde")]
+mod base64;
+#[cfg(feature = "serde")]
+mod compare;
+mod content;
+#[cfg(feature = "serde")]
 mod de;
+mod intern;
 pub mod mapping;
 #[cfg(feature = "serde")]
+mod options;
+#[cfg(feature = "serde")]
+mod path;
+#[cfg(feature = "serde")]
+mod seed;
+#[cfg(feature = "serde")]
 mod ser;
+mod write;
 
 use super::Jinterners;
 #[cfg(feature = "retain")]
 use super::RetainBuilder;
-use blazinterner::{ArenaStr, InternedSlice, InternedStr};
+use blazinterner::{InternedSlice, InternedStr};
+#[cfg(feature = "serde")]
+pub(crate) use content::Content;
 #[cfg(feature = "serde")]
 use de::ValueDeserializer;
+#[cfg(feature = "serde")]
+pub use de::ValueRefDeserializer;
 #[cfg(feature = "get-size2")]
 use get_size2::GetSize;
+#[cfg(feature = "serde")]
+pub use options::{BytesEncoding, LossyNumbers, SerdeOptions};
 use ordered_float::OrderedFloat;
 #[cfg(feature = "serde")]
+use path::Path;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+pub(crate) use seed::deserialize_slice;
+#[cfg(feature = "serde")]
+pub use seed::{DuplicateKeys, InternedSeed};
+#[cfg(feature = "serde")]
 use ser::{ValueSerializer, ValueSerializerMut};
 #[cfg(feature = "serde")]
-use serde::{Deserialize, Serialize};
-use serde_json::{Number, Value};
+use serde::de::DeserializeSeed;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Number, Value};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt::Debug;
+pub use write::WriteOptions;
+pub(crate) use write::write_json_str;
 
 /// An interned key for JSON objects.
 ///
@@ -39,31 +72,176 @@ impl Default for InternedStrKey {
 #[derive(Default, Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "get-size2", derive(GetSize))]
-pub struct IValue(IValueImpl);
+pub struct IValue(pub(crate) IValueImpl);
 
 impl IValue {
     /// Interns the given [`serde_json::Value`] into the given [`Jinterners`]
     /// arena.
     pub(crate) fn from(interners: &Jinterners, source: Value) -> Self {
-        Self(IValueImpl::from(interners, source))
+        Self(IValueImpl::from(
+            interners,
+            &mut InternScratch::default(),
+            source,
+        ))
     }
 
     /// Interns the given [`serde_json::Value`] into the given [`Jinterners`]
     /// arena.
     pub(crate) fn from_ref(interners: &Jinterners, source: &Value) -> Self {
-        Self(IValueImpl::from_ref(interners, source))
+        Self(IValueImpl::from_ref(
+            interners,
+            &mut InternScratch::default(),
+            source,
+        ))
+    }
+
+    /// Interns the given slice as an array into the given [`Jinterners`]
+    /// arena.
+    pub(crate) fn from_array_ref(interners: &Jinterners, source: &[Value]) -> Self {
+        Self(IValueImpl::from_array_ref(
+            interners,
+            &mut InternScratch::default(),
+            source,
+        ))
+    }
+
+    /// Interns the given map as an object into the given [`Jinterners`]
+    /// arena.
+    pub(crate) fn from_object_ref(interners: &Jinterners, source: &Map<String, Value>) -> Self {
+        Self(IValueImpl::from_object_ref(
+            interners,
+            &mut InternScratch::default(),
+            source,
+        ))
+    }
+
+    /// Interns the given [`ijson::IValue`] into the given [`Jinterners`]
+    /// arena.
+    #[cfg(feature = "ijson")]
+    pub(crate) fn from_ijson(interners: &Jinterners, source: &ijson::IValue) -> Self {
+        Self(IValueImpl::from_ijson(
+            interners,
+            &mut InternScratch::default(),
+            source,
+        ))
+    }
+
+    /// Interns the given [`opentelemetry::Value`] into the given
+    /// [`Jinterners`] arena.
+    #[cfg(feature = "opentelemetry")]
+    pub(crate) fn from_otel(interners: &Jinterners, source: &opentelemetry::Value) -> Self {
+        Self(IValueImpl::from_otel(
+            interners,
+            &mut InternScratch::default(),
+            source,
+        ))
+    }
+
+    /// Interns the given [`opentelemetry::logs::AnyValue`] into the given
+    /// [`Jinterners`] arena.
+    #[cfg(feature = "opentelemetry")]
+    pub(crate) fn from_otel_any(
+        interners: &Jinterners,
+        source: &opentelemetry::logs::AnyValue,
+    ) -> Self {
+        Self(IValueImpl::from_otel_any(
+            interners,
+            &mut InternScratch::default(),
+            source,
+        ))
+    }
+
+    /// Interns the given span or resource attributes as an object into the
+    /// given [`Jinterners`] arena.
+    #[cfg(feature = "opentelemetry")]
+    pub(crate) fn from_otel_attributes<'a>(
+        interners: &Jinterners,
+        attributes: impl IntoIterator<Item = &'a opentelemetry::KeyValue>,
+    ) -> Self {
+        Self::from_otel_entries(
+            interners,
+            attributes.into_iter().map(|kv| (&kv.key, &kv.value)),
+            IValueImpl::from_otel,
+        )
+    }
+
+    /// Interns the given log record attributes as an object into the given
+    /// [`Jinterners`] arena.
+    #[cfg(feature = "opentelemetry")]
+    pub(crate) fn from_otel_log_attributes<'a>(
+        interners: &Jinterners,
+        attributes: impl IntoIterator<Item = &'a (opentelemetry::Key, opentelemetry::logs::AnyValue)>,
+    ) -> Self {
+        Self::from_otel_entries(
+            interners,
+            attributes.into_iter().map(|(k, v)| (k, v)),
+            IValueImpl::from_otel_any,
+        )
+    }
+
+    /// Interns the given OpenTelemetry attributes as an object, converting
+    /// each value with the given function.
+    ///
+    /// If a key is repeated, the last value wins.
+    #[cfg(feature = "opentelemetry")]
+    fn from_otel_entries<'a, V: 'a>(
+        interners: &Jinterners,
+        attributes: impl IntoIterator<Item = (&'a opentelemetry::Key, &'a V)>,
+        from_value: fn(&Jinterners, &mut InternScratch, &V) -> IValueImpl,
+    ) -> Self {
+        let mut scratch = InternScratch::default();
+        for (k, v) in attributes {
+            let k = InternedStrKey(interners.intern_string(k.as_str()));
+            let v = IValue(from_value(interners, &mut scratch, v));
+            scratch.object.push((k, v));
+        }
+        Self(IValueImpl::Object(
+            scratch.finish_object_last_wins(interners, 0),
+        ))
+    }
+
+    /// Finds the given [`serde_json::Value`] in the given [`Jinterners`] arena,
+    /// without interning anything.
+    pub(crate) fn find(interners: &Jinterners, source: &Value) -> Option<Self> {
+        IValueImpl::find(interners, source).map(Self)
     }
 
     /// Interns the given [`serde_json::Value`] into the given [`Jinterners`]
     /// arena.
     pub(crate) fn from_mut(interners: &mut Jinterners, source: Value) -> Self {
-        Self(IValueImpl::from_mut(interners, source))
+        Self(IValueImpl::from_mut(
+            interners,
+            &mut InternScratch::default(),
+            source,
+        ))
     }
 
     /// Interns the given [`serde_json::Value`] into the given [`Jinterners`]
     /// arena.
     pub(crate) fn from_ref_mut(interners: &mut Jinterners, source: &Value) -> Self {
-        Self(IValueImpl::from_ref_mut(interners, source))
+        Self(IValueImpl::from_ref_mut(
+            interners,
+            &mut InternScratch::default(),
+            source,
+        ))
+    }
+
+    /// Interns the given batch of [`serde_json::Value`]s into the given
+    /// [`Jinterners`] arena.
+    pub(crate) fn from_many_ref(interners: &Jinterners, sources: &[Value]) -> Vec<Self> {
+        let mut scratch = InternScratch::default();
+        let mut keys = HashMap::new();
+        sources
+            .iter()
+            .map(|source| {
+                Self(IValueImpl::from_ref_batch(
+                    interners,
+                    &mut scratch,
+                    &mut keys,
+                    source,
+                ))
+            })
+            .collect()
     }
 
     /// Retrieves the corresponding [`serde_json::Value`] inside the given
@@ -72,12 +250,50 @@ impl IValue {
         self.0.lookup(interners)
     }
 
+    /// Retrieves the corresponding [`serde_json::Value`] inside the given
+    /// [`Jinterners`] arena, materializing the top-level children in parallel.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn lookup_parallel(&self, interners: &Jinterners) -> Value {
+        self.0.lookup_parallel(interners)
+    }
+
     /// Performs a shallow lookup of this value inside the given [`Jinterners`]
     /// arena.
     pub(crate) fn lookup_ref<'a>(&self, interners: &'a Jinterners) -> ValueRef<'a> {
         self.0.lookup_ref(interners)
     }
 
+    /// Encodes this handle as a tag and a payload, independently of any arena.
+    pub(crate) fn to_raw_parts(self) -> (u64, u64) {
+        match self.0 {
+            IValueImpl::Null => (0, 0),
+            IValueImpl::Bool(x) => (1, x.into()),
+            IValueImpl::U64(x) => (2, x),
+            IValueImpl::I64(x) => (3, x as u64),
+            IValueImpl::F64(x) => (4, x.0.to_bits()),
+            IValueImpl::String(x) => (5, x.id().into()),
+            IValueImpl::Array(x) => (6, x.id().into()),
+            IValueImpl::Object(x) => (7, x.id().into()),
+        }
+    }
+
+    /// Decodes a handle encoded with [`to_raw_parts()`](Self::to_raw_parts),
+    /// or returns [`None`] if the encoding is invalid.
+    pub(crate) fn from_raw_parts(tag: u64, payload: u64) -> Option<Self> {
+        let id = || u32::try_from(payload).ok();
+        Some(IValue(match tag {
+            0 => IValueImpl::Null,
+            1 => IValueImpl::Bool(payload != 0),
+            2 => IValueImpl::U64(payload),
+            3 => IValueImpl::I64(payload as i64),
+            4 => IValueImpl::F64(Float64(OrderedFloat(f64::from_bits(payload)))),
+            5 => IValueImpl::String(InternedStr::from_id(id()?)),
+            6 => IValueImpl::Array(InternedSlice::from_id(id()?)),
+            7 => IValueImpl::Object(InternedSlice::from_id(id()?)),
+            _ => return None,
+        }))
+    }
+
     /// Convert an arbitrary type into an [`IValue`] using that type's
     /// [`Serialize`] implementation.
     ///
@@ -89,7 +305,29 @@ impl IValue {
     where
         T: Serialize,
     {
-        value.serialize(ValueSerializer { interners }).map(IValue)
+        Self::from_value_with_options(value, interners, SerdeOptions::default())
+    }
+
+    /// Convert an arbitrary type into an [`IValue`] using that type's
+    /// [`Serialize`] implementation and the given options.
+    #[cfg(feature = "serde")]
+    pub fn from_value_with_options<T>(
+        value: T,
+        interners: &Jinterners,
+        options: SerdeOptions,
+    ) -> Result<Self, serde_json::error::Error>
+    where
+        T: Serialize,
+    {
+        let path = Path::default();
+        value
+            .serialize(ValueSerializer {
+                interners,
+                options,
+                path: path.root(),
+            })
+            .map(IValue)
+            .map_err(|e| path.wrap_error(e, interners))
     }
 
     /// Convert an arbitrary type into an [`IValue`] using that type's
@@ -106,9 +344,60 @@ impl IValue {
     where
         T: Serialize,
     {
-        value
-            .serialize(ValueSerializerMut { interners })
-            .map(IValue)
+        Self::from_value_mut_with_options(value, interners, SerdeOptions::default())
+    }
+
+    /// Convert an arbitrary type into an [`IValue`] using that type's
+    /// [`Serialize`] implementation and the given options.
+    ///
+    /// Contrary to
+    /// [`from_value_with_options()`](Self::from_value_with_options),
+    /// no locks are held internally because this function already takes an
+    /// exclusive mutable reference to the [`Jinterners`] arena.
+    #[cfg(feature = "serde")]
+    pub fn from_value_mut_with_options<T>(
+        value: T,
+        interners: &mut Jinterners,
+        options: SerdeOptions,
+    ) -> Result<Self, serde_json::error::Error>
+    where
+        T: Serialize,
+    {
+        let path = Path::default();
+        let result = value
+            .serialize(ValueSerializerMut {
+                interners,
+                options,
+                path: path.root(),
+            })
+            .map(IValue);
+        result.map_err(|e| path.wrap_error(e, interners))
+    }
+
+    /// Interns a value read from an arbitrary self-describing [`Deserializer`],
+    /// without materializing an intermediate [`serde_json::Value`].
+    ///
+    /// This is a shorthand for deserializing with an [`InternedSeed`], and
+    /// allows to intern values from formats such as CBOR, MessagePack or YAML.
+    ///
+    /// ```
+    /// use jinterner::{IValue, Jinterners};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let mut deserializer = serde_json::Deserializer::from_str(r#"[1, {"a": null}]"#);
+    /// let ivalue = IValue::from_deserializer(&mut deserializer, &interners).unwrap();
+    /// assert_eq!(interners.lookup(&ivalue), json!([1, {"a": null}]));
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn from_deserializer<'de, D>(
+        deserializer: D,
+        interners: &Jinterners,
+    ) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        InternedSeed::new(interners).deserialize(deserializer)
     }
 
     /// Convert an [`IValue`] into an arbitrary type using that type's
@@ -121,10 +410,28 @@ impl IValue {
     where
         T: Deserialize<'de>,
     {
+        self.to_value_with_options(interners, SerdeOptions::default())
+    }
+
+    /// Convert an [`IValue`] into an arbitrary type using that type's
+    /// [`Deserialize`] implementation and the given options.
+    #[cfg(feature = "serde")]
+    pub fn to_value_with_options<'de, T>(
+        &self,
+        interners: &'de Jinterners,
+        options: SerdeOptions,
+    ) -> Result<T, serde_json::error::Error>
+    where
+        T: Deserialize<'de>,
+    {
+        let path = Path::default();
         T::deserialize(ValueDeserializer {
             value: &self.0,
             interners,
+            options,
+            path: path.root(),
         })
+        .map_err(|e| path.wrap_error(e, interners))
     }
 
     #[cfg(feature = "retain")]
@@ -158,7 +465,7 @@ impl IValue {
 
 #[derive(Default, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-struct Float64(OrderedFloat<f64>);
+pub(crate) struct Float64(pub(crate) OrderedFloat<f64>);
 
 impl Debug for Float64 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -166,6 +473,70 @@ impl Debug for Float64 {
     }
 }
 
+impl Float64 {
+    /// Applies the given policy to a float, returning [`None`] if it's
+    /// rejected.
+    pub(crate) fn new(x: f64, policy: FloatPolicy) -> Option<Self> {
+        let x = match policy {
+            FloatPolicy::Preserve => x,
+            _ if x == 0.0 => 0.0,
+            FloatPolicy::Canonicalize => x,
+            FloatPolicy::Reject if !x.is_finite() => return None,
+            FloatPolicy::Reject => x,
+        };
+        Some(Float64(OrderedFloat(x)))
+    }
+}
+
+/// The policy applied to floating-point numbers when interning them.
+///
+/// Floats are compared with [`OrderedFloat`] semantics, so `-0.0` and `0.0`
+/// are equal while being distinct JSON values. With the default
+/// [`Preserve`](Self::Preserve) policy, an array containing one of them may
+/// therefore be deduplicated with an array containing the other, depending on
+/// which one was interned first.
+///
+/// This is set with [`Jinterners::set_float_policy()`].
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum FloatPolicy {
+    /// Floats are stored as is.
+    #[default]
+    Preserve,
+    /// Negative zero is stored as positive zero.
+    ///
+    /// As with [`Preserve`](Self::Preserve), NaN and infinite floats aren't
+    /// valid JSON and are looked up as null.
+    Canonicalize,
+    /// Negative zero is stored as positive zero, and NaN or infinite floats are
+    /// rejected with an error where the API is fallible.
+    ///
+    /// Note that [`serde_json::Value`]s never contain such floats.
+    Reject,
+}
+
+/// The Unicode normalization applied to strings and object keys when
+/// interning them.
+///
+/// Without normalization, strings that render identically but use different
+/// code point sequences (for example a precomposed `é` versus an `e` followed
+/// by a combining accent) are interned separately, so looking up an object key
+/// only succeeds with the same sequence as the producer of the value.
+///
+/// This is set with [`Jinterners::set_string_normalization()`].
+#[cfg(feature = "unicode-normalization")]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum StringNormalization {
+    /// Strings are stored as is.
+    #[default]
+    Preserve,
+    /// Strings are stored in Normalization Form C (canonical composition), and
+    /// queried keys are normalized the same way.
+    ///
+    /// If several keys of an object are equal after normalization, only one
+    /// of their values is kept.
+    Nfc,
+}
+
 #[cfg(feature = "get-size2")]
 impl GetSize for Float64 {
     // There is nothing on the heap, so the default implementation works out of the
@@ -175,7 +546,7 @@ impl GetSize for Float64 {
 #[derive(Default, Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "get-size2", derive(GetSize))]
-enum IValueImpl {
+pub(crate) enum IValueImpl {
     #[default]
     Null,
     Bool(bool),
@@ -187,151 +558,413 @@ enum IValueImpl {
     Object(InternedSlice<(InternedStrKey, IValue)>),
 }
 
+/// Scratch buffers shared across a recursive interning call.
+///
+/// Children of arrays and objects are pushed on these stacks and the resulting
+/// slice is interned directly from there, instead of collecting each container
+/// into its own temporary allocation.
+#[derive(Default)]
+pub(crate) struct InternScratch {
+    array: Vec<IValue>,
+    object: Vec<(InternedStrKey, IValue)>,
+}
+
+impl InternScratch {
+    /// Interns the array items pushed since `start`, and pops them.
+    fn finish_array(&mut self, interners: &Jinterners, start: usize) -> InternedSlice<IValue> {
+        let id = interners.intern_array_slice(&self.array[start..]);
+        self.array.truncate(start);
+        id
+    }
+
+    /// Interns the array items pushed since `start`, and pops them.
+    fn finish_array_mut(
+        &mut self,
+        interners: &mut Jinterners,
+        start: usize,
+    ) -> InternedSlice<IValue> {
+        let id = interners.intern_array_slice_mut(&self.array[start..]);
+        self.array.truncate(start);
+        id
+    }
+
+    /// Sorts and interns the object entries pushed since `start`, and pops
+    /// them.
+    fn finish_object(
+        &mut self,
+        interners: &Jinterners,
+        start: usize,
+    ) -> InternedSlice<(InternedStrKey, IValue)> {
+        let entries = &mut self.object[start..];
+        entries.sort_unstable_by_key(|(k, _)| *k);
+        let id = interners.intern_object_slice(entries);
+        self.object.truncate(start);
+        id
+    }
+
+    /// Same as [`finish_object()`](Self::finish_object), but only keeps the
+    /// last entry pushed for each key.
+    #[cfg(feature = "opentelemetry")]
+    fn finish_object_last_wins(
+        &mut self,
+        interners: &Jinterners,
+        start: usize,
+    ) -> InternedSlice<(InternedStrKey, IValue)> {
+        let entries = &mut self.object[start..];
+        // A stable sort keeps the entries of each key in insertion order.
+        entries.sort_by_key(|(k, _)| *k);
+        let mut len = 0;
+        for i in 0..entries.len() {
+            if entries.get(i + 1).is_none_or(|next| next.0 != entries[i].0) {
+                entries[len] = entries[i];
+                len += 1;
+            }
+        }
+        let id = interners.intern_object_slice(&entries[..len]);
+        self.object.truncate(start);
+        id
+    }
+
+    /// Sorts and interns the object entries pushed since `start`, and pops
+    /// them.
+    fn finish_object_mut(
+        &mut self,
+        interners: &mut Jinterners,
+        start: usize,
+    ) -> InternedSlice<(InternedStrKey, IValue)> {
+        let entries = &mut self.object[start..];
+        entries.sort_unstable_by_key(|(k, _)| *k);
+        let id = interners.intern_object_slice_mut(entries);
+        self.object.truncate(start);
+        id
+    }
+}
+
 impl IValueImpl {
-    fn from(interners: &Jinterners, source: Value) -> Self {
+    fn from_number(x: &Number, policy: FloatPolicy) -> Self {
+        if x.is_u64() {
+            IValueImpl::U64(x.as_u64().unwrap())
+        } else if x.is_i64() {
+            IValueImpl::I64(x.as_i64().unwrap())
+        } else {
+            // Numbers are always finite, so no policy rejects them.
+            IValueImpl::F64(Float64::new(x.as_f64().unwrap(), policy).unwrap())
+        }
+    }
+
+    fn from(interners: &Jinterners, scratch: &mut InternScratch, source: Value) -> Self {
         match source {
             Value::Null => IValueImpl::Null,
             Value::Bool(x) => IValueImpl::Bool(x),
-            Value::Number(x) => {
-                if x.is_u64() {
-                    IValueImpl::U64(x.as_u64().unwrap())
-                } else if x.is_i64() {
-                    IValueImpl::I64(x.as_i64().unwrap())
-                } else {
-                    IValueImpl::F64(Float64(OrderedFloat(x.as_f64().unwrap())))
+            Value::Number(x) => Self::from_number(&x, interners.float_policy),
+            Value::String(s) => IValueImpl::String(interners.intern_string(&s)),
+            Value::Array(a) => {
+                let start = scratch.array.len();
+                for v in a {
+                    let v = IValue(Self::from(interners, scratch, v));
+                    scratch.array.push(v);
                 }
+                IValueImpl::Array(scratch.finish_array(interners, start))
             }
-            Value::String(s) => IValueImpl::String(interners.string.intern(&s)),
-            Value::Array(a) => IValueImpl::Array(
-                interners.iarray.intern_copy(
-                    &a.into_iter()
-                        .map(|v| interners.intern(v))
-                        .collect::<Box<[_]>>(),
-                ),
-            ),
             Value::Object(o) => {
-                let mut io: Box<[_]> = o
-                    .into_iter()
-                    .map(|(k, v)| {
-                        (
-                            InternedStrKey(interners.string.intern(&k)),
-                            interners.intern(v),
-                        )
-                    })
-                    .collect();
-                io.sort_unstable_by_key(|(k, _)| *k);
-                IValueImpl::Object(interners.iobject.intern_copy(&io))
+                let start = scratch.object.len();
+                for (k, v) in o {
+                    let k = InternedStrKey(interners.intern_string(&k));
+                    let v = IValue(Self::from(interners, scratch, v));
+                    scratch.object.push((k, v));
+                }
+                IValueImpl::Object(scratch.finish_object(interners, start))
             }
         }
     }
 
-    fn from_ref(interners: &Jinterners, source: &Value) -> Self {
+    fn from_ref(interners: &Jinterners, scratch: &mut InternScratch, source: &Value) -> Self {
         match source {
             Value::Null => IValueImpl::Null,
             Value::Bool(x) => IValueImpl::Bool(*x),
-            Value::Number(x) => {
-                if x.is_u64() {
-                    IValueImpl::U64(x.as_u64().unwrap())
-                } else if x.is_i64() {
-                    IValueImpl::I64(x.as_i64().unwrap())
-                } else {
-                    IValueImpl::F64(Float64(OrderedFloat(x.as_f64().unwrap())))
+            Value::Number(x) => Self::from_number(x, interners.float_policy),
+            Value::String(s) => IValueImpl::String(interners.intern_string(s.as_str())),
+            Value::Array(a) => Self::from_array_ref(interners, scratch, a),
+            Value::Object(o) => Self::from_object_ref(interners, scratch, o),
+        }
+    }
+
+    fn from_array_ref(
+        interners: &Jinterners,
+        scratch: &mut InternScratch,
+        source: &[Value],
+    ) -> Self {
+        let start = scratch.array.len();
+        for v in source {
+            let v = IValue(Self::from_ref(interners, scratch, v));
+            scratch.array.push(v);
+        }
+        IValueImpl::Array(scratch.finish_array(interners, start))
+    }
+
+    fn from_object_ref(
+        interners: &Jinterners,
+        scratch: &mut InternScratch,
+        source: &Map<String, Value>,
+    ) -> Self {
+        let start = scratch.object.len();
+        for (k, v) in source {
+            let k = InternedStrKey(interners.intern_string(k.as_str()));
+            let v = IValue(Self::from_ref(interners, scratch, v));
+            scratch.object.push((k, v));
+        }
+        IValueImpl::Object(scratch.finish_object(interners, start))
+    }
+
+    #[cfg(feature = "ijson")]
+    fn from_ijson(
+        interners: &Jinterners,
+        scratch: &mut InternScratch,
+        source: &ijson::IValue,
+    ) -> Self {
+        use ijson::DestructuredRef;
+        match source.destructure_ref() {
+            DestructuredRef::Null => IValueImpl::Null,
+            DestructuredRef::Bool(x) => IValueImpl::Bool(x),
+            DestructuredRef::Number(x) => match (x.has_decimal_point(), x.to_u64(), x.to_i64()) {
+                (false, Some(x), _) => IValueImpl::U64(x),
+                (false, None, Some(x)) => IValueImpl::I64(x),
+                // Numbers are always finite, even when rounded, so no policy
+                // rejects them.
+                _ => {
+                    IValueImpl::F64(Float64::new(x.to_f64_lossy(), interners.float_policy).unwrap())
+                }
+            },
+            DestructuredRef::String(s) => IValueImpl::String(interners.intern_string(s.as_str())),
+            DestructuredRef::Array(a) => {
+                let start = scratch.array.len();
+                for v in a {
+                    let v = IValue(Self::from_ijson(interners, scratch, v));
+                    scratch.array.push(v);
                 }
+                IValueImpl::Array(scratch.finish_array(interners, start))
             }
-            Value::String(s) => IValueImpl::String(interners.string.intern(s.as_str())),
-            Value::Array(a) => IValueImpl::Array(
-                interners.iarray.intern_copy(
-                    &a.iter()
-                        .map(|v| interners.intern_ref(v))
-                        .collect::<Box<[_]>>(),
-                ),
-            ),
-            Value::Object(o) => {
-                let mut io: Box<[_]> = o
-                    .iter()
-                    .map(|(k, v)| {
-                        (
-                            InternedStrKey(interners.string.intern(k.as_str())),
-                            interners.intern_ref(v),
-                        )
-                    })
-                    .collect();
-                io.sort_unstable_by_key(|(k, _)| *k);
-                IValueImpl::Object(interners.iobject.intern_copy(&io))
+            DestructuredRef::Object(o) => {
+                let start = scratch.object.len();
+                for (k, v) in o {
+                    let k = InternedStrKey(interners.intern_string(k.as_str()));
+                    let v = IValue(Self::from_ijson(interners, scratch, v));
+                    scratch.object.push((k, v));
+                }
+                IValueImpl::Object(scratch.finish_object(interners, start))
             }
         }
     }
 
-    fn from_mut(interners: &mut Jinterners, source: Value) -> Self {
+    #[cfg(feature = "opentelemetry")]
+    fn from_otel_int(x: i64) -> Self {
+        u64::try_from(x).map_or(IValueImpl::I64(x), IValueImpl::U64)
+    }
+
+    /// Non-finite floats rejected by the policy are interned as null, like
+    /// [`serde_json`] does.
+    #[cfg(feature = "opentelemetry")]
+    fn from_otel_float(interners: &Jinterners, x: f64) -> Self {
+        Float64::new(x, interners.float_policy).map_or(IValueImpl::Null, IValueImpl::F64)
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    fn from_otel_array<T>(
+        interners: &Jinterners,
+        scratch: &mut InternScratch,
+        items: &[T],
+        from_item: impl Fn(&Jinterners, &mut InternScratch, &T) -> Self,
+    ) -> Self {
+        let start = scratch.array.len();
+        for item in items {
+            let v = IValue(from_item(interners, scratch, item));
+            scratch.array.push(v);
+        }
+        IValueImpl::Array(scratch.finish_array(interners, start))
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    fn from_otel(
+        interners: &Jinterners,
+        scratch: &mut InternScratch,
+        source: &opentelemetry::Value,
+    ) -> Self {
+        use opentelemetry::{Array, StringValue, Value};
+        let string = |interners: &Jinterners, _: &mut InternScratch, s: &StringValue| {
+            IValueImpl::String(interners.intern_string(s.as_str()))
+        };
         match source {
-            Value::Null => IValueImpl::Null,
-            Value::Bool(x) => IValueImpl::Bool(x),
-            Value::Number(x) => {
-                if x.is_u64() {
-                    IValueImpl::U64(x.as_u64().unwrap())
-                } else if x.is_i64() {
-                    IValueImpl::I64(x.as_i64().unwrap())
-                } else {
-                    IValueImpl::F64(Float64(OrderedFloat(x.as_f64().unwrap())))
-                }
+            Value::Bool(x) => IValueImpl::Bool(*x),
+            Value::I64(x) => Self::from_otel_int(*x),
+            Value::F64(x) => Self::from_otel_float(interners, *x),
+            Value::String(s) => string(interners, scratch, s),
+            Value::Array(Array::Bool(a)) => {
+                Self::from_otel_array(interners, scratch, a, |_, _, x| IValueImpl::Bool(*x))
             }
-            Value::String(s) => IValueImpl::String(interners.string.intern_mut(&s)),
-            Value::Array(a) => {
-                let a = a
-                    .into_iter()
-                    .map(|v| interners.intern_mut(v))
-                    .collect::<Box<[_]>>();
-                IValueImpl::Array(interners.iarray.intern_copy_mut(&a))
+            Value::Array(Array::I64(a)) => {
+                Self::from_otel_array(interners, scratch, a, |_, _, x| Self::from_otel_int(*x))
             }
-            Value::Object(o) => {
-                let mut io: Box<[_]> = o
-                    .into_iter()
-                    .map(|(k, v)| {
-                        (
-                            InternedStrKey(interners.string.intern_mut(&k)),
-                            interners.intern(v),
-                        )
-                    })
-                    .collect();
-                io.sort_unstable_by_key(|(k, _)| *k);
-                IValueImpl::Object(interners.iobject.intern_copy_mut(&io))
+            Value::Array(Array::F64(a)) => {
+                Self::from_otel_array(interners, scratch, a, |i, _, x| {
+                    Self::from_otel_float(i, *x)
+                })
             }
+            Value::Array(Array::String(a)) => Self::from_otel_array(interners, scratch, a, string),
+            // Variants added in future versions have no known representation.
+            _ => IValueImpl::Null,
         }
     }
 
-    fn from_ref_mut(interners: &mut Jinterners, source: &Value) -> Self {
+    #[cfg(feature = "opentelemetry")]
+    fn from_otel_any(
+        interners: &Jinterners,
+        scratch: &mut InternScratch,
+        source: &opentelemetry::logs::AnyValue,
+    ) -> Self {
+        use opentelemetry::logs::AnyValue;
+        match source {
+            AnyValue::Int(x) => Self::from_otel_int(*x),
+            AnyValue::Double(x) => Self::from_otel_float(interners, *x),
+            AnyValue::String(s) => IValueImpl::String(interners.intern_string(s.as_str())),
+            AnyValue::Boolean(x) => IValueImpl::Bool(*x),
+            AnyValue::Bytes(bytes) => {
+                Self::from_otel_array(interners, scratch, bytes, |_, _, x| {
+                    IValueImpl::U64((*x).into())
+                })
+            }
+            AnyValue::ListAny(items) => {
+                Self::from_otel_array(interners, scratch, items, Self::from_otel_any)
+            }
+            AnyValue::Map(map) => {
+                let start = scratch.object.len();
+                for (k, v) in map.iter() {
+                    let k = InternedStrKey(interners.intern_string(k.as_str()));
+                    let v = IValue(Self::from_otel_any(interners, scratch, v));
+                    scratch.object.push((k, v));
+                }
+                IValueImpl::Object(scratch.finish_object(interners, start))
+            }
+            // Variants added in future versions have no known representation.
+            _ => IValueImpl::Null,
+        }
+    }
+
+    /// Same as [`from_ref()`](Self::from_ref), but memoizes object keys in
+    /// the given map, as they are typically repeated across a batch of values.
+    fn from_ref_batch<'a>(
+        interners: &Jinterners,
+        scratch: &mut InternScratch,
+        keys: &mut HashMap<&'a str, InternedStrKey>,
+        source: &'a Value,
+    ) -> Self {
         match source {
             Value::Null => IValueImpl::Null,
             Value::Bool(x) => IValueImpl::Bool(*x),
-            Value::Number(x) => {
-                if x.is_u64() {
-                    IValueImpl::U64(x.as_u64().unwrap())
-                } else if x.is_i64() {
-                    IValueImpl::I64(x.as_i64().unwrap())
-                } else {
-                    IValueImpl::F64(Float64(OrderedFloat(x.as_f64().unwrap())))
+            Value::Number(x) => Self::from_number(x, interners.float_policy),
+            Value::String(s) => IValueImpl::String(interners.intern_string(s.as_str())),
+            Value::Array(a) => {
+                let start = scratch.array.len();
+                for v in a {
+                    let v = IValue(Self::from_ref_batch(interners, scratch, keys, v));
+                    scratch.array.push(v);
+                }
+                IValueImpl::Array(scratch.finish_array(interners, start))
+            }
+            Value::Object(o) => {
+                let start = scratch.object.len();
+                for (k, v) in o {
+                    let k = *keys
+                        .entry(k.as_str())
+                        .or_insert_with(|| InternedStrKey(interners.intern_string(k.as_str())));
+                    let v = IValue(Self::from_ref_batch(interners, scratch, keys, v));
+                    scratch.object.push((k, v));
                 }
+                IValueImpl::Object(scratch.finish_object(interners, start))
             }
-            Value::String(s) => IValueImpl::String(interners.string.intern_mut(s.as_str())),
+        }
+    }
+
+    fn find(interners: &Jinterners, source: &Value) -> Option<Self> {
+        Some(match source {
+            Value::Null => IValueImpl::Null,
+            Value::Bool(x) => IValueImpl::Bool(*x),
+            Value::Number(x) => Self::from_number(x, interners.float_policy),
+            Value::String(s) => IValueImpl::String(interners.find_string(s)?),
             Value::Array(a) => {
-                let a = a
+                let array = a
                     .iter()
-                    .map(|v| interners.intern_ref_mut(v))
-                    .collect::<Box<[_]>>();
-                IValueImpl::Array(interners.iarray.intern_copy_mut(&a))
+                    .map(|v| IValue::find(interners, v))
+                    .collect::<Option<Vec<_>>>()?;
+                IValueImpl::Array(interners.iarray.find(&array)?)
             }
             Value::Object(o) => {
-                let mut io: Box<[_]> = o
+                let mut object = o
                     .iter()
                     .map(|(k, v)| {
-                        (
-                            InternedStrKey(interners.string.intern_mut(k.as_str())),
-                            interners.intern_ref_mut(v),
-                        )
+                        Some((
+                            InternedStrKey(interners.find_string(k)?),
+                            IValue::find(interners, v)?,
+                        ))
                     })
-                    .collect();
-                io.sort_unstable_by_key(|(k, _)| *k);
-                IValueImpl::Object(interners.iobject.intern_copy_mut(&io))
+                    .collect::<Option<Vec<_>>>()?;
+                object.sort_unstable_by_key(|(k, _)| *k);
+                IValueImpl::Object(interners.find_object_slice(&object)?)
+            }
+        })
+    }
+
+    fn from_mut(interners: &mut Jinterners, scratch: &mut InternScratch, source: Value) -> Self {
+        match source {
+            Value::Null => IValueImpl::Null,
+            Value::Bool(x) => IValueImpl::Bool(x),
+            Value::Number(x) => Self::from_number(&x, interners.float_policy),
+            Value::String(s) => IValueImpl::String(interners.intern_string_mut(&s)),
+            Value::Array(a) => {
+                let start = scratch.array.len();
+                for v in a {
+                    let v = IValue(Self::from_mut(interners, scratch, v));
+                    scratch.array.push(v);
+                }
+                IValueImpl::Array(scratch.finish_array_mut(interners, start))
+            }
+            Value::Object(o) => {
+                let start = scratch.object.len();
+                for (k, v) in o {
+                    let k = InternedStrKey(interners.intern_string_mut(&k));
+                    let v = IValue(Self::from_mut(interners, scratch, v));
+                    scratch.object.push((k, v));
+                }
+                IValueImpl::Object(scratch.finish_object_mut(interners, start))
+            }
+        }
+    }
+
+    fn from_ref_mut(
+        interners: &mut Jinterners,
+        scratch: &mut InternScratch,
+        source: &Value,
+    ) -> Self {
+        match source {
+            Value::Null => IValueImpl::Null,
+            Value::Bool(x) => IValueImpl::Bool(*x),
+            Value::Number(x) => Self::from_number(x, interners.float_policy),
+            Value::String(s) => IValueImpl::String(interners.intern_string_mut(s.as_str())),
+            Value::Array(a) => {
+                let start = scratch.array.len();
+                for v in a {
+                    let v = IValue(Self::from_ref_mut(interners, scratch, v));
+                    scratch.array.push(v);
+                }
+                IValueImpl::Array(scratch.finish_array_mut(interners, start))
+            }
+            Value::Object(o) => {
+                let start = scratch.object.len();
+                for (k, v) in o {
+                    let k = InternedStrKey(interners.intern_string_mut(k.as_str()));
+                    let v = IValue(Self::from_ref_mut(interners, scratch, v));
+                    scratch.object.push((k, v));
+                }
+                IValueImpl::Object(scratch.finish_object_mut(interners, start))
             }
         }
     }
@@ -342,8 +975,10 @@ impl IValueImpl {
             IValueImpl::Bool(x) => Value::Bool(*x),
             IValueImpl::U64(x) => Value::Number(Number::from_u128(*x as u128).unwrap()),
             IValueImpl::I64(x) => Value::Number(Number::from_i128(*x as i128).unwrap()),
+            // Non-finite floats can't be represented in JSON, serde_json converts
+            // them to null.
             IValueImpl::F64(Float64(OrderedFloat(x))) => {
-                Value::Number(Number::from_f64(*x).unwrap())
+                Number::from_f64(*x).map_or(Value::Null, Value::Number)
             }
             IValueImpl::String(s) => Value::String(interners.string.lookup(*s).into()),
             IValueImpl::Array(a) => Value::Array(
@@ -351,7 +986,7 @@ impl IValueImpl {
                     .iarray
                     .lookup(*a)
                     .iter()
-                    .map(|v| interners.lookup(v))
+                    .map(|v| v.lookup(interners))
                     .collect(),
             ),
             IValueImpl::Object(o) => Value::Object(
@@ -359,12 +994,36 @@ impl IValueImpl {
                     .iobject
                     .lookup(*o)
                     .iter()
-                    .map(|(k, v)| (interners.string.lookup(k.0).into(), interners.lookup(v)))
+                    .map(|(k, v)| (interners.string.lookup(k.0).into(), v.lookup(interners)))
                     .collect(),
             ),
         }
     }
 
+    #[cfg(feature = "rayon")]
+    fn lookup_parallel(&self, interners: &Jinterners) -> Value {
+        match self {
+            IValueImpl::Array(a) => Value::Array(
+                interners
+                    .iarray
+                    .lookup(*a)
+                    .par_iter()
+                    .map(|v| v.lookup(interners))
+                    .collect(),
+            ),
+            IValueImpl::Object(o) => {
+                let entries: Vec<(String, Value)> = interners
+                    .iobject
+                    .lookup(*o)
+                    .par_iter()
+                    .map(|(k, v)| (interners.string.lookup(k.0).into(), v.lookup(interners)))
+                    .collect();
+                Value::Object(entries.into_iter().collect())
+            }
+            _ => self.lookup(interners),
+        }
+    }
+
     fn lookup_ref<'a>(&self, interners: &'a Jinterners) -> ValueRef<'a> {
         match self {
             IValueImpl::Null => ValueRef::Null,
@@ -373,9 +1032,14 @@ impl IValueImpl {
             IValueImpl::I64(x) => ValueRef::I64(*x),
             IValueImpl::F64(Float64(OrderedFloat(x))) => ValueRef::F64(*x),
             IValueImpl::String(s) => ValueRef::String(interners.string.lookup(*s)),
-            IValueImpl::Array(a) => ValueRef::Array(interners.iarray.lookup(*a)),
+            IValueImpl::Array(a) => ValueRef::Array(ArrayRef {
+                interners,
+                handle: *a,
+                array: interners.iarray.lookup(*a),
+            }),
             IValueImpl::Object(o) => ValueRef::Object(MapRef {
-                arena_str: &interners.string,
+                interners,
+                handle: *o,
                 map: interners.iobject.lookup(*o),
             }),
         }
@@ -383,6 +1047,7 @@ impl IValueImpl {
 }
 
 /// A shallow reference to a JSON value.
+#[derive(Clone, Copy)]
 pub enum ValueRef<'a> {
     /// JSON null value.
     Null,
@@ -397,14 +1062,169 @@ pub enum ValueRef<'a> {
     /// JSON string.
     String(&'a str),
     /// JSON array.
-    Array(&'a [IValue]),
+    Array(ArrayRef<'a>),
     /// JSON object.
     Object(MapRef<'a>),
 }
 
+impl ValueRef<'_> {
+    /// Returns the elements of this value as [`f64`]s if it's an array of
+    /// numbers, or [`None`] otherwise.
+    ///
+    /// See [`ArrayRef::as_f64_vec()`].
+    pub fn as_f64_vec(&self) -> Option<Vec<f64>> {
+        match self {
+            ValueRef::Array(array) => array.as_f64_vec(),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of this value as [`i64`]s if it's an array of
+    /// integers that fit in an [`i64`], or [`None`] otherwise.
+    ///
+    /// See [`ArrayRef::as_i64_vec()`].
+    pub fn as_i64_vec(&self) -> Option<Vec<i64>> {
+        match self {
+            ValueRef::Array(array) => array.as_i64_vec(),
+            _ => None,
+        }
+    }
+}
+
+/// The kind of a JSON value.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum ValueKind {
+    /// JSON null value.
+    Null,
+    /// JSON boolean value.
+    Bool,
+    /// JSON number.
+    Number,
+    /// JSON string.
+    String,
+    /// JSON array.
+    Array,
+    /// JSON object.
+    Object,
+}
+
+impl std::fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ValueKind::Null => "null",
+            ValueKind::Bool => "boolean",
+            ValueKind::Number => "number",
+            ValueKind::String => "string",
+            ValueKind::Array => "array",
+            ValueKind::Object => "object",
+        })
+    }
+}
+
+impl IValue {
+    /// Returns the kind of this value.
+    pub fn kind(&self) -> ValueKind {
+        match self.0 {
+            IValueImpl::Null => ValueKind::Null,
+            IValueImpl::Bool(_) => ValueKind::Bool,
+            IValueImpl::U64(_) | IValueImpl::I64(_) | IValueImpl::F64(_) => ValueKind::Number,
+            IValueImpl::String(_) => ValueKind::String,
+            IValueImpl::Array(_) => ValueKind::Array,
+            IValueImpl::Object(_) => ValueKind::Object,
+        }
+    }
+}
+
+/// A shallow reference to a JSON array.
+#[derive(Clone, Copy)]
+pub struct ArrayRef<'a> {
+    interners: &'a Jinterners,
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    handle: InternedSlice<IValue>,
+    array: &'a [IValue],
+}
+
+impl<'a> ArrayRef<'a> {
+    /// Returns the elements of this JSON array.
+    pub fn as_slice(&self) -> &'a [IValue] {
+        self.array
+    }
+
+    /// Returns the number of elements in this JSON array.
+    pub fn len(&self) -> usize {
+        self.array.len()
+    }
+
+    /// Checks whether this JSON array is empty.
+    pub fn is_empty(&self) -> bool {
+        self.array.is_empty()
+    }
+
+    /// Returns the element at the given index, or [`None`] if the index is out
+    /// of bounds.
+    pub fn get(&self, index: usize) -> Option<&'a IValue> {
+        self.array.get(index)
+    }
+
+    /// Iterates over the elements of this JSON array.
+    pub fn iter(&self) -> std::slice::Iter<'a, IValue> {
+        self.array.iter()
+    }
+
+    /// Returns the elements of this JSON array as [`f64`]s, or [`None`] if
+    /// any element isn't a number.
+    ///
+    /// Integers are converted to the nearest [`f64`]. Numbers are stored
+    /// inline in the array, so this reads the array in a single pass without
+    /// resolving any element in the arena.
+    ///
+    /// ```
+    /// use jinterner::{Jinterners, ValueRef};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.intern(json!([1, -2, 3.5]));
+    /// let ValueRef::Array(array) = interners.lookup_ref(&ivalue) else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(array.as_f64_vec(), Some(vec![1.0, -2.0, 3.5]));
+    /// assert_eq!(array.as_i64_vec(), None);
+    /// ```
+    pub fn as_f64_vec(&self) -> Option<Vec<f64>> {
+        self.array
+            .iter()
+            .map(|v| match v.0 {
+                IValueImpl::U64(x) => Some(x as f64),
+                IValueImpl::I64(x) => Some(x as f64),
+                IValueImpl::F64(Float64(OrderedFloat(x))) => Some(x),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the elements of this JSON array as [`i64`]s, or [`None`] if
+    /// any element isn't an integer that fits in an [`i64`].
+    ///
+    /// Like [`as_f64_vec()`](Self::as_f64_vec), this reads the array in a
+    /// single pass without resolving any element in the arena.
+    pub fn as_i64_vec(&self) -> Option<Vec<i64>> {
+        self.array
+            .iter()
+            .map(|v| match v.0 {
+                IValueImpl::U64(x) => x.try_into().ok(),
+                IValueImpl::I64(x) => Some(x),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
 /// A shallow reference to a JSON map.
+#[derive(Clone, Copy)]
 pub struct MapRef<'a> {
-    arena_str: &'a ArenaStr,
+    interners: &'a Jinterners,
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    handle: InternedSlice<(InternedStrKey, IValue)>,
     map: &'a [(InternedStrKey, IValue)],
 }
 
@@ -416,7 +1236,7 @@ impl<'a> MapRef<'a> {
     /// it once with [`Jinterners::find_key()`] and then use
     /// [`get_by_key()`](Self::get_by_key).
     pub fn get(&self, key: &str) -> Option<&'a IValue> {
-        let k = InternedStrKey(self.arena_str.find(key)?);
+        let k = InternedStrKey(self.interners.find_string(key)?);
         self.get_by_key(k)
     }
 
@@ -428,13 +1248,119 @@ impl<'a> MapRef<'a> {
     }
 
     /// Iterates over the key-value pairs in this JSON map, in arbitrary order.
-    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&'a str, &'a IValue)> {
-        self.map
-            .iter()
-            .map(|(k, v)| (self.arena_str.lookup(k.0), v))
+    pub fn iter(&self) -> MapIter<'a> {
+        MapIter {
+            interners: self.interners,
+            iter: self.map.iter(),
+        }
+    }
+
+    /// Returns the key-value pairs in this JSON map, sorted by key in
+    /// lexicographic order.
+    pub fn iter_sorted(&self) -> std::vec::IntoIter<(&'a str, &'a IValue)> {
+        self.iter_sorted_by(str::cmp)
+    }
+
+    /// Returns the key-value pairs in this JSON map, sorted by key with the
+    /// given comparison function.
+    ///
+    /// This allows to present keys in a custom order, for example a natural
+    /// ordering of numbers or a locale-aware collation, independently of the
+    /// order in which they are stored.
+    ///
+    /// ```
+    /// use jinterner::{Jinterners, ValueRef};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.intern(json!({"b": 1, "a": 2, "C": 3}));
+    /// let ValueRef::Object(map) = interners.lookup_ref(&ivalue) else {
+    ///     unreachable!()
+    /// };
+    /// let keys: Vec<&str> = map
+    ///     .iter_sorted_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()))
+    ///     .map(|(k, _)| k)
+    ///     .collect();
+    /// assert_eq!(keys, ["a", "b", "C"]);
+    /// ```
+    pub fn iter_sorted_by(
+        &self,
+        mut compare: impl FnMut(&str, &str) -> Ordering,
+    ) -> std::vec::IntoIter<(&'a str, &'a IValue)> {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_by(|a, b| compare(a.0, b.0));
+        entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for ArrayRef<'a> {
+    type Item = &'a IValue;
+    type IntoIter = std::slice::Iter<'a, IValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.array.iter()
     }
 }
 
+impl<'a> IntoIterator for &ArrayRef<'a> {
+    type Item = &'a IValue;
+    type IntoIter = std::slice::Iter<'a, IValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.array.iter()
+    }
+}
+
+impl<'a> IntoIterator for MapRef<'a> {
+    type Item = (&'a str, &'a IValue);
+    type IntoIter = MapIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &MapRef<'a> {
+    type Item = (&'a str, &'a IValue);
+    type IntoIter = MapIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the key-value pairs of a JSON map.
+///
+/// This struct is created by the [`iter()`](MapRef::iter) method on
+/// [`MapRef`].
+#[derive(Clone)]
+pub struct MapIter<'a> {
+    interners: &'a Jinterners,
+    iter: std::slice::Iter<'a, (InternedStrKey, IValue)>,
+}
+
+impl<'a> Iterator for MapIter<'a> {
+    type Item = (&'a str, &'a IValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (k, v) = self.iter.next()?;
+        Some((self.interners.string.lookup(k.0), v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for MapIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (k, v) = self.iter.next_back()?;
+        Some((self.interners.string.lookup(k.0), v))
+    }
+}
+
+impl ExactSizeIterator for MapIter<'_> {}
+
 #[cfg(all(feature = "delta", feature = "serde"))]
 mod delta {
     use super::*;
@@ -505,6 +1431,7 @@ mod delta {
                 string,
                 iarray: iarray.into_inner(),
                 iobject: iobject.into_inner(),
+                ..Default::default()
             }))
         }
     }
@@ -989,4 +1916,35 @@ mod serde_test {
             .expect("Failed to convert to value");
         assert_eq!(deser, original);
     }
+
+    #[test]
+    fn float_policy() {
+        use serde::de::IntoDeserializer;
+        use serde::de::value::F64Deserializer;
+
+        let mut interners = Jinterners::default();
+        // With the default policy, -0.0 and 0.0 are deduplicated.
+        let positive = interners.intern(json!([0.0]));
+        let negative = interners.intern(json!([-0.0]));
+        assert_eq!(positive, negative);
+        let nan = IValue::from_value(f64::NAN, &interners).unwrap();
+        assert_eq!(interners.lookup(&nan), Value::Null);
+
+        interners.set_float_policy(FloatPolicy::Canonicalize);
+        let zero = interners.intern(json!(-0.0));
+        assert!(interners.lookup(&zero).as_f64().unwrap().is_sign_positive());
+        let nan = IValue::from_value(f64::NAN, &interners).unwrap();
+        assert_eq!(interners.lookup(&nan), Value::Null);
+
+        interners.set_float_policy(FloatPolicy::Reject);
+        let zero = IValue::from_value(-0.0, &interners).unwrap();
+        assert!(interners.lookup(&zero).as_f64().unwrap().is_sign_positive());
+        let error = IValue::from_value_mut(vec![f64::INFINITY], &mut interners).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "float inf rejected by FloatPolicy::Reject at /0"
+        );
+        let deserializer: F64Deserializer<serde::de::value::Error> = f64::NAN.into_deserializer();
+        assert!(IValue::from_deserializer(deserializer, &interners).is_err());
+    }
 }
@@ -0,0 +1,310 @@
+/// Options to convert between [`IValue`](crate::IValue)s and arbitrary
+/// types with [`serde`].
+///
+/// See [`IValue::from_value_with_options()`](crate::IValue::from_value_with_options)
+/// and [`IValue::to_value_with_options()`](crate::IValue::to_value_with_options).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SerdeOptions {
+    pub(crate) bytes: BytesEncoding,
+    pub(crate) human_readable: bool,
+    pub(crate) lossy_numbers: LossyNumbers,
+}
+
+impl Default for SerdeOptions {
+    fn default() -> Self {
+        Self {
+            bytes: BytesEncoding::default(),
+            human_readable: true,
+            lossy_numbers: LossyNumbers::default(),
+        }
+    }
+}
+
+impl SerdeOptions {
+    /// Sets how byte slices are represented in JSON.
+    pub fn bytes(mut self, bytes: BytesEncoding) -> Self {
+        self.bytes = bytes;
+        self
+    }
+
+    /// Sets the value returned by `is_human_readable()` on the serializer and
+    /// deserializer, which defaults to `true` like for [`serde_json`].
+    ///
+    /// Some types choose a different representation depending on this value,
+    /// for example a string or a compact array of numbers.
+    pub fn human_readable(mut self, human_readable: bool) -> Self {
+        self.human_readable = human_readable;
+        self
+    }
+
+    /// Sets whether numbers may lose precision when converted back to a type
+    /// requesting a float, which is allowed by default like in
+    /// [`serde_json`].
+    ///
+    /// For example, an integer above 2^53 can't be exactly represented as an
+    /// `f64`.
+    pub fn lossy_numbers(mut self, lossy_numbers: LossyNumbers) -> Self {
+        self.lossy_numbers = lossy_numbers;
+        self
+    }
+}
+
+/// Representation of byte slices in JSON.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum BytesEncoding {
+    /// An array of numbers, one per byte, like [`serde_json`] does.
+    #[default]
+    Array,
+    /// A string containing the standard base64 encoding of the bytes, with
+    /// padding.
+    ///
+    /// When converting back to a type requesting bytes, such a string is
+    /// decoded.
+    Base64,
+}
+
+/// Policy for numeric conversions that lose precision.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum LossyNumbers {
+    /// The number is rounded to the nearest representable value.
+    #[default]
+    Allow,
+    /// The conversion fails with an error.
+    Reject,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{IValue, Jinterners};
+    use serde::de::{SeqAccess, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::json;
+    use std::fmt;
+
+    /// A byte buffer serialized with `serialize_bytes()`.
+    #[derive(Debug, PartialEq)]
+    struct Bytes(Vec<u8>);
+
+    impl Serialize for Bytes {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Bytes {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct BytesVisitor;
+
+            impl<'de> Visitor<'de> for BytesVisitor {
+                type Value = Bytes;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("bytes")
+                }
+
+                fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Bytes, E> {
+                    Ok(Bytes(v))
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Bytes, A::Error> {
+                    let mut bytes = Vec::new();
+                    while let Some(byte) = seq.next_element()? {
+                        bytes.push(byte);
+                    }
+                    Ok(Bytes(bytes))
+                }
+            }
+
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+
+    /// A type serialized as a string in human-readable formats, and as a tuple
+    /// otherwise.
+    #[derive(Debug, PartialEq)]
+    struct Version(u8, u8);
+
+    impl Serialize for Version {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                format!("{}.{}", self.0, self.1).serialize(serializer)
+            } else {
+                (self.0, self.1).serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Version {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                let s = String::deserialize(deserializer)?;
+                let (major, minor) = s
+                    .split_once('.')
+                    .ok_or_else(|| serde::de::Error::custom("invalid version"))?;
+                let parse = |x: &str| x.parse().map_err(serde::de::Error::custom);
+                Ok(Version(parse(major)?, parse(minor)?))
+            } else {
+                let (major, minor) = Deserialize::deserialize(deserializer)?;
+                Ok(Version(major, minor))
+            }
+        }
+    }
+
+    #[test]
+    fn human_readable() {
+        let mut interners = Jinterners::default();
+        let version = Version(1, 2);
+
+        let text = IValue::from_value(&version, &interners).unwrap();
+        assert_eq!(interners.lookup(&text), json!("1.2"));
+        assert_eq!(text.to_value::<Version>(&interners).unwrap(), version);
+
+        let options = SerdeOptions::default().human_readable(false);
+        let compact = IValue::from_value_with_options(&version, &interners, options).unwrap();
+        assert_eq!(interners.lookup(&compact), json!([1, 2]));
+        assert_eq!(
+            IValue::from_value_mut_with_options(&version, &mut interners, options).unwrap(),
+            compact
+        );
+        assert_eq!(
+            compact
+                .to_value_with_options::<Version>(&interners, options)
+                .unwrap(),
+            version
+        );
+        assert!(compact.to_value::<Version>(&interners).is_err());
+    }
+
+    #[test]
+    fn bytes_encoding() {
+        let mut interners = Jinterners::default();
+        let bytes = Bytes(b"hello".to_vec());
+
+        let array = IValue::from_value(&bytes, &interners).unwrap();
+        assert_eq!(interners.lookup(&array), json!([104, 101, 108, 108, 111]));
+        assert_eq!(array.to_value::<Bytes>(&interners).unwrap(), bytes);
+
+        let options = SerdeOptions::default().bytes(BytesEncoding::Base64);
+        let base64 = IValue::from_value_with_options(&bytes, &interners, options).unwrap();
+        assert_eq!(interners.lookup(&base64), json!("aGVsbG8="));
+        assert_eq!(
+            IValue::from_value_mut_with_options(&bytes, &mut interners, options).unwrap(),
+            base64
+        );
+        assert_eq!(
+            base64
+                .to_value_with_options::<Bytes>(&interners, options)
+                .unwrap(),
+            bytes
+        );
+
+        let invalid = interners.intern(json!("not base64"));
+        assert!(
+            invalid
+                .to_value_with_options::<Bytes>(&interners, options)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn lossy_numbers() {
+        let interners = Jinterners::default();
+        let big = interners.intern(json!((1u64 << 53) + 1));
+        let small = interners.intern(json!([1, -2, 0.5]));
+
+        assert_eq!(
+            big.to_value::<f64>(&interners).unwrap(),
+            (1u64 << 53) as f64
+        );
+        let options = SerdeOptions::default().lossy_numbers(LossyNumbers::Reject);
+        assert_eq!(
+            big.to_value_with_options::<f64>(&interners, options)
+                .unwrap_err()
+                .to_string(),
+            "invalid value: integer `9007199254740993`, expected an exact f64"
+        );
+        assert_eq!(
+            small
+                .to_value_with_options::<Vec<f32>>(&interners, options)
+                .unwrap(),
+            [1.0, -2.0, 0.5]
+        );
+
+        let precise = interners.intern(json!({"x": [0.1]}));
+        let error = precise
+            .to_value_with_options::<std::collections::BTreeMap<String, Vec<f32>>>(
+                &interners, options,
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid value: floating point `0.1`, expected an exact f32 at /x/0"
+        );
+
+        let value_ref = interners.lookup_ref(&big);
+        assert!(f64::deserialize(crate::ValueRefDeserializer::new(value_ref)).is_ok());
+        assert!(
+            f64::deserialize(crate::ValueRefDeserializer::with_options(
+                value_ref, options
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn serde_bytes() {
+        use serde_bytes::ByteBuf;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Blob {
+            #[serde(with = "serde_bytes")]
+            data: Vec<u8>,
+        }
+
+        let interners = Jinterners::default();
+        let blob = Blob {
+            data: vec![0, 1, 254, 255],
+        };
+        for bytes in [BytesEncoding::Array, BytesEncoding::Base64] {
+            let options = SerdeOptions::default().bytes(bytes);
+            let ivalue = IValue::from_value_with_options(&blob, &interners, options).unwrap();
+            assert_eq!(
+                ivalue
+                    .to_value_with_options::<Blob>(&interners, options)
+                    .unwrap(),
+                blob
+            );
+        }
+
+        // Numeric arrays are accepted regardless of the encoding.
+        let array = interners.intern(json!([104, 105]));
+        let options = SerdeOptions::default().bytes(BytesEncoding::Base64);
+        for options in [SerdeOptions::default(), options] {
+            assert_eq!(
+                array
+                    .to_value_with_options::<ByteBuf>(&interners, options)
+                    .unwrap(),
+                b"hi"
+            );
+        }
+        // Plain strings are deserialized as their UTF-8 bytes.
+        let string = interners.intern(json!("hi"));
+        assert_eq!(string.to_value::<ByteBuf>(&interners).unwrap(), b"hi");
+
+        let error = interners
+            .intern(json!([1, 256]))
+            .to_value::<ByteBuf>(&interners);
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "invalid value: integer `256`, expected a byte at /1"
+        );
+        let error = interners
+            .intern(json!([1, "2"]))
+            .to_value::<ByteBuf>(&interners);
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "invalid type: string \"2\", expected a byte at /1"
+        );
+    }
+}
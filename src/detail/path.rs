@@ -0,0 +1,188 @@
+use crate::Jinterners;
+use blazinterner::InternedStr;
+use serde::de::Error as _;
+use serde_json::Error;
+use std::cell::RefCell;
+use std::fmt::{self, Display, Write};
+
+/// Tracks the path to the value being serialized or deserialized, in order to
+/// report it when an error happens.
+#[derive(Default)]
+pub(crate) struct Path(RefCell<Vec<Segment>>);
+
+/// A component of a [`Path`].
+#[derive(Clone, Copy)]
+pub(crate) enum Segment {
+    /// Index in an array.
+    Index(usize),
+    /// Interned object key.
+    Key(InternedStr),
+    /// Struct field or enum variant name.
+    Name(&'static str),
+}
+
+/// A position in a [`Path`].
+#[derive(Clone, Copy)]
+pub(crate) struct PathRef<'a> {
+    path: &'a Path,
+    depth: usize,
+}
+
+impl Path {
+    pub(crate) fn root(&self) -> PathRef<'_> {
+        PathRef {
+            path: self,
+            depth: 0,
+        }
+    }
+
+    /// Appends the JSON pointer of the value that failed to the given error
+    /// message, if the failure didn't happen at the root.
+    pub(crate) fn wrap_error(&self, error: Error, interners: &Jinterners) -> Error {
+        let segments = self.0.borrow();
+        if segments.is_empty() {
+            return error;
+        }
+        Error::custom(format_args!(
+            "{error} at {}",
+            Pointer {
+                segments: &segments,
+                interners,
+            }
+        ))
+    }
+}
+
+impl PathRef<'_> {
+    /// Runs the given function on the child at the given segment.
+    ///
+    /// On success, the child segment is removed from the path. On failure, it's
+    /// kept so that the path to the failing value can be reported.
+    pub(crate) fn child<T, E>(
+        self,
+        segment: Segment,
+        f: impl FnOnce(Self) -> Result<T, E>,
+    ) -> Result<T, E> {
+        {
+            let mut segments = self.path.0.borrow_mut();
+            // Discard any segments left by a failure that was recovered from.
+            segments.truncate(self.depth);
+            segments.push(segment);
+        }
+        let result = f(PathRef {
+            path: self.path,
+            depth: self.depth + 1,
+        });
+        if result.is_ok() {
+            self.path.0.borrow_mut().truncate(self.depth);
+        }
+        result
+    }
+
+    /// Formats this position as a JSON pointer.
+    pub(crate) fn pointer(self, interners: &Jinterners) -> String {
+        let segments = self.path.0.borrow();
+        Pointer {
+            segments: &segments[..self.depth],
+            interners,
+        }
+        .to_string()
+    }
+}
+
+/// Formats a path as a JSON pointer.
+struct Pointer<'a> {
+    segments: &'a [Segment],
+    interners: &'a Jinterners,
+}
+
+impl Display for Pointer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in self.segments {
+            f.write_char('/')?;
+            let key = match segment {
+                Segment::Index(i) => {
+                    write!(f, "{i}")?;
+                    continue;
+                }
+                Segment::Key(key) => self.interners.string.lookup(*key),
+                Segment::Name(name) => name,
+            };
+            for c in key.chars() {
+                match c {
+                    '~' => f.write_str("~0")?,
+                    '/' => f.write_str("~1")?,
+                    _ => f.write_char(c)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{IValue, Jinterners};
+    use serde::Deserialize;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct User {
+        id: u64,
+        role: Role,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    enum Role {
+        Admin { level: u8 },
+        Guest,
+    }
+
+    fn to_value_error<T>(value: serde_json::Value) -> String
+    where
+        T: for<'de> Deserialize<'de> + std::fmt::Debug,
+    {
+        let interners = Jinterners::default();
+        let ivalue = interners.intern(value);
+        ivalue.to_value::<T>(&interners).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn to_value_error_path() {
+        assert_eq!(
+            to_value_error::<Vec<User>>(json!([{"id": 1, "role": "Guest"}, {"id": "x"}])),
+            "invalid type: string \"x\", expected u64 at /1/id"
+        );
+        assert_eq!(
+            to_value_error::<Vec<User>>(json!([{"id": 1, "role": "Guest"}, {"id": 2}])),
+            "missing field `role` at /1"
+        );
+        assert_eq!(
+            to_value_error::<Vec<User>>(json!([{"id": 1, "role": {"Admin": {"level": -1}}}])),
+            "invalid value: integer `-1`, expected u8 at /0/role/Admin/level"
+        );
+        assert_eq!(
+            to_value_error::<BTreeMap<String, u64>>(json!({"a": 1, "b/c~d": null})),
+            "invalid type: null, expected u64 at /b~1c~0d"
+        );
+        // Errors at the root have no path.
+        assert_eq!(
+            to_value_error::<u64>(json!(true)),
+            "invalid type: boolean `true`, expected u64"
+        );
+    }
+
+    #[test]
+    fn from_value_error_path() {
+        let mut interners = Jinterners::default();
+        let value = BTreeMap::from([("outer", vec![BTreeMap::from([(vec![1u8], 2u8)])])]);
+
+        let error = IValue::from_value(&value, &interners).unwrap_err();
+        assert!(error.to_string().ends_with(" at /outer/0"), "{error}");
+        let error = IValue::from_value_mut(&value, &mut interners).unwrap_err();
+        assert!(error.to_string().ends_with(" at /outer/0"), "{error}");
+    }
+}
@@ -0,0 +1,323 @@
+use super::path::{PathRef, Segment};
+use super::{Float64, IValue, IValueImpl, InternedStrKey, Path};
+use crate::Jinterners;
+use serde::Deserializer;
+use serde::de::{DeserializeSeed, Error, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+
+/// Deserializes the given JSON text with the given seed, failing if the text
+/// contains anything but whitespace after the value.
+pub(crate) fn deserialize_slice<'de, S>(
+    seed: S,
+    json: &'de [u8],
+) -> Result<S::Value, serde_json::Error>
+where
+    S: DeserializeSeed<'de>,
+{
+    let mut deserializer = serde_json::Deserializer::from_slice(json);
+    let value = seed.deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// A [`DeserializeSeed`] that interns the deserialized JSON value into a
+/// [`Jinterners`] arena.
+///
+/// This allows to intern a value from any self-describing format, without
+/// first materializing it as a [`serde_json::Value`].
+///
+/// ```
+/// use jinterner::{InternedSeed, Jinterners};
+/// use serde::de::DeserializeSeed;
+/// use serde_json::json;
+///
+/// let interners = Jinterners::default();
+/// let mut deserializer = serde_json::Deserializer::from_str(r#"{"name": "John"}"#);
+/// let ivalue = InternedSeed::new(&interners)
+///     .deserialize(&mut deserializer)
+///     .unwrap();
+/// assert_eq!(interners.lookup(&ivalue), json!({"name": "John"}));
+/// ```
+#[derive(Clone, Copy)]
+pub struct InternedSeed<'a> {
+    interners: &'a Jinterners,
+    duplicate_keys: DuplicateKeys,
+    /// Path to the current value, only tracked to report duplicate keys.
+    path: Option<PathRef<'a>>,
+}
+
+/// How to handle duplicate keys in a deserialized object.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Keep the value of the last occurrence of the key, like [`serde_json`].
+    #[default]
+    LastWins,
+    /// Fail with an error containing the key and the JSON pointer of the
+    /// object.
+    Reject,
+}
+
+impl fmt::Debug for InternedSeed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InternedSeed")
+            .field("duplicate_keys", &self.duplicate_keys)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> InternedSeed<'a> {
+    /// Creates a seed interning values into the given arena.
+    pub fn new(interners: &'a Jinterners) -> Self {
+        Self {
+            interners,
+            duplicate_keys: DuplicateKeys::default(),
+            path: None,
+        }
+    }
+
+    /// Sets how duplicate object keys are handled, which defaults to
+    /// [`DuplicateKeys::LastWins`].
+    ///
+    /// ```
+    /// use jinterner::{DuplicateKeys, InternedSeed, Jinterners};
+    /// use serde::de::DeserializeSeed;
+    ///
+    /// let interners = Jinterners::default();
+    /// let mut deserializer = serde_json::Deserializer::from_str(r#"{"a": [{"b": 1, "b": 2}]}"#);
+    /// let error = InternedSeed::new(&interners)
+    ///     .duplicate_keys(DuplicateKeys::Reject)
+    ///     .deserialize(&mut deserializer)
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "duplicate key \"b\" at /a/0 at line 1 column 23"
+    /// );
+    /// ```
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    /// Runs the given function on the child at the given segment, tracking
+    /// the path if needed.
+    fn child<T, E>(self, segment: Segment, f: impl FnOnce(Self) -> Result<T, E>) -> Result<T, E> {
+        match self.path {
+            None => f(self),
+            Some(path) => path.child(segment, |path| {
+                f(Self {
+                    path: Some(path),
+                    ..self
+                })
+            }),
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for InternedSeed<'_> {
+    type Value = IValue;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if self.duplicate_keys == DuplicateKeys::Reject && self.path.is_none() {
+            let path = Path::default();
+            return deserializer.deserialize_any(InternedSeed {
+                interners: self.interners,
+                duplicate_keys: self.duplicate_keys,
+                path: Some(path.root()),
+            });
+        }
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for InternedSeed<'_> {
+    type Value = IValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any valid JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(IValue(IValueImpl::Bool(value)))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+        // Non-negative integers are always interned as unsigned, like in
+        // serde_json.
+        Ok(IValue(match u64::try_from(value) {
+            Ok(x) => IValueImpl::U64(x),
+            Err(_) => IValueImpl::I64(value),
+        }))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(IValue(IValueImpl::U64(value)))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        match Float64::new(value, self.interners.float_policy) {
+            None => Err(E::custom(format_args!(
+                "float {value} rejected by FloatPolicy::Reject"
+            ))),
+            // Non-finite numbers aren't valid JSON, serde_json converts them to null.
+            Some(_) if !value.is_finite() => Ok(IValue(IValueImpl::Null)),
+            Some(x) => Ok(IValue(IValueImpl::F64(x))),
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(IValue(IValueImpl::String(
+            self.interners.intern_string(value),
+        )))
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(IValue(IValueImpl::Null))
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(IValue(IValueImpl::Null))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = self.child(Segment::Index(array.len()), |seed| {
+            seq.next_element_seed(seed)
+        })? {
+            array.push(value);
+        }
+        Ok(IValue(IValueImpl::Array(
+            self.interners.intern_array_slice(&array),
+        )))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut object = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(key) = map.next_key_seed(KeySeed {
+            interners: self.interners,
+        })? {
+            let value = self.child(Segment::Key(key.0), |seed| map.next_value_seed(seed))?;
+            object.push((key, value));
+        }
+        object.sort_by_key(|(k, _)| *k);
+        if let Some(path) = self.path
+            && let Some(pair) = object.windows(2).find(|pair| pair[0].0 == pair[1].0)
+        {
+            let key = self.interners.string.lookup(pair[0].0.0);
+            return Err(match path.pointer(self.interners) {
+                pointer if pointer.is_empty() => {
+                    A::Error::custom(format_args!("duplicate key {key:?}"))
+                }
+                pointer => A::Error::custom(format_args!("duplicate key {key:?} at {pointer}")),
+            });
+        }
+        // Keep the last value of duplicate keys, like serde_json.
+        object.dedup_by(|later, earlier| {
+            let duplicate = later.0 == earlier.0;
+            if duplicate {
+                earlier.1 = later.1;
+            }
+            duplicate
+        });
+        Ok(IValue(IValueImpl::Object(
+            self.interners.intern_object_slice(&object),
+        )))
+    }
+}
+
+/// A [`DeserializeSeed`] that interns an object key.
+#[derive(Clone, Copy)]
+struct KeySeed<'a> {
+    interners: &'a Jinterners,
+}
+
+impl<'de> DeserializeSeed<'de> for KeySeed<'_> {
+    type Value = InternedStrKey;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeySeed<'_> {
+    type Value = InternedStrKey;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string key")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(InternedStrKey(self.interners.intern_string(value)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn deserialize_seed() {
+        let interners = Jinterners::default();
+        let text = r#"{"b": [1, -2, 3.5, null, true, "x"], "a": {"b": {}}, "b": []}"#;
+        let mut deserializer = serde_json::Deserializer::from_str(text);
+        let ivalue = InternedSeed::new(&interners)
+            .deserialize(&mut deserializer)
+            .unwrap();
+
+        let expected = json!({"a": {"b": {}}, "b": []});
+        assert_eq!(interners.lookup(&ivalue), expected);
+        assert_eq!(ivalue, interners.intern_ref(&expected));
+    }
+
+    #[test]
+    fn deserialize_seed_from_value() {
+        let interners = Jinterners::default();
+        let value = json!({"name": "John", "tags": ["a", 1, -1, 1.5], "nested": {"x": null}});
+        let ivalue = InternedSeed::new(&interners)
+            .deserialize(value.clone())
+            .unwrap();
+        assert_eq!(ivalue, interners.intern_ref(&value));
+    }
+
+    #[test]
+    fn from_deserializer() {
+        use serde::de::IntoDeserializer;
+        use serde::de::value::{Error, MapDeserializer};
+        use std::collections::BTreeMap;
+
+        let interners = Jinterners::default();
+        let map = BTreeMap::from([("a", 1u32), ("b", 2u32)]);
+        let deserializer: MapDeserializer<'_, _, Error> = map.into_deserializer();
+        let ivalue = IValue::from_deserializer(deserializer, &interners).unwrap();
+        assert_eq!(interners.lookup(&ivalue), json!({"a": 1, "b": 2}));
+    }
+}
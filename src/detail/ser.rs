@@ -1,6 +1,8 @@
-use super::{Float64, IValue, IValueImpl, InternedStrKey};
+use super::path::{PathRef, Segment};
+use super::{
+    BytesEncoding, Float64, FloatPolicy, IValue, IValueImpl, InternedStrKey, SerdeOptions, base64,
+};
 use crate::Jinterners;
-use ordered_float::OrderedFloat;
 use serde::ser::{
     Error as _, Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
     SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
@@ -8,8 +10,31 @@ use serde::ser::{
 use serde::{Serialize, Serializer};
 use serde_json::error::Error;
 
+/// Serializes an integer, storing non-negative integers as unsigned like
+/// when interning a [`serde_json::Value`].
+fn serialize_int(value: i64) -> IValueImpl {
+    match u64::try_from(value) {
+        Ok(x) => IValueImpl::U64(x),
+        Err(_) => IValueImpl::I64(value),
+    }
+}
+
+/// Serializes a float according to the given policy.
+fn serialize_float(value: f64, policy: FloatPolicy) -> Result<IValueImpl, Error> {
+    match Float64::new(value, policy) {
+        None => Err(Error::custom(format_args!(
+            "float {value} rejected by FloatPolicy::Reject"
+        ))),
+        // Non-finite numbers aren't valid JSON, serde_json converts them to null.
+        Some(_) if !value.is_finite() => Ok(IValueImpl::Null),
+        Some(x) => Ok(IValueImpl::F64(x)),
+    }
+}
+
 pub(super) struct ValueSerializer<'a> {
     pub interners: &'a Jinterners,
+    pub options: SerdeOptions,
+    pub path: PathRef<'a>,
 }
 
 impl<'a> Serializer for ValueSerializer<'a> {
@@ -24,24 +49,28 @@ impl<'a> Serializer for ValueSerializer<'a> {
     type SerializeStruct = SerializeObject<'a>;
     type SerializeStructVariant = SerializeObjectVariant<'a>;
 
+    fn is_human_readable(&self) -> bool {
+        self.options.human_readable
+    }
+
     fn serialize_bool(self, value: bool) -> Result<Self::Ok, Self::Error> {
         Ok(IValueImpl::Bool(value))
     }
 
     fn serialize_i8(self, value: i8) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::I64(value.into()))
+        Ok(serialize_int(value.into()))
     }
 
     fn serialize_i16(self, value: i16) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::I64(value.into()))
+        Ok(serialize_int(value.into()))
     }
 
     fn serialize_i32(self, value: i32) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::I64(value.into()))
+        Ok(serialize_int(value.into()))
     }
 
     fn serialize_i64(self, value: i64) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::I64(value))
+        Ok(serialize_int(value))
     }
 
     fn serialize_u8(self, value: u8) -> Result<Self::Ok, Self::Error> {
@@ -61,11 +90,11 @@ impl<'a> Serializer for ValueSerializer<'a> {
     }
 
     fn serialize_f32(self, value: f32) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::F64(Float64(OrderedFloat(value.into()))))
+        serialize_float(value.into(), self.interners.float_policy)
     }
 
     fn serialize_f64(self, value: f64) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::F64(Float64(OrderedFloat(value))))
+        serialize_float(value, self.interners.float_policy)
     }
 
     fn serialize_char(self, value: char) -> Result<Self::Ok, Self::Error> {
@@ -75,17 +104,20 @@ impl<'a> Serializer for ValueSerializer<'a> {
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::String(self.interners.string.intern(value)))
+        Ok(IValueImpl::String(self.interners.intern_string(value)))
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Self::Error> {
-        // TODO: Can we do better?
+        if self.options.bytes == BytesEncoding::Base64 {
+            return self.serialize_str(&base64::encode(value));
+        }
+
         let iter = value
             .iter()
             .map(|byte| IValue(IValueImpl::U64(*byte as u64)));
         // SAFETY: The iterator length is trusted, as it's a simple mapping on a slice
         // iterator.
-        let index = unsafe { self.interners.iarray.intern_iter(iter) };
+        let index = unsafe { self.interners.intern_array_iter(iter) };
         Ok(IValueImpl::Array(index))
     }
 
@@ -139,19 +171,25 @@ impl<'a> Serializer for ValueSerializer<'a> {
         T: ?Sized + Serialize,
     {
         let object = [(
-            InternedStrKey(self.interners.string.intern(variant)),
-            IValue(value.serialize(ValueSerializer {
-                interners: self.interners,
+            InternedStrKey(self.interners.intern_string(variant)),
+            IValue(self.path.child(Segment::Name(variant), |path| {
+                value.serialize(ValueSerializer {
+                    interners: self.interners,
+                    options: self.options,
+                    path,
+                })
             })?),
         )];
         Ok(IValueImpl::Object(
-            self.interners.iobject.intern_array(object),
+            self.interners.intern_object_slice(&object),
         ))
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
         Ok(SerializeArray {
             interners: self.interners,
+            options: self.options,
+            path: self.path,
             array: Vec::with_capacity(len.unwrap_or(0)),
         })
     }
@@ -177,6 +215,8 @@ impl<'a> Serializer for ValueSerializer<'a> {
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
         Ok(SerializeArrayVariant {
             interners: self.interners,
+            options: self.options,
+            path: self.path,
             variant,
             array: Vec::with_capacity(len),
         })
@@ -185,6 +225,8 @@ impl<'a> Serializer for ValueSerializer<'a> {
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
         Ok(SerializeObject {
             interners: self.interners,
+            options: self.options,
+            path: self.path,
             object: Vec::with_capacity(len.unwrap_or(0)),
             key: None,
         })
@@ -207,6 +249,8 @@ impl<'a> Serializer for ValueSerializer<'a> {
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
         Ok(SerializeObjectVariant {
             interners: self.interners,
+            options: self.options,
+            path: self.path,
             variant,
             object: Vec::with_capacity(len),
         })
@@ -215,6 +259,8 @@ impl<'a> Serializer for ValueSerializer<'a> {
 
 pub(super) struct SerializeArray<'a> {
     interners: &'a Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
     array: Vec<IValue>,
 }
 
@@ -226,15 +272,22 @@ impl SerializeSeq for SerializeArray<'_> {
     where
         T: ?Sized + Serialize,
     {
-        self.array.push(IValue(value.serialize(ValueSerializer {
-            interners: self.interners,
-        })?));
+        self.array.push(IValue(self.path.child(
+            Segment::Index(self.array.len()),
+            |path| {
+                value.serialize(ValueSerializer {
+                    interners: self.interners,
+                    options: self.options,
+                    path,
+                })
+            },
+        )?));
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
         Ok(IValueImpl::Array(
-            self.interners.iarray.intern_copy(&self.array),
+            self.interners.intern_array_slice(&self.array),
         ))
     }
 }
@@ -273,6 +326,8 @@ impl SerializeTupleStruct for SerializeArray<'_> {
 
 pub(super) struct SerializeArrayVariant<'a> {
     interners: &'a Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
     variant: &'static str,
     array: Vec<IValue>,
 }
@@ -285,27 +340,38 @@ impl SerializeTupleVariant for SerializeArrayVariant<'_> {
     where
         T: ?Sized + Serialize,
     {
-        self.array.push(IValue(value.serialize(ValueSerializer {
-            interners: self.interners,
-        })?));
+        self.array.push(IValue(self.path.child(
+            Segment::Name(self.variant),
+            |path| {
+                path.child(Segment::Index(self.array.len()), |path| {
+                    value.serialize(ValueSerializer {
+                        interners: self.interners,
+                        options: self.options,
+                        path,
+                    })
+                })
+            },
+        )?));
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        let key = InternedStrKey(self.interners.string.intern(self.variant));
+        let key = InternedStrKey(self.interners.intern_string(self.variant));
         let value = IValue(IValueImpl::Array(
-            self.interners.iarray.intern_copy(&self.array),
+            self.interners.intern_array_slice(&self.array),
         ));
 
         let object = [(key, value)];
         Ok(IValueImpl::Object(
-            self.interners.iobject.intern_array(object),
+            self.interners.intern_object_slice(&object),
         ))
     }
 }
 
 pub(super) struct SerializeObject<'a> {
     interners: &'a Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
     object: Vec<(InternedStrKey, IValue)>,
     key: Option<InternedStrKey>,
 }
@@ -341,8 +407,12 @@ impl SerializeMap for SerializeObject<'_> {
             .expect("serialize_value called before serialize_key");
         self.object.push((
             key,
-            IValue(value.serialize(ValueSerializer {
-                interners: self.interners,
+            IValue(self.path.child(Segment::Key(key.0), |path| {
+                value.serialize(ValueSerializer {
+                    interners: self.interners,
+                    options: self.options,
+                    path,
+                })
             })?),
         ));
         Ok(())
@@ -356,7 +426,7 @@ impl SerializeMap for SerializeObject<'_> {
         }
         self.object.sort_unstable_by_key(|(k, _)| *k);
         Ok(IValueImpl::Object(
-            self.interners.iobject.intern_copy(&self.object),
+            self.interners.intern_object_slice(&self.object),
         ))
     }
 }
@@ -379,6 +449,8 @@ impl SerializeStruct for SerializeObject<'_> {
 
 pub(super) struct SerializeObjectVariant<'a> {
     interners: &'a Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
     variant: &'static str,
     object: Vec<(InternedStrKey, IValue)>,
 }
@@ -392,25 +464,31 @@ impl SerializeStructVariant for SerializeObjectVariant<'_> {
         T: ?Sized + Serialize,
     {
         self.object.push((
-            InternedStrKey(self.interners.string.intern(key)),
-            IValue(value.serialize(ValueSerializer {
-                interners: self.interners,
+            InternedStrKey(self.interners.intern_string(key)),
+            IValue(self.path.child(Segment::Name(self.variant), |path| {
+                path.child(Segment::Name(key), |path| {
+                    value.serialize(ValueSerializer {
+                        interners: self.interners,
+                        options: self.options,
+                        path,
+                    })
+                })
             })?),
         ));
         Ok(())
     }
 
     fn end(mut self) -> Result<Self::Ok, Self::Error> {
-        let key = InternedStrKey(self.interners.string.intern(self.variant));
+        let key = InternedStrKey(self.interners.intern_string(self.variant));
 
         self.object.sort_unstable_by_key(|(k, _)| *k);
         let value = IValue(IValueImpl::Object(
-            self.interners.iobject.intern_copy(&self.object),
+            self.interners.intern_object_slice(&self.object),
         ));
 
         let object = [(key, value)];
         Ok(IValueImpl::Object(
-            self.interners.iobject.intern_array(object),
+            self.interners.intern_object_slice(&object),
         ))
     }
 }
@@ -490,7 +568,7 @@ impl Serializer for ObjectKeySerializer<'_> {
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok, Self::Error> {
-        Ok(InternedStrKey(self.interners.string.intern(value)))
+        Ok(InternedStrKey(self.interners.intern_string(value)))
     }
 
     fn serialize_bytes(self, _value: &[u8]) -> Result<Self::Ok, Self::Error> {
@@ -522,7 +600,7 @@ impl Serializer for ObjectKeySerializer<'_> {
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        Ok(InternedStrKey(self.interners.string.intern(variant)))
+        Ok(InternedStrKey(self.interners.intern_string(variant)))
     }
 
     fn serialize_newtype_struct<T>(
@@ -600,6 +678,8 @@ impl Serializer for ObjectKeySerializer<'_> {
 
 pub(super) struct ValueSerializerMut<'a> {
     pub interners: &'a mut Jinterners,
+    pub options: SerdeOptions,
+    pub path: PathRef<'a>,
 }
 
 impl<'a> Serializer for ValueSerializerMut<'a> {
@@ -614,24 +694,28 @@ impl<'a> Serializer for ValueSerializerMut<'a> {
     type SerializeStruct = SerializeObjectMut<'a>;
     type SerializeStructVariant = SerializeObjectVariantMut<'a>;
 
+    fn is_human_readable(&self) -> bool {
+        self.options.human_readable
+    }
+
     fn serialize_bool(self, value: bool) -> Result<Self::Ok, Self::Error> {
         Ok(IValueImpl::Bool(value))
     }
 
     fn serialize_i8(self, value: i8) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::I64(value.into()))
+        Ok(serialize_int(value.into()))
     }
 
     fn serialize_i16(self, value: i16) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::I64(value.into()))
+        Ok(serialize_int(value.into()))
     }
 
     fn serialize_i32(self, value: i32) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::I64(value.into()))
+        Ok(serialize_int(value.into()))
     }
 
     fn serialize_i64(self, value: i64) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::I64(value))
+        Ok(serialize_int(value))
     }
 
     fn serialize_u8(self, value: u8) -> Result<Self::Ok, Self::Error> {
@@ -651,11 +735,11 @@ impl<'a> Serializer for ValueSerializerMut<'a> {
     }
 
     fn serialize_f32(self, value: f32) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::F64(Float64(OrderedFloat(value.into()))))
+        serialize_float(value.into(), self.interners.float_policy)
     }
 
     fn serialize_f64(self, value: f64) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::F64(Float64(OrderedFloat(value))))
+        serialize_float(value, self.interners.float_policy)
     }
 
     fn serialize_char(self, value: char) -> Result<Self::Ok, Self::Error> {
@@ -665,17 +749,20 @@ impl<'a> Serializer for ValueSerializerMut<'a> {
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok, Self::Error> {
-        Ok(IValueImpl::String(self.interners.string.intern_mut(value)))
+        Ok(IValueImpl::String(self.interners.intern_string_mut(value)))
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Self::Error> {
-        // TODO: Can we do better?
+        if self.options.bytes == BytesEncoding::Base64 {
+            return self.serialize_str(&base64::encode(value));
+        }
+
         let iter = value
             .iter()
             .map(|byte| IValue(IValueImpl::U64(*byte as u64)));
         // SAFETY: The iterator length is trusted, as it's a simple mapping on a slice
         // iterator.
-        let index = unsafe { self.interners.iarray.intern_iter_mut(iter) };
+        let index = unsafe { self.interners.intern_array_iter_mut(iter) };
         Ok(IValueImpl::Array(index))
     }
 
@@ -729,19 +816,25 @@ impl<'a> Serializer for ValueSerializerMut<'a> {
         T: ?Sized + Serialize,
     {
         let object = [(
-            InternedStrKey(self.interners.string.intern_mut(variant)),
-            IValue(value.serialize(ValueSerializerMut {
-                interners: self.interners,
+            InternedStrKey(self.interners.intern_string_mut(variant)),
+            IValue(self.path.child(Segment::Name(variant), |path| {
+                value.serialize(ValueSerializerMut {
+                    interners: self.interners,
+                    options: self.options,
+                    path,
+                })
             })?),
         )];
         Ok(IValueImpl::Object(
-            self.interners.iobject.intern_array_mut(object),
+            self.interners.intern_object_slice_mut(&object),
         ))
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
         Ok(SerializeArrayMut {
             interners: self.interners,
+            options: self.options,
+            path: self.path,
             array: Vec::with_capacity(len.unwrap_or(0)),
         })
     }
@@ -767,6 +860,8 @@ impl<'a> Serializer for ValueSerializerMut<'a> {
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
         Ok(SerializeArrayVariantMut {
             interners: self.interners,
+            options: self.options,
+            path: self.path,
             variant,
             array: Vec::with_capacity(len),
         })
@@ -775,6 +870,8 @@ impl<'a> Serializer for ValueSerializerMut<'a> {
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
         Ok(SerializeObjectMut {
             interners: self.interners,
+            options: self.options,
+            path: self.path,
             object: Vec::with_capacity(len.unwrap_or(0)),
             key: None,
         })
@@ -797,6 +894,8 @@ impl<'a> Serializer for ValueSerializerMut<'a> {
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
         Ok(SerializeObjectVariantMut {
             interners: self.interners,
+            options: self.options,
+            path: self.path,
             variant,
             object: Vec::with_capacity(len),
         })
@@ -805,6 +904,8 @@ impl<'a> Serializer for ValueSerializerMut<'a> {
 
 pub(super) struct SerializeArrayMut<'a> {
     interners: &'a mut Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
     array: Vec<IValue>,
 }
 
@@ -816,15 +917,22 @@ impl SerializeSeq for SerializeArrayMut<'_> {
     where
         T: ?Sized + Serialize,
     {
-        self.array.push(IValue(value.serialize(ValueSerializerMut {
-            interners: self.interners,
-        })?));
+        self.array.push(IValue(self.path.child(
+            Segment::Index(self.array.len()),
+            |path| {
+                value.serialize(ValueSerializerMut {
+                    interners: self.interners,
+                    options: self.options,
+                    path,
+                })
+            },
+        )?));
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
         Ok(IValueImpl::Array(
-            self.interners.iarray.intern_copy_mut(&self.array),
+            self.interners.intern_array_slice_mut(&self.array),
         ))
     }
 }
@@ -863,6 +971,8 @@ impl SerializeTupleStruct for SerializeArrayMut<'_> {
 
 pub(super) struct SerializeArrayVariantMut<'a> {
     interners: &'a mut Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
     variant: &'static str,
     array: Vec<IValue>,
 }
@@ -875,27 +985,38 @@ impl SerializeTupleVariant for SerializeArrayVariantMut<'_> {
     where
         T: ?Sized + Serialize,
     {
-        self.array.push(IValue(value.serialize(ValueSerializerMut {
-            interners: self.interners,
-        })?));
+        self.array.push(IValue(self.path.child(
+            Segment::Name(self.variant),
+            |path| {
+                path.child(Segment::Index(self.array.len()), |path| {
+                    value.serialize(ValueSerializerMut {
+                        interners: self.interners,
+                        options: self.options,
+                        path,
+                    })
+                })
+            },
+        )?));
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        let key = InternedStrKey(self.interners.string.intern_mut(self.variant));
+        let key = InternedStrKey(self.interners.intern_string_mut(self.variant));
         let value = IValue(IValueImpl::Array(
-            self.interners.iarray.intern_copy_mut(&self.array),
+            self.interners.intern_array_slice_mut(&self.array),
         ));
 
         let object = [(key, value)];
         Ok(IValueImpl::Object(
-            self.interners.iobject.intern_array_mut(object),
+            self.interners.intern_object_slice_mut(&object),
         ))
     }
 }
 
 pub(super) struct SerializeObjectMut<'a> {
     interners: &'a mut Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
     object: Vec<(InternedStrKey, IValue)>,
     key: Option<InternedStrKey>,
 }
@@ -931,8 +1052,12 @@ impl SerializeMap for SerializeObjectMut<'_> {
             .expect("serialize_value called before serialize_key");
         self.object.push((
             key,
-            IValue(value.serialize(ValueSerializerMut {
-                interners: self.interners,
+            IValue(self.path.child(Segment::Key(key.0), |path| {
+                value.serialize(ValueSerializerMut {
+                    interners: self.interners,
+                    options: self.options,
+                    path,
+                })
             })?),
         ));
         Ok(())
@@ -946,7 +1071,7 @@ impl SerializeMap for SerializeObjectMut<'_> {
         }
         self.object.sort_unstable_by_key(|(k, _)| *k);
         Ok(IValueImpl::Object(
-            self.interners.iobject.intern_copy_mut(&self.object),
+            self.interners.intern_object_slice_mut(&self.object),
         ))
     }
 }
@@ -969,6 +1094,8 @@ impl SerializeStruct for SerializeObjectMut<'_> {
 
 pub(super) struct SerializeObjectVariantMut<'a> {
     interners: &'a mut Jinterners,
+    options: SerdeOptions,
+    path: PathRef<'a>,
     variant: &'static str,
     object: Vec<(InternedStrKey, IValue)>,
 }
@@ -982,25 +1109,31 @@ impl SerializeStructVariant for SerializeObjectVariantMut<'_> {
         T: ?Sized + Serialize,
     {
         self.object.push((
-            InternedStrKey(self.interners.string.intern_mut(key)),
-            IValue(value.serialize(ValueSerializerMut {
-                interners: self.interners,
+            InternedStrKey(self.interners.intern_string_mut(key)),
+            IValue(self.path.child(Segment::Name(self.variant), |path| {
+                path.child(Segment::Name(key), |path| {
+                    value.serialize(ValueSerializerMut {
+                        interners: self.interners,
+                        options: self.options,
+                        path,
+                    })
+                })
             })?),
         ));
         Ok(())
     }
 
     fn end(mut self) -> Result<Self::Ok, Self::Error> {
-        let key = InternedStrKey(self.interners.string.intern_mut(self.variant));
+        let key = InternedStrKey(self.interners.intern_string_mut(self.variant));
 
         self.object.sort_unstable_by_key(|(k, _)| *k);
         let value = IValue(IValueImpl::Object(
-            self.interners.iobject.intern_copy_mut(&self.object),
+            self.interners.intern_object_slice_mut(&self.object),
         ));
 
         let object = [(key, value)];
         Ok(IValueImpl::Object(
-            self.interners.iobject.intern_array_mut(object),
+            self.interners.intern_object_slice_mut(&object),
         ))
     }
 }
@@ -1080,7 +1213,7 @@ impl Serializer for ObjectKeySerializerMut<'_> {
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok, Self::Error> {
-        Ok(InternedStrKey(self.interners.string.intern_mut(value)))
+        Ok(InternedStrKey(self.interners.intern_string_mut(value)))
     }
 
     fn serialize_bytes(self, _value: &[u8]) -> Result<Self::Ok, Self::Error> {
@@ -1112,7 +1245,7 @@ impl Serializer for ObjectKeySerializerMut<'_> {
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        Ok(InternedStrKey(self.interners.string.intern_mut(variant)))
+        Ok(InternedStrKey(self.interners.intern_string_mut(variant)))
     }
 
     fn serialize_newtype_struct<T>(
@@ -0,0 +1,410 @@
+use super::{ArrayRef, Float64, IValue, IValueImpl, MapRef, ValueRef};
+use crate::Jinterners;
+use ordered_float::OrderedFloat;
+use serde_json::Number;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display, Write};
+
+/// A comparison function between object keys.
+pub(crate) type KeyCompare<'a> = &'a dyn Fn(&str, &str) -> Ordering;
+
+/// Options for the JSON text written by
+/// [`Bound::display_with()`](crate::Bound::display_with).
+///
+/// The default options write the same text as [`serde_json`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct WriteOptions {
+    ascii_only: bool,
+    escape_slash: bool,
+    short_escapes: bool,
+    uppercase_hex: bool,
+    sort_keys: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            ascii_only: false,
+            escape_slash: false,
+            short_escapes: true,
+            uppercase_hex: false,
+            sort_keys: false,
+        }
+    }
+}
+
+impl WriteOptions {
+    /// Sets whether non-ASCII characters in strings are escaped as `\uXXXX`,
+    /// with a surrogate pair for characters outside the basic multilingual
+    /// plane. Disabled by default.
+    pub fn ascii_only(mut self, ascii_only: bool) -> Self {
+        self.ascii_only = ascii_only;
+        self
+    }
+
+    /// Sets whether forward slashes in strings are escaped as `\/`, for
+    /// example to embed JSON in an HTML `<script>` element. Disabled by
+    /// default.
+    pub fn escape_slash(mut self, escape_slash: bool) -> Self {
+        self.escape_slash = escape_slash;
+        self
+    }
+
+    /// Sets whether the control characters that have a short escape sequence
+    /// (`\b`, `\f`, `\n`, `\r` and `\t`) use it rather than `\uXXXX`. Enabled
+    /// by default.
+    pub fn short_escapes(mut self, short_escapes: bool) -> Self {
+        self.short_escapes = short_escapes;
+        self
+    }
+
+    /// Sets whether the hexadecimal digits of `\uXXXX` escapes are written in
+    /// uppercase. Disabled by default.
+    pub fn uppercase_hex(mut self, uppercase_hex: bool) -> Self {
+        self.uppercase_hex = uppercase_hex;
+        self
+    }
+
+    /// Sets whether the keys of objects are written in lexicographic order of
+    /// their strings, rather than in the order in which they are stored in
+    /// the arena. Disabled by default.
+    ///
+    /// The arena order depends on the order in which keys were first
+    /// interned, so it can differ between arenas containing the same values.
+    /// Sorting gives a deterministic output without rebuilding the arena with
+    /// [`Jinterners::optimize()`](crate::Jinterners::optimize), at the cost of
+    /// sorting the keys of each object as it's written.
+    pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+        self.sort_keys = sort_keys;
+        self
+    }
+
+    /// Returns the comparison function of object keys, if they are sorted.
+    pub(crate) fn key_compare(&self) -> Option<KeyCompare<'static>> {
+        self.sort_keys.then_some(&str::cmp)
+    }
+}
+
+impl IValue {
+    /// Writes this value as compact JSON, resolving nested values in the given
+    /// [`Jinterners`] arena, without materializing a [`serde_json::Value`].
+    pub(crate) fn write_json(&self, interners: &Jinterners, w: &mut impl Write) -> fmt::Result {
+        self.write_json_sorted_by(interners, w, None)
+    }
+
+    /// Same as [`write_json()`](Self::write_json), but writes object keys in
+    /// the order defined by the given comparison function rather than in arena
+    /// order, if any.
+    pub(crate) fn write_json_sorted_by(
+        &self,
+        interners: &Jinterners,
+        w: &mut impl Write,
+        compare: Option<KeyCompare<'_>>,
+    ) -> fmt::Result {
+        self.write_json_with(interners, w, compare, WriteOptions::default())
+    }
+
+    /// Same as [`write_json_sorted_by()`](Self::write_json_sorted_by), but
+    /// escapes strings according to the given options.
+    pub(crate) fn write_json_with(
+        &self,
+        interners: &Jinterners,
+        w: &mut impl Write,
+        compare: Option<KeyCompare<'_>>,
+        options: WriteOptions,
+    ) -> fmt::Result {
+        match &self.0 {
+            IValueImpl::Null => w.write_str("null"),
+            IValueImpl::Bool(x) => write!(w, "{x}"),
+            IValueImpl::U64(x) => write!(w, "{x}"),
+            IValueImpl::I64(x) => write!(w, "{x}"),
+            IValueImpl::F64(Float64(OrderedFloat(x))) => write_json_f64(w, *x),
+            IValueImpl::String(s) => write_json_str_with(w, interners.string.lookup(*s), options),
+            IValueImpl::Array(a) => {
+                w.write_char('[')?;
+                for (i, v) in interners.iarray.lookup(*a).iter().enumerate() {
+                    if i != 0 {
+                        w.write_char(',')?;
+                    }
+                    v.write_json_with(interners, w, compare, options)?;
+                }
+                w.write_char(']')
+            }
+            IValueImpl::Object(o) => {
+                let map = MapRef {
+                    interners,
+                    handle: *o,
+                    map: interners.iobject.lookup(*o),
+                };
+                match compare {
+                    None => write_json_entries(interners, w, map.iter(), None, options),
+                    Some(compare) => write_json_entries(
+                        interners,
+                        w,
+                        map.iter_sorted_by(compare),
+                        Some(compare),
+                        options,
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Returns this value as compact JSON, truncated to at most `max_chars`
+    /// characters followed by an ellipsis.
+    pub(crate) fn preview(&self, interners: &Jinterners, max_chars: usize) -> String {
+        let mut writer = TruncatedWriter {
+            output: String::new(),
+            remaining: max_chars,
+        };
+        if self.write_json(interners, &mut writer).is_err() {
+            writer.output.push('…');
+        }
+        writer.output
+    }
+}
+
+/// Writes this value as compact JSON, resolving nested values in the arena it
+/// was looked up from.
+impl Display for ValueRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueRef::Null => f.write_str("null"),
+            ValueRef::Bool(x) => write!(f, "{x}"),
+            ValueRef::U64(x) => write!(f, "{x}"),
+            ValueRef::I64(x) => write!(f, "{x}"),
+            ValueRef::F64(x) => write_json_f64(f, *x),
+            ValueRef::String(s) => write_json_str(f, s),
+            ValueRef::Array(a) => Display::fmt(a, f),
+            ValueRef::Object(o) => Display::fmt(o, f),
+        }
+    }
+}
+
+/// Writes this array as compact JSON.
+impl Display for ArrayRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('[')?;
+        for (i, v) in self.array.iter().enumerate() {
+            if i != 0 {
+                f.write_char(',')?;
+            }
+            v.write_json(self.interners, f)?;
+        }
+        f.write_char(']')
+    }
+}
+
+/// Writes this map as compact JSON.
+impl Display for MapRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('{')?;
+        for (i, (k, v)) in self.iter().enumerate() {
+            if i != 0 {
+                f.write_char(',')?;
+            }
+            write_json_str(f, k)?;
+            f.write_char(':')?;
+            v.write_json(self.interners, f)?;
+        }
+        f.write_char('}')
+    }
+}
+
+impl Debug for ValueRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Debug for ArrayRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Debug for MapRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+/// Writes the given object entries as compact JSON.
+fn write_json_entries<'a>(
+    interners: &Jinterners,
+    w: &mut impl Write,
+    entries: impl IntoIterator<Item = (&'a str, &'a IValue)>,
+    compare: Option<KeyCompare<'_>>,
+    options: WriteOptions,
+) -> fmt::Result {
+    w.write_char('{')?;
+    for (i, (k, v)) in entries.into_iter().enumerate() {
+        if i != 0 {
+            w.write_char(',')?;
+        }
+        write_json_str_with(w, k, options)?;
+        w.write_char(':')?;
+        v.write_json_with(interners, w, compare, options)?;
+    }
+    w.write_char('}')
+}
+
+/// Writes a floating-point number the same way as [`serde_json`], i.e. with
+/// non-finite values written as `null`.
+pub(crate) fn write_json_f64(w: &mut impl Write, x: f64) -> fmt::Result {
+    match Number::from_f64(x) {
+        Some(n) => write!(w, "{n}"),
+        None => w.write_str("null"),
+    }
+}
+
+/// Writes a quoted and escaped JSON string.
+pub(crate) fn write_json_str(w: &mut impl Write, s: &str) -> fmt::Result {
+    write_json_str_with(w, s, WriteOptions::default())
+}
+
+/// Writes a quoted JSON string, escaped according to the given options.
+fn write_json_str_with(w: &mut impl Write, s: &str, options: WriteOptions) -> fmt::Result {
+    w.write_char('"')?;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escape = match c {
+            '"' => Some("\\\""),
+            '\\' => Some("\\\\"),
+            '/' if options.escape_slash => Some("\\/"),
+            '\n' => Some("\\n"),
+            '\r' => Some("\\r"),
+            '\t' => Some("\\t"),
+            '\u{08}' => Some("\\b"),
+            '\u{0C}' => Some("\\f"),
+            '\0'..='\u{1F}' => None,
+            _ if options.ascii_only && !c.is_ascii() => None,
+            _ => continue,
+        };
+        w.write_str(&s[start..i])?;
+        match escape {
+            Some(escape) if options.short_escapes || !c.is_control() => w.write_str(escape)?,
+            _ => {
+                // Characters outside the basic multilingual plane are escaped
+                // as a surrogate pair.
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    if options.uppercase_hex {
+                        write!(w, "\\u{unit:04X}")?;
+                    } else {
+                        write!(w, "\\u{unit:04x}")?;
+                    }
+                }
+            }
+        }
+        start = i + c.len_utf8();
+    }
+    w.write_str(&s[start..])?;
+    w.write_char('"')
+}
+
+/// A writer that fails once a given number of characters has been written.
+struct TruncatedWriter {
+    output: String,
+    remaining: usize,
+}
+
+impl Write for TruncatedWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.remaining == 0 {
+                return Err(fmt::Error);
+            }
+            self.output.push(c);
+            self.remaining -= 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn write_json() {
+        let interners = Jinterners::default();
+        let values = [
+            json!(null),
+            json!([true, false, 0, -1, 1.5, 1e100, u64::MAX, i64::MIN]),
+            json!("quote\" backslash\\ control\n\t\u{01}\u{1F} unicode é😀"),
+            json!({"b": {"x": []}, "a": [{}], "": ""}),
+        ];
+        for value in values {
+            let ivalue = interners.intern_ref(&value);
+            let mut json = String::new();
+            ivalue.write_json(&interners, &mut json).unwrap();
+            assert_eq!(json, serde_json::to_string(&value).unwrap());
+            assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn write_json_sorted() {
+        let interners = Jinterners::default();
+        // Intern keys in an order that differs from the lexicographic order.
+        interners.intern(json!(["b", "a10", "a9"]));
+        let ivalue = interners.intern(json!([{"a9": 1, "a10": {"b": 2, "a": 3}}, "b"]));
+
+        let mut json = String::new();
+        ivalue
+            .write_json_sorted_by(&interners, &mut json, Some(&str::cmp))
+            .unwrap();
+        assert_eq!(json, r#"[{"a10":{"a":3,"b":2},"a9":1},"b"]"#);
+
+        let by_len = |a: &str, b: &str| (a.len(), a).cmp(&(b.len(), b));
+        let mut json = String::new();
+        ivalue
+            .write_json_sorted_by(&interners, &mut json, Some(&by_len))
+            .unwrap();
+        assert_eq!(json, r#"[{"a9":1,"a10":{"a":3,"b":2}},"b"]"#);
+
+        let ValueRef::Array(array) = interners.lookup_ref(&ivalue) else {
+            unreachable!()
+        };
+        let ValueRef::Object(map) = interners.lookup_ref(array.get(0).unwrap()) else {
+            unreachable!()
+        };
+        let keys: Vec<_> = map.iter_sorted().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["a10", "a9"]);
+        let keys: Vec<_> = map.iter_sorted_by(by_len).map(|(k, _)| k).collect();
+        assert_eq!(keys, ["a9", "a10"]);
+    }
+
+    #[test]
+    fn preview() {
+        let interners = Jinterners::default();
+        let ivalue = interners.intern(json!({"name": "John", "tags": ["a", "b"]}));
+        assert_eq!(
+            ivalue.preview(&interners, 100),
+            r#"{"name":"John","tags":["a","b"]}"#
+        );
+        assert_eq!(ivalue.preview(&interners, 10), r#"{"name":"J…"#);
+    }
+
+    #[test]
+    fn display_value_ref() {
+        let interners = Jinterners::default();
+        let values = [
+            json!(null),
+            json!(true),
+            json!(42),
+            json!(-1),
+            json!(1.5),
+            json!("a \"quoted\" string"),
+            json!([1, "a", [null], {"b": false}]),
+            json!({"b": {"x": []}, "a": [{}], "": ""}),
+        ];
+        for value in values {
+            let ivalue = interners.intern_ref(&value);
+            let display = interners.lookup_ref(&ivalue).to_string();
+            // Object keys are written in arena order.
+            assert_eq!(serde_json::from_str::<Value>(&display).unwrap(), value);
+        }
+    }
+}
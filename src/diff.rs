@@ -0,0 +1,216 @@
+use crate::detail::IValueImpl;
+use crate::pointer::push_token;
+use crate::{IValue, Jinterners};
+use std::cmp::Ordering;
+
+/// The kind of a change between two versions of a value or of a document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// The value only exists in the new version.
+    Added,
+    /// The value only exists in the old version.
+    Removed,
+    /// The value exists in both versions, with different contents.
+    Modified,
+}
+
+/// A change found by [`Jinterners::diff()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValueChange {
+    /// JSON pointer to the changed value.
+    pub pointer: String,
+    /// Kind of change.
+    pub kind: ChangeKind,
+    /// Old value, unless it was [added](ChangeKind::Added).
+    pub from: Option<IValue>,
+    /// New value, unless it was [removed](ChangeKind::Removed).
+    pub to: Option<IValue>,
+}
+
+impl Jinterners {
+    /// Computes the changes between two values interned in this arena.
+    ///
+    /// Objects are compared key by key and arrays index by index, recursively.
+    /// Other values, as well as values of different kinds, are reported as
+    /// [modified](ChangeKind::Modified) as a whole. As equal values are
+    /// interned to the same [`IValue`], unchanged sub-trees are skipped without
+    /// visiting them.
+    ///
+    /// Changes are returned in depth-first order, with object keys in arena
+    /// order.
+    ///
+    /// ```
+    /// use jinterner::{ChangeKind, Jinterners};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let old = interners.intern(json!({"retries": 3, "hosts": ["a", "b"]}));
+    /// let new = interners.intern(json!({"retries": 5, "hosts": ["a", "b", "c"]}));
+    ///
+    /// let changes: Vec<_> = interners
+    ///     .diff(&old, &new)
+    ///     .into_iter()
+    ///     .map(|change| (change.pointer, change.kind))
+    ///     .collect();
+    /// assert_eq!(
+    ///     changes,
+    ///     [
+    ///         ("/hosts/2".to_owned(), ChangeKind::Added),
+    ///         ("/retries".to_owned(), ChangeKind::Modified),
+    ///     ]
+    /// );
+    /// ```
+    pub fn diff(&self, from: &IValue, to: &IValue) -> Vec<ValueChange> {
+        let mut changes = Vec::new();
+        let mut pointer = String::new();
+        self.diff_into(*from, *to, &mut pointer, &mut changes);
+        changes
+    }
+
+    fn diff_into(
+        &self,
+        from: IValue,
+        to: IValue,
+        pointer: &mut String,
+        changes: &mut Vec<ValueChange>,
+    ) {
+        if from == to {
+            return;
+        }
+        let len = pointer.len();
+        match (from.0, to.0) {
+            (IValueImpl::Array(a), IValueImpl::Array(b)) => {
+                let (a, b) = (self.iarray.lookup(a), self.iarray.lookup(b));
+                for i in 0..a.len().max(b.len()) {
+                    push_token(pointer, &i.to_string());
+                    self.diff_child(a.get(i), b.get(i), pointer, changes);
+                    pointer.truncate(len);
+                }
+            }
+            (IValueImpl::Object(a), IValueImpl::Object(b)) => {
+                let (mut a, mut b) = (self.iobject.lookup(a), self.iobject.lookup(b));
+                // Keys are sorted by id, so both objects can be merged.
+                loop {
+                    let (key, x, y) = match (a.first(), b.first()) {
+                        (None, None) => break,
+                        (Some((k, x)), None) => {
+                            a = &a[1..];
+                            (k, Some(x), None)
+                        }
+                        (None, Some((k, y))) => {
+                            b = &b[1..];
+                            (k, None, Some(y))
+                        }
+                        (Some((k, x)), Some((l, y))) => match k.cmp(l) {
+                            Ordering::Less => {
+                                a = &a[1..];
+                                (k, Some(x), None)
+                            }
+                            Ordering::Greater => {
+                                b = &b[1..];
+                                (l, None, Some(y))
+                            }
+                            Ordering::Equal => {
+                                a = &a[1..];
+                                b = &b[1..];
+                                (k, Some(x), Some(y))
+                            }
+                        },
+                    };
+                    push_token(pointer, self.string.lookup(key.0));
+                    self.diff_child(x, y, pointer, changes);
+                    pointer.truncate(len);
+                }
+            }
+            _ => changes.push(ValueChange {
+                pointer: pointer.clone(),
+                kind: ChangeKind::Modified,
+                from: Some(from),
+                to: Some(to),
+            }),
+        }
+    }
+
+    fn diff_child(
+        &self,
+        from: Option<&IValue>,
+        to: Option<&IValue>,
+        pointer: &mut String,
+        changes: &mut Vec<ValueChange>,
+    ) {
+        let kind = match (from, to) {
+            (Some(from), Some(to)) => return self.diff_into(*from, *to, pointer, changes),
+            (None, Some(_)) => ChangeKind::Added,
+            (Some(_), None) => ChangeKind::Removed,
+            (None, None) => return,
+        };
+        changes.push(ValueChange {
+            pointer: pointer.clone(),
+            kind,
+            from: from.copied(),
+            to: to.copied(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff() {
+        let interners = Jinterners::default();
+        let from = interners.intern(json!({"a/b": [1, {"x": true}], "c": null, "d": "same"}));
+        let to = interners.intern(json!({"a/b": [1, {"x": false}, 2], "d": "same", "e": {}}));
+        let changes: Vec<_> = interners
+            .diff(&from, &to)
+            .into_iter()
+            .map(|change| {
+                let lookup = |value: Option<IValue>| value.map(|v| interners.lookup(&v));
+                (
+                    change.pointer,
+                    change.kind,
+                    lookup(change.from),
+                    lookup(change.to),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (
+                    "/a~1b/1/x".to_owned(),
+                    ChangeKind::Modified,
+                    Some(json!(true)),
+                    Some(json!(false))
+                ),
+                (
+                    "/a~1b/2".to_owned(),
+                    ChangeKind::Added,
+                    None,
+                    Some(json!(2))
+                ),
+                (
+                    "/c".to_owned(),
+                    ChangeKind::Removed,
+                    Some(json!(null)),
+                    None
+                ),
+                ("/e".to_owned(), ChangeKind::Added, None, Some(json!({}))),
+            ]
+        );
+
+        assert!(interners.diff(&from, &from).is_empty());
+        let scalar = interners.intern(json!(1));
+        assert_eq!(
+            interners.diff(&from, &scalar),
+            [ValueChange {
+                pointer: String::new(),
+                kind: ChangeKind::Modified,
+                from: Some(from),
+                to: Some(scalar),
+            }]
+        );
+    }
+}
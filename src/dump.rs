@@ -0,0 +1,219 @@
+use crate::analysis::ReferenceCounts;
+use crate::detail::write_json_str;
+use crate::{InternedStrKey, Jinterners};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Format of the flat dumps written by
+/// [`dump_strings()`](Jinterners::dump_strings) and
+/// [`dump_keys()`](Jinterners::dump_keys).
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// Comma-separated values, with a header line naming the columns. Fields
+    /// are quoted as per RFC 4180 when needed.
+    Csv,
+    /// One JSON object per line, whose keys are the column names.
+    JsonLines,
+}
+
+/// A field of a dumped row.
+enum Field<'a> {
+    Int(usize),
+    Str(&'a str),
+}
+
+/// Writes the given rows, each with a field per column.
+fn write_rows<'a, const N: usize>(
+    w: &mut impl Write,
+    format: DumpFormat,
+    columns: [&str; N],
+    rows: impl Iterator<Item = [Field<'a>; N]>,
+) -> io::Result<()> {
+    let mut line = String::new();
+    if format == DumpFormat::Csv {
+        writeln!(w, "{}", columns.join(","))?;
+    }
+    for row in rows {
+        line.clear();
+        match format {
+            DumpFormat::Csv => {
+                for (i, field) in row.iter().enumerate() {
+                    if i != 0 {
+                        line.push(',');
+                    }
+                    match field {
+                        Field::Int(x) => write!(line, "{x}").unwrap(),
+                        Field::Str(s) if s.contains([',', '"', '\n', '\r']) => {
+                            write!(line, "\"{}\"", s.replace('"', "\"\"")).unwrap()
+                        }
+                        Field::Str(s) => line.push_str(s),
+                    }
+                }
+            }
+            DumpFormat::JsonLines => {
+                for (i, (column, field)) in columns.iter().zip(&row).enumerate() {
+                    line.push(if i == 0 { '{' } else { ',' });
+                    write!(line, "\"{column}\":").unwrap();
+                    match field {
+                        Field::Int(x) => write!(line, "{x}").unwrap(),
+                        Field::Str(s) => write_json_str(&mut line, s).unwrap(),
+                    }
+                }
+                line.push('}');
+            }
+        }
+        line.push('\n');
+        w.write_all(line.as_bytes())?;
+    }
+    Ok(())
+}
+
+impl Jinterners {
+    /// Writes one row per interned string, with the following columns:
+    /// - `id`: index of the string in the arena,
+    /// - `length`: length of the string in bytes,
+    /// - `references`: number of references to the string from the arrays and
+    ///   objects of this arena, as a key or a value,
+    /// - `content`: the string itself.
+    ///
+    /// This allows inspecting the contents of an arena with standard tools.
+    /// The writer should be buffered, as each row is written separately.
+    ///
+    /// ```
+    /// use jinterner::{DumpFormat, Jinterners};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// interners.intern(json!({"name": "Doe, John"}));
+    ///
+    /// let mut csv = Vec::new();
+    /// interners.dump_strings(&mut csv, DumpFormat::Csv)?;
+    /// assert_eq!(
+    ///     String::from_utf8(csv).unwrap(),
+    ///     "id,length,references,content\n0,4,1,name\n1,9,1,\"Doe, John\"\n"
+    /// );
+    ///
+    /// let mut jsonl = Vec::new();
+    /// interners.dump_strings(&mut jsonl, DumpFormat::JsonLines)?;
+    /// assert_eq!(
+    ///     String::from_utf8(jsonl).unwrap().lines().next(),
+    ///     Some(r#"{"id":0,"length":4,"references":1,"content":"name"}"#)
+    /// );
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn dump_strings(&self, w: &mut impl Write, format: DumpFormat) -> io::Result<()> {
+        let references = ReferenceCounts::new(self).strings;
+        write_rows(
+            w,
+            format,
+            ["id", "length", "references", "content"],
+            self.string.iter().enumerate().map(|(i, s)| {
+                [
+                    Field::Int(i),
+                    Field::Int(s.len()),
+                    Field::Int(references[i]),
+                    Field::Str(s),
+                ]
+            }),
+        )
+    }
+
+    /// Writes one row per string used as an object key, by decreasing number
+    /// of objects, with the following columns:
+    /// - `id`: index of the key in the string arena,
+    /// - `key`: the key itself,
+    /// - `objects`: number of interned objects containing the key,
+    /// - `distinct_values`: number of distinct values associated with the key
+    ///   across these objects.
+    ///
+    /// See [`dump_strings()`](Self::dump_strings) for the formats.
+    ///
+    /// ```
+    /// use jinterner::{DumpFormat, Jinterners};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// interners.intern(json!([{"id": 1, "tag": "a"}, {"id": 2, "tag": "a"}]));
+    ///
+    /// let mut csv = Vec::new();
+    /// interners.dump_keys(&mut csv, DumpFormat::Csv)?;
+    /// assert_eq!(
+    ///     String::from_utf8(csv).unwrap(),
+    ///     "id,key,objects,distinct_values\n0,id,2,2\n1,tag,2,1\n"
+    /// );
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn dump_keys(&self, w: &mut impl Write, format: DumpFormat) -> io::Result<()> {
+        let mut distinct = vec![0; self.string.strings()];
+        let mut seen = HashSet::new();
+        for object in self.iobject.iter() {
+            for entry in object {
+                if seen.insert(*entry) {
+                    distinct[entry.0.0.id() as usize] += 1;
+                }
+            }
+        }
+        write_rows(
+            w,
+            format,
+            ["id", "key", "objects", "distinct_values"],
+            self.key_frequencies()
+                .into_iter()
+                .map(|(InternedStrKey(k), objects)| {
+                    [
+                        Field::Int(k.id() as usize),
+                        Field::Str(self.string.lookup(k)),
+                        Field::Int(objects),
+                        Field::Int(distinct[k.id() as usize]),
+                    ]
+                }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn dump() {
+        let interners = Jinterners::default();
+        interners.intern(json!([{"a": "x\"y"}, {"a": "line\nbreak", "b": "a"}, "a"]));
+
+        let mut csv = Vec::new();
+        interners.dump_strings(&mut csv, DumpFormat::Csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,length,references,content\n\
+             0,1,4,a\n\
+             1,3,1,\"x\"\"y\"\n\
+             2,10,1,\"line\nbreak\"\n\
+             3,1,1,b\n"
+        );
+
+        let mut jsonl = Vec::new();
+        interners
+            .dump_keys(&mut jsonl, DumpFormat::JsonLines)
+            .unwrap();
+        let rows: Vec<Value> = String::from_utf8(jsonl)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                json!({"id": 0, "key": "a", "objects": 2, "distinct_values": 2}),
+                json!({"id": 3, "key": "b", "objects": 1, "distinct_values": 1}),
+            ]
+        );
+
+        let mut empty = Vec::new();
+        Jinterners::default()
+            .dump_strings(&mut empty, DumpFormat::JsonLines)
+            .unwrap();
+        assert!(empty.is_empty());
+    }
+}
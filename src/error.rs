@@ -0,0 +1,179 @@
+use crate::{ArenaKind, PointerError, ValidationReport};
+use std::fmt::{self, Display};
+use std::io;
+
+/// An error returned by the fallible operations of this crate.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An arena cannot hold any more entries, as their identifiers or the
+    /// total size of their contents would exceed [`u32::MAX`].
+    Capacity {
+        /// Arena that is full.
+        arena: ArenaKind,
+    },
+    /// An interned handle doesn't refer to an entry of its arena.
+    InvalidId {
+        /// Arena that the handle refers to.
+        arena: ArenaKind,
+        /// Identifier of the handle.
+        id: u32,
+    },
+    /// A value is nested deeper than the allowed limit.
+    DepthLimit {
+        /// Maximum allowed depth.
+        limit: usize,
+    },
+    /// JSON text couldn't be parsed.
+    Parse(serde_json::Error),
+    /// Reading or writing a stream failed.
+    Io(io::Error),
+    /// A JSON pointer couldn't be resolved.
+    Pointer(PointerError),
+    /// An operation of a patch couldn't be applied.
+    Patch {
+        /// Index of the failing operation in the patch.
+        index: usize,
+        /// Location and reason of the failure.
+        error: PointerError,
+    },
+    /// An arena is inconsistent.
+    Validation(ValidationReport),
+    /// An archive is malformed.
+    Archive {
+        /// Description of the problem.
+        reason: &'static str,
+    },
+    /// The buffer of a [`JinternersView`](crate::JinternersView) is
+    /// malformed.
+    View {
+        /// Description of the problem.
+        reason: &'static str,
+    },
+    /// An [`Increment`](crate::Increment) cannot be computed or applied.
+    Replication {
+        /// Description of the problem.
+        reason: &'static str,
+    },
+    /// The path of an [`Accessor`](crate::Accessor) or a
+    /// [`FieldMask`](crate::FieldMask) is malformed.
+    Accessor {
+        /// The malformed component of the path.
+        component: String,
+    },
+    /// An [`IngestPool`](crate::IngestPool) couldn't intern a submitted value.
+    Ingest {
+        /// Description of the problem.
+        reason: &'static str,
+    },
+    /// The entries of an arena don't match the expected
+    /// [`Checksums`](crate::Checksums).
+    Integrity {
+        /// The first arena whose checksum doesn't match.
+        arena: ArenaKind,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Capacity { arena } => write!(f, "the {} arena is full", arena.name()),
+            Error::InvalidId { arena, id } => {
+                write!(f, "invalid id {id} in the {} arena", arena.name())
+            }
+            Error::DepthLimit { limit } => write!(f, "nesting depth exceeds the limit of {limit}"),
+            Error::Parse(e) => write!(f, "parse error: {e}"),
+            Error::Io(e) => write!(f, "I/O error: {e}"),
+            Error::Pointer(e) => e.fmt(f),
+            Error::Patch { index, error } => write!(f, "patch operation {index} failed: {error}"),
+            Error::Validation(report) => write!(f, "validation failed: {report}"),
+            Error::Archive { reason } => write!(f, "invalid archive: {reason}"),
+            Error::View { reason } => write!(f, "invalid view: {reason}"),
+            Error::Replication { reason } => write!(f, "replication failed: {reason}"),
+            Error::Accessor { component } => {
+                write!(f, "invalid accessor path component {component:?}")
+            }
+            Error::Ingest { reason } => write!(f, "ingestion failed: {reason}"),
+            Error::Integrity { arena } => {
+                write!(
+                    f,
+                    "the checksum of the {} arena doesn't match",
+                    arena.name()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Parse(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Pointer(e) => Some(e),
+            Error::Patch { error, .. } => Some(error),
+            Error::Validation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Parse(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<ValidationReport> for Error {
+    fn from(e: ValidationReport) -> Self {
+        Error::Validation(e)
+    }
+}
+
+impl From<PointerError> for Error {
+    fn from(e: PointerError) -> Self {
+        Error::Pointer(e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Jinterners;
+    use serde_json::json;
+
+    #[test]
+    fn display() {
+        let interners = Jinterners::default();
+        let ivalue = interners.intern(json!({"a": [1]}));
+        let error: Error = interners.pointer(&ivalue, "/a/b").unwrap_err().into();
+        assert_eq!(error.to_string(), "invalid array index \"b\" at /a");
+
+        let error = Error::InvalidId {
+            arena: ArenaKind::Object,
+            id: 42,
+        };
+        assert_eq!(error.to_string(), "invalid id 42 in the object arena");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn parse() {
+        let interners = Jinterners::default();
+        let ivalue = interners.parse(r#"[1, {"a": null}]"#).unwrap();
+        assert_eq!(interners.lookup(&ivalue), json!([1, {"a": null}]));
+
+        let error = interners.parse("[1] 2").unwrap_err();
+        assert!(matches!(error, Error::Parse(_)));
+        assert_eq!(
+            error.to_string(),
+            "parse error: trailing characters at line 1 column 5"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{IValue, InternedStrKey};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// A projection of the memory that interning a set of values would save.
+///
+/// This struct is created by the [`estimate_savings()`] function.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SavingsEstimate {
+    /// Number of values.
+    pub values: usize,
+    /// Number of bytes used by the values as [`serde_json::Value`]s.
+    pub uninterned_bytes: usize,
+    /// Number of bytes that the values would use once interned, i.e. the
+    /// contents of a [`Jinterners`](crate::Jinterners) arena containing them
+    /// and one [`IValue`] handle per value.
+    pub interned_bytes: usize,
+    /// Deduplication of strings, including object keys.
+    pub strings: DedupStats,
+    /// Deduplication of arrays.
+    pub arrays: DedupStats,
+    /// Deduplication of objects.
+    pub objects: DedupStats,
+}
+
+/// How many of the strings, arrays or objects within a set of values are
+/// distinct.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DedupStats {
+    /// Total number of occurrences.
+    pub total: usize,
+    /// Number of distinct entries, i.e. entries of the arena once interned.
+    pub distinct: usize,
+}
+
+impl SavingsEstimate {
+    /// Returns the number of bytes saved by interning, or zero if interning
+    /// would use more memory.
+    pub fn saved_bytes(&self) -> usize {
+        self.uninterned_bytes.saturating_sub(self.interned_bytes)
+    }
+
+    /// Returns the ratio of the interned size over the uninterned size, i.e.
+    /// a value below 1 when interning saves memory.
+    pub fn ratio(&self) -> f64 {
+        self.interned_bytes as f64 / self.uninterned_bytes.max(1) as f64
+    }
+}
+
+/// Estimates how much memory interning the given values would save, without
+/// building any arena.
+///
+/// Each string, array and object is hashed instead of interned, so that the
+/// estimate only needs memory proportional to the number of distinct entries,
+/// and the values can be streamed. Hash collisions are very unlikely but would
+/// slightly under-estimate the interned size.
+///
+/// Both sizes only count the contents of the values, i.e. they exclude
+/// allocator overhead, the spare capacity of collections and the bookkeeping
+/// of the interning hash tables.
+///
+/// ```
+/// use jinterner::estimate_savings;
+/// use serde_json::json;
+///
+/// let values: Vec<_> = (0..100)
+///     .map(|i| json!({"id": i, "status": "active", "tags": ["a", "b"]}))
+///     .collect();
+///
+/// let estimate = estimate_savings(&values);
+/// assert_eq!(estimate.values, 100);
+/// assert_eq!(estimate.arrays.total, 100);
+/// assert_eq!(estimate.arrays.distinct, 1);
+/// assert!(estimate.ratio() < 0.5);
+/// ```
+pub fn estimate_savings<'a>(values: impl IntoIterator<Item = &'a Value>) -> SavingsEstimate {
+    let mut estimator = Estimator::default();
+    for value in values {
+        estimator.estimate.values += 1;
+        estimator.estimate.uninterned_bytes += size_of::<Value>();
+        estimator.estimate.interned_bytes += size_of::<IValue>();
+        estimator.visit(value);
+    }
+    estimator.estimate
+}
+
+#[derive(Default)]
+struct Estimator {
+    strings: HashSet<u64>,
+    arrays: HashSet<u64>,
+    objects: HashSet<u64>,
+    estimate: SavingsEstimate,
+}
+
+impl Estimator {
+    /// Accounts for the given value, and returns its hash.
+    fn visit(&mut self, value: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        match value {
+            Value::Null => 0u8.hash(&mut hasher),
+            Value::Bool(x) => (1u8, x).hash(&mut hasher),
+            Value::Number(x) => (2u8, x).hash(&mut hasher),
+            Value::String(x) => (3u8, self.visit_str(x)).hash(&mut hasher),
+            Value::Array(array) => (4u8, self.visit_array(array)).hash(&mut hasher),
+            Value::Object(map) => (5u8, self.visit_object(map)).hash(&mut hasher),
+        }
+        hasher.finish()
+    }
+
+    fn visit_str(&mut self, s: &str) -> u64 {
+        let hash = hash_one(s);
+        let stats = &mut self.estimate.strings;
+        stats.total += 1;
+        self.estimate.uninterned_bytes += s.len();
+        if self.strings.insert(hash) {
+            stats.distinct += 1;
+            self.estimate.interned_bytes += s.len();
+        }
+        hash
+    }
+
+    fn visit_array(&mut self, array: &[Value]) -> u64 {
+        let items: Vec<u64> = array.iter().map(|item| self.visit(item)).collect();
+        let hash = hash_one(&items);
+        let stats = &mut self.estimate.arrays;
+        stats.total += 1;
+        self.estimate.uninterned_bytes += size_of_val(array);
+        if self.arrays.insert(hash) {
+            stats.distinct += 1;
+            self.estimate.interned_bytes += array.len() * size_of::<IValue>();
+        }
+        hash
+    }
+
+    fn visit_object(&mut self, map: &Map<String, Value>) -> u64 {
+        let mut entries: Vec<(u64, u64)> = map
+            .iter()
+            .map(|(key, value)| (self.visit_str(key), self.visit(value)))
+            .collect();
+        // Interned objects don't depend on the order of their keys.
+        entries.sort_unstable();
+        let hash = hash_one(&entries);
+        let stats = &mut self.estimate.objects;
+        stats.total += 1;
+        self.estimate.uninterned_bytes += map.len() * size_of::<(String, Value)>();
+        if self.objects.insert(hash) {
+            stats.distinct += 1;
+            self.estimate.interned_bytes += map.len() * size_of::<(InternedStrKey, IValue)>();
+        }
+        hash
+    }
+}
+
+fn hash_one(x: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    x.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Jinterners;
+    use serde_json::json;
+
+    #[test]
+    fn estimate_savings() {
+        let values = [
+            json!({"name": "John", "tags": ["a", "bc", ["a"]]}),
+            json!({"tags": ["a", "bc", ["a"]], "name": "John"}),
+            json!({"name": "Jane", "tags": []}),
+            json!(null),
+        ];
+        let estimate = super::estimate_savings(&values);
+        assert_eq!(estimate.values, 4);
+        assert_eq!(
+            estimate.strings,
+            DedupStats {
+                total: 15,
+                distinct: 6
+            }
+        );
+        assert_eq!(
+            estimate.arrays,
+            DedupStats {
+                total: 5,
+                distinct: 3
+            }
+        );
+        assert_eq!(
+            estimate.objects,
+            DedupStats {
+                total: 3,
+                distinct: 2
+            }
+        );
+
+        // The interned size matches the contents of an actual arena.
+        let interners = Jinterners::default();
+        interners.intern_many_ref(&values);
+        let report = interners.storage_report();
+        assert_eq!(
+            estimate.interned_bytes,
+            4 * size_of::<IValue>()
+                + report.strings.payload_bytes
+                + report.arrays.payload_bytes
+                + report.objects.payload_bytes
+        );
+        assert!(estimate.saved_bytes() > 0);
+        assert!(estimate.ratio() < 1.0);
+
+        let empty = super::estimate_savings([]);
+        assert_eq!(empty.saved_bytes(), 0);
+        assert_eq!(empty.ratio(), 0.0);
+    }
+}
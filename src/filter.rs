@@ -0,0 +1,196 @@
+use crate::Jinterners;
+use std::fmt::Debug;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Number of filter bits per expected string, for a false positive rate of
+/// about 1%.
+const BITS_PER_STRING: usize = 10;
+/// Number of words in a block, so that a block fits in a 64-byte cache line.
+const BLOCK_WORDS: usize = 8;
+/// Number of bits set in a block for each string.
+const PROBES: u32 = 6;
+
+type Block = [AtomicU64; BLOCK_WORDS];
+
+/// An optional blocked Bloom filter over the strings of a [`Jinterners`]
+/// arena, to answer most lookups of missing strings without probing the arena.
+///
+/// All the bits of a given string are in the same block, so that each lookup
+/// touches a single cache line.
+#[derive(Default)]
+pub(crate) struct StringFilter(Option<Box<[Block]>>);
+
+impl StringFilter {
+    /// Creates a filter sized for the given number of strings, containing the
+    /// given strings.
+    fn new<'a>(expected_strings: usize, strings: impl Iterator<Item = &'a str>) -> Self {
+        let blocks = (expected_strings * BITS_PER_STRING)
+            .div_ceil(BLOCK_WORDS * 64)
+            .max(1);
+        Self::with_blocks(blocks, strings)
+    }
+
+    /// Creates a filter of the same size as this one, containing only the
+    /// given strings, or a disabled filter if this one is disabled.
+    pub(crate) fn rebuild<'a>(&self, strings: impl Iterator<Item = &'a str>) -> Self {
+        match &self.0 {
+            None => Self(None),
+            Some(blocks) => Self::with_blocks(blocks.len(), strings),
+        }
+    }
+
+    fn with_blocks<'a>(blocks: usize, strings: impl Iterator<Item = &'a str>) -> Self {
+        let filter = Self(Some((0..blocks).map(|_| Default::default()).collect()));
+        for s in strings {
+            filter.insert(s);
+        }
+        filter
+    }
+
+    /// Adds the given string to this filter, if enabled.
+    pub(crate) fn insert(&self, value: &str) {
+        if let Some(blocks) = &self.0 {
+            let (block, probes) = probe(blocks, value);
+            for (word, mask) in probes {
+                block[word].fetch_or(mask, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns false if the given string was definitely never added to this
+    /// filter, and true if it may have been added or if the filter is
+    /// disabled.
+    pub(crate) fn may_contain(&self, value: &str) -> bool {
+        match &self.0 {
+            None => true,
+            Some(blocks) => {
+                let (block, mut probes) = probe(blocks, value);
+                probes.all(|(word, mask)| block[word].load(Ordering::Relaxed) & mask == mask)
+            }
+        }
+    }
+}
+
+/// Returns the block of the given string, and the words and masks of its bits
+/// within this block.
+fn probe<'a>(blocks: &'a [Block], value: &str) -> (&'a Block, impl Iterator<Item = (usize, u64)>) {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    let hash = hasher.finish();
+    let block = ((u128::from(hash) * blocks.len() as u128) >> 64) as usize;
+    // Derive the bit positions from a remix of the hash, so that they're
+    // independent of the block index.
+    let bits = hash.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    let probes = (0..PROBES).map(move |i| {
+        let bit = (bits >> (9 * i)) as usize % (BLOCK_WORDS * 64);
+        (bit / 64, 1 << (bit % 64))
+    });
+    (&blocks[block], probes)
+}
+
+impl Clone for StringFilter {
+    fn clone(&self) -> Self {
+        Self(self.0.as_ref().map(|blocks| {
+            blocks
+                .iter()
+                .map(|block| {
+                    std::array::from_fn(|i| AtomicU64::new(block[i].load(Ordering::Relaxed)))
+                })
+                .collect()
+        }))
+    }
+}
+
+impl Debug for StringFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("StringFilter")
+            .field(&self.0.as_ref().map(|blocks| blocks.len()))
+            .finish()
+    }
+}
+
+impl PartialEq for StringFilter {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for StringFilter {}
+
+impl Jinterners {
+    /// Enables a Bloom filter over the strings of this arena, sized for the
+    /// given number of strings, or disables it if [`None`] is passed.
+    ///
+    /// The filter is consulted before probing the arena when looking up a
+    /// string that may be missing, for example with [`MapRef::get()`],
+    /// [`find()`](Self::find) or [`find_key()`](Self::find_key). This makes
+    /// negative lookups, which are common when checking optional fields,
+    /// nearly free. The filter uses 10 bits per string, and the rate of false
+    /// positives increases once the arena contains more strings than it was
+    /// sized for. Calling this method again resizes it.
+    ///
+    /// The filter is preserved in arenas derived from this one (for example
+    /// with [`optimize()`](Self::optimize)), but isn't serialized.
+    ///
+    /// [`MapRef::get()`]: crate::MapRef::get
+    ///
+    /// ```
+    /// use jinterner::{Jinterners, ValueRef};
+    /// use serde_json::json;
+    ///
+    /// let mut interners = Jinterners::default();
+    /// interners.set_string_filter(Some(1_000));
+    ///
+    /// let ivalue = interners.intern(json!({"name": "John"}));
+    /// let ValueRef::Object(map) = interners.lookup_ref(&ivalue) else {
+    ///     unreachable!()
+    /// };
+    /// assert!(map.get("name").is_some());
+    /// assert!(map.get("email").is_none());
+    /// ```
+    pub fn set_string_filter(&mut self, expected_strings: Option<usize>) {
+        self.string_filter = match expected_strings {
+            None => StringFilter::default(),
+            Some(expected_strings) => StringFilter::new(
+                expected_strings.max(self.string.strings()),
+                self.string.iter(),
+            ),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn no_false_negatives() {
+        let mut interners = Jinterners::default();
+        interners.intern(json!(["before", "the", "filter"]));
+        interners.set_string_filter(Some(1000));
+        let values: Vec<_> = (0..1000).map(|i| json!({ format!("key{i}"): i })).collect();
+        interners.intern_many_ref(&values);
+
+        for s in interners.string.iter() {
+            assert!(interners.string_filter.may_contain(s), "{s}");
+        }
+        for (i, value) in values.iter().enumerate() {
+            assert!(interners.find_key(&format!("key{i}")).is_some());
+            assert!(interners.find(value).is_some());
+        }
+        let (optimized, _) = interners.optimize(None).unwrap();
+        assert!(optimized.find_key("key0").is_some());
+        assert!(interners.clone().find_key("filter").is_some());
+
+        let false_positives = (0..1000)
+            .filter(|i| interners.string_filter.may_contain(&format!("missing{i}")))
+            .count();
+        assert!(false_positives < 50, "{false_positives}");
+
+        interners.set_string_filter(None);
+        assert!(interners.string_filter.may_contain("missing"));
+        assert!(interners.find_key("missing").is_none());
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{IValue, InternedStrKey, Jinterners};
+use flatbuffers::{FlatBufferBuilder, Push, PushAlignment, WIPOffset};
+use std::io::{self, Write};
+
+/// The FlatBuffers schema of the buffers written by
+/// [`Jinterners::write_flatbuffer()`], from which code to read them can be
+/// generated for any language supported by `flatc`.
+pub const FLATBUFFERS_SCHEMA: &str = include_str!("jinterner.fbs");
+
+/// File identifier of the buffers, as declared in the schema.
+const FILE_IDENTIFIER: &str = "JINT";
+
+/// Offsets of the fields of the tables of the schema in their vtables.
+const ARRAY_ITEMS: u16 = 4;
+const OBJECT_ENTRIES: u16 = 4;
+const ARENA_STRINGS: u16 = 4;
+const ARENA_ARRAYS: u16 = 6;
+const ARENA_OBJECTS: u16 = 8;
+const ARENA_ROOTS: u16 = 10;
+
+/// The `Value` struct of the schema: a little-endian `u64` payload followed
+/// by a tag byte, padded to 16 bytes.
+#[derive(Clone, Copy)]
+struct FbValue([u8; 16]);
+
+impl FbValue {
+    fn new(value: &IValue) -> Self {
+        let (tag, payload) = value.to_raw_parts();
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&payload.to_le_bytes());
+        bytes[8] = tag as u8;
+        Self(bytes)
+    }
+}
+
+impl Push for FbValue {
+    type Output = FbValue;
+
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        dst[..16].copy_from_slice(&self.0);
+    }
+
+    fn alignment() -> PushAlignment {
+        PushAlignment::new(8)
+    }
+}
+
+/// The `Entry` struct of the schema: a little-endian `u32` key index, padded
+/// to 8 bytes, followed by a `Value`.
+#[derive(Clone, Copy)]
+struct FbEntry([u8; 24]);
+
+impl FbEntry {
+    fn new((k, v): &(InternedStrKey, IValue)) -> Self {
+        let mut bytes = [0; 24];
+        bytes[..4].copy_from_slice(&k.0.id().to_le_bytes());
+        bytes[8..].copy_from_slice(&FbValue::new(v).0);
+        Self(bytes)
+    }
+}
+
+impl Push for FbEntry {
+    type Output = FbEntry;
+
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        dst[..24].copy_from_slice(&self.0);
+    }
+
+    fn alignment() -> PushAlignment {
+        PushAlignment::new(8)
+    }
+}
+
+/// Creates a table whose only field is the given vector.
+fn single_field_table<T>(
+    fbb: &mut FlatBufferBuilder,
+    field: u16,
+    vector: WIPOffset<T>,
+) -> WIPOffset<()> {
+    let table = fbb.start_table();
+    fbb.push_slot_always(field, vector);
+    WIPOffset::new(fbb.end_table(table).value())
+}
+
+impl Jinterners {
+    /// Writes this arena as a FlatBuffer following the
+    /// [`FLATBUFFERS_SCHEMA`], together with the given root values.
+    ///
+    /// Consumers in other languages can then access the arena in place,
+    /// without deserializing it, with code generated from the schema. Values
+    /// are encoded as a tag and a payload, and reference the entries of the
+    /// arena by index.
+    ///
+    /// ```
+    /// use jinterner::{FLATBUFFERS_SCHEMA, Jinterners};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let root = interners.intern(json!({"name": "John"}));
+    ///
+    /// let mut buffer = Vec::new();
+    /// interners.write_flatbuffer(&[root], &mut buffer)?;
+    /// assert_eq!(&buffer[4..8], b"JINT");
+    /// assert!(FLATBUFFERS_SCHEMA.contains("root_type Arena;"));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn write_flatbuffer(&self, roots: &[IValue], w: &mut impl Write) -> io::Result<()> {
+        let mut fbb = FlatBufferBuilder::with_capacity(
+            self.string.bytes() + 16 * (self.iarray.slices() + self.iobject.slices()),
+        );
+
+        let strings: Vec<_> = self.string.iter().map(|s| fbb.create_string(s)).collect();
+        let strings = fbb.create_vector(&strings);
+        let mut items = Vec::new();
+        let arrays: Vec<_> = self
+            .iarray
+            .iter()
+            .map(|array| {
+                items.clear();
+                items.extend(array.iter().map(FbValue::new));
+                let items = fbb.create_vector(&items);
+                single_field_table(&mut fbb, ARRAY_ITEMS, items)
+            })
+            .collect();
+        let arrays = fbb.create_vector(&arrays);
+        let mut entries = Vec::new();
+        let objects: Vec<_> = self
+            .iobject
+            .iter()
+            .map(|object| {
+                entries.clear();
+                entries.extend(object.iter().map(FbEntry::new));
+                let entries = fbb.create_vector(&entries);
+                single_field_table(&mut fbb, OBJECT_ENTRIES, entries)
+            })
+            .collect();
+        let objects = fbb.create_vector(&objects);
+        let roots: Vec<_> = roots.iter().map(FbValue::new).collect();
+        let roots = fbb.create_vector(&roots);
+
+        let arena = fbb.start_table();
+        fbb.push_slot_always(ARENA_STRINGS, strings);
+        fbb.push_slot_always(ARENA_ARRAYS, arrays);
+        fbb.push_slot_always(ARENA_OBJECTS, objects);
+        fbb.push_slot_always(ARENA_ROOTS, roots);
+        let arena = fbb.end_table(arena);
+        fbb.finish(arena, Some(FILE_IDENTIFIER));
+        w.write_all(fbb.finished_data())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::detail::IValueImpl;
+    use flatbuffers::{Follow, ForwardsUOffset, Table, Vector};
+    use serde_json::{Map, Value, json};
+
+    impl<'a> Follow<'a> for FbValue {
+        type Inner = IValue;
+
+        unsafe fn follow(buf: &'a [u8], loc: usize) -> IValue {
+            let payload = u64::from_le_bytes(buf[loc..loc + 8].try_into().unwrap());
+            IValue::from_raw_parts(buf[loc + 8].into(), payload).unwrap()
+        }
+    }
+
+    impl<'a> Follow<'a> for FbEntry {
+        type Inner = (u32, IValue);
+
+        unsafe fn follow(buf: &'a [u8], loc: usize) -> (u32, IValue) {
+            let key = u32::from_le_bytes(buf[loc..loc + 4].try_into().unwrap());
+            // SAFETY: The value of an entry is 8 bytes after its key.
+            (key, unsafe { FbValue::follow(buf, loc + 8) })
+        }
+    }
+
+    /// Reads the given vector field of a table.
+    fn field<'a, T: Follow<'a> + 'a>(table: &Table<'a>, field: u16) -> Vector<'a, T> {
+        // SAFETY: The buffer was written with this field being a vector of
+        // `T`.
+        unsafe { table.get::<ForwardsUOffset<Vector<'a, T>>>(field, None) }.unwrap()
+    }
+
+    /// Decodes the given value from a buffer, as a consumer would.
+    fn decode(arena: &Table<'_>, value: IValue) -> Value {
+        match value.0 {
+            IValueImpl::String(s) => Value::String(
+                field::<ForwardsUOffset<&str>>(arena, ARENA_STRINGS)
+                    .get(s.id() as usize)
+                    .into(),
+            ),
+            IValueImpl::Array(a) => {
+                let array =
+                    field::<ForwardsUOffset<Table>>(arena, ARENA_ARRAYS).get(a.id() as usize);
+                let items = field::<FbValue>(&array, ARRAY_ITEMS);
+                items.iter().map(|item| decode(arena, item)).collect()
+            }
+            IValueImpl::Object(o) => {
+                let object =
+                    field::<ForwardsUOffset<Table>>(arena, ARENA_OBJECTS).get(o.id() as usize);
+                let strings = field::<ForwardsUOffset<&str>>(arena, ARENA_STRINGS);
+                let entries = field::<FbEntry>(&object, OBJECT_ENTRIES);
+                let map: Map<String, Value> = entries
+                    .iter()
+                    .map(|(k, v)| (strings.get(k as usize).to_owned(), decode(arena, v)))
+                    .collect();
+                Value::Object(map)
+            }
+            IValueImpl::Null => Value::Null,
+            IValueImpl::Bool(x) => x.into(),
+            IValueImpl::U64(x) => x.into(),
+            IValueImpl::I64(x) => x.into(),
+            IValueImpl::F64(x) => x.0.0.into(),
+        }
+    }
+
+    #[test]
+    fn flatbuffer() {
+        let interners = Jinterners::default();
+        let values = [
+            json!({"name": "John", "tags": ["a", {"b": [1.5, -2, null, true]}], "empty": {}}),
+            json!([[], "a", u64::MAX]),
+        ];
+        let roots: Vec<IValue> = values.iter().map(|v| interners.intern_ref(v)).collect();
+
+        let mut buffer = Vec::new();
+        interners.write_flatbuffer(&roots, &mut buffer).unwrap();
+        assert!(flatbuffers::buffer_has_identifier(
+            &buffer,
+            FILE_IDENTIFIER,
+            false
+        ));
+
+        let root = u32::from_le_bytes(buffer[..4].try_into().unwrap()) as usize;
+        // SAFETY: A finished buffer starts with the offset of its root table.
+        let arena = unsafe { Table::new(&buffer, root) };
+        let decoded_roots = field::<FbValue>(&arena, ARENA_ROOTS);
+        assert_eq!(decoded_roots.len(), roots.len());
+        for (i, value) in values.iter().enumerate() {
+            assert_eq!(decode(&arena, decoded_roots.get(i)), *value);
+        }
+        assert_eq!(
+            field::<ForwardsUOffset<Table>>(&arena, ARENA_OBJECTS).len(),
+            interners.iobject.slices()
+        );
+    }
+}
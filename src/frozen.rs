@@ -0,0 +1,103 @@
+use crate::{IValue, Jinterners, ValueRef};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// A read-only, cheaply cloneable handle to a [`Jinterners`] arena.
+///
+/// No value can be interned through this handle, and its lookup methods never
+/// take a lock. This makes it suitable for latency-sensitive threads that must
+/// not contend with threads interning new values.
+///
+/// This struct is created by the [`freeze()`](Jinterners::freeze) and
+/// [`snapshot()`](Jinterners::snapshot) methods on [`Jinterners`].
+#[derive(Clone, Debug)]
+pub struct FrozenView {
+    jinterners: Arc<Jinterners>,
+}
+
+impl FrozenView {
+    /// Retrieves the given interned value from the underlying arena, without
+    /// allocating.
+    ///
+    /// The caller is responsible for ensuring that the same arena was used to
+    /// intern this value, otherwise an arbitrary value will be returned or
+    /// a panic will happen.
+    pub fn lookup_ref(&self, value: &IValue) -> ValueRef<'_> {
+        self.jinterners.lookup_ref(value)
+    }
+
+    /// Retrieves the given interned value from the underlying arena.
+    ///
+    /// The caller is responsible for ensuring that the same arena was used to
+    /// intern this value, otherwise an arbitrary value will be returned or
+    /// a panic will happen.
+    ///
+    /// Contrary to [`lookup_ref()`](Self::lookup_ref), this allocates the
+    /// resulting deep copy of the value.
+    pub fn lookup(&self, value: &IValue) -> Value {
+        self.jinterners.lookup(value)
+    }
+
+    /// Returns the underlying arena if this is the only handle to it, or this
+    /// handle otherwise.
+    pub fn thaw(self) -> Result<Jinterners, Self> {
+        Arc::try_unwrap(self.jinterners).map_err(|jinterners| Self { jinterners })
+    }
+}
+
+impl From<Jinterners> for FrozenView {
+    fn from(jinterners: Jinterners) -> Self {
+        Self {
+            jinterners: Arc::new(jinterners),
+        }
+    }
+}
+
+impl Jinterners {
+    /// Converts this arena into a read-only handle.
+    pub fn freeze(self) -> FrozenView {
+        FrozenView::from(self)
+    }
+
+    /// Returns a read-only handle to a copy of this arena.
+    ///
+    /// Values interned in this arena afterwards aren't visible through the
+    /// snapshot.
+    pub fn snapshot(&self) -> FrozenView {
+        FrozenView::from(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn freeze() {
+        fn assert_send_sync_static<T: Send + Sync + 'static>() {}
+        assert_send_sync_static::<FrozenView>();
+
+        let jinterners = Jinterners::default();
+        let value = json!({"name": "John", "tags": ["a", "b"]});
+        let ivalue = jinterners.intern_ref(&value);
+
+        let snapshot = jinterners.snapshot();
+        jinterners.intern(json!(["c"]));
+
+        let frozen = jinterners.freeze();
+        let clone = frozen.clone();
+        assert_eq!(frozen.lookup(&ivalue), value);
+        assert_eq!(snapshot.lookup(&ivalue), value);
+        match clone.lookup_ref(&ivalue) {
+            ValueRef::Object(map) => {
+                assert_eq!(clone.lookup(map.get("name").unwrap()), json!("John"))
+            }
+            _ => panic!("expected an object"),
+        }
+
+        let frozen = frozen.thaw().unwrap_err();
+        drop(clone);
+        assert!(frozen.thaw().is_ok());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{IValue, InternedStrKey, Jinterners};
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// A hook to observe or rewrite values before they're added to a
+/// [`Jinterners`] arena, for example to truncate giant strings, drop
+/// denylisted keys or normalize timestamps.
+///
+/// A hook can be installed with
+/// [`set_intern_hook()`](Jinterners::set_intern_hook). It then applies to the
+/// values built from external data by the arena: the `intern*()` and
+/// `parse*()` methods, deserialization with `IValue::from_value()`, builders
+/// and integrations, so that policy code doesn't need to wrap every call site.
+/// It also applies to the values looked up by [`find()`](Jinterners::find),
+/// [`find_key()`](Jinterners::find_key) and `IValue::eq_json_str()`.
+///
+/// It doesn't apply to entries copied verbatim from another arena, which are
+/// expected to already be rewritten: increments applied with
+/// [`apply_increment()`](Jinterners::apply_increment), streamed deltas,
+/// merged arenas and the strings of a segment `Dictionary`.
+///
+/// ```
+/// use jinterner::{IValue, InternHook, Jinterners};
+/// use serde_json::json;
+/// use std::sync::Arc;
+///
+/// struct Redact;
+///
+/// impl InternHook for Redact {
+///     fn rewrite_string(&self, value: &str) -> Option<String> {
+///         (value.len() > 8).then(|| format!("{}...", &value[..5]))
+///     }
+///
+///     fn rewrite_field(&self, _: &Jinterners, key: &str, value: IValue) -> Option<IValue> {
+///         (key != "password").then_some(value)
+///     }
+/// }
+///
+/// let mut interners = Jinterners::default();
+/// interners.set_intern_hook(Some(Arc::new(Redact)));
+///
+/// let ivalue = interners.intern(json!({"user": "john", "password": "hunter2", "bio": "Lorem ipsum"}));
+/// assert_eq!(
+///     interners.lookup(&ivalue),
+///     json!({"user": "john", "bio": "Lorem..."})
+/// );
+/// ```
+pub trait InternHook: Send + Sync {
+    /// Rewrites a string before it's interned, returning [`None`] to keep it
+    /// unchanged.
+    ///
+    /// This applies to string values as well as object keys. It also applies
+    /// to strings that are looked up in the arena, so that looking up a string
+    /// finds its rewritten version.
+    fn rewrite_string(&self, value: &str) -> Option<String> {
+        let _ = value;
+        None
+    }
+
+    /// Rewrites a field of an object before the object is interned, returning
+    /// the value to keep for this field or [`None`] to drop the field.
+    ///
+    /// The given value has already been interned into the arena, so a dropped
+    /// value still occupies space until unused entries are removed from the
+    /// arena. A replacement value can be interned into the given arena.
+    ///
+    /// This also applies to objects that are looked up in the arena, so this
+    /// may intern a replacement value even when nothing else is interned.
+    fn rewrite_field(&self, jinterners: &Jinterners, key: &str, value: IValue) -> Option<IValue> {
+        let _ = (jinterners, key);
+        Some(value)
+    }
+}
+
+/// The optional [`InternHook`] installed on a [`Jinterners`].
+///
+/// This is ignored when comparing, measuring or serializing arenas.
+#[derive(Clone, Default)]
+pub(crate) struct InstalledHook(Option<Arc<dyn InternHook>>);
+
+impl InstalledHook {
+    pub(crate) fn is_installed(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Applies the hook to a string about to be interned or looked up.
+    pub(crate) fn rewrite_string<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        match self.0.as_ref().and_then(|hook| hook.rewrite_string(value)) {
+            Some(rewritten) => Cow::Owned(rewritten),
+            None => Cow::Borrowed(value),
+        }
+    }
+
+    /// Applies the hook to the fields of an object about to be interned.
+    pub(crate) fn rewrite_fields<'a>(
+        &self,
+        jinterners: &Jinterners,
+        entries: Cow<'a, [(InternedStrKey, IValue)]>,
+    ) -> Cow<'a, [(InternedStrKey, IValue)]> {
+        match &self.0 {
+            None => entries,
+            Some(hook) => entries
+                .iter()
+                .filter_map(|(k, v)| {
+                    let key = jinterners.string.lookup(k.0);
+                    Some((*k, hook.rewrite_field(jinterners, key, *v)?))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Debug for InstalledHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("InstalledHook")
+            .field(&self.0.as_ref().map(|_| ..))
+            .finish()
+    }
+}
+
+impl PartialEq for InstalledHook {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for InstalledHook {}
+
+impl Jinterners {
+    /// Installs a hook to observe or rewrite values before they're interned
+    /// into this arena, or removes it if [`None`] is passed. See
+    /// [`InternHook`].
+    ///
+    /// The hook is preserved in arenas derived from this one (for example with
+    /// [`optimize()`](Self::optimize)), but isn't serialized. Values already
+    /// in the arena aren't rewritten.
+    pub fn set_intern_hook(&mut self, hook: Option<Arc<dyn InternHook>>) {
+        self.intern_hook = InstalledHook(hook);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    /// Normalizes dates written as `DD/MM/YYYY` and replaces `secret` fields
+    /// by `null`.
+    struct Normalize;
+
+    impl InternHook for Normalize {
+        fn rewrite_string(&self, value: &str) -> Option<String> {
+            let mut parts = value.split('/');
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(d), Some(m), Some(y), None) => Some(format!("{y}-{m}-{d}")),
+                _ => None,
+            }
+        }
+
+        fn rewrite_field(
+            &self,
+            jinterners: &Jinterners,
+            key: &str,
+            value: IValue,
+        ) -> Option<IValue> {
+            match key {
+                "secret" => Some(jinterners.intern_ref(&json!(null))),
+                "drop" => None,
+                _ => Some(value),
+            }
+        }
+    }
+
+    #[test]
+    fn intern_hook() {
+        let mut interners = Jinterners::default();
+        interners.set_intern_hook(Some(Arc::new(Normalize)));
+
+        let value = json!({
+            "date": "18/10/2026",
+            "list": [{"secret": "x", "drop": 1, "a/b/c": true}],
+            // Keys rewritten to the same string are merged.
+            "1/2/3": 1,
+            "3-2-1": 1,
+        });
+        let expected = json!({
+            "date": "2026-10-18",
+            "list": [{"secret": null, "c-b-a": true}],
+            "3-2-1": 1,
+        });
+        let a = interners.intern_ref(&value);
+        let b = interners.intern_mut(value.clone());
+        assert_eq!(a, b);
+        assert_eq!(interners.lookup(&a), expected);
+        #[cfg(feature = "serde")]
+        {
+            let c = interners.parse(&value.to_string()).unwrap();
+            assert_eq!(a, c);
+        }
+
+        // Lookups see the rewritten strings and fields.
+        assert!(interners.find(&json!("18/10/2026")).is_some());
+        assert_eq!(interners.find(&value), Some(a));
+        #[cfg(feature = "serde")]
+        assert!(a.eq_json_str(&interners, &value.to_string()).unwrap());
+        assert_eq!(interners.find_key("a/b/c"), interners.find_key("c-b-a"));
+
+        // The hook doesn't affect equality, and can be removed.
+        let other = interners.clone();
+        interners.set_intern_hook(None);
+        assert_eq!(interners, other);
+        assert_eq!(interners.lookup(&interners.intern_ref(&value)), value);
+    }
+}
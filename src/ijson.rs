@@ -0,0 +1,100 @@
+use crate::{Bound, IValue, Jinterners, ValueRef};
+use ijson::{IArray, INumber, IObject, IString};
+
+impl Jinterners {
+    /// Interns the given [`ijson::IValue`] into this arena, without
+    /// converting it to a [`serde_json::Value`] first.
+    ///
+    /// Numbers that don't fit in a [`u64`], [`i64`] or [`f64`] are rounded to
+    /// the nearest [`f64`], as when parsing them with [`serde_json`].
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let value = ijson::ijson!({"name": "John", "tags": [1, -2, 1.5]});
+    ///
+    /// let ivalue = interners.intern_ijson(&value);
+    /// assert_eq!(
+    ///     interners.lookup(&ivalue),
+    ///     json!({"name": "John", "tags": [1, -2, 1.5]})
+    /// );
+    /// assert_eq!(interners.lookup_ijson(&ivalue), value);
+    /// ```
+    pub fn intern_ijson(&self, source: &ijson::IValue) -> IValue {
+        IValue::from_ijson(self, source)
+    }
+
+    /// Retrieves the given interned value as an [`ijson::IValue`], without
+    /// converting it to a [`serde_json::Value`] first.
+    ///
+    /// Non-finite floats are converted to null, as with
+    /// [`lookup()`](Self::lookup).
+    pub fn lookup_ijson(&self, value: &IValue) -> ijson::IValue {
+        self.bind(*value).into()
+    }
+}
+
+impl From<Bound<'_>> for ijson::IValue {
+    fn from(bound: Bound<'_>) -> Self {
+        let interners = bound.interners();
+        match bound.lookup_ref() {
+            ValueRef::Null => ijson::IValue::NULL,
+            ValueRef::Bool(x) => x.into(),
+            ValueRef::U64(x) => INumber::from(x).into(),
+            ValueRef::I64(x) => INumber::from(x).into(),
+            ValueRef::F64(x) => INumber::try_from(x).map_or(ijson::IValue::NULL, Into::into),
+            ValueRef::String(x) => IString::intern(x).into(),
+            ValueRef::Array(array) => {
+                let mut result = IArray::with_capacity(array.len());
+                for item in array {
+                    result.push(interners.lookup_ijson(item));
+                }
+                result.into()
+            }
+            ValueRef::Object(map) => {
+                let mut result = IObject::with_capacity(map.iter().len());
+                for (key, item) in map {
+                    result.insert(key, interners.lookup_ijson(item));
+                }
+                result.into()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::detail::{Float64, IValueImpl};
+    use ijson::ijson;
+    use ordered_float::OrderedFloat;
+    use serde_json::json;
+
+    #[test]
+    fn ijson() {
+        let interners = Jinterners::default();
+        let value = ijson!({
+            "null": null,
+            "bool": true,
+            "numbers": [0, 1.0, -1, u64::MAX, 0.5],
+            "nested": {"a": ["x", {}]},
+        });
+        let ivalue = interners.intern_ijson(&value);
+        assert_eq!(
+            interners.lookup(&ivalue),
+            json!({
+                "null": null,
+                "bool": true,
+                "numbers": [0, 1.0, -1, u64::MAX, 0.5],
+                "nested": {"a": ["x", {}]},
+            })
+        );
+        assert_eq!(ivalue, interners.intern_ref(&interners.lookup(&ivalue)));
+        assert_eq!(interners.lookup_ijson(&ivalue), value);
+
+        let nan = IValue(IValueImpl::F64(Float64(OrderedFloat(f64::NAN))));
+        assert_eq!(interners.lookup_ijson(&nan), ijson::IValue::NULL);
+    }
+}
@@ -0,0 +1,511 @@
+use crate::detail::IValueImpl;
+use crate::integrity::Fnv;
+use crate::validate::find_cycles;
+use crate::{ArenaKind, Checksums, Error, IValue, Integrity, InternedStrKey, Jinterners};
+use blazinterner::{InternedSlice, InternedStr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The number of entries in each underlying arena of a [`Jinterners`].
+///
+/// As arenas are append-only, a watermark identifies a prefix of an arena,
+/// from which an [`Increment`] can be computed.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Watermark {
+    /// Number of strings.
+    pub strings: usize,
+    /// Number of arrays.
+    pub arrays: usize,
+    /// Number of objects.
+    pub objects: usize,
+}
+
+/// The entries appended to a [`Jinterners`] arena between two
+/// [`Watermark`]s, created by
+/// [`increment_since()`](Jinterners::increment_since).
+///
+/// Applying an increment to a copy of the arena at its start watermark with
+/// [`apply_increment()`](Jinterners::apply_increment) brings the copy to the
+/// end watermark, with the same identifiers as the original arena. This
+/// allows mirroring an arena without copying it entirely each time. An
+/// increment carries the [`Checksums`] of the original arena at its end
+/// watermark, which are verified before applying it.
+///
+/// ```
+/// use jinterner::Jinterners;
+/// use serde_json::json;
+///
+/// let primary = Jinterners::default();
+/// let follower = Jinterners::default();
+///
+/// let a = primary.intern(json!({"name": "John"}));
+/// let increment = primary.increment_since(follower.watermark())?;
+/// follower.apply_increment(&increment)?;
+/// assert_eq!(follower.lookup(&a), json!({"name": "John"}));
+///
+/// let b = primary.intern(json!(["John", "Jane"]));
+/// let increment = primary.increment_since(follower.watermark())?;
+/// assert_eq!(increment.strings(), 1);
+/// follower.apply_increment(&increment)?;
+/// assert_eq!(follower.lookup(&b), json!(["John", "Jane"]));
+/// assert_eq!(follower, primary);
+/// # Ok::<(), jinterner::Error>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Increment {
+    from: Watermark,
+    strings: Vec<Box<str>>,
+    arrays: Vec<Box<[IValue]>>,
+    objects: Vec<Box<[(InternedStrKey, IValue)]>>,
+    checksums: Checksums,
+}
+
+impl Increment {
+    /// Returns the watermark that this increment starts from.
+    pub fn from(&self) -> Watermark {
+        self.from
+    }
+
+    /// Returns the watermark that this increment leads to.
+    pub fn to(&self) -> Watermark {
+        Watermark {
+            strings: self.from.strings + self.strings.len(),
+            arrays: self.from.arrays + self.arrays.len(),
+            objects: self.from.objects + self.objects.len(),
+        }
+    }
+
+    /// Returns the checksums of the original arena at the end watermark of
+    /// this increment.
+    pub fn checksums(&self) -> Checksums {
+        self.checksums
+    }
+
+    /// Returns the number of strings in this increment.
+    pub fn strings(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns the number of arrays in this increment.
+    pub fn arrays(&self) -> usize {
+        self.arrays.len()
+    }
+
+    /// Returns the number of objects in this increment.
+    pub fn objects(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Returns whether this increment doesn't contain any entry.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty() && self.arrays.is_empty() && self.objects.is_empty()
+    }
+
+    /// Checks that all the entries of this increment only reference entries
+    /// below its end watermark, that they don't contain themselves, and that
+    /// objects are sorted by key.
+    ///
+    /// Entries may reference entries interned after them, as in the arenas
+    /// returned by [`Jinterners::optimize()`].
+    fn validate(&self) -> Result<(), Error> {
+        let (from, to) = (self.from, self.to());
+        for array in &self.arrays {
+            check_array(array, to)?;
+        }
+        for object in &self.objects {
+            check_object(object, to)?;
+        }
+        let mut cycle = false;
+        find_cycles(
+            [to.arrays, to.objects],
+            [from.arrays, from.objects],
+            |(arena, index)| -> Box<dyn Iterator<Item = IValue> + '_> {
+                if arena == 0 {
+                    Box::new(self.arrays[index - from.arrays].iter().copied())
+                } else {
+                    Box::new(self.objects[index - from.objects].iter().map(|(_, v)| *v))
+                }
+            },
+            |_, _| cycle = true,
+        );
+        if cycle {
+            return Err(CYCLE);
+        }
+        Ok(())
+    }
+
+    /// Returns the checksums of an arena with the given checksums at the
+    /// start watermark of this increment once this increment is applied.
+    fn extend_checksums(&self, checksums: Checksums) -> Checksums {
+        let mut strings = Fnv::resume(checksums.strings);
+        self.strings.iter().for_each(|s| strings.write_str(s));
+        let mut arrays = Fnv::resume(checksums.arrays);
+        self.arrays.iter().for_each(|a| arrays.write_array(a));
+        let mut objects = Fnv::resume(checksums.objects);
+        self.objects.iter().for_each(|o| objects.write_object(o));
+        Checksums {
+            strings: strings.finish(),
+            arrays: arrays.finish(),
+            objects: objects.finish(),
+        }
+    }
+}
+
+/// The error returned when the entries of an increment contain themselves.
+pub(crate) const CYCLE: Error = Error::Replication {
+    reason: "an entry contains itself",
+};
+
+/// Checks that the given value only references entries below the given
+/// watermark.
+pub(crate) fn check_value(value: &IValue, below: Watermark) -> Result<(), Error> {
+    let (arena, id, len) = match value.0 {
+        IValueImpl::String(s) => (ArenaKind::String, s.id(), below.strings),
+        IValueImpl::Array(a) => (ArenaKind::Array, a.id(), below.arrays),
+        IValueImpl::Object(o) => (ArenaKind::Object, o.id(), below.objects),
+        _ => return Ok(()),
+    };
+    if id as usize >= len {
+        return Err(Error::InvalidId { arena, id });
+    }
+    Ok(())
+}
+
+/// Checks that an arena holding the given number of entries, whose contents
+/// total the given number of items (bytes for strings), can be indexed by
+/// `u32` identifiers and offsets.
+pub(crate) fn check_capacity(arena: ArenaKind, entries: usize, items: usize) -> Result<(), Error> {
+    if entries > u32::MAX as usize || items > u32::MAX as usize {
+        return Err(Error::Capacity { arena });
+    }
+    Ok(())
+}
+
+/// Checks that the items of an array only reference entries below the given
+/// watermark.
+pub(crate) fn check_array(items: &[IValue], to: Watermark) -> Result<(), Error> {
+    items.iter().try_for_each(|v| check_value(v, to))
+}
+
+/// Checks that the entries of an object only reference entries below the
+/// given watermark, and are sorted by key.
+pub(crate) fn check_object(
+    entries: &[(InternedStrKey, IValue)],
+    to: Watermark,
+) -> Result<(), Error> {
+    for (k, v) in entries {
+        check_value(&IValue(IValueImpl::String(k.0)), to)?;
+        check_value(v, to)?;
+    }
+    if entries.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+        return Err(Error::Replication {
+            reason: "object keys aren't sorted",
+        });
+    }
+    Ok(())
+}
+
+impl Jinterners {
+    /// Returns the current [`Watermark`] of this arena.
+    ///
+    /// If values are concurrently interned into this arena, the watermark
+    /// may exclude the most recent entries, but always delimits a consistent
+    /// prefix of the arena, i.e. entries below the watermark only reference
+    /// entries below the watermark.
+    pub fn watermark(&self) -> Watermark {
+        // Entries interned concurrently only reference entries interned
+        // before them. Objects are counted before and after the arrays, and
+        // the objects interned in between are checked for references to
+        // arrays interned after the arrays were counted. Strings are counted
+        // last.
+        let objects_before = self.iobject.slices();
+        let arrays = self.iarray.slices();
+        let objects_after = self.iobject.slices();
+        let objects = (objects_before..objects_after)
+            .find(|i| {
+                self.iobject
+                    .lookup(InternedSlice::from_id(*i as u32))
+                    .iter()
+                    .any(|(_, v)| matches!(v.0, IValueImpl::Array(a) if a.id() as usize >= arrays))
+            })
+            .unwrap_or(objects_after);
+        Watermark {
+            strings: self.string.strings(),
+            arrays,
+            objects,
+        }
+    }
+
+    /// Returns the entries of this arena between the given watermark and the
+    /// current [`watermark()`](Self::watermark).
+    ///
+    /// Returns [`Error::Replication`] if the given watermark is beyond the
+    /// current watermark of this arena.
+    pub fn increment_since(&self, from: Watermark) -> Result<Increment, Error> {
+        let to = self.watermark();
+        if from.strings > to.strings || from.arrays > to.arrays || from.objects > to.objects {
+            return Err(Error::Replication {
+                reason: "the watermark is beyond the arena",
+            });
+        }
+        Ok(Increment {
+            from,
+            strings: (from.strings..to.strings)
+                .map(|i| self.string.lookup(InternedStr::from_id(i as u32)).into())
+                .collect(),
+            arrays: (from.arrays..to.arrays)
+                .map(|i| self.iarray.lookup(InternedSlice::from_id(i as u32)).into())
+                .collect(),
+            objects: (from.objects..to.objects)
+                .map(|i| self.iobject.lookup(InternedSlice::from_id(i as u32)).into())
+                .collect(),
+            checksums: self
+                .checksums(to)
+                .expect("the watermark of an arena is within the arena"),
+        })
+    }
+
+    /// Appends the entries of the given increment to this arena, returning
+    /// the new watermark of this arena.
+    ///
+    /// The entries are copied as is: they aren't rewritten by the installed
+    /// [`InternHook`](crate::InternHook) nor normalized, and aren't reported
+    /// to key watchers.
+    ///
+    /// Returns [`Error::Replication`] if this arena isn't at the start
+    /// watermark of the increment or if an entry of the increment contains
+    /// itself, [`Error::InvalidId`] if the increment references entries that
+    /// neither this arena nor the increment contain, [`Error::Capacity`] if
+    /// this arena can't hold the entries of the increment, and
+    /// [`Error::Integrity`] if this arena diverged from the arena that the
+    /// increment was computed from, or if the increment was corrupted. These
+    /// errors are detected before modifying this arena. Values shouldn't be
+    /// interned into this arena by other means, otherwise the arena may
+    /// diverge. If an error happens while entries are being appended, the
+    /// arena contains part of the increment and needs to be resynchronized.
+    pub fn apply_increment(&self, increment: &Increment) -> Result<Watermark, Error> {
+        let diverged = Error::Replication {
+            reason: "the arena diverged from the increment",
+        };
+        let from = increment.from;
+        if self.string.strings() != from.strings
+            || self.iarray.slices() != from.arrays
+            || self.iobject.slices() != from.objects
+        {
+            return Err(Error::Replication {
+                reason: "the arena isn't at the start of the increment",
+            });
+        }
+        increment.validate()?;
+        let checksums = increment.extend_checksums(
+            self.checksums(from)
+                .expect("the arena is at the start of the increment"),
+        );
+        checksums.check(&increment.checksums)?;
+        let to = increment.to();
+        let bytes: usize = increment.strings.iter().map(|s| s.len()).sum();
+        check_capacity(ArenaKind::String, to.strings, self.string.bytes() + bytes)?;
+        let items: usize = increment.arrays.iter().map(|a| a.len()).sum();
+        check_capacity(ArenaKind::Array, to.arrays, self.iarray.items() + items)?;
+        let items: usize = increment.objects.iter().map(|o| o.len()).sum();
+        check_capacity(ArenaKind::Object, to.objects, self.iobject.items() + items)?;
+
+        for (i, string) in increment.strings.iter().enumerate() {
+            if self.string.intern(string).id() as usize != from.strings + i {
+                return Err(diverged);
+            }
+            self.string_filter.insert(string);
+        }
+        for (i, array) in increment.arrays.iter().enumerate() {
+            if self.iarray.intern_copy(array).id() as usize != from.arrays + i {
+                return Err(diverged);
+            }
+        }
+        for (i, object) in increment.objects.iter().enumerate() {
+            if self.iobject.intern_copy(object).id() as usize != from.objects + i {
+                return Err(diverged);
+            }
+        }
+        self.integrity.update(Integrity {
+            watermark: to,
+            checksums,
+        });
+        Ok(to)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn increment() {
+        let primary = Jinterners::default();
+        let follower = Jinterners::default();
+        let values = [
+            json!({"a": [1, "x"], "b": {"c": null}}),
+            json!([{"a": []}, "y"]),
+            json!({"a": [1, "x"], "d": "x"}),
+        ];
+        for value in &values {
+            let ivalue = primary.intern_ref(value);
+            let increment = primary.increment_since(follower.watermark()).unwrap();
+            assert_eq!(increment.from(), follower.watermark());
+            assert_eq!(increment.to(), primary.watermark());
+            assert_eq!(
+                follower.apply_increment(&increment).unwrap(),
+                primary.watermark()
+            );
+            assert_eq!(follower.lookup(&ivalue), *value);
+        }
+        assert_eq!(follower, primary);
+        assert_eq!(follower.validate(), Ok(()));
+        assert!(
+            primary
+                .increment_since(follower.watermark())
+                .unwrap()
+                .is_empty()
+        );
+
+        // Increments only apply at their start watermark.
+        let increment = primary.increment_since(Watermark::default()).unwrap();
+        assert!(matches!(
+            follower.apply_increment(&increment),
+            Err(Error::Replication { .. })
+        ));
+        assert!(matches!(
+            Jinterners::default().increment_since(primary.watermark()),
+            Err(Error::Replication { .. })
+        ));
+
+        // A follower that diverged is detected, as well as a corrupted
+        // increment.
+        let follower = Jinterners::default();
+        follower.intern(json!("y"));
+        let mut increment = primary.increment_since(follower.watermark()).unwrap();
+        assert!(matches!(
+            follower.apply_increment(&increment),
+            Err(Error::Integrity {
+                arena: ArenaKind::String
+            })
+        ));
+        assert_eq!(follower.watermark().strings, 1);
+        let mut corrupted = primary.increment_since(Watermark::default()).unwrap();
+        corrupted.arrays.swap(0, 1);
+        assert!(matches!(
+            Jinterners::default().apply_increment(&corrupted),
+            Err(Error::Integrity {
+                arena: ArenaKind::Array
+            })
+        ));
+
+        // Dangling references are rejected before modifying the arena.
+        let follower = Jinterners::default();
+        increment.from = Watermark::default();
+        increment.strings.clear();
+        assert!(matches!(
+            follower.apply_increment(&increment),
+            Err(Error::InvalidId {
+                arena: ArenaKind::String,
+                ..
+            })
+        ));
+        assert_eq!(follower, Jinterners::default());
+    }
+
+    #[test]
+    fn cyclic_increment() {
+        let array = |id| IValue(IValueImpl::Array(InternedSlice::from_id(id)));
+        let object = |id| IValue(IValueImpl::Object(InternedSlice::from_id(id)));
+        let key = InternedStrKey(InternedStr::from_id(0));
+        let forge = |arrays: Vec<Box<[IValue]>>, objects: Vec<Box<[(InternedStrKey, IValue)]>>| {
+            let mut increment = Increment {
+                from: Watermark::default(),
+                strings: vec!["k".into()],
+                arrays,
+                objects,
+                checksums: Checksums::default(),
+            };
+            // Checksums are recomputed, as a malicious producer would.
+            increment.checksums = increment.extend_checksums(Checksums::default());
+            increment
+        };
+
+        // An array containing itself.
+        let follower = Jinterners::default();
+        let increment = forge(vec![Box::new([array(0)])], vec![]);
+        assert!(matches!(
+            follower.apply_increment(&increment),
+            Err(Error::Replication {
+                reason: "an entry contains itself"
+            })
+        ));
+        assert_eq!(follower, Jinterners::default());
+
+        // An array containing an array past the end of the increment.
+        let increment = forge(vec![Box::new([array(1)])], vec![]);
+        assert!(matches!(
+            follower.apply_increment(&increment),
+            Err(Error::InvalidId {
+                arena: ArenaKind::Array,
+                id: 1
+            })
+        ));
+        assert_eq!(follower, Jinterners::default());
+
+        // An array and an object containing each other.
+        let increment = forge(
+            vec![Box::new([object(0)])],
+            vec![Box::new([(key, array(0))])],
+        );
+        assert!(matches!(
+            follower.apply_increment(&increment),
+            Err(Error::Replication {
+                reason: "an entry contains itself"
+            })
+        ));
+        assert_eq!(follower, Jinterners::default());
+    }
+
+    #[test]
+    fn optimized_increment() {
+        let interners = Jinterners::default();
+        let values = [
+            json!([[1], [[2]], {"a": [3]}]),
+            json!({"b": [[1], {"c": {"d": [4]}}]}),
+            json!([[[2]], [[2]], [1]]),
+        ];
+        for value in &values {
+            interners.intern_ref(value);
+        }
+        let (primary, _) = interners.optimize(None).unwrap();
+        assert_eq!(primary.validate(), Ok(()));
+        // The optimized arena contains entries referencing later entries.
+        let increment = primary.increment_since(Watermark::default()).unwrap();
+        assert!(increment.objects.iter().enumerate().any(|(i, object)| {
+            object
+                .iter()
+                .any(|(_, v)| matches!(v.0, IValueImpl::Object(o) if o.id() as usize > i))
+        }));
+
+        let follower = Jinterners::default();
+        assert_eq!(
+            follower.apply_increment(&increment).unwrap(),
+            primary.watermark()
+        );
+        assert_eq!(follower, primary);
+    }
+
+    #[test]
+    fn capacity() {
+        let max = u32::MAX as usize;
+        assert!(check_capacity(ArenaKind::Array, max, max).is_ok());
+        for (entries, items) in [(max + 1, 0), (0, max + 1)] {
+            let error = check_capacity(ArenaKind::String, entries, items).unwrap_err();
+            assert_eq!(error.to_string(), "the string arena is full");
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{Error, IValue, Jinterners};
+use serde_json::Value;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+
+/// A value to intern, submitted to an [`IngestPool`].
+enum Input {
+    Value(Value),
+    Line(String),
+}
+
+/// A submitted value, together with the channel to send its handle back.
+struct Job {
+    input: Input,
+    reply: SyncSender<Result<IValue, Error>>,
+}
+
+/// A pool of threads interning the values submitted by any number of
+/// producers into a shared [`Jinterners`] arena.
+///
+/// Submitted values wait in a bounded queue, so producers block when the
+/// workers can't keep up. Each submission returns an [`IngestReply`] to
+/// retrieve the interned handle.
+///
+/// This isn't available on WebAssembly, where threads can't be spawned.
+///
+/// ```
+/// use jinterner::{IngestPool, Jinterners};
+/// use serde_json::json;
+/// use std::sync::Arc;
+///
+/// let interners = Arc::new(Jinterners::default());
+/// let pool = IngestPool::new(Arc::clone(&interners), 4, 128);
+///
+/// let producers: Vec<_> = (0..3)
+///     .map(|i| {
+///         let sender = pool.sender();
+///         std::thread::spawn(move || {
+///             let reply = sender.submit_line(format!(r#"{{"producer": {i}}}"#));
+///             reply.wait()
+///         })
+///     })
+///     .collect();
+/// for (i, producer) in producers.into_iter().enumerate() {
+///     let ivalue = producer.join().unwrap()?;
+///     assert_eq!(interners.lookup(&ivalue), json!({"producer": i}));
+/// }
+/// pool.join();
+/// # Ok::<(), jinterner::Error>(())
+/// ```
+pub struct IngestPool {
+    sender: IngestSender,
+    workers: Vec<JoinHandle<()>>,
+}
+
+/// A handle to submit values to an [`IngestPool`], which can be cloned and
+/// sent to producer threads.
+#[derive(Clone)]
+pub struct IngestSender(SyncSender<Job>);
+
+/// The pending result of a value submitted to an [`IngestPool`].
+#[must_use = "the interned handle is only available through the reply"]
+pub struct IngestReply(Receiver<Result<IValue, Error>>);
+
+impl IngestPool {
+    /// Spawns the given number of worker threads, interning values into the
+    /// given arena, with a queue holding up to `capacity` pending values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `workers` is zero.
+    pub fn new(interners: Arc<Jinterners>, workers: usize, capacity: usize) -> Self {
+        assert!(workers > 0, "an ingest pool needs at least one worker");
+        let (sender, receiver) = mpsc::sync_channel::<Job>(capacity);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..workers)
+            .map(|_| {
+                let interners = Arc::clone(&interners);
+                let receiver = Arc::clone(&receiver);
+                std::thread::spawn(move || {
+                    loop {
+                        // The lock is released before interning, so that
+                        // workers intern values concurrently.
+                        let job = receiver
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .recv();
+                        let Ok(job) = job else {
+                            return;
+                        };
+                        let result = match job.input {
+                            Input::Value(value) => Ok(interners.intern(value)),
+                            Input::Line(line) => parse(&interners, &line),
+                        };
+                        // The producer may have dropped the reply.
+                        let _ = job.reply.send(result);
+                    }
+                })
+            })
+            .collect();
+        Self {
+            sender: IngestSender(sender),
+            workers,
+        }
+    }
+
+    /// Returns a handle to submit values to this pool.
+    pub fn sender(&self) -> IngestSender {
+        self.sender.clone()
+    }
+
+    /// Waits for the workers to process all the submitted values and stop.
+    ///
+    /// The workers only stop once all the [`IngestSender`]s are dropped, so
+    /// producers must drop them beforehand.
+    pub fn join(self) {
+        drop(self.sender);
+        for worker in self.workers {
+            // A worker that panicked already failed its pending reply.
+            let _ = worker.join();
+        }
+    }
+}
+
+impl IngestSender {
+    /// Submits a value to intern, blocking while the queue of the pool is
+    /// full.
+    pub fn submit(&self, value: Value) -> IngestReply {
+        self.send(Input::Value(value))
+    }
+
+    /// Submits a line of JSON text to parse and intern, blocking while the
+    /// queue of the pool is full.
+    ///
+    /// Parsing happens on the workers, so that producers only need to split
+    /// their input into lines.
+    pub fn submit_line(&self, line: String) -> IngestReply {
+        self.send(Input::Line(line))
+    }
+
+    fn send(&self, input: Input) -> IngestReply {
+        let (reply, receiver) = mpsc::sync_channel(1);
+        // If all the workers stopped, the reply is dropped and waiting for it
+        // reports the error.
+        let _ = self.0.send(Job { input, reply });
+        IngestReply(receiver)
+    }
+}
+
+impl IngestReply {
+    /// Blocks until the submitted value is interned, and returns its handle.
+    ///
+    /// Returns [`Error::Parse`] if a submitted line isn't valid JSON, and
+    /// [`Error::Ingest`] if the worker processing the value panicked.
+    pub fn wait(self) -> Result<IValue, Error> {
+        self.0.recv().unwrap_or(Err(Error::Ingest {
+            reason: "the worker stopped before interning the value",
+        }))
+    }
+}
+
+/// Parses and interns the given JSON text.
+fn parse(interners: &Jinterners, line: &str) -> Result<IValue, Error> {
+    #[cfg(feature = "serde")]
+    return interners.parse(line);
+    #[cfg(not(feature = "serde"))]
+    return Ok(interners.intern(serde_json::from_str(line)?));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ingest() {
+        let interners = Arc::new(Jinterners::default());
+        let pool = IngestPool::new(Arc::clone(&interners), 3, 2);
+        let results: Vec<Vec<Result<IValue, Error>>> = std::thread::scope(|s| {
+            let producers: Vec<_> = (0..4)
+                .map(|i| {
+                    let sender = pool.sender();
+                    s.spawn(move || {
+                        let replies: Vec<IngestReply> = (0..20)
+                            .map(|j| match j % 3 {
+                                0 => sender.submit(json!({"p": i, "n": [j]})),
+                                1 => sender.submit_line(format!(r#"{{"p": {i}, "n": [{j}]}}"#)),
+                                _ => sender.submit_line("{".into()),
+                            })
+                            .collect();
+                        replies.into_iter().map(IngestReply::wait).collect()
+                    })
+                })
+                .collect();
+            producers.into_iter().map(|p| p.join().unwrap()).collect()
+        });
+        pool.join();
+
+        for (i, results) in results.iter().enumerate() {
+            for (j, result) in results.iter().enumerate() {
+                match result {
+                    Ok(ivalue) => assert_eq!(interners.lookup(ivalue), json!({"p": i, "n": [j]})),
+                    Err(e) => {
+                        assert_eq!(j % 3, 2);
+                        assert!(matches!(e, Error::Parse(_)));
+                    }
+                }
+            }
+        }
+        assert_eq!(interners.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,347 @@
+use crate::{ArenaKind, Error, IValue, InternedStrKey, Jinterners, Watermark};
+use blazinterner::{InternedSlice, InternedStr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::sync::{Mutex, PoisonError};
+
+/// Checksums of the entries of each underlying arena of a [`Jinterners`] below
+/// a [`Watermark`], computed by [`checksums()`](Jinterners::checksums).
+///
+/// Checksums only depend on the entries and their order, so they can be
+/// compared across processes, platforms and versions of this crate. They
+/// detect arenas that diverged, but aren't meant to resist deliberate
+/// collisions.
+///
+/// The [`Default`] checksums are those of an empty arena.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Checksums {
+    /// Checksum of the strings.
+    pub strings: u64,
+    /// Checksum of the arrays.
+    pub arrays: u64,
+    /// Checksum of the objects.
+    pub objects: u64,
+}
+
+impl Default for Checksums {
+    fn default() -> Self {
+        let empty = Fnv::default().finish();
+        Self {
+            strings: empty,
+            arrays: empty,
+            objects: empty,
+        }
+    }
+}
+
+impl Checksums {
+    /// Returns a single checksum combining the checksums of the three arenas,
+    /// which identifies the whole arena below the watermark.
+    pub fn root(&self) -> u64 {
+        let mut root = Fnv::default();
+        root.write_u64(self.strings);
+        root.write_u64(self.arrays);
+        root.write_u64(self.objects);
+        root.finish()
+    }
+
+    /// Returns the first arena whose checksum differs from the expected one.
+    pub(crate) fn check(&self, expected: &Checksums) -> Result<(), Error> {
+        let arena = if self.strings != expected.strings {
+            ArenaKind::String
+        } else if self.arrays != expected.arrays {
+            ArenaKind::Array
+        } else if self.objects != expected.objects {
+            ArenaKind::Object
+        } else {
+            return Ok(());
+        };
+        Err(Error::Integrity { arena })
+    }
+}
+
+/// The checksums of a [`Jinterners`] arena at a watermark, returned by
+/// [`integrity()`](Jinterners::integrity).
+///
+/// Storing it alongside a serialized arena allows checking the arena once
+/// deserialized, with [`verify_integrity()`](Jinterners::verify_integrity).
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Integrity {
+    /// The watermark below which the checksums were computed.
+    pub watermark: Watermark,
+    /// The checksums of the arena below the watermark.
+    pub checksums: Checksums,
+}
+
+/// A 64-bit FNV-1a hasher, whose output is stable contrary to
+/// [`DefaultHasher`](std::hash::DefaultHasher).
+///
+/// The state of the hasher is its output, so that a checksum can be extended
+/// with more entries.
+#[derive(Clone, Copy)]
+pub(crate) struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    /// Resumes hashing after the entries summarized by the given checksum.
+    pub(crate) fn resume(checksum: u64) -> Self {
+        Self(checksum)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, x: u64) {
+        self.write(&x.to_le_bytes());
+    }
+
+    fn write_value(&mut self, value: &IValue) {
+        let (tag, payload) = value.to_raw_parts();
+        self.write_u64(tag);
+        self.write_u64(payload);
+    }
+
+    pub(crate) fn write_str(&mut self, value: &str) {
+        self.write_u64(value.len() as u64);
+        self.write(value.as_bytes());
+    }
+
+    pub(crate) fn write_array(&mut self, items: &[IValue]) {
+        self.write_u64(items.len() as u64);
+        items.iter().for_each(|v| self.write_value(v));
+    }
+
+    pub(crate) fn write_object(&mut self, entries: &[(InternedStrKey, IValue)]) {
+        self.write_u64(entries.len() as u64);
+        for (k, v) in entries {
+            self.write_u64(k.0.id().into());
+            self.write_value(v);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// The checksums of the longest prefix of an arena computed so far, from
+/// which the checksums of longer prefixes are computed incrementally as
+/// entries are appended.
+#[derive(Default)]
+pub(crate) struct IntegrityCache(Mutex<Integrity>);
+
+impl IntegrityCache {
+    fn get(&self) -> Integrity {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Replaces the cached checksums if the given ones cover more entries.
+    pub(crate) fn update(&self, integrity: Integrity) {
+        let mut cached = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let (old, new) = (cached.watermark, integrity.watermark);
+        if new.strings >= old.strings && new.arrays >= old.arrays && new.objects >= old.objects {
+            *cached = integrity;
+        }
+    }
+}
+
+impl Clone for IntegrityCache {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.get()))
+    }
+}
+
+impl Debug for IntegrityCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("IntegrityCache").field(&self.get()).finish()
+    }
+}
+
+impl PartialEq for IntegrityCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for IntegrityCache {}
+
+/// Extends the checksum of the first `done` entries of an arena up to `end`
+/// entries, or computes it from scratch if `end` is below `done`.
+fn extend(done: usize, checksum: u64, end: usize, mut write: impl FnMut(&mut Fnv, u32)) -> u64 {
+    let (start, mut fnv) = if done <= end {
+        (done, Fnv::resume(checksum))
+    } else {
+        (0, Fnv::default())
+    };
+    for i in start..end {
+        write(&mut fnv, i as u32);
+    }
+    fnv.finish()
+}
+
+impl Jinterners {
+    /// Returns the checksums of the entries of this arena below the given
+    /// watermark, or [`None`] if the watermark is beyond this arena.
+    ///
+    /// Checksums are computed incrementally: this takes time proportional to
+    /// the number of entries appended since the longest watermark for which
+    /// checksums were computed, or to the number of entries below the given
+    /// watermark if it's below that one.
+    pub fn checksums(&self, watermark: Watermark) -> Option<Checksums> {
+        if watermark.strings > self.string.strings()
+            || watermark.arrays > self.iarray.slices()
+            || watermark.objects > self.iobject.slices()
+        {
+            return None;
+        }
+        let cached = self.integrity.get();
+        let checksums = Checksums {
+            strings: extend(
+                cached.watermark.strings,
+                cached.checksums.strings,
+                watermark.strings,
+                |fnv, i| fnv.write_str(self.string.lookup(InternedStr::from_id(i))),
+            ),
+            arrays: extend(
+                cached.watermark.arrays,
+                cached.checksums.arrays,
+                watermark.arrays,
+                |fnv, i| fnv.write_array(self.iarray.lookup(InternedSlice::from_id(i))),
+            ),
+            objects: extend(
+                cached.watermark.objects,
+                cached.checksums.objects,
+                watermark.objects,
+                |fnv, i| fnv.write_object(self.iobject.lookup(InternedSlice::from_id(i))),
+            ),
+        };
+        self.integrity.update(Integrity {
+            watermark,
+            checksums,
+        });
+        Some(checksums)
+    }
+
+    /// Returns the checksums of this arena at its current
+    /// [`watermark()`](Self::watermark).
+    ///
+    /// The checksums are extended as entries are appended, so calling this
+    /// regularly only hashes the new entries.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let a = Jinterners::default();
+    /// let b = Jinterners::default();
+    /// a.intern(json!({"name": "John"}));
+    /// let before = a.integrity();
+    /// a.intern(json!({"name": "Jane"}));
+    /// b.intern(json!({"name": "John"}));
+    /// b.intern(json!({"name": "Jane"}));
+    ///
+    /// assert_ne!(a.integrity(), before);
+    /// assert_eq!(a.integrity(), b.integrity());
+    /// assert_eq!(a.integrity().checksums.root(), b.integrity().checksums.root());
+    /// ```
+    pub fn integrity(&self) -> Integrity {
+        let watermark = self.watermark();
+        Integrity {
+            watermark,
+            checksums: self
+                .checksums(watermark)
+                .expect("the watermark of an arena is within the arena"),
+        }
+    }
+
+    /// Checks that the entries of this arena below the watermark of the given
+    /// integrity match its checksums, typically after deserializing an arena
+    /// whose integrity was stored alongside it.
+    ///
+    /// Returns [`Error::Integrity`] with the first arena that doesn't match,
+    /// or [`Error::Replication`] if the watermark is beyond this arena.
+    ///
+    /// ```
+    /// use jinterner::{ArenaKind, Error, Jinterners};
+    /// use serde_json::json;
+    ///
+    /// let jinterners = Jinterners::default();
+    /// jinterners.intern(json!({"name": "John"}));
+    /// let integrity = jinterners.integrity();
+    ///
+    /// // Stands for an arena deserialized together with its integrity.
+    /// let loaded = Jinterners::default();
+    /// loaded.intern(json!({"name": "John"}));
+    /// loaded.verify_integrity(&integrity)?;
+    ///
+    /// // Entries interned afterwards aren't covered by the checksums.
+    /// loaded.intern(json!("Jane"));
+    /// loaded.verify_integrity(&integrity)?;
+    ///
+    /// let other = Jinterners::default();
+    /// other.intern(json!({"name": "Jane"}));
+    /// assert!(matches!(
+    ///     other.verify_integrity(&integrity),
+    ///     Err(Error::Integrity { arena: ArenaKind::String })
+    /// ));
+    /// # Ok::<(), jinterner::Error>(())
+    /// ```
+    pub fn verify_integrity(&self, expected: &Integrity) -> Result<(), Error> {
+        self.checksums(expected.watermark)
+            .ok_or(Error::Replication {
+                reason: "the watermark is beyond the arena",
+            })?
+            .check(&expected.checksums)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn integrity() {
+        let jinterners = Jinterners::default();
+        assert_eq!(jinterners.integrity(), Integrity::default());
+        jinterners.intern(json!({"a": [1, "x"], "b": {}}));
+        let first = jinterners.integrity();
+        jinterners.intern(json!(["x", {"c": [true]}]));
+        let second = jinterners.integrity();
+
+        // Incremental checksums match checksums computed from scratch, for
+        // older watermarks too.
+        let fresh = jinterners.clone();
+        *fresh.integrity.0.lock().unwrap() = Integrity::default();
+        assert_eq!(fresh.checksums(second.watermark), Some(second.checksums));
+        assert_eq!(jinterners.checksums(first.watermark), Some(first.checksums));
+        assert_eq!(jinterners.integrity(), second);
+        assert_ne!(first.checksums.root(), second.checksums.root());
+
+        jinterners.verify_integrity(&first).unwrap();
+        jinterners.verify_integrity(&second).unwrap();
+        let mut tampered = second;
+        tampered.checksums.objects ^= 1;
+        assert!(matches!(
+            jinterners.verify_integrity(&tampered),
+            Err(Error::Integrity {
+                arena: ArenaKind::Object
+            })
+        ));
+        assert!(matches!(
+            Jinterners::default().verify_integrity(&second),
+            Err(Error::Replication { .. })
+        ));
+    }
+}
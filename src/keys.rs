@@ -0,0 +1,484 @@
+use crate::detail::IValueImpl;
+use crate::pointer::parse_index;
+use crate::{Error, IValue, InternedStrKey, Jinterners, Mapping, ValueRef};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Declares a struct caching the [`InternedStrKey`](crate::InternedStrKey)s of
+/// a fixed set of object keys.
+///
+/// Looking up object fields by [`InternedStrKey`](crate::InternedStrKey) with
+/// [`MapRef::get_by_key()`](crate::MapRef::get_by_key) avoids hashing the key
+/// string on every query. This macro generates a struct holding one key per
+/// field, with the following methods:
+/// - `new(&Jinterners) -> Self` interns all the keys in the given arena,
+/// - `remap(&self, &Mapping) -> Self` converts the keys to the destination of
+///   the given [`Mapping`](crate::Mapping).
+///
+/// ```
+/// use jinterner::{Jinterners, ValueRef, interned_keys};
+/// use serde_json::json;
+///
+/// interned_keys! {
+///     /// Keys of a user record.
+///     pub struct UserKeys {
+///         pub user_id: "user_id",
+///         pub ts: "ts",
+///     }
+/// }
+///
+/// let interners = Jinterners::default();
+/// let keys = UserKeys::new(&interners);
+///
+/// let user = interners.intern(json!({"user_id": 42, "ts": 1234}));
+/// let ValueRef::Object(user) = interners.lookup_ref(&user) else {
+///     unreachable!()
+/// };
+/// let user_id = user.get_by_key(keys.user_id).unwrap();
+/// assert_eq!(interners.lookup(user_id), json!(42));
+/// ```
+#[macro_export]
+macro_rules! interned_keys {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident : $key:literal
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $crate::InternedStrKey,
+            )*
+        }
+
+        impl $name {
+            /// Interns all the keys in the given arena.
+            #[allow(dead_code)]
+            $vis fn new(interners: &$crate::Jinterners) -> Self {
+                Self {
+                    $( $field: interners.intern_key($key), )*
+                }
+            }
+
+            /// Converts all the keys to the destination of the given mapping.
+            ///
+            /// The mapping must not have removed any of these keys, which is
+            /// the case of the mappings returned by
+            /// [`Jinterners::optimize()`]($crate::Jinterners::optimize).
+            /// Otherwise, create new keys in the destination arena with
+            /// `new()`.
+            #[allow(dead_code)]
+            $vis fn remap(&self, mapping: &$crate::Mapping) -> Self {
+                Self {
+                    $( $field: mapping.map_str_key(self.$field), )*
+                }
+            }
+        }
+    };
+}
+
+/// A path of object keys, resolved once to [`InternedStrKey`]s to efficiently
+/// extract a nested field from many values.
+///
+/// This is an [`Accessor`] without array indices, whose keys can contain
+/// brackets.
+///
+/// ```
+/// use jinterner::{Jinterners, KeyPath};
+/// use serde_json::json;
+///
+/// let interners = Jinterners::default();
+/// let event = interners.intern(json!({"user": {"id": 42}}));
+/// let path = KeyPath::new(&interners, "user.id");
+/// let id = path.get(&interners, event).unwrap();
+/// assert_eq!(interners.lookup(&id), json!(42));
+/// ```
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct KeyPath(Accessor);
+
+impl KeyPath {
+    /// Resolves the given dot-separated path of object keys, for example
+    /// `user.id`, in the given arena.
+    ///
+    /// Keys are looked up without interning them. If one of them isn't
+    /// interned yet, no value interned so far contains this path, and
+    /// [`get()`](Self::get) always returns [`None`], even for values interned
+    /// after the creation of this path.
+    pub fn new(interners: &Jinterners, path: &str) -> Self {
+        Self(Accessor {
+            steps: path
+                .split('.')
+                .map(|key| interners.find_key(key).map(Step::Key))
+                .collect(),
+        })
+    }
+
+    /// Creates a path from the given object keys.
+    pub fn from_keys(keys: impl IntoIterator<Item = InternedStrKey>) -> Self {
+        Self(Accessor {
+            steps: Some(keys.into_iter().map(Step::Key).collect()),
+        })
+    }
+
+    /// Returns the field at this path in the given value, or [`None`] if the
+    /// value doesn't contain this path.
+    ///
+    /// The caller is responsible for ensuring that the value and this path
+    /// were created with the same arena, otherwise an arbitrary value will be
+    /// returned or a panic will happen.
+    pub fn get(&self, interners: &Jinterners, value: IValue) -> Option<IValue> {
+        self.0.get(interners, value)
+    }
+
+    /// Converts the keys to the destination of the given mapping.
+    ///
+    /// Like for the structs declared with [`interned_keys!`], the mapping must
+    /// not have removed any of these keys.
+    pub fn remap(&self, mapping: &Mapping) -> Self {
+        Self(self.0.remap(mapping))
+    }
+}
+
+/// A path of object keys and array indices, resolved once in a [`Jinterners`]
+/// arena to efficiently extract the same field from many values.
+///
+/// Contrary to [`KeyPath`], this can also step into arrays. Extracting a field
+/// only performs a binary search per object key and an index lookup per array
+/// index, without hashing or comparing any string.
+///
+/// ```
+/// use jinterner::{Accessor, Jinterners};
+/// use serde_json::json;
+///
+/// let interners = Jinterners::default();
+/// let event = interners.intern(json!({"users": [{"id": 42}, {"id": 43}]}));
+/// let accessor = Accessor::compile(&interners, "users[1].id")?;
+/// let id = accessor.get(&interners, event).unwrap();
+/// assert_eq!(interners.lookup(&id), json!(43));
+/// # Ok::<(), jinterner::Error>(())
+/// ```
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Accessor {
+    /// The steps, or [`None`] if one of the keys isn't interned.
+    steps: Option<Vec<Step>>,
+}
+
+/// A step of an [`Accessor`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+enum Step {
+    Key(InternedStrKey),
+    Index(usize),
+}
+
+impl Step {
+    fn apply(&self, interners: &Jinterners, value: IValue) -> Option<IValue> {
+        match (self, value.0) {
+            (Step::Key(key), IValueImpl::Object(object)) => {
+                let object = interners.iobject.lookup(object);
+                let i = object.binary_search_by_key(key, |(k, _)| *k).ok()?;
+                Some(object[i].1)
+            }
+            (Step::Index(i), IValueImpl::Array(array)) => {
+                interners.iarray.lookup(array).get(*i).copied()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A step of a path written in the syntax of [`Accessor::compile()`], before
+/// its keys are resolved in an arena.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PathStep<'a> {
+    Key(&'a str),
+    Index(usize),
+    /// The `[*]` index, selecting all the items of an array.
+    Items,
+}
+
+/// Parses the given path in the syntax of [`Accessor::compile()`], also
+/// accepting the `[*]` index if `items` is set.
+///
+/// Returns [`Error::Accessor`] if an array index is malformed.
+pub(crate) fn parse_path(path: &str, items: bool) -> Result<Vec<PathStep<'_>>, Error> {
+    let mut steps = Vec::new();
+    for component in path.split('.') {
+        let (key, mut indices) = component.split_at(component.find('[').unwrap_or(component.len()));
+        if !key.is_empty() || indices.is_empty() {
+            steps.push(PathStep::Key(key));
+        }
+        while !indices.is_empty() {
+            let (step, rest) = indices
+                .strip_prefix('[')
+                .and_then(|rest| rest.split_once(']'))
+                .and_then(|(index, rest)| match index {
+                    "*" if items => Some((PathStep::Items, rest)),
+                    _ => Some((PathStep::Index(parse_index(index)?), rest)),
+                })
+                .ok_or_else(|| Error::Accessor {
+                    component: component.into(),
+                })?;
+            steps.push(step);
+            indices = rest;
+        }
+    }
+    Ok(steps)
+}
+
+/// Number of values processed by each task of
+/// [`Accessor::apply_many_parallel()`].
+#[cfg(feature = "rayon")]
+const BATCH_SIZE: usize = 4096;
+
+impl Accessor {
+    /// Resolves the given path in the given arena.
+    ///
+    /// The path consists of dot-separated object keys, each followed by any
+    /// number of array indices in brackets, for example `a.b[3].c` or
+    /// `[0][1].id`. Keys cannot contain dots or brackets, use
+    /// [`Jinterners::pointer()`] to reach such keys.
+    ///
+    /// Keys are looked up without interning them. If one of them isn't
+    /// interned yet, no value interned so far contains this path, and
+    /// [`get()`](Self::get) always returns [`None`], even for values interned
+    /// after the creation of this accessor.
+    ///
+    /// Returns [`Error::Accessor`] if an array index is malformed.
+    pub fn compile(interners: &Jinterners, path: &str) -> Result<Self, Error> {
+        let steps = parse_path(path, false)?;
+        Ok(Self {
+            steps: steps
+                .into_iter()
+                .map(|step| match step {
+                    PathStep::Key(key) => interners.find_key(key).map(Step::Key),
+                    PathStep::Index(i) => Some(Step::Index(i)),
+                    PathStep::Items => unreachable!("`[*]` isn't accepted in accessors"),
+                })
+                .collect(),
+        })
+    }
+
+    /// Checks whether all the keys of this accessor were interned when it was
+    /// compiled. Otherwise, [`get()`](Self::get) always returns [`None`].
+    pub(crate) fn is_resolved(&self) -> bool {
+        self.steps.is_some()
+    }
+
+    /// Returns the field at this path in the given value, or [`None`] if the
+    /// value doesn't contain this path.
+    ///
+    /// The caller is responsible for ensuring that the value and this accessor
+    /// were created with the same arena, otherwise an arbitrary value will be
+    /// returned or a panic will happen.
+    pub fn get(&self, interners: &Jinterners, value: IValue) -> Option<IValue> {
+        let mut value = value;
+        for step in self.steps.as_ref()? {
+            value = step.apply(interners, value)?;
+        }
+        Some(value)
+    }
+
+    /// Returns the field at this path in each of the given values, in the same
+    /// order as the values.
+    ///
+    /// This is equivalent to calling [`get()`](Self::get) on each value, but
+    /// runs each step of the path on the whole batch before moving on to the
+    /// next step, which keeps the arena entries touched by a step close in
+    /// time. This is the building block for columnar exports and filters.
+    ///
+    /// The caller is responsible for ensuring that the values and this
+    /// accessor were created with the same arena, otherwise arbitrary values
+    /// will be returned or a panic will happen.
+    ///
+    /// ```
+    /// use jinterner::{Accessor, Jinterners, ValueRef};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let roots = [
+    ///     interners.intern(json!({"tags": ["a", "b"]})),
+    ///     interners.intern(json!({"tags": []})),
+    ///     interners.intern(json!({"tags": ["c"]})),
+    /// ];
+    /// let accessor = Accessor::compile(&interners, "tags[0]")?;
+    /// let column = accessor.apply_many(&interners, &roots);
+    /// assert!(matches!(
+    ///     column[..],
+    ///     [Some(ValueRef::String("a")), None, Some(ValueRef::String("c"))]
+    /// ));
+    /// # Ok::<(), jinterner::Error>(())
+    /// ```
+    pub fn apply_many<'a>(
+        &self,
+        interners: &'a Jinterners,
+        values: &[IValue],
+    ) -> Vec<Option<ValueRef<'a>>> {
+        let Some(steps) = &self.steps else {
+            return vec![None; values.len()];
+        };
+        let mut values: Vec<Option<IValue>> = values.iter().copied().map(Some).collect();
+        for step in steps {
+            for value in &mut values {
+                *value = value.and_then(|v| step.apply(interners, v));
+            }
+        }
+        values
+            .into_iter()
+            .map(|value| Some(interners.lookup_ref(&value?)))
+            .collect()
+    }
+
+    /// Returns the field at this path in each of the given values, processing
+    /// batches of values in parallel.
+    ///
+    /// This is equivalent to [`apply_many()`](Self::apply_many), but can be
+    /// significantly faster for millions of values.
+    #[cfg(feature = "rayon")]
+    pub fn apply_many_parallel<'a>(
+        &self,
+        interners: &'a Jinterners,
+        values: &[IValue],
+    ) -> Vec<Option<ValueRef<'a>>> {
+        values
+            .par_chunks(BATCH_SIZE)
+            .flat_map_iter(|batch| self.apply_many(interners, batch))
+            .collect()
+    }
+
+    /// Converts the keys to the destination of the given mapping.
+    ///
+    /// Like for [`KeyPath::remap()`], the mapping must not have removed any of
+    /// these keys.
+    pub fn remap(&self, mapping: &Mapping) -> Self {
+        Self {
+            steps: self.steps.as_ref().map(|steps| {
+                steps
+                    .iter()
+                    .map(|step| match step {
+                        Step::Key(k) => Step::Key(mapping.map_str_key(*k)),
+                        Step::Index(i) => Step::Index(*i),
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ValueRef;
+    use serde_json::json;
+
+    interned_keys! {
+        struct TestKeys {
+            name: "name",
+            tags: "tags",
+        }
+    }
+
+    #[test]
+    fn key_path() {
+        let interners = Jinterners::default();
+        let value = interners.intern(json!({"a": {"b": [1], "c": 2}, "b": 3}));
+        let get = |path: &KeyPath| path.get(&interners, value).map(|v| interners.lookup(&v));
+
+        assert_eq!(get(&KeyPath::new(&interners, "a.b")), Some(json!([1])));
+        assert_eq!(get(&KeyPath::new(&interners, "b")), Some(json!(3)));
+        assert_eq!(get(&KeyPath::new(&interners, "a.b.c")), None);
+        assert_eq!(get(&KeyPath::new(&interners, "a.missing")), None);
+        assert_eq!(get(&KeyPath::new(&interners, "c")), None);
+
+        let path = KeyPath::new(&interners, "a.c");
+        let (optimized, mapping) = interners.optimize(None).unwrap();
+        let value = path.remap(&mapping).get(&optimized, mapping.map(value));
+        assert_eq!(optimized.lookup(&value.unwrap()), json!(2));
+    }
+
+    #[test]
+    fn accessor() {
+        let interners = Jinterners::default();
+        let value = interners.intern(json!({"a": {"b": [1, [2, {"c": 3}]]}, "d": [[4]]}));
+        let get = |path| {
+            Accessor::compile(&interners, path)
+                .unwrap()
+                .get(&interners, value)
+                .map(|v| interners.lookup(&v))
+        };
+
+        assert_eq!(get("a.b[0]"), Some(json!(1)));
+        assert_eq!(get("a.b[1][1].c"), Some(json!(3)));
+        assert_eq!(get("d[0][0]"), Some(json!(4)));
+        assert_eq!(get("a.b[2]"), None);
+        assert_eq!(get("a[0]"), None);
+        assert_eq!(get("d.c"), None);
+        assert_eq!(get("a.missing"), None);
+        let inner = interners.intern(json!([[5]]));
+        let accessor = Accessor::compile(&interners, "[0][0]").unwrap();
+        assert_eq!(accessor.get(&interners, inner), interners.find(&json!(5)));
+
+        for path in ["a[", "a[x]", "a[01]", "a[1]b", "a.b[-1]", "a[*]"] {
+            assert!(
+                matches!(
+                    Accessor::compile(&interners, path),
+                    Err(Error::Accessor { .. })
+                ),
+                "{path}"
+            );
+        }
+
+        let values = [value, inner, value];
+        let accessor = Accessor::compile(&interners, "a.b[1][1]").unwrap();
+        let column = accessor.apply_many(&interners, &values);
+        assert!(column[0].is_some_and(|v| v == json!({"c": 3})));
+        assert!(column[1].is_none());
+        assert!(column[2].is_some_and(|v| v == json!({"c": 3})));
+        #[cfg(feature = "rayon")]
+        assert_eq!(
+            format!("{:?}", accessor.apply_many_parallel(&interners, &values)),
+            format!("{column:?}")
+        );
+        let missing = Accessor::compile(&interners, "missing").unwrap();
+        assert!(
+            missing
+                .apply_many(&interners, &values)
+                .iter()
+                .all(Option::is_none)
+        );
+
+        let accessor = Accessor::compile(&interners, "a.b[1][1].c").unwrap();
+        let (optimized, mapping) = interners.optimize(None).unwrap();
+        let value = accessor.remap(&mapping).get(&optimized, mapping.map(value));
+        assert_eq!(optimized.lookup(&value.unwrap()), json!(3));
+    }
+
+    #[test]
+    fn interned_keys() {
+        let interners = Jinterners::default();
+        let value = json!({"tags": ["a"], "other": 1, "name": "John"});
+        let ivalue = interners.intern_ref(&value);
+        let keys = TestKeys::new(&interners);
+        assert_eq!(keys.name, interners.find_key("name").unwrap());
+        assert_eq!(keys.tags, interners.find_key("tags").unwrap());
+
+        let (optimized, mapping) = interners.optimize(None).unwrap();
+        let keys = keys.remap(&mapping);
+        let ValueRef::Object(map) = optimized.lookup_ref(&mapping.map(ivalue)) else {
+            panic!("expected an object");
+        };
+        assert_eq!(
+            optimized.lookup_ref(map.get_by_key(keys.name).unwrap()),
+            json!("John")
+        );
+        assert_eq!(
+            optimized.lookup_ref(map.get_by_key(keys.tags).unwrap()),
+            json!(["a"])
+        );
+    }
+}
@@ -9,25 +9,174 @@
 )]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+mod aggregate;
+mod align;
+mod analysis;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "axum")]
+mod axum;
+mod bound;
+mod cache;
 #[cfg(feature = "delta")]
 mod delta;
 mod detail;
+mod diff;
+mod dump;
+mod error;
+mod estimate;
+mod filter;
+#[cfg(feature = "flatbuffers")]
+mod flatbuffers;
+mod frozen;
+mod hook;
+#[cfg(feature = "ijson")]
+mod ijson;
+mod increment;
+#[cfg(not(target_family = "wasm"))]
+mod ingest;
+mod integrity;
+mod keys;
+#[cfg(feature = "retain")]
+mod maintainer;
+mod mask;
+#[cfg(feature = "rayon")]
+mod merge;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "opentelemetry")]
+mod opentelemetry;
+mod patch;
+mod pointer;
+#[cfg(feature = "postgres-types")]
+mod postgres;
+pub mod prelude;
+mod project;
+mod prometheus;
+#[cfg(feature = "tokio")]
+mod replicate;
+mod report;
+#[cfg(any(feature = "test-utils", all(test, feature = "arbitrary")))]
+mod rng;
+mod roots;
+#[cfg(feature = "rusqlite")]
+mod rusqlite;
+#[cfg(feature = "serde")]
+pub mod scoped;
+#[cfg(feature = "arc-swap")]
+mod shared;
+mod side;
+mod store;
+#[cfg(feature = "tokio")]
+mod stream;
+mod sync;
+#[cfg(feature = "serde")]
+mod syntax;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+mod translate;
+mod validate;
+mod view;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod watch;
 
+pub use aggregate::Aggregate;
+pub use align::PartialMapping;
+pub use analysis::{
+    HistogramBucket, LargeEntry, LargestEntries, NormalizationCandidate, ReferenceReport,
+    ReferenceStats, ReferencedEntry, RetentionPolicy, SharedString, SizeBreakdown,
+    StringUsageReport, StringUsageStats,
+};
+#[cfg(feature = "axum")]
+pub use axum::{FromInternedJson, InternedJson, InternedJsonRejection};
 use blazinterner::{ArenaSlice, ArenaStr, InternedSlice};
 #[cfg(feature = "retain")]
 use blazinterner::{RetainSliceBuilder, RetainStrBuilder};
+pub use bound::{Bound, DisplayWith, SortedDisplay};
+use cache::LookupCache;
+pub use cache::TextCache;
 #[cfg(feature = "delta")]
 pub use delta::DeltaEncoding;
+use detail::IValueImpl;
+#[cfg(feature = "unicode-normalization")]
+pub use detail::StringNormalization;
+#[cfg(feature = "serde")]
+use detail::deserialize_slice;
 pub use detail::mapping::Mapping;
 use detail::mapping::{MappingNoStrings, MappingStrings};
-pub use detail::{IValue, InternedStrKey, MapRef, ValueRef};
+pub use detail::{
+    ArrayRef, FloatPolicy, IValue, InternedStrKey, MapIter, MapRef, ValueKind, ValueRef,
+    WriteOptions,
+};
+#[cfg(feature = "serde")]
+pub use detail::{
+    BytesEncoding, DuplicateKeys, InternedSeed, LossyNumbers, SerdeOptions, ValueRefDeserializer,
+};
+pub use diff::{ChangeKind, ValueChange};
+pub use dump::DumpFormat;
+pub use error::Error;
+pub use estimate::{DedupStats, SavingsEstimate, estimate_savings};
+use filter::StringFilter;
+#[cfg(feature = "flatbuffers")]
+pub use flatbuffers::FLATBUFFERS_SCHEMA;
+pub use frozen::FrozenView;
 #[cfg(feature = "get-size2")]
 use get_size2::GetSize;
-use serde_json::Value;
+use hook::InstalledHook;
+pub use hook::InternHook;
+pub use increment::{Increment, Watermark};
+#[cfg(not(target_family = "wasm"))]
+pub use ingest::{IngestPool, IngestReply, IngestSender};
+use integrity::IntegrityCache;
+pub use integrity::{Checksums, Integrity};
+pub use keys::{Accessor, KeyPath};
+#[cfg(feature = "retain")]
+pub use maintainer::{JinternersMaintainer, MaintenanceOutcome, MaintenanceStats};
+pub use mask::FieldMask;
+#[cfg(feature = "metrics")]
+use metrics::MetricsHook;
+#[cfg(feature = "metrics")]
+pub use metrics::{InternCounters, MetricsCrateRecorder, MetricsRecorder};
+pub use patch::PatchOperation;
+pub use pointer::{PointerError, PointerErrorReason};
+#[cfg(feature = "postgres-types")]
+pub use postgres::RawJson;
+pub use project::ProjectedColumn;
+#[cfg(feature = "tokio")]
+pub use replicate::{ReplicationConsumer, ReplicationProducer};
+pub use report::{
+    ArenaDiff, ArenaDiffSide, ArenaKind, ArenaReport, DiffReport, EntryReport, StorageReport,
+};
+pub use roots::{RootId, Roots};
+#[cfg(feature = "rusqlite")]
+pub use rusqlite::register_sqlite_extract;
+use serde_json::{Map, Value};
 #[cfg(feature = "serde")]
 use serde_tuple::{Deserialize_tuple, Serialize_tuple};
+#[cfg(feature = "arc-swap")]
+pub use shared::{Generation, SharedGuard, SharedJinterners};
+pub use side::SideTable;
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+#[cfg(feature = "serde")]
+pub use store::{Archive, Dictionary, FORMAT_VERSION, Segments, SpillingIngest};
+pub use store::{DocId, DocIter, JDocStore, VersionId, VersionedStore};
+pub use sync::{SyncRequest, SyncResponse};
+#[cfg(feature = "serde")]
+pub use syntax::SourceLayout;
+pub use validate::{ValidationIssue, ValidationReport};
+pub use view::{JinternersView, ViewArrayRef, ViewMapRef, ViewValueRef};
+pub use watch::WatchId;
+use watch::Watchers;
 
 /// An arena to store interned JSON values.
+///
+/// With the `serde` feature, an arena is serialized as its three underlying
+/// arenas. All the strings are serialized as their lengths followed by a
+/// single concatenated string. Deserializing an arena from an in-memory
+/// buffer therefore borrows the string data when possible and copies it into
+/// one allocation, rather than allocating each string separately.
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize_tuple, Deserialize_tuple))]
 #[cfg_attr(feature = "get-size2", derive(GetSize))]
@@ -35,6 +184,32 @@ pub struct Jinterners {
     string: ArenaStr,
     iarray: ArenaSlice<IValue>,
     iobject: ArenaSlice<(InternedStrKey, IValue)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "get-size2", get_size(ignore))]
+    float_policy: FloatPolicy,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "get-size2", get_size(ignore))]
+    string_filter: StringFilter,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "get-size2", get_size(ignore))]
+    lookup_cache: LookupCache,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "get-size2", get_size(ignore))]
+    watchers: Watchers,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "get-size2", get_size(ignore))]
+    intern_hook: InstalledHook,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "get-size2", get_size(ignore))]
+    integrity: IntegrityCache,
+    #[cfg(feature = "unicode-normalization")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "get-size2", get_size(ignore))]
+    string_normalization: StringNormalization,
+    #[cfg(feature = "metrics")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "get-size2", get_size(ignore))]
+    metrics: MetricsHook,
 }
 
 #[cfg(feature = "get-size2")]
@@ -53,6 +228,45 @@ impl Jinterners {
     pub fn get_size_objects(&self) -> usize {
         self.iobject.get_size()
     }
+
+    /// Gets the total size in bytes of the underlying arenas, i.e. the sum of
+    /// [`get_size_strings()`](Self::get_size_strings),
+    /// [`get_size_arrays()`](Self::get_size_arrays) and
+    /// [`get_size_objects()`](Self::get_size_objects).
+    pub fn get_size_total(&self) -> usize {
+        self.get_size_strings() + self.get_size_arrays() + self.get_size_objects()
+    }
+
+    /// Gets the shallow size in bytes of the given entry in this arena, i.e.
+    /// the bytes of a string or the elements of an array or object, without
+    /// the entries that they refer to.
+    ///
+    /// Scalars other than strings are stored inline in the [`IValue`] and
+    /// therefore have a size of zero.
+    ///
+    /// ```
+    /// use jinterner::{IValue, Jinterners};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let array = interners.intern(json!(["hello", 42]));
+    /// assert_eq!(interners.entry_size(array), 2 * size_of::<IValue>());
+    /// let string = interners.intern(json!("hello"));
+    /// assert_eq!(interners.entry_size(string), 5);
+    /// assert_eq!(interners.entry_size(interners.intern(json!(42))), 0);
+    /// ```
+    pub fn entry_size(&self, value: IValue) -> usize {
+        match value.0 {
+            IValueImpl::Null
+            | IValueImpl::Bool(_)
+            | IValueImpl::U64(_)
+            | IValueImpl::I64(_)
+            | IValueImpl::F64(_) => 0,
+            IValueImpl::String(s) => self.string.lookup(s).len(),
+            IValueImpl::Array(a) => size_of_val(self.iarray.lookup(a)),
+            IValueImpl::Object(o) => size_of_val(self.iobject.lookup(o)),
+        }
+    }
 }
 
 #[cfg(feature = "debug")]
@@ -76,7 +290,77 @@ impl Jinterners {
     }
 }
 
+#[cfg(feature = "metrics")]
+impl Jinterners {
+    /// Installs a hook to record metrics about interning and lookups in this
+    /// arena, or removes it if [`None`] is passed.
+    ///
+    /// The hook is preserved in arenas derived from this one (for example with
+    /// [`optimize()`](Self::optimize)), but isn't serialized.
+    pub fn set_metrics_recorder(&mut self, recorder: Option<Arc<dyn MetricsRecorder>>) {
+        self.metrics = MetricsHook::new(recorder);
+    }
+}
+
 impl Jinterners {
+    /// Returns a new empty arena with the same settings as this one: float
+    /// policy, string filter, lookup cache, watchers, hooks and normalization.
+    ///
+    /// Arenas derived from this one are built from this, so that settings
+    /// only need to be listed here.
+    pub(crate) fn with_settings_of(&self) -> Jinterners {
+        Jinterners {
+            string: ArenaStr::default(),
+            iarray: ArenaSlice::default(),
+            iobject: ArenaSlice::default(),
+            float_policy: self.float_policy,
+            string_filter: self.string_filter.rebuild(std::iter::empty()),
+            lookup_cache: self.lookup_cache.emptied(),
+            watchers: self.watchers.clone(),
+            intern_hook: self.intern_hook.clone(),
+            integrity: Default::default(),
+            #[cfg(feature = "unicode-normalization")]
+            string_normalization: self.string_normalization,
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+        }
+    }
+
+    /// Sets the policy applied to floating-point numbers when interning them
+    /// into this arena. See [`FloatPolicy`].
+    ///
+    /// The policy is preserved in arenas derived from this one (for example
+    /// with [`optimize()`](Self::optimize)), but isn't serialized.
+    pub fn set_float_policy(&mut self, policy: FloatPolicy) {
+        self.float_policy = policy;
+    }
+
+    /// Returns the policy applied to floating-point numbers when interning
+    /// them into this arena.
+    pub fn float_policy(&self) -> FloatPolicy {
+        self.float_policy
+    }
+
+    /// Sets the Unicode normalization applied to strings and object keys when
+    /// interning them into this arena. See [`StringNormalization`].
+    ///
+    /// This should be set before interning anything, as strings that were
+    /// already interned aren't normalized retroactively.
+    ///
+    /// The normalization is preserved in arenas derived from this one (for
+    /// example with [`optimize()`](Self::optimize)), but isn't serialized.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn set_string_normalization(&mut self, normalization: StringNormalization) {
+        self.string_normalization = normalization;
+    }
+
+    /// Returns the Unicode normalization applied to strings and object keys
+    /// when interning them into this arena.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn string_normalization(&self) -> StringNormalization {
+        self.string_normalization
+    }
+
     /// Interns the given [`serde_json::Value`] into this arena.
     pub fn intern(&self, source: Value) -> IValue {
         IValue::from(self, source)
@@ -87,6 +371,72 @@ impl Jinterners {
         IValue::from_ref(self, source)
     }
 
+    /// Interns the given [`serde_json::Map`] as an object into this arena.
+    ///
+    /// This is equivalent to interning a [`Value::Object`], without having to
+    /// wrap the map in a [`Value`] first.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::{Map, json};
+    ///
+    /// let interners = Jinterners::default();
+    /// let mut map = Map::new();
+    /// map.insert("name".into(), json!("John"));
+    /// let ivalue = interners.intern_object(&map);
+    /// assert_eq!(ivalue, interners.intern(json!({"name": "John"})));
+    /// ```
+    pub fn intern_object(&self, source: &Map<String, Value>) -> IValue {
+        IValue::from_object_ref(self, source)
+    }
+
+    /// Interns the given slice of [`serde_json::Value`]s as an array into this
+    /// arena.
+    ///
+    /// This is equivalent to interning a [`Value::Array`], without having to
+    /// collect the items into a [`Value`] first.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let items = [json!(1), json!("a")];
+    /// let ivalue = interners.intern_values(&items);
+    /// assert_eq!(ivalue, interners.intern(json!([1, "a"])));
+    /// ```
+    pub fn intern_values(&self, source: &[Value]) -> IValue {
+        IValue::from_array_ref(self, source)
+    }
+
+    /// Returns the interned value equal to the given [`serde_json::Value`], or
+    /// [`None`] if it hasn't been interned in this arena.
+    ///
+    /// Contrary to [`intern_ref()`](Self::intern_ref), this never adds
+    /// anything to the arena, which makes it suitable to query values, for
+    /// example to compare them with already interned values by handle.
+    pub fn find(&self, source: &Value) -> Option<IValue> {
+        IValue::find(self, source)
+    }
+
+    /// Interns the given batch of [`serde_json::Value`]s into this arena.
+    ///
+    /// This is equivalent to calling [`intern()`](Self::intern) on each value,
+    /// but is more efficient as temporary buffers and object keys are shared
+    /// across the whole batch.
+    pub fn intern_many(&self, sources: Vec<Value>) -> Vec<IValue> {
+        self.intern_many_ref(&sources)
+    }
+
+    /// Interns the given batch of [`serde_json::Value`]s into this arena.
+    ///
+    /// This is equivalent to calling [`intern_ref()`](Self::intern_ref) on each
+    /// value, but is more efficient as temporary buffers and object keys are
+    /// shared across the whole batch.
+    pub fn intern_many_ref(&self, sources: &[Value]) -> Vec<IValue> {
+        IValue::from_many_ref(self, sources)
+    }
+
     /// Interns the given [`serde_json::Value`] into this arena.
     pub fn intern_mut(&mut self, source: Value) -> IValue {
         IValue::from_mut(self, source)
@@ -97,6 +447,92 @@ impl Jinterners {
         IValue::from_ref_mut(self, source)
     }
 
+    /// Interns the given string into this arena.
+    ///
+    /// This is equivalent to interning a [`Value::String`], without having to
+    /// allocate it first.
+    pub fn intern_str(&self, source: &str) -> IValue {
+        IValue(detail::IValueImpl::String(self.intern_string(source)))
+    }
+
+    /// Interns the given string into this arena.
+    ///
+    /// This is equivalent to interning a [`Value::String`], without having to
+    /// allocate it first.
+    pub fn intern_str_mut(&mut self, source: &str) -> IValue {
+        IValue(detail::IValueImpl::String(self.intern_string_mut(source)))
+    }
+
+    /// Parses the given JSON text and interns it into this arena, without
+    /// materializing an intermediate [`Value`].
+    ///
+    /// ```
+    /// use jinterner::{Error, Jinterners};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.parse(r#"{"name": "John"}"#).unwrap();
+    /// assert_eq!(interners.lookup(&ivalue), json!({"name": "John"}));
+    /// assert!(matches!(interners.parse("{"), Err(Error::Parse(_))));
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn parse(&self, text: &str) -> Result<IValue, Error> {
+        Ok(deserialize_slice(InternedSeed::new(self), text.as_bytes())?)
+    }
+
+    /// Same as [`parse()`](Self::parse), but fails if an object contains
+    /// duplicate keys instead of keeping the last value.
+    ///
+    /// The error message contains the duplicate key and the JSON pointer of
+    /// the object containing it.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    ///
+    /// let interners = Jinterners::default();
+    /// let text = r#"{"role": "user", "role": "admin"}"#;
+    /// assert!(interners.parse(text).is_ok());
+    /// assert_eq!(
+    ///     interners.parse_strict(text).unwrap_err().to_string(),
+    ///     "parse error: duplicate key \"role\" at line 1 column 33"
+    /// );
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn parse_strict(&self, text: &str) -> Result<IValue, Error> {
+        let seed = InternedSeed::new(self).duplicate_keys(DuplicateKeys::Reject);
+        Ok(deserialize_slice(seed, text.as_bytes())?)
+    }
+
+    /// Same as [`parse()`](Self::parse), but accepts JSON5 text, which is a
+    /// superset of JSON and of JSONC: comments, trailing commas, unquoted
+    /// keys, single-quoted strings, hexadecimal numbers, etc.
+    ///
+    /// ```
+    /// use jinterner::{Error, Jinterners};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let text = r#"{
+    ///     // Comments are allowed.
+    ///     name: "John",
+    ///     tags: ["a", "b",],
+    /// }"#;
+    /// let ivalue = interners.parse_json5(text).unwrap();
+    /// assert_eq!(
+    ///     interners.lookup(&ivalue),
+    ///     json!({"name": "John", "tags": ["a", "b"]})
+    /// );
+    /// assert!(matches!(interners.parse_json5("{"), Err(Error::Parse(_))));
+    /// ```
+    #[cfg(feature = "json5")]
+    pub fn parse_json5(&self, text: &str) -> Result<IValue, Error> {
+        use serde::de::Error as _;
+        let mut deserializer =
+            json5::Deserializer::from_str(text).map_err(serde_json::Error::custom)?;
+        IValue::from_deserializer(&mut deserializer, self)
+            .map_err(|e| Error::Parse(serde_json::Error::custom(e)))
+    }
+
     /// Retrieves the given interned value from this arena.
     ///
     /// The caller is responsible for ensuring that the same arena was used to
@@ -106,9 +542,28 @@ impl Jinterners {
     /// See also [`lookup_ref()`](Self::lookup_ref) if you only need a shallow
     /// view.
     pub fn lookup(&self, value: &IValue) -> Value {
+        #[cfg(feature = "metrics")]
+        self.metrics.record_lookup();
         value.lookup(self)
     }
 
+    /// Retrieves the given interned value from this arena, materializing the
+    /// top-level array elements or object fields in parallel.
+    ///
+    /// The caller is responsible for ensuring that the same arena was used to
+    /// intern this value, otherwise an arbitrary value will be returned or
+    /// a panic will happen.
+    ///
+    /// This is equivalent to [`lookup()`](Self::lookup), but can be
+    /// significantly faster for large arrays or objects, for example when
+    /// exporting a big interned corpus back to JSON.
+    #[cfg(feature = "rayon")]
+    pub fn lookup_parallel(&self, value: &IValue) -> Value {
+        #[cfg(feature = "metrics")]
+        self.metrics.record_lookup();
+        value.lookup_parallel(self)
+    }
+
     /// Retrieves the given interned value from this arena.
     ///
     /// The caller is responsible for ensuring that the same arena was used to
@@ -119,6 +574,8 @@ impl Jinterners {
     /// deep copy of the value, and is therefore likely more efficient if
     /// you only need to query specific object field(s) or array element(s).
     pub fn lookup_ref(&self, value: &IValue) -> ValueRef<'_> {
+        #[cfg(feature = "metrics")]
+        self.metrics.record_lookup();
         value.lookup_ref(self)
     }
 
@@ -127,7 +584,54 @@ impl Jinterners {
     ///
     /// This can be useful in combination with [`MapRef::get_by_key()`].
     pub fn find_key(&self, key: &str) -> Option<InternedStrKey> {
-        self.string.find(key).map(InternedStrKey)
+        self.find_string(key).map(InternedStrKey)
+    }
+
+    /// Interns the given string as an object key.
+    ///
+    /// Contrary to [`find_key()`](Self::find_key), this always returns a key,
+    /// which is useful to cache keys before the objects containing them are
+    /// interned.
+    pub fn intern_key(&self, key: &str) -> InternedStrKey {
+        InternedStrKey(self.intern_string(key))
+    }
+
+    /// Iterates over the objects of this arena whose entries match the given
+    /// `filter`, in interning order.
+    ///
+    /// The filter receives the entries of each object, sorted by
+    /// [`InternedStrKey`], so that a key cached with
+    /// [`find_key()`](Self::find_key) can be searched efficiently. Each object
+    /// of the arena is visited once, including objects nested in other values
+    /// and objects that are no longer referenced by any root.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// interners.intern(json!([{"id": 1, "error": "timeout"}, {"id": 2}]));
+    /// interners.intern(json!({"job": {"error": "disk full"}}));
+    ///
+    /// let error = interners.find_key("error").unwrap();
+    /// let errors: Vec<_> = interners
+    ///     .scan_objects(|entries| entries.binary_search_by_key(&error, |(k, _)| *k).is_ok())
+    ///     .map(|object| interners.lookup(&object))
+    ///     .collect();
+    /// assert_eq!(
+    ///     errors,
+    ///     [json!({"id": 1, "error": "timeout"}), json!({"error": "disk full"})]
+    /// );
+    /// ```
+    pub fn scan_objects<'a>(
+        &'a self,
+        filter: impl Fn(&[(InternedStrKey, IValue)]) -> bool + 'a,
+    ) -> impl Iterator<Item = IValue> + 'a {
+        self.iobject
+            .iter()
+            .enumerate()
+            .filter(move |(_, entries)| filter(entries))
+            .map(|(i, _)| IValue(IValueImpl::Object(InternedSlice::from_id(i as u32))))
     }
 
     /// Returns an optimized version of this [`Jinterners`], or [`None`] if the
@@ -174,6 +678,8 @@ impl Jinterners {
                                 string,
                                 iarray,
                                 iobject,
+                                string_filter: self.string_filter.clone(),
+                                ..self.with_settings_of()
                             },
                             mapping_opt.promote(num_strings as u32),
                         )
@@ -192,6 +698,50 @@ impl Jinterners {
         optimized
     }
 
+    /// Serializes a fully [optimized](Self::optimize) version of this arena,
+    /// in the same format as its [`Serialize`](serde::Serialize)
+    /// implementation with [`serde_json`].
+    ///
+    /// Optimizing sorts all the entries of the arena, so that arenas with the
+    /// same contents are serialized to identical bytes regardless of the order
+    /// in which values were interned. This is useful for reproducible builds
+    /// and content-addressed storage of snapshots.
+    ///
+    /// [`IValue`]s rooted in this [`Jinterners`] need to be converted using the
+    /// resulting [`Mapping`] to be used in the deserialized arena, unless
+    /// [`None`] is returned because this arena was already optimized.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let a = Jinterners::default();
+    /// a.intern(json!({"name": "John"}));
+    /// let ivalue = a.intern(json!(["Jane", 42]));
+    ///
+    /// let b = Jinterners::default();
+    /// b.intern(json!(["Jane", 42]));
+    /// b.intern(json!({"name": "John"}));
+    ///
+    /// let (bytes, mapping) = a.to_canonical_bytes();
+    /// assert_eq!(bytes, b.to_canonical_bytes().0);
+    ///
+    /// let canonical: Jinterners = serde_json::from_slice(&bytes)?;
+    /// let ivalue = mapping.map_or(ivalue, |mapping| mapping.map(ivalue));
+    /// assert_eq!(canonical.lookup(&ivalue), json!(["Jane", 42]));
+    /// # Ok::<(), serde_json::Error>(())
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn to_canonical_bytes(&self) -> (Vec<u8>, Option<Mapping>) {
+        let to_vec = |jinterners: &Jinterners| {
+            serde_json::to_vec(jinterners).expect("serializing an arena never fails")
+        };
+        match self.optimize(None) {
+            Some((optimized, mapping)) => (to_vec(&optimized), Some(mapping)),
+            None => (to_vec(self), None),
+        }
+    }
+
     /// Returns a partially optimized version of this [`Jinterners`], or
     /// [`None`] if this instance was already optimized.
     ///
@@ -206,9 +756,9 @@ impl Jinterners {
         let iobject_map = self.iobject.sort();
 
         let mapping = Mapping {
-            string: string_map.forward,
-            iarray: iarray_map.forward,
-            iobject: iobject_map.forward,
+            string: string_map.forward.into(),
+            iarray: iarray_map.forward.into(),
+            iobject: iobject_map.forward.into(),
         };
         if mapping.is_identity() {
             return None;
@@ -222,6 +772,8 @@ impl Jinterners {
                 .iarray
                 .map2(&iarray_map.reverse, |ivalue| mapping.map(*ivalue)),
             iobject: ArenaSlice::with_capacity(iobject_map_iter.len(), self.iobject.items()),
+            string_filter: self.string_filter.clone(),
+            ..self.with_settings_of()
         };
 
         let mut buffer = Vec::new();
@@ -257,6 +809,8 @@ impl Jinterners {
             string: self.string.map(&string_map.reverse),
             iarray: ArenaSlice::with_capacity(iarray_iter.len(), self.iarray.items()),
             iobject: ArenaSlice::with_capacity(iobject_iter.len(), self.iobject.items()),
+            string_filter: self.string_filter.clone(),
+            ..self.with_settings_of()
         };
 
         for array in iarray_iter {
@@ -399,16 +953,20 @@ impl RetainBuilder<'_> {
         let iobject_map = self.objects.build();
 
         let mapping = Mapping {
-            string: string_map.forward,
-            iarray: iarray_map.forward,
-            iobject: iobject_map.forward,
+            string: string_map.forward.into(),
+            iarray: iarray_map.forward.into(),
+            iobject: iobject_map.forward.into(),
         };
         if mapping.is_identity() {
             return None;
         }
 
+        let string = self.jinterners.string.map(&string_map.reverse);
         let jinterners = Jinterners {
-            string: self.jinterners.string.map(&string_map.reverse),
+            // Rebuild the filter, as removed strings would otherwise remain
+            // false positives.
+            string_filter: self.jinterners.string_filter.rebuild(string.iter()),
+            string,
             iarray: self
                 .jinterners
                 .iarray
@@ -420,6 +978,7 @@ impl RetainBuilder<'_> {
                     // Retained keys are still in the same order, so we don't need to re-sort them.
                     (mapping.map_str_key(*k), mapping.map(*ivalue))
                 }),
+            ..self.jinterners.with_settings_of()
         };
 
         Some((jinterners, mapping))
@@ -428,11 +987,273 @@ impl RetainBuilder<'_> {
 
 #[cfg(test)]
 mod test {
-    #[cfg(feature = "retain")]
     use super::*;
-    #[cfg(feature = "retain")]
     use serde_json::json;
 
+    #[test]
+    fn intern_containers() {
+        let interners = Jinterners::default();
+        let value = json!({"b": [1, {"c": null}], "a": "x", "d": {}});
+        let Value::Object(map) = &value else {
+            unreachable!()
+        };
+        assert_eq!(interners.intern_object(map), interners.intern_ref(&value));
+        assert_eq!(
+            interners.intern_object(&Map::new()),
+            interners.intern(json!({}))
+        );
+
+        let items = [json!([]), json!({"a": [2.5]}), json!(-1)];
+        assert_eq!(
+            interners.intern_values(&items),
+            interners.intern(Value::Array(items.to_vec()))
+        );
+        assert_eq!(interners.intern_values(&[]), interners.intern(json!([])));
+    }
+
+    #[test]
+    fn numeric_arrays() {
+        let interners = Jinterners::default();
+        let extract = |value| {
+            let ivalue = interners.intern(value);
+            let value = interners.lookup_ref(&ivalue);
+            (value.as_f64_vec(), value.as_i64_vec())
+        };
+        assert_eq!(
+            extract(json!([0, -1, u64::MAX, 0.5])),
+            (Some(vec![0.0, -1.0, u64::MAX as f64, 0.5]), None)
+        );
+        assert_eq!(
+            extract(json!([i64::MIN, 2, i64::MAX])),
+            (
+                Some(vec![i64::MIN as f64, 2.0, i64::MAX as f64]),
+                Some(vec![i64::MIN, 2, i64::MAX])
+            )
+        );
+        assert_eq!(extract(json!([])), (Some(vec![]), Some(vec![])));
+        assert_eq!(extract(json!([1, "2"])), (None, None));
+        assert_eq!(extract(json!([1, null])), (None, None));
+        assert_eq!(extract(json!({"a": 1})), (None, None));
+        assert_eq!(extract(json!(1)), (None, None));
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn parse_json5() {
+        let interners = Jinterners::default();
+        let text = r#"
+            /* Block comment. */
+            {
+                name: 'John', // Line comment.
+                "age": 0x2a,
+                nested: {tags: ["a", "b",], empty: {},},
+            }
+        "#;
+        let ivalue = interners.parse_json5(text).unwrap();
+        assert_eq!(
+            interners.lookup(&ivalue),
+            json!({"name": "John", "age": 42, "nested": {"tags": ["a", "b"], "empty": {}}})
+        );
+        assert_eq!(
+            interners.parse_json5("[1, 2.5, null]").unwrap(),
+            interners.parse("[1, 2.5, null]").unwrap()
+        );
+
+        assert!(matches!(
+            interners.parse_json5("{a: 1} trailing"),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            interners.parse_json5("{a: }"),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn scan_objects() {
+        let interners = Jinterners::default();
+        let value = json!([
+            {"type": "a", "n": 1},
+            {"type": "b", "n": 2, "child": {"type": "a"}},
+            {"n": 3},
+        ]);
+        let ivalue = interners.intern_ref(&value);
+
+        let n = interners.find_key("n").unwrap();
+        let ty = interners.find_key("type").unwrap();
+        let a = interners.find(&json!("a")).unwrap();
+        let type_a = |entries: &[(InternedStrKey, IValue)]| entries.contains(&(ty, a));
+        let found: Vec<Value> = interners
+            .scan_objects(type_a)
+            .map(|object| interners.lookup(&object))
+            .collect();
+        assert_eq!(found, [json!({"type": "a", "n": 1}), json!({"type": "a"})]);
+        assert_eq!(
+            interners
+                .scan_objects(|entries| entries.iter().any(|(k, _)| *k == n))
+                .count(),
+            3
+        );
+        assert_eq!(interners.scan_objects(|_| false).count(), 0);
+        assert!(
+            !interners
+                .scan_objects(|_| true)
+                .any(|object| object == ivalue)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn to_canonical_bytes() {
+        let values = [
+            json!({"b": [1, "x"], "a": {"y": null}}),
+            json!(["z", {"a": 2.5}]),
+            json!("w"),
+        ];
+        let a = Jinterners::default();
+        let ia: Vec<IValue> = values.iter().map(|v| a.intern_ref(v)).collect();
+        let b = Jinterners::default();
+        for value in values.iter().rev() {
+            b.intern_ref(value);
+        }
+
+        let (bytes, mapping) = a.to_canonical_bytes();
+        assert_eq!(b.to_canonical_bytes().0, bytes);
+
+        // Values are converted with the mapping into the serialized arena.
+        let mapping = mapping.unwrap();
+        let canonical: Jinterners = serde_json::from_slice(&bytes).unwrap();
+        for (ivalue, value) in ia.iter().zip(&values) {
+            assert_eq!(canonical.lookup(&mapping.map(*ivalue)), *value);
+        }
+
+        // An arena that is already canonical doesn't need a mapping.
+        let (again, mapping) = canonical.to_canonical_bytes();
+        assert_eq!(again, bytes);
+        assert!(mapping.is_none());
+    }
+
+    #[cfg(feature = "get-size2")]
+    #[test]
+    fn entry_size() {
+        let interners = Jinterners::default();
+        let object = interners.intern(json!({"name": "John", "tags": ["a", "b", "c"]}));
+        assert_eq!(
+            interners.entry_size(object),
+            2 * size_of::<(InternedStrKey, IValue)>()
+        );
+        let tags = interners.pointer(&object, "/tags").unwrap();
+        assert_eq!(interners.entry_size(tags), 3 * size_of::<IValue>());
+        let name = interners.pointer(&object, "/name").unwrap();
+        assert_eq!(interners.entry_size(name), 4);
+        assert_eq!(interners.entry_size(interners.intern(json!(null))), 0);
+        assert_eq!(
+            interners.get_size_total(),
+            interners.get_size_strings()
+                + interners.get_size_arrays()
+                + interners.get_size_objects()
+        );
+    }
+
+    #[test]
+    fn intern_nested() {
+        let mut interners = Jinterners::default();
+
+        let value = json!({
+            "name": "John",
+            "tags": ["a", "b", ["a", "b"], {"name": "a"}],
+            "nested": {"tags": ["a", "b"], "count": 2},
+        });
+
+        let a = interners.intern_ref(&value);
+        let b = interners.intern(value.clone());
+        let c = interners.intern_ref_mut(&value);
+        let d = interners.intern_mut(value.clone());
+        assert_eq!(a, b);
+        assert_eq!(a, c);
+        assert_eq!(a, d);
+
+        assert_eq!(interners.lookup(&a), value);
+    }
+
+    #[test]
+    fn intern_many() {
+        let interners = Jinterners::default();
+
+        let values: Vec<Value> = (0..100)
+            .map(|i| json!({"id": i % 10, "tags": ["a", {"id": i % 3}], "name": "x"}))
+            .collect();
+
+        let batch = interners.intern_many(values.clone());
+        let batch_ref = interners.intern_many_ref(&values);
+        assert_eq!(batch, batch_ref);
+        for (value, ivalue) in values.iter().zip(&batch) {
+            assert_eq!(interners.intern_ref(value), *ivalue);
+            assert_eq!(interners.lookup(ivalue), *value);
+        }
+    }
+
+    #[test]
+    fn intern_str() {
+        let mut interners = Jinterners::default();
+
+        let a = interners.intern_str("tag");
+        let b = interners.intern_str_mut("tag");
+        assert_eq!(a, b);
+        assert_eq!(a, interners.intern(json!("tag")));
+        assert_eq!(interners.lookup(&a), json!("tag"));
+
+        // Strings and object keys share the same arena.
+        let key = interners.intern_key("tag");
+        assert_eq!(interners.find_key("tag"), Some(key));
+    }
+
+    #[test]
+    fn iterate_refs() {
+        let interners = Jinterners::default();
+        let value = interners.intern(json!({"b": [1, 2, 3], "a": "x"}));
+
+        let ValueRef::Object(map) = interners.lookup_ref(&value) else {
+            panic!("expected an object");
+        };
+        let mut keys = Vec::new();
+        for (key, value) in &map {
+            keys.push(key);
+            if let ValueRef::Array(array) = interners.lookup_ref(value) {
+                let sum: u64 = array
+                    .into_iter()
+                    .map(|v| match interners.lookup_ref(v) {
+                        ValueRef::U64(x) => x,
+                        _ => 0,
+                    })
+                    .sum();
+                assert_eq!(sum, 6);
+            }
+        }
+        keys.sort();
+        assert_eq!(keys, ["a", "b"]);
+        assert_eq!(map.into_iter().len(), 2);
+        assert_eq!(map.iter().rev().count(), 2);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn lookup_parallel() {
+        let interners = Jinterners::default();
+
+        let array = json!(
+            (0..1000)
+                .map(|i| json!({"id": i, "tags": ["a", i % 7]}))
+                .collect::<Vec<_>>()
+        );
+        let object = json!({"first": array.clone(), "second": [1, 2, 3], "third": null});
+
+        let iarray = interners.intern_ref(&array);
+        let iobject = interners.intern_ref(&object);
+        assert_eq!(interners.lookup_parallel(&iarray), array);
+        assert_eq!(interners.lookup_parallel(&iobject), object);
+    }
+
     #[cfg(feature = "retain")]
     #[test]
     fn retain() {
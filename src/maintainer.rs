@@ -0,0 +1,253 @@
+use crate::{IValue, Jinterners, Mapping};
+#[cfg(not(target_family = "wasm"))]
+use std::sync::Arc;
+#[cfg(not(target_family = "wasm"))]
+use std::thread::JoinHandle;
+
+/// A maintenance policy to periodically compact and optimize a [`Jinterners`]
+/// arena.
+///
+/// Each maintenance run first measures how far the arena is from its optimized
+/// order, and runs [`Jinterners::optimize()`] if needed. It then computes which
+/// items are reachable from the given roots, and drops the unreachable ones if
+/// they make up a large enough share of the arena.
+///
+/// Runs can be performed on the current thread with [`run()`](Self::run), or
+/// off-thread with [`spawn()`](Self::spawn).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JinternersMaintainer {
+    disorder_threshold: f64,
+    garbage_threshold: f64,
+    optimize_limit: Option<usize>,
+}
+
+/// Measurements of a [`Jinterners`] arena, used by [`JinternersMaintainer`] to
+/// decide which maintenance to run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MaintenanceStats {
+    /// Number of interned strings.
+    pub strings: usize,
+    /// Number of interned arrays.
+    pub arrays: usize,
+    /// Number of interned objects.
+    pub objects: usize,
+    /// Number of strings that are ordered after their successor.
+    pub unsorted_strings: usize,
+    /// Number of arrays that are ordered after their successor.
+    pub unsorted_arrays: usize,
+    /// Number of objects that are ordered after their successor.
+    pub unsorted_objects: usize,
+}
+
+impl MaintenanceStats {
+    /// Measures the given arena.
+    pub fn measure(jinterners: &Jinterners) -> Self {
+        Self {
+            strings: jinterners.string.strings(),
+            arrays: jinterners.iarray.slices(),
+            objects: jinterners.iobject.slices(),
+            unsorted_strings: count_unsorted(jinterners.string.iter().map(|s| (s.len(), s))),
+            unsorted_arrays: count_unsorted(jinterners.iarray.iter().map(|a| (a.len(), a))),
+            unsorted_objects: count_unsorted(jinterners.iobject.iter().map(|o| (o.len(), o))),
+        }
+    }
+
+    /// Returns the total number of interned items.
+    pub fn items(&self) -> usize {
+        self.strings + self.arrays + self.objects
+    }
+
+    /// Returns the share of items that are ordered after their successor,
+    /// between 0 and 1.
+    pub fn disorder_ratio(&self) -> f64 {
+        ratio(
+            self.unsorted_strings + self.unsorted_arrays + self.unsorted_objects,
+            self.items(),
+        )
+    }
+}
+
+/// Result of a maintenance run.
+pub struct MaintenanceOutcome {
+    /// The maintained arena.
+    pub jinterners: Jinterners,
+    /// The mapping to convert values from the original arena to the maintained
+    /// one.
+    pub mapping: Mapping,
+    /// Measurements of the original arena.
+    pub before: MaintenanceStats,
+    /// Measurements of the maintained arena.
+    pub after: MaintenanceStats,
+}
+
+impl Default for JinternersMaintainer {
+    fn default() -> Self {
+        Self {
+            disorder_threshold: 0.1,
+            garbage_threshold: 0.1,
+            optimize_limit: None,
+        }
+    }
+}
+
+impl JinternersMaintainer {
+    /// Creates a maintainer with the default policy: the arena is optimized or
+    /// compacted when more than 10% of the items are out of order or
+    /// unreachable, respectively.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the share of out-of-order items (between 0 and 1) above which the
+    /// arena is optimized.
+    pub fn disorder_threshold(mut self, threshold: f64) -> Self {
+        self.disorder_threshold = threshold;
+        self
+    }
+
+    /// Sets the share of unreachable items (between 0 and 1) above which the
+    /// arena is compacted.
+    pub fn garbage_threshold(mut self, threshold: f64) -> Self {
+        self.garbage_threshold = threshold;
+        self
+    }
+
+    /// Sets the maximal number of iterations of the optimization routine, see
+    /// [`Jinterners::optimize()`]. Limiting it allows to amortize the
+    /// optimization over several maintenance runs.
+    pub fn optimize_limit(mut self, limit: Option<usize>) -> Self {
+        self.optimize_limit = limit;
+        self
+    }
+
+    /// Runs maintenance on the given arena, keeping only items reachable from
+    /// the given roots.
+    ///
+    /// Returns [`None`] if no maintenance was needed.
+    pub fn run(&self, jinterners: &Jinterners, roots: &[IValue]) -> Option<MaintenanceOutcome> {
+        let before = MaintenanceStats::measure(jinterners);
+
+        let optimized = if before.disorder_ratio() > self.disorder_threshold {
+            jinterners.optimize(self.optimize_limit)
+        } else {
+            None
+        };
+
+        // Compaction happens after optimization, so that the resulting mappings
+        // can be composed.
+        let source = match &optimized {
+            Some((jinterners, _)) => jinterners,
+            None => jinterners,
+        };
+        let mut builder = source.retain_builder();
+        for root in roots {
+            builder.insert(match &optimized {
+                Some((_, mapping)) => mapping.map(*root),
+                None => *root,
+            });
+        }
+        let compacted = builder.build().filter(|(compacted, _)| {
+            let garbage = count_items(source) - count_items(compacted);
+            ratio(garbage, before.items()) > self.garbage_threshold
+        });
+
+        let (jinterners, mapping) = match (optimized, compacted) {
+            (None, None) => return None,
+            (Some(optimized), None) => optimized,
+            (None, Some(compacted)) => compacted,
+            (Some((_, optimized)), Some((jinterners, compacted))) => {
+                (jinterners, optimized.then(compacted))
+            }
+        };
+
+        Some(MaintenanceOutcome {
+            after: MaintenanceStats::measure(&jinterners),
+            jinterners,
+            mapping,
+            before,
+        })
+    }
+
+    /// Runs maintenance on a background thread, and passes the result of
+    /// [`run()`](Self::run) to the given callback.
+    ///
+    /// The callback can for example send the outcome to a
+    /// [`channel`](std::sync::mpsc::channel), or publish it to a
+    /// [`SharedJinterners`](crate::SharedJinterners) handle.
+    ///
+    /// This method isn't available on WebAssembly, where threads can't be
+    /// spawned.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn spawn(
+        &self,
+        jinterners: Arc<Jinterners>,
+        roots: Vec<IValue>,
+        on_done: impl FnOnce(Option<MaintenanceOutcome>) + Send + 'static,
+    ) -> JoinHandle<()> {
+        let maintainer = *self;
+        std::thread::spawn(move || on_done(maintainer.run(&jinterners, &roots)))
+    }
+}
+
+fn count_items(jinterners: &Jinterners) -> usize {
+    jinterners.string.strings() + jinterners.iarray.slices() + jinterners.iobject.slices()
+}
+
+fn count_unsorted<T: Ord>(iter: impl Iterator<Item = T>) -> usize {
+    let mut count = 0;
+    let mut previous = None;
+    for item in iter {
+        if previous.as_ref().is_some_and(|p| *p > item) {
+            count += 1;
+        }
+        previous = Some(item);
+    }
+    count
+}
+
+fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::sync::mpsc;
+
+    #[test]
+    fn maintain_off_thread() {
+        let jinterners = Jinterners::default();
+        let keep = json!({"name": "John", "tags": ["b", "a"]});
+        let drop = json!({"name": "Mary", "tags": ["c", "d", "e"]});
+        let ikeep = jinterners.intern_ref(&keep);
+        jinterners.intern_ref(&drop);
+
+        let (sender, receiver) = mpsc::channel();
+        JinternersMaintainer::new()
+            .spawn(Arc::new(jinterners), vec![ikeep], move |outcome| {
+                sender.send(outcome).unwrap();
+            })
+            .join()
+            .unwrap();
+
+        let outcome = receiver.recv().unwrap().unwrap();
+        assert!(outcome.before.disorder_ratio() > 0.0);
+        assert_eq!(outcome.after.disorder_ratio(), 0.0);
+        assert!(outcome.after.items() < outcome.before.items());
+
+        let mapped = outcome.mapping.map(ikeep);
+        assert_eq!(outcome.jinterners.lookup(&mapped), keep);
+
+        // Nothing left to do.
+        assert!(
+            JinternersMaintainer::new()
+                .run(&outcome.jinterners, &[mapped])
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,505 @@
+use crate::detail::IValueImpl;
+#[cfg(feature = "serde")]
+use crate::detail::deserialize_slice;
+use crate::keys::{PathStep, parse_path};
+use crate::{Error, IValue, Jinterners};
+#[cfg(feature = "serde")]
+use crate::{InternedSeed, InternedStrKey};
+#[cfg(feature = "serde")]
+use serde::de::{DeserializeOwned, DeserializeSeed, Deserializer, IgnoredAny, MapAccess};
+#[cfg(feature = "serde")]
+use serde::de::{SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+#[cfg(feature = "serde")]
+use std::fmt;
+
+/// A set of fields to select in JSON documents, to only visit, intern or
+/// deserialize the parts of the documents that a consumer needs.
+///
+/// The selected parts of a document are returned as a document with the same
+/// structure, in which objects only contain the selected keys and arrays only
+/// contain the selected items, in their original order. A path whose value
+/// isn't an object or an array where the mask expects one selects nothing.
+///
+/// ```
+/// use jinterner::{FieldMask, Jinterners};
+/// use serde_json::json;
+///
+/// let interners = Jinterners::default();
+/// let mask = FieldMask::compile("user.id, events[*].ts")?;
+/// let value = interners.intern(json!({
+///     "user": {"id": 42, "name": "John"},
+///     "events": [{"ts": 1, "kind": "a"}, {"ts": 2}, {"kind": "c"}],
+///     "payload": [1, 2, 3],
+/// }));
+/// assert_eq!(
+///     mask.select(&interners, &value),
+///     json!({"user": {"id": 42}, "events": [{"ts": 1}, {"ts": 2}, {}]})
+/// );
+/// # Ok::<(), jinterner::Error>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldMask {
+    root: Node,
+}
+
+/// The selected fields below a value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Node {
+    /// Whether the whole value is selected, in which case the other fields
+    /// are empty.
+    whole: bool,
+    /// Selected fields of an object.
+    keys: BTreeMap<String, Node>,
+    /// Selected fields of all the items of an array.
+    items: Option<Box<Node>>,
+    /// Selected fields of specific items of an array.
+    indices: BTreeMap<usize, Node>,
+}
+
+impl Node {
+    /// Selects the given path below this node.
+    fn insert(&mut self, steps: &[PathStep]) {
+        let mut node = self;
+        for step in steps {
+            if node.whole {
+                return;
+            }
+            node = match step {
+                PathStep::Key(key) => node.keys.entry((*key).into()).or_default(),
+                PathStep::Index(i) => node.indices.entry(*i).or_default(),
+                PathStep::Items => node.items.get_or_insert_default(),
+            };
+        }
+        *node = Node {
+            whole: true,
+            ..Node::default()
+        };
+    }
+
+    /// Adds the fields selected by the given node to this node.
+    fn merge(&mut self, other: &Node) {
+        if self.whole {
+            return;
+        }
+        if other.whole {
+            *self = other.clone();
+            return;
+        }
+        for (key, child) in &other.keys {
+            self.keys.entry(key.clone()).or_default().merge(child);
+        }
+        if let Some(items) = &other.items {
+            self.items.get_or_insert_default().merge(items);
+        }
+        for (i, child) in &other.indices {
+            self.indices.entry(*i).or_default().merge(child);
+        }
+    }
+
+    /// Merges the fields selected for all items into the fields selected for
+    /// specific items, so that [`item()`](Self::item) only needs to return
+    /// one node.
+    fn normalize(&mut self) {
+        if let Some(items) = &mut self.items {
+            for child in self.indices.values_mut() {
+                child.merge(items);
+            }
+            items.normalize();
+        }
+        for child in self.keys.values_mut().chain(self.indices.values_mut()) {
+            child.normalize();
+        }
+    }
+
+    /// Returns the fields selected in the array item at the given index.
+    fn item(&self, i: usize) -> Option<&Node> {
+        self.indices.get(&i).or(self.items.as_deref())
+    }
+
+    fn select(&self, interners: &Jinterners, value: &IValue) -> Option<Value> {
+        if self.whole {
+            return Some(interners.lookup(value));
+        }
+        match value.0 {
+            IValueImpl::Object(object) => {
+                let object = interners.iobject.lookup(object);
+                let mut map = Map::new();
+                for (key, child) in &self.keys {
+                    let Some(k) = interners.find_key(key) else {
+                        continue;
+                    };
+                    if let Ok(i) = object.binary_search_by_key(&k, |(k, _)| *k)
+                        && let Some(selected) = child.select(interners, &object[i].1)
+                    {
+                        map.insert(key.clone(), selected);
+                    }
+                }
+                Some(Value::Object(map))
+            }
+            IValueImpl::Array(array) => {
+                let array = interners.iarray.lookup(array);
+                let selected = match &self.items {
+                    Some(_) => array
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, item)| self.item(i)?.select(interners, item))
+                        .collect(),
+                    None => self
+                        .indices
+                        .iter()
+                        .filter_map(|(i, child)| child.select(interners, array.get(*i)?))
+                        .collect(),
+                };
+                Some(Value::Array(selected))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FieldMask {
+    /// Compiles the given comma-separated paths into a mask.
+    ///
+    /// Each path follows the syntax of [`Accessor::compile()`]: dot-separated
+    /// object keys, each followed by any number of array indices in brackets.
+    /// In addition, the `[*]` index selects all the items of an array, for
+    /// example `events[*].ts`. Whitespace around paths is ignored.
+    ///
+    /// Returns [`Error::Accessor`] if a path is empty or an array index is
+    /// malformed.
+    ///
+    /// [`Accessor::compile()`]: crate::Accessor::compile
+    pub fn compile(paths: &str) -> Result<Self, Error> {
+        let mut root = Node::default();
+        for path in paths.split(',').map(str::trim) {
+            if path.is_empty() {
+                return Err(Error::Accessor {
+                    component: path.into(),
+                });
+            }
+            let steps = parse_path(path, true)?;
+            root.insert(&steps);
+        }
+        root.normalize();
+        Ok(Self { root })
+    }
+
+    /// Returns the selected parts of the given value, only visiting the
+    /// selected fields.
+    ///
+    /// Returns `null` if the value isn't an object or an array.
+    ///
+    /// The caller is responsible for ensuring that the value was interned in
+    /// the given arena, otherwise an arbitrary value will be returned or a
+    /// panic will happen.
+    pub fn select(&self, interners: &Jinterners, value: &IValue) -> Value {
+        self.root.select(interners, value).unwrap_or(Value::Null)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl IValue {
+    /// Converts the selected parts of this [`IValue`] into an arbitrary type
+    /// using that type's [`Deserialize`](serde::Deserialize) implementation.
+    ///
+    /// The selected parts are first materialized with
+    /// [`FieldMask::select()`], then deserialized. As only the selected fields
+    /// are visited, this is cheaper than [`to_value()`](Self::to_value) when
+    /// the type only needs a few fields of large documents, but the selected
+    /// parts are still allocated as a [`serde_json::Value`].
+    ///
+    /// ```
+    /// use jinterner::{FieldMask, Jinterners};
+    /// use serde::Deserialize;
+    /// use serde_json::json;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Event {
+    ///     ts: u64,
+    /// }
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Summary {
+    ///     events: Vec<Event>,
+    /// }
+    ///
+    /// let interners = Jinterners::default();
+    /// let mask = FieldMask::compile("events[*].ts")?;
+    /// let value = interners.intern(json!({
+    ///     "events": [{"ts": 1, "kind": "a"}, {"ts": 2, "kind": "b"}],
+    ///     "user": {"name": "John"},
+    /// }));
+    /// let summary: Summary = value.to_value_masked(&interners, &mask)?;
+    /// assert_eq!(summary.events[1].ts, 2);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn to_value_masked<T>(
+        &self,
+        interners: &Jinterners,
+        mask: &FieldMask,
+    ) -> Result<T, serde_json::error::Error>
+    where
+        T: DeserializeOwned,
+    {
+        T::deserialize(mask.select(interners, self))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Jinterners {
+    /// Parses the given JSON text and interns the parts selected by the given
+    /// mask into this arena.
+    ///
+    /// The text is fully validated, but the fields that aren't selected are
+    /// skipped without being interned, so that they don't grow the arena.
+    /// The result is `null` if the document isn't an object or an array.
+    ///
+    /// ```
+    /// use jinterner::{FieldMask, Jinterners};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let mask = FieldMask::compile("user.id, events[*].ts")?;
+    /// let text = r#"{"user": {"id": 42, "name": "John"}, "events": [{"ts": 1, "kind": "a"}]}"#;
+    /// let ivalue = interners.parse_masked(text, &mask)?;
+    /// assert_eq!(
+    ///     interners.lookup(&ivalue),
+    ///     json!({"user": {"id": 42}, "events": [{"ts": 1}]})
+    /// );
+    /// assert_eq!(interners.find(&json!("John")), None);
+    /// # Ok::<(), jinterner::Error>(())
+    /// ```
+    pub fn parse_masked(&self, text: &str, mask: &FieldMask) -> Result<IValue, Error> {
+        let seed = MaskedSeed {
+            interners: self,
+            node: &mask.root,
+        };
+        let ivalue = deserialize_slice(seed, text.as_bytes())?;
+        Ok(ivalue.unwrap_or(IValue(IValueImpl::Null)))
+    }
+}
+
+/// A [`DeserializeSeed`] that interns the fields selected by a [`Node`], or
+/// returns [`None`] if the value contains no such field.
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy)]
+struct MaskedSeed<'a> {
+    interners: &'a Jinterners,
+    node: &'a Node,
+}
+
+#[cfg(feature = "serde")]
+impl<'de> DeserializeSeed<'de> for MaskedSeed<'_> {
+    type Value = Option<IValue>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if self.node.whole {
+            InternedSeed::new(self.interners)
+                .deserialize(deserializer)
+                .map(Some)
+        } else {
+            deserializer.deserialize_any(self)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Visitor<'de> for MaskedSeed<'_> {
+    type Value = Option<IValue>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any valid JSON value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut array = Vec::new();
+        for i in 0.. {
+            match self.node.item(i) {
+                Some(node) => match seq.next_element_seed(MaskedSeed { node, ..self })? {
+                    None => break,
+                    Some(Some(value)) => array.push(value),
+                    Some(None) => (),
+                },
+                None => {
+                    if seq.next_element::<IgnoredAny>()?.is_none() {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(Some(IValue(IValueImpl::Array(
+            self.interners.intern_array_slice(&array),
+        ))))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut object = Vec::new();
+        while let Some(selected) = map.next_key_seed(MaskedKeySeed(self))? {
+            match selected {
+                Some((key, node)) => {
+                    if let Some(value) = map.next_value_seed(MaskedSeed { node, ..self })? {
+                        object.push((key, value));
+                    }
+                }
+                None => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        object.sort_by_key(|(k, _)| *k);
+        // Keep the last value of duplicate keys, like serde_json.
+        object.dedup_by(|later, earlier| {
+            let duplicate = later.0 == earlier.0;
+            if duplicate {
+                earlier.1 = later.1;
+            }
+            duplicate
+        });
+        Ok(Some(IValue(IValueImpl::Object(
+            self.interners.intern_object_slice(&object),
+        ))))
+    }
+}
+
+/// A [`DeserializeSeed`] that interns an object key if it's selected by the
+/// node of the given [`MaskedSeed`], and returns the node selecting its value.
+#[cfg(feature = "serde")]
+struct MaskedKeySeed<'a>(MaskedSeed<'a>);
+
+#[cfg(feature = "serde")]
+impl<'de, 'a> DeserializeSeed<'de> for MaskedKeySeed<'a> {
+    type Value = Option<(InternedStrKey, &'a Node)>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, 'a> Visitor<'de> for MaskedKeySeed<'a> {
+    type Value = Option<(InternedStrKey, &'a Node)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string key")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        let MaskedSeed { interners, node } = self.0;
+        Ok(node
+            .keys
+            .get(value)
+            .map(|node| (InternedStrKey(interners.intern_string(value)), node)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn compile() {
+        let mask = FieldMask::compile("a.b, a, c[*].d, c[1].e, c[1][0]").unwrap();
+        let c = &mask.root.keys["c"];
+        assert!(mask.root.keys["a"].whole);
+        assert!(mask.root.keys["a"].keys.is_empty());
+        assert_eq!(c.item(0), c.items.as_deref());
+        let c1 = c.item(1).unwrap();
+        assert!(c1.keys["d"].whole && c1.keys["e"].whole && c1.indices[&0].whole);
+
+        for paths in ["a,", "a[x]", "a[*", "a.b[-1]"] {
+            assert!(matches!(
+                FieldMask::compile(paths),
+                Err(Error::Accessor { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn select() {
+        let interners = Jinterners::default();
+        let document = json!({
+            "id": 1,
+            "user": {"id": 2, "name": "John", "tags": ["a", "b", "c"]},
+            "events": [{"ts": 1, "kind": "a"}, "skipped", {"kind": "b"}, {"ts": 4}],
+            "other": {"ts": 5},
+        });
+        let value = interners.intern_ref(&document);
+        let mask = FieldMask::compile(
+            "id, user.name, user.tags[2], user.tags[7], events[*].ts, other.ts.x, missing",
+        )
+        .unwrap();
+        let expected = json!({
+            "id": 1,
+            "user": {"name": "John", "tags": ["c"]},
+            "events": [{"ts": 1}, {}, {"ts": 4}],
+            "other": {},
+        });
+        assert_eq!(mask.select(&interners, &value), expected);
+        assert_eq!(
+            mask.select(&interners, &interners.intern(json!("x"))),
+            Value::Null
+        );
+
+        #[cfg(feature = "serde")]
+        {
+            let text = document.to_string();
+            let masked = Jinterners::default();
+            let ivalue = masked.parse_masked(&text, &mask).unwrap();
+            assert_eq!(masked.lookup(&ivalue), expected);
+            assert_eq!(masked.find(&json!("a")), None);
+            assert_eq!(masked.find_key("kind"), None);
+
+            let ivalue = masked
+                .parse_masked(r#"{"id": 1, "id": [2]}"#, &mask)
+                .unwrap();
+            assert_eq!(masked.lookup(&ivalue), json!({"id": [2]}));
+            assert!(matches!(
+                masked.parse_masked(r#"{"skipped": [}"#, &mask),
+                Err(Error::Parse(_))
+            ));
+        }
+    }
+}
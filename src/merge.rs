@@ -0,0 +1,200 @@
+use crate::detail::mapping::IdMap;
+use crate::translate::{CopyInto, Translator};
+use crate::{Jinterners, Mapping};
+use blazinterner::{ArenaStr, InternedStr};
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+impl Jinterners {
+    /// Merges the given arenas into a single one, in parallel.
+    ///
+    /// This is useful to combine the per-shard arenas produced by a map-reduce
+    /// ingest into one global snapshot. The strings of all the parts are
+    /// merged with a k-way merge, which is fastest if the parts were
+    /// [optimized](Self::optimize) beforehand, then the arrays and objects of
+    /// all the parts are interned concurrently and the result is optimized.
+    ///
+    /// Returns the merged arena, together with the mapping to convert values
+    /// from each part into the merged arena, in the same order as the parts.
+    /// The merged arena has the settings of the first part.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let a = Jinterners::default();
+    /// let ia = a.intern(json!({"name": "John", "tags": ["x"]}));
+    /// let b = Jinterners::default();
+    /// let ib = b.intern(json!({"name": "Jane", "tags": ["x"]}));
+    ///
+    /// let (merged, mappings) = Jinterners::merge_all(vec![a, b]);
+    /// let ia = mappings[0].map(ia);
+    /// let ib = mappings[1].map(ib);
+    /// assert_eq!(merged.lookup(&ia), json!({"name": "John", "tags": ["x"]}));
+    /// assert_eq!(merged.lookup(&ib), json!({"name": "Jane", "tags": ["x"]}));
+    /// assert_eq!(merged.bind(ia).get("tags"), merged.bind(ib).get("tags"));
+    /// ```
+    pub fn merge_all(parts: Vec<Jinterners>) -> (Jinterners, Vec<Mapping>) {
+        let Some(first) = parts.first() else {
+            return (Jinterners::default(), Vec::new());
+        };
+
+        let (string, string_tables) = merge_strings(&parts);
+        let merged = Jinterners {
+            string_filter: first.string_filter.rebuild(string.iter()),
+            string,
+            ..first.with_settings_of()
+        };
+
+        let tables: Vec<[Vec<u32>; 3]> = parts
+            .par_iter()
+            .zip(string_tables)
+            .map(|(part, strings)| {
+                let strings = strings
+                    .into_iter()
+                    .map(|id| Some(InternedStr::from_id(id)))
+                    .collect();
+                // The merged arena is only populated with copies of existing
+                // values, so this bypasses the hooks and metrics of the merged
+                // arena.
+                Translator::new(part, strings, CopyInto(&merged))
+                    .translate_all()
+                    .map(|ids| {
+                        ids.into_iter()
+                            .map(|id| id.expect("merged entries are always interned"))
+                            .collect()
+                    })
+            })
+            .collect();
+
+        // Arrays and objects were interned in a nondeterministic order, so
+        // optimize the result to make it canonical.
+        let (merged, optimization) = match merged.optimize(None) {
+            None => (merged, None),
+            Some((optimized, mapping)) => (optimized, Some(mapping)),
+        };
+        let mappings = tables
+            .into_par_iter()
+            .map(|[strings, arrays, objects]| {
+                let table = |ids: Vec<u32>, then: Option<&IdMap>| {
+                    IdMap::Table(match then {
+                        None => ids.into(),
+                        Some(then) => ids.into_iter().map(|id| then.at(id)).collect(),
+                    })
+                };
+                Mapping {
+                    string: table(strings, optimization.as_ref().map(|m| &m.string)),
+                    iarray: table(arrays, optimization.as_ref().map(|m| &m.iarray)),
+                    iobject: table(objects, optimization.as_ref().map(|m| &m.iobject)),
+                }
+            })
+            .collect();
+        (merged, mappings)
+    }
+}
+
+/// Merges the strings of the given arenas into a sorted arena, returning the
+/// index of each string of each part in the merged arena.
+fn merge_strings(parts: &[Jinterners]) -> (ArenaStr, Vec<Vec<u32>>) {
+    // Indices of the strings of each part, in lexicographic order. This is
+    // already the case for optimized arenas, which the sort detects quickly.
+    let orders: Vec<Vec<u32>> = parts
+        .par_iter()
+        .map(|part| {
+            let mut order: Vec<u32> = (0..part.string.strings() as u32).collect();
+            order.sort_unstable_by_key(|i| part.string.lookup(InternedStr::from_id(*i)));
+            order
+        })
+        .collect();
+
+    let strings = parts.iter().map(|part| part.string.strings()).sum();
+    let bytes = parts.iter().map(|part| part.string.bytes()).sum();
+    let mut merged = ArenaStr::with_capacity(strings, bytes);
+    let mut tables: Vec<Vec<u32>> = parts
+        .iter()
+        .map(|part| vec![0; part.string.strings()])
+        .collect();
+
+    let lookup = |part: usize, position: usize| {
+        let id = orders[part][position];
+        (parts[part].string.lookup(InternedStr::from_id(id)), id)
+    };
+    let mut heap = BinaryHeap::new();
+    for (part, order) in orders.iter().enumerate() {
+        if !order.is_empty() {
+            heap.push(Reverse((lookup(part, 0), part, 0)));
+        }
+    }
+    let mut last: Option<(&str, u32)> = None;
+    while let Some(Reverse(((string, id), part, position))) = heap.pop() {
+        let merged_id = match last {
+            Some((previous, merged_id)) if previous == string => merged_id,
+            _ => {
+                let merged_id = merged.push_mut(string);
+                last = Some((string, merged_id));
+                merged_id
+            }
+        };
+        tables[part][id as usize] = merged_id;
+        if position + 1 < orders[part].len() {
+            heap.push(Reverse((lookup(part, position + 1), part, position + 1)));
+        }
+    }
+    (merged, tables)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::IValue;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn merge_all() {
+        let values: Vec<Value> = (0..60)
+            .map(|i| json!({"id": i % 10, "tags": ["a", format!("t{}", i % 7)], "n": {"x": [i % 3]}}))
+            .collect();
+        let parts: Vec<(Jinterners, Vec<IValue>)> = values
+            .chunks(20)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let part = Jinterners::default();
+                let roots: Vec<IValue> = chunk.iter().map(|v| part.intern_ref(v)).collect();
+                // Mix optimized and non-optimized parts.
+                match part.optimize(None).filter(|_| i % 2 == 0) {
+                    Some((optimized, mapping)) => {
+                        let roots = roots.into_iter().map(|r| mapping.map(r)).collect();
+                        (optimized, roots)
+                    }
+                    None => (part, roots),
+                }
+            })
+            .collect();
+        let (parts, roots): (Vec<_>, Vec<_>) = parts.into_iter().unzip();
+
+        let (merged, mappings) = Jinterners::merge_all(parts);
+        assert_eq!(mappings.len(), 3);
+        let merged_roots: Vec<IValue> = roots
+            .iter()
+            .zip(&mappings)
+            .flat_map(|(roots, mapping)| roots.iter().map(|r| mapping.map(*r)))
+            .collect();
+        for (root, value) in merged_roots.iter().zip(&values) {
+            assert_eq!(merged.lookup(root), *value);
+        }
+
+        // The result is the same as interning everything into one arena.
+        let single = Jinterners::default();
+        for value in &values {
+            single.intern_ref(value);
+        }
+        let single = single.optimize(None).unwrap().0;
+        assert_eq!(merged, single);
+        assert!(merged.optimize(None).is_none());
+
+        let (empty, mappings) = Jinterners::merge_all(Vec::new());
+        assert_eq!(empty, Jinterners::default());
+        assert!(mappings.is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::ArenaKind;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A hook to record metrics about the usage of a
+/// [`Jinterners`](crate::Jinterners) arena.
+///
+/// A recorder can be installed with
+/// [`set_metrics_recorder()`](crate::Jinterners::set_metrics_recorder). See
+/// [`MetricsCrateRecorder`] for an implementation forwarding to the
+/// [`metrics`] crate.
+pub trait MetricsRecorder: Send + Sync {
+    /// Records that an entry was interned in the given arena.
+    ///
+    /// A hit means that the entry was already present in the arena. Otherwise,
+    /// `bytes` were appended to the arena to store the entry.
+    ///
+    /// Note that when the same new entry is interned concurrently by several
+    /// threads, it may be reported as a miss more than once.
+    fn record_intern(&self, arena: ArenaKind, hit: bool, bytes: usize);
+
+    /// Records that a value was looked up.
+    fn record_lookup(&self);
+}
+
+/// A [`MetricsRecorder`] forwarding to the global recorder of the [`metrics`]
+/// crate.
+///
+/// The following counters are reported, with an `arena` label for the first
+/// three:
+/// - `jinterner_intern_hits_total`,
+/// - `jinterner_intern_misses_total`,
+/// - `jinterner_appended_bytes_total`,
+/// - `jinterner_lookups_total`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsCrateRecorder;
+
+impl MetricsRecorder for MetricsCrateRecorder {
+    fn record_intern(&self, arena: ArenaKind, hit: bool, bytes: usize) {
+        if hit {
+            metrics::counter!("jinterner_intern_hits_total", "arena" => arena.name()).increment(1);
+        } else {
+            metrics::counter!("jinterner_intern_misses_total", "arena" => arena.name())
+                .increment(1);
+            metrics::counter!("jinterner_appended_bytes_total", "arena" => arena.name())
+                .increment(bytes as u64);
+        }
+    }
+
+    fn record_lookup(&self) {
+        metrics::counter!("jinterner_lookups_total").increment(1);
+    }
+}
+
+/// A [`MetricsRecorder`] keeping counters in memory.
+///
+/// The counters can be read individually, or rendered with
+/// [`write_prometheus()`](Self::write_prometheus).
+#[derive(Debug, Default)]
+pub struct InternCounters {
+    hits: [AtomicU64; 3],
+    misses: [AtomicU64; 3],
+    appended_bytes: [AtomicU64; 3],
+    lookups: AtomicU64,
+}
+
+impl InternCounters {
+    /// Returns the number of interned entries that were already present in the
+    /// given arena.
+    pub fn hits(&self, arena: ArenaKind) -> u64 {
+        self.hits[arena as usize].load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of interned entries that were appended to the given
+    /// arena.
+    pub fn misses(&self, arena: ArenaKind) -> u64 {
+        self.misses[arena as usize].load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes appended to the given arena.
+    pub fn appended_bytes(&self, arena: ArenaKind) -> u64 {
+        self.appended_bytes[arena as usize].load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of lookups.
+    pub fn lookups(&self) -> u64 {
+        self.lookups.load(Ordering::Relaxed)
+    }
+}
+
+impl MetricsRecorder for InternCounters {
+    fn record_intern(&self, arena: ArenaKind, hit: bool, bytes: usize) {
+        if hit {
+            self.hits[arena as usize].fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses[arena as usize].fetch_add(1, Ordering::Relaxed);
+            self.appended_bytes[arena as usize].fetch_add(bytes as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn record_lookup(&self) {
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The optional [`MetricsRecorder`] installed on a
+/// [`Jinterners`](crate::Jinterners).
+///
+/// This is ignored when comparing, measuring or serializing arenas.
+#[derive(Clone, Default)]
+pub(crate) struct MetricsHook(Option<Arc<dyn MetricsRecorder>>);
+
+impl MetricsHook {
+    pub(crate) fn new(recorder: Option<Arc<dyn MetricsRecorder>>) -> Self {
+        Self(recorder)
+    }
+
+    /// Records that an entry was interned with the given `id`, in an arena
+    /// which contained `before` entries prior to interning.
+    pub(crate) fn record_intern(&self, arena: ArenaKind, before: usize, id: u32, bytes: usize) {
+        if let Some(recorder) = &self.0 {
+            recorder.record_intern(arena, (id as usize) < before, bytes);
+        }
+    }
+
+    pub(crate) fn record_lookup(&self) {
+        if let Some(recorder) = &self.0 {
+            recorder.record_lookup();
+        }
+    }
+}
+
+impl Debug for MetricsHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("MetricsHook")
+            .field(&self.0.as_ref().map(|_| ..))
+            .finish()
+    }
+}
+
+impl PartialEq for MetricsHook {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for MetricsHook {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{IValue, InternedStrKey, Jinterners};
+    use serde_json::json;
+
+    #[test]
+    fn record_metrics() {
+        let counters = Arc::new(InternCounters::default());
+        let mut interners = Jinterners::default();
+        interners.set_metrics_recorder(Some(counters.clone()));
+
+        let value = json!({"name": "John", "tags": ["John", "Doe"]});
+        let a = interners.intern_ref(&value);
+        let b = interners.intern_ref_mut(&value);
+        assert_eq!(a, b);
+        interners.lookup(&a);
+        interners.lookup_ref(&b);
+
+        let arenas = [ArenaKind::String, ArenaKind::Array, ArenaKind::Object];
+        // Strings "name", "John", "tags", "John", "Doe".
+        assert_eq!(arenas.map(|a| counters.misses(a)), [4, 1, 1]);
+        assert_eq!(arenas.map(|a| counters.hits(a)), [6, 1, 1]);
+        assert_eq!(
+            arenas.map(|a| counters.appended_bytes(a)),
+            [
+                15,
+                2 * size_of::<IValue>() as u64,
+                2 * size_of::<(InternedStrKey, IValue)>() as u64
+            ]
+        );
+        assert_eq!(counters.lookups(), 2);
+
+        // The recorder doesn't affect equality.
+        assert_eq!(interners, interners.clone());
+        let mut other = interners.clone();
+        other.set_metrics_recorder(None);
+        assert_eq!(interners, other);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{IValue, Jinterners};
+use opentelemetry::logs::AnyValue;
+use opentelemetry::{Key, KeyValue, Value};
+
+impl Jinterners {
+    /// Interns the given span or resource attributes into this arena, as an
+    /// object mapping each key to its value.
+    ///
+    /// Attributes are interned directly, without materializing an intermediate
+    /// [`serde_json::Value`]. As telemetry pipelines see the same keys and
+    /// values over and over, each distinct key, value and attribute set is
+    /// only stored once.
+    ///
+    /// If a key is repeated, the last value wins. Arrays are interned as JSON
+    /// arrays, and non-finite floats rejected by the
+    /// [`FloatPolicy`](crate::FloatPolicy) as null.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use opentelemetry::KeyValue;
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let mut attributes = [
+    ///     KeyValue::new("http.request.method", "GET"),
+    ///     KeyValue::new("http.response.status_code", 200),
+    /// ];
+    ///
+    /// let ivalue = interners.intern_otel_attributes(&attributes);
+    /// assert_eq!(
+    ///     interners.lookup(&ivalue),
+    ///     json!({"http.request.method": "GET", "http.response.status_code": 200})
+    /// );
+    /// // The same attribute set, in any order, is interned to the same value.
+    /// attributes.reverse();
+    /// assert_eq!(interners.intern_otel_attributes(&attributes), ivalue);
+    /// ```
+    pub fn intern_otel_attributes<'a>(
+        &self,
+        attributes: impl IntoIterator<Item = &'a KeyValue>,
+    ) -> IValue {
+        IValue::from_otel_attributes(self, attributes)
+    }
+
+    /// Interns the given log record attributes into this arena, as an object
+    /// mapping each key to its value.
+    ///
+    /// This is the same as
+    /// [`intern_otel_attributes()`](Self::intern_otel_attributes), for the
+    /// attributes of log records. Byte values are interned as arrays of
+    /// numbers, like [`serde_json`] does.
+    pub fn intern_otel_log_attributes<'a>(
+        &self,
+        attributes: impl IntoIterator<Item = &'a (Key, AnyValue)>,
+    ) -> IValue {
+        IValue::from_otel_log_attributes(self, attributes)
+    }
+
+    /// Interns the given attribute value into this arena.
+    pub fn intern_otel_value(&self, value: &Value) -> IValue {
+        IValue::from_otel(self, value)
+    }
+
+    /// Interns the given log record value into this arena, such as the body of
+    /// a log record.
+    pub fn intern_otel_any_value(&self, value: &AnyValue) -> IValue {
+        IValue::from_otel_any(self, value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FloatPolicy;
+    use opentelemetry::{Array, StringValue};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn attributes() {
+        let interners = Jinterners::default();
+        let attributes = [
+            KeyValue::new("bool", true),
+            KeyValue::new("int", -1),
+            KeyValue::new("count", 3),
+            KeyValue::new("float", 0.5),
+            KeyValue::new("nan", f64::NAN),
+            KeyValue::new("key", "first"),
+            KeyValue::new(
+                "strings",
+                Value::Array(Array::String(vec![StringValue::from("a")])),
+            ),
+            KeyValue::new("key", "last"),
+        ];
+        let ivalue = interners.intern_otel_attributes(&attributes);
+        assert_eq!(
+            interners.lookup(&ivalue),
+            json!({
+                "bool": true,
+                "int": -1,
+                "count": 3,
+                "float": 0.5,
+                "nan": null,
+                "key": "last",
+                "strings": ["a"],
+            })
+        );
+        assert_eq!(
+            interners.intern_otel_value(&Value::Array(Array::I64(vec![3, -1]))),
+            interners.intern(json!([3, -1]))
+        );
+
+        let mut interners = Jinterners::default();
+        interners.set_float_policy(FloatPolicy::Reject);
+        let nan = interners.intern_otel_value(&Value::F64(f64::NAN));
+        assert_eq!(nan, interners.intern(json!(null)));
+    }
+
+    #[test]
+    fn log_attributes() {
+        let interners = Jinterners::default();
+        let body = AnyValue::Map(Box::new(HashMap::from([
+            (Key::new("bytes"), AnyValue::Bytes(Box::new(vec![0, 255]))),
+            (
+                Key::new("list"),
+                AnyValue::ListAny(Box::new(vec![
+                    AnyValue::Int(1),
+                    AnyValue::Double(1.5),
+                    AnyValue::Boolean(false),
+                ])),
+            ),
+        ])));
+        let attributes = [
+            (Key::new("body"), body.clone()),
+            (Key::new("message"), AnyValue::from("hello")),
+        ];
+        let ivalue = interners.intern_otel_log_attributes(&attributes);
+        assert_eq!(
+            interners.lookup(&ivalue),
+            json!({
+                "body": {"bytes": [0, 255], "list": [1, 1.5, false]},
+                "message": "hello",
+            })
+        );
+        assert_eq!(
+            interners.pointer(&ivalue, "/body").unwrap(),
+            interners.intern_otel_any_value(&body)
+        );
+    }
+}
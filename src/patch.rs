@@ -0,0 +1,371 @@
+use crate::pointer::Edit;
+use crate::{Error, IValue, Jinterners, PointerError, PointerErrorReason};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// An operation of a [RFC 6902](https://www.rfc-editor.org/rfc/rfc6902) JSON
+/// patch, applied by [`Jinterners::apply_patch()`].
+///
+/// With the `serde` feature, operations are (de)serialized as in the JSON
+/// representation of the RFC, for example
+/// `{"op": "add", "path": "/a", "value": 1}`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(tag = "op", rename_all = "lowercase")
+)]
+pub enum PatchOperation {
+    /// Adds or replaces an object member, inserts an array item before the
+    /// given index or at the end with the `-` token, or replaces the whole
+    /// value with the empty pointer.
+    Add {
+        /// JSON pointer of the location to add to.
+        path: String,
+        /// Value to add.
+        value: Value,
+    },
+    /// Removes an existing object member or array item.
+    Remove {
+        /// JSON pointer of the value to remove.
+        path: String,
+    },
+    /// Replaces an existing value.
+    Replace {
+        /// JSON pointer of the value to replace.
+        path: String,
+        /// Replacement value.
+        value: Value,
+    },
+    /// Removes a value and adds it at another location.
+    Move {
+        /// JSON pointer of the value to move.
+        from: String,
+        /// JSON pointer of the location to add to.
+        path: String,
+    },
+    /// Adds a copy of a value at another location.
+    Copy {
+        /// JSON pointer of the value to copy.
+        from: String,
+        /// JSON pointer of the location to add to.
+        path: String,
+    },
+    /// Checks that a value is equal to the given value.
+    Test {
+        /// JSON pointer of the value to check.
+        path: String,
+        /// Expected value.
+        value: Value,
+    },
+}
+
+impl PatchOperation {
+    /// Applies this operation to a value interned in the given arena,
+    /// returning the new value.
+    ///
+    /// As interned values are immutable, the given value is left untouched,
+    /// and the modified values on the path to the operation's location are
+    /// interned as new values. Fails with the location and reason of the
+    /// failure if a pointer can't be resolved, or with
+    /// [`PointerErrorReason::TestFailed`] if a
+    /// [`Test`](PatchOperation::Test) operation fails.
+    ///
+    /// ```
+    /// use jinterner::{Jinterners, PatchOperation};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.intern(json!({"tags": ["a"]}));
+    ///
+    /// let add = PatchOperation::Add {
+    ///     path: "/tags/0".into(),
+    ///     value: json!("b"),
+    /// };
+    /// let patched = add.apply(&interners, &ivalue).unwrap();
+    /// assert_eq!(interners.lookup(&patched), json!({"tags": ["b", "a"]}));
+    ///
+    /// let remove = PatchOperation::Remove {
+    ///     path: "/tags/a".into(),
+    /// };
+    /// let error = remove.apply(&interners, &ivalue).unwrap_err();
+    /// assert_eq!(error.to_string(), "invalid array index \"a\" at /tags");
+    /// ```
+    pub fn apply(&self, interners: &Jinterners, value: &IValue) -> Result<IValue, PointerError> {
+        let edit = |path: &str, edit| interners.edit(value, path, edit).map(|(value, _)| value);
+        match self {
+            PatchOperation::Add { path, value } => {
+                edit(path, Edit::Add(interners.intern_ref(value)))
+            }
+            PatchOperation::Remove { path } => edit(path, Edit::Remove),
+            PatchOperation::Replace { path, value } => {
+                edit(path, Edit::Replace(interners.intern_ref(value)))
+            }
+            PatchOperation::Move { from, path } => {
+                let (value, moved) = interners.edit(value, from, Edit::Remove)?;
+                let moved = moved.expect("a removed value was returned");
+                interners
+                    .edit(&value, path, Edit::Add(moved))
+                    .map(|(value, _)| value)
+            }
+            PatchOperation::Copy { from, path } => {
+                edit(path, Edit::Add(interners.pointer(value, from)?))
+            }
+            PatchOperation::Test {
+                path,
+                value: expected,
+            } => {
+                if interners.find(expected) != Some(interners.pointer(value, path)?) {
+                    return Err(PointerError::new(path, PointerErrorReason::TestFailed));
+                }
+                Ok(*value)
+            }
+        }
+    }
+}
+
+impl Jinterners {
+    /// Applies the operations of a JSON patch in order to a value interned in
+    /// this arena, returning the new value.
+    ///
+    /// See [`PatchOperation::apply()`]. Fails with [`Error::Patch`] at the
+    /// first operation that can't be applied, in which case the given value
+    /// is left untouched.
+    ///
+    /// ```
+    /// use jinterner::{Jinterners, PatchOperation};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.intern(json!({"retries": 3}));
+    ///
+    /// let patch = [
+    ///     PatchOperation::Test {
+    ///         path: "/retries".into(),
+    ///         value: json!(3),
+    ///     },
+    ///     PatchOperation::Replace {
+    ///         path: "/retries".into(),
+    ///         value: json!(5),
+    ///     },
+    /// ];
+    /// let patched = interners.apply_patch(&ivalue, &patch).unwrap();
+    /// assert_eq!(interners.lookup(&patched), json!({"retries": 5}));
+    ///
+    /// // The test now fails on the patched value.
+    /// let error = interners.apply_patch(&patched, &patch).unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "patch operation 0 failed: value differs from the expected value at /retries"
+    /// );
+    /// ```
+    pub fn apply_patch(&self, value: &IValue, patch: &[PatchOperation]) -> Result<IValue, Error> {
+        patch
+            .iter()
+            .enumerate()
+            .try_fold(*value, |value, (index, operation)| {
+                operation
+                    .apply(self, &value)
+                    .map_err(|error| Error::Patch { index, error })
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn operations() {
+        let interners = Jinterners::default();
+        let ivalue = interners.intern(json!({"a": {"b": [1, 2]}, "c": "x"}));
+        let apply = |operation: PatchOperation| {
+            operation
+                .apply(&interners, &ivalue)
+                .map(|value| interners.lookup(&value))
+        };
+
+        assert_eq!(
+            apply(PatchOperation::Add {
+                path: "/a/b/1".into(),
+                value: json!({"d": null}),
+            }),
+            Ok(json!({"a": {"b": [1, {"d": null}, 2]}, "c": "x"}))
+        );
+        assert_eq!(
+            apply(PatchOperation::Add {
+                path: "/a/e".into(),
+                value: json!([]),
+            }),
+            Ok(json!({"a": {"b": [1, 2], "e": []}, "c": "x"}))
+        );
+        assert_eq!(
+            apply(PatchOperation::Remove {
+                path: "/a/b/0".into(),
+            }),
+            Ok(json!({"a": {"b": [2]}, "c": "x"}))
+        );
+        assert_eq!(
+            apply(PatchOperation::Replace {
+                path: "".into(),
+                value: json!(1),
+            }),
+            Ok(json!(1))
+        );
+        assert_eq!(
+            apply(PatchOperation::Move {
+                from: "/c".into(),
+                path: "/a/b/-".into(),
+            }),
+            Ok(json!({"a": {"b": [1, 2, "x"]}}))
+        );
+        assert_eq!(
+            apply(PatchOperation::Copy {
+                from: "/a/b".into(),
+                path: "/c".into(),
+            }),
+            Ok(json!({"a": {"b": [1, 2]}, "c": [1, 2]}))
+        );
+        assert_eq!(
+            apply(PatchOperation::Test {
+                path: "/a/b".into(),
+                value: json!([1, 2]),
+            }),
+            Ok(json!({"a": {"b": [1, 2]}, "c": "x"}))
+        );
+        // The original value is left untouched.
+        assert_eq!(
+            interners.lookup(&ivalue),
+            json!({"a": {"b": [1, 2]}, "c": "x"})
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let interners = Jinterners::default();
+        let ivalue = interners.intern(json!({"a": {"b": [1, 2]}, "c": "x"}));
+        let error = |operation: PatchOperation| {
+            operation
+                .apply(&interners, &ivalue)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error(PatchOperation::Add {
+                path: "/a/b/3".into(),
+                value: json!(3),
+            }),
+            "index 3 out of bounds for array of length 2 at /a/b"
+        );
+        assert_eq!(
+            error(PatchOperation::Remove {
+                path: "/a/x".into(),
+            }),
+            "missing key \"x\" at /a"
+        );
+        assert_eq!(
+            error(PatchOperation::Remove { path: "".into() }),
+            "cannot remove the root value"
+        );
+        assert_eq!(
+            error(PatchOperation::Replace {
+                path: "/a/b/-".into(),
+                value: json!(3),
+            }),
+            "invalid array index \"-\" at /a/b"
+        );
+        assert_eq!(
+            error(PatchOperation::Move {
+                from: "/a".into(),
+                path: "/a/b/0".into(),
+            }),
+            "missing key \"a\" at the root"
+        );
+        assert_eq!(
+            error(PatchOperation::Copy {
+                from: "/c/0".into(),
+                path: "/d".into(),
+            }),
+            "expected array, found string at /c"
+        );
+        assert_eq!(
+            error(PatchOperation::Test {
+                path: "/a/b/1".into(),
+                value: json!(1),
+            }),
+            "value differs from the expected value at /a/b/1"
+        );
+        assert_eq!(
+            error(PatchOperation::Test {
+                path: "a".into(),
+                value: json!(1),
+            }),
+            "invalid JSON pointer syntax: \"a\""
+        );
+    }
+
+    #[test]
+    fn patch() {
+        let interners = Jinterners::default();
+        let ivalue = interners.intern(json!({"a": [1]}));
+        let patch = [
+            PatchOperation::Add {
+                path: "/a/-".into(),
+                value: json!(2),
+            },
+            PatchOperation::Copy {
+                from: "/a".into(),
+                path: "/b".into(),
+            },
+            PatchOperation::Remove {
+                path: "/a/2".into(),
+            },
+        ];
+        let patched = interners.apply_patch(&ivalue, &patch[..2]).unwrap();
+        assert_eq!(
+            interners.lookup(&patched),
+            json!({"a": [1, 2], "b": [1, 2]})
+        );
+
+        let error = interners.apply_patch(&ivalue, &patch).unwrap_err();
+        let Error::Patch {
+            index,
+            error: cause,
+        } = &error
+        else {
+            panic!("expected a patch error, got {error:?}");
+        };
+        assert_eq!(*index, 2);
+        assert_eq!(cause.pointer(), "/a");
+        assert_eq!(
+            error.to_string(),
+            "patch operation 2 failed: index 2 out of bounds for array of length 2 at /a"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let patch: Vec<PatchOperation> = serde_json::from_value(json!([
+            {"op": "add", "path": "/a", "value": 1},
+            {"op": "move", "from": "/a", "path": "/b"},
+        ]))
+        .unwrap();
+        assert_eq!(
+            patch,
+            [
+                PatchOperation::Add {
+                    path: "/a".into(),
+                    value: json!(1),
+                },
+                PatchOperation::Move {
+                    from: "/a".into(),
+                    path: "/b".into(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,485 @@
+use crate::detail::IValueImpl;
+use crate::{Bound, IValue, InternedStrKey, Jinterners, ValueKind, ValueRef};
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// An error returned when resolving a JSON pointer fails.
+///
+/// The [`Display`] output describes the failure together with the location at
+/// which it happened, and is suitable to be reported to API clients, for
+/// example `expected array, found object at /items`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PointerError {
+    pointer: String,
+    reason: PointerErrorReason,
+}
+
+/// The reason why resolving a JSON pointer failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PointerErrorReason {
+    /// The pointer is neither empty nor starts with a `/`.
+    Syntax,
+    /// The value at the failing location doesn't have the expected kind.
+    TypeMismatch {
+        /// Kind of value that the pointer token requires.
+        expected: ValueKind,
+        /// Kind of value that was found.
+        found: ValueKind,
+    },
+    /// The object at the failing location doesn't contain the key.
+    MissingKey {
+        /// Key that was looked up.
+        key: String,
+    },
+    /// The token isn't a valid index in the array at the failing location.
+    InvalidIndex {
+        /// Token that was looked up.
+        token: String,
+    },
+    /// The index is out of bounds of the array at the failing location.
+    IndexOutOfBounds {
+        /// Index that was looked up.
+        index: usize,
+        /// Length of the array.
+        len: usize,
+    },
+    /// The pointer is empty, but the root value can't be removed.
+    RootRemoval,
+    /// The value at the failing location differs from the value expected by
+    /// a [`Test`](crate::PatchOperation::Test) operation.
+    TestFailed,
+}
+
+impl PointerError {
+    pub(crate) fn new(pointer: &str, reason: PointerErrorReason) -> Self {
+        Self {
+            pointer: pointer.to_owned(),
+            reason,
+        }
+    }
+
+    /// Returns the JSON pointer of the value at which resolution failed.
+    ///
+    /// This is a prefix of the pointer that was resolved, or the whole pointer
+    /// in case of a syntax error or of a failed
+    /// [`Test`](crate::PatchOperation::Test) operation.
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    /// Returns the reason of the failure.
+    pub fn reason(&self) -> &PointerErrorReason {
+        &self.reason
+    }
+}
+
+impl Display for PointerErrorReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointerErrorReason::Syntax => f.write_str("invalid JSON pointer syntax"),
+            PointerErrorReason::TypeMismatch { expected, found } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            PointerErrorReason::MissingKey { key } => write!(f, "missing key {key:?}"),
+            PointerErrorReason::InvalidIndex { token } => {
+                write!(f, "invalid array index {token:?}")
+            }
+            PointerErrorReason::IndexOutOfBounds { index, len } => {
+                write!(f, "index {index} out of bounds for array of length {len}")
+            }
+            PointerErrorReason::RootRemoval => f.write_str("cannot remove the root value"),
+            PointerErrorReason::TestFailed => f.write_str("value differs from the expected value"),
+        }
+    }
+}
+
+impl Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            PointerErrorReason::Syntax => write!(f, "{}: {:?}", self.reason, self.pointer),
+            PointerErrorReason::RootRemoval => self.reason.fmt(f),
+            _ if self.pointer.is_empty() => write!(f, "{} at the root", self.reason),
+            _ => write!(f, "{} at {}", self.reason, self.pointer),
+        }
+    }
+}
+
+impl Error for PointerError {}
+
+impl Jinterners {
+    /// Looks up a value by a [RFC 6901](https://www.rfc-editor.org/rfc/rfc6901)
+    /// JSON pointer, like [`serde_json::Value::pointer()`].
+    ///
+    /// Contrary to [`serde_json::Value::pointer()`], this returns an error
+    /// describing where and why the lookup failed.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.intern(json!({"items": [{"id": 1}]}));
+    ///
+    /// let id = interners.pointer(&ivalue, "/items/0/id").unwrap();
+    /// assert_eq!(interners.lookup(&id), json!(1));
+    ///
+    /// let error = interners.pointer(&ivalue, "/items/id").unwrap_err();
+    /// assert_eq!(error.to_string(), "invalid array index \"id\" at /items");
+    /// ```
+    pub fn pointer(&self, value: &IValue, pointer: &str) -> Result<IValue, PointerError> {
+        let mut current = *value;
+        let mut location = String::new();
+        for token in tokens(pointer)? {
+            current = self.child(current, &token, &location)?;
+            push_token(&mut location, &token);
+        }
+        Ok(current)
+    }
+
+    /// Sets the value at a JSON pointer, returning the new root value.
+    ///
+    /// An existing object member or array item is replaced, a missing object
+    /// member is added, and the `-` token appends the value to an array. The
+    /// empty pointer replaces the whole value. As interned values are
+    /// immutable, the given value is left untouched, and the modified values
+    /// on the path to the pointer are interned as new values.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.intern(json!({"items": [{"id": 1}]}));
+    /// let two = interners.intern(json!(2));
+    ///
+    /// let set = interners.pointer_set(&ivalue, "/items/-", two).unwrap();
+    /// assert_eq!(interners.lookup(&set), json!({"items": [{"id": 1}, 2]}));
+    ///
+    /// let error = interners.pointer_set(&ivalue, "/items/1/id", two).unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "index 1 out of bounds for array of length 1 at /items"
+    /// );
+    /// ```
+    pub fn pointer_set(
+        &self,
+        value: &IValue,
+        pointer: &str,
+        new: IValue,
+    ) -> Result<IValue, PointerError> {
+        self.edit(value, pointer, Edit::Set(new))
+            .map(|(value, _)| value)
+    }
+
+    /// Applies the given edit to the value at a JSON pointer, returning the
+    /// new root value and the value that was replaced or removed, if any.
+    pub(crate) fn edit(
+        &self,
+        value: &IValue,
+        pointer: &str,
+        edit: Edit,
+    ) -> Result<(IValue, Option<IValue>), PointerError> {
+        let tokens = tokens(pointer)?;
+        if tokens.is_empty() {
+            return match edit {
+                Edit::Add(new) | Edit::Set(new) | Edit::Replace(new) => Ok((new, Some(*value))),
+                Edit::Remove => Err(PointerError {
+                    pointer: String::new(),
+                    reason: PointerErrorReason::RootRemoval,
+                }),
+            };
+        }
+        self.edit_at(*value, &tokens, &mut String::new(), edit)
+    }
+
+    /// Applies the given edit at the given non-empty tokens below the value at
+    /// the given location.
+    fn edit_at(
+        &self,
+        current: IValue,
+        tokens: &[String],
+        location: &mut String,
+        edit: Edit,
+    ) -> Result<(IValue, Option<IValue>), PointerError> {
+        let (token, rest) = tokens.split_first().expect("the tokens are non-empty");
+        if rest.is_empty() {
+            return self.edit_child(current, token, location, edit);
+        }
+        let child = self.child(current, token, location)?;
+        let len = location.len();
+        push_token(location, token);
+        let (child, old) = self.edit_at(child, rest, location, edit)?;
+        location.truncate(len);
+        let (current, _) = self.edit_child(current, token, location, Edit::Replace(child))?;
+        Ok((current, old))
+    }
+
+    /// Returns the child of the given value at the given token.
+    fn child(&self, current: IValue, token: &str, location: &str) -> Result<IValue, PointerError> {
+        let error = |reason| PointerError {
+            pointer: location.to_owned(),
+            reason,
+        };
+        match current.lookup_ref(self) {
+            ValueRef::Object(map) => map.get(token).copied().ok_or_else(|| {
+                error(PointerErrorReason::MissingKey {
+                    key: token.to_owned(),
+                })
+            }),
+            ValueRef::Array(array) => {
+                let index = parse_index(token).ok_or_else(|| {
+                    error(PointerErrorReason::InvalidIndex {
+                        token: token.to_owned(),
+                    })
+                })?;
+                array.get(index).copied().ok_or_else(|| {
+                    error(PointerErrorReason::IndexOutOfBounds {
+                        index,
+                        len: array.len(),
+                    })
+                })
+            }
+            _ => Err(error(mismatch(token, current))),
+        }
+    }
+
+    /// Applies the given edit to the child of the given value at the given
+    /// token.
+    fn edit_child(
+        &self,
+        current: IValue,
+        token: &str,
+        location: &str,
+        edit: Edit,
+    ) -> Result<(IValue, Option<IValue>), PointerError> {
+        let error = |reason| PointerError {
+            pointer: location.to_owned(),
+            reason,
+        };
+        match current.0 {
+            IValueImpl::Object(o) => {
+                let mut entries = self.iobject.lookup(o).to_vec();
+                let found = self.find_string(token).and_then(|key| {
+                    entries
+                        .binary_search_by_key(&InternedStrKey(key), |(k, _)| *k)
+                        .ok()
+                });
+                let old = found.map(|i| entries[i].1);
+                match (edit, found) {
+                    (Edit::Add(new) | Edit::Set(new) | Edit::Replace(new), Some(i)) => {
+                        entries[i].1 = new;
+                    }
+                    (Edit::Add(new) | Edit::Set(new), None) => {
+                        let key = InternedStrKey(self.intern_string(token));
+                        match entries.binary_search_by_key(&key, |(k, _)| *k) {
+                            Ok(i) => entries[i].1 = new,
+                            Err(i) => entries.insert(i, (key, new)),
+                        }
+                    }
+                    (Edit::Remove, Some(i)) => {
+                        entries.remove(i);
+                    }
+                    (Edit::Replace(_) | Edit::Remove, None) => {
+                        return Err(error(PointerErrorReason::MissingKey {
+                            key: token.to_owned(),
+                        }));
+                    }
+                }
+                let object = self.intern_object_slice(&entries);
+                Ok((IValue(IValueImpl::Object(object)), old))
+            }
+            IValueImpl::Array(a) => {
+                let mut items = self.iarray.lookup(a).to_vec();
+                let len = items.len();
+                let append = token == "-" && matches!(edit, Edit::Add(_) | Edit::Set(_));
+                let index = match append {
+                    true => len,
+                    false => parse_index(token).ok_or_else(|| {
+                        error(PointerErrorReason::InvalidIndex {
+                            token: token.to_owned(),
+                        })
+                    })?,
+                };
+                // Only additions may happen at the end of the array.
+                if index > len || index == len && !append && !matches!(edit, Edit::Add(_)) {
+                    return Err(error(PointerErrorReason::IndexOutOfBounds { index, len }));
+                }
+                let old = match edit {
+                    Edit::Add(new) => {
+                        items.insert(index, new);
+                        None
+                    }
+                    Edit::Set(new) if append => {
+                        items.push(new);
+                        None
+                    }
+                    Edit::Set(new) | Edit::Replace(new) => {
+                        Some(std::mem::replace(&mut items[index], new))
+                    }
+                    Edit::Remove => Some(items.remove(index)),
+                };
+                let array = self.intern_array_slice(&items);
+                Ok((IValue(IValueImpl::Array(array)), old))
+            }
+            _ => Err(error(mismatch(token, current))),
+        }
+    }
+}
+
+/// An edit of the value at a JSON pointer.
+#[derive(Clone, Copy)]
+pub(crate) enum Edit {
+    /// Adds or replaces an object member, or inserts an array item before the
+    /// given index or at the end with the `-` token.
+    Add(IValue),
+    /// Adds or replaces an object member, or replaces an array item or
+    /// appends it with the `-` token.
+    Set(IValue),
+    /// Replaces an existing object member or array item.
+    Replace(IValue),
+    /// Removes an existing object member or array item.
+    Remove,
+}
+
+/// Splits a JSON pointer into unescaped tokens.
+fn tokens(pointer: &str) -> Result<Vec<String>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(tokens) = pointer.strip_prefix('/') else {
+        return Err(PointerError {
+            pointer: pointer.to_owned(),
+            reason: PointerErrorReason::Syntax,
+        });
+    };
+    Ok(tokens
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// Returns the reason why the given token can't be looked up in a value that
+/// is neither an array nor an object.
+fn mismatch(token: &str, found: IValue) -> PointerErrorReason {
+    let expected = if parse_index(token).is_some() || token == "-" {
+        ValueKind::Array
+    } else {
+        ValueKind::Object
+    };
+    PointerErrorReason::TypeMismatch {
+        expected,
+        found: found.kind(),
+    }
+}
+
+impl<'a> Bound<'a> {
+    /// Looks up a value by a JSON pointer. See [`Jinterners::pointer()`].
+    pub fn pointer(&self, pointer: &str) -> Result<Bound<'a>, PointerError> {
+        let interners = self.interners();
+        interners
+            .pointer(&self.value(), pointer)
+            .map(|value| interners.bind(value))
+    }
+}
+
+/// Parses an array index token, without leading zeros as mandated by RFC 6901.
+pub(crate) fn parse_index(token: &str) -> Option<usize> {
+    if token.len() > 1 && token.starts_with('0') || token.starts_with('+') {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// Appends an escaped token to a JSON pointer.
+pub(crate) fn push_token(pointer: &mut String, token: &str) {
+    pointer.push('/');
+    for c in token.chars() {
+        match c {
+            '~' => pointer.push_str("~0"),
+            '/' => pointer.push_str("~1"),
+            _ => pointer.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn pointer() {
+        let interners = Jinterners::default();
+        let value = json!({"a/b": {"c~d": [1, {"e": null}]}, "list": [true]});
+        let ivalue = interners.intern_ref(&value);
+        let bound = interners.bind(ivalue);
+
+        for pointer in ["", "/a~1b", "/a~1b/c~0d/1/e", "/list/0"] {
+            assert_eq!(
+                bound.pointer(pointer).unwrap().to_json(),
+                *value.pointer(pointer).unwrap()
+            );
+        }
+
+        let error = |pointer| bound.pointer(pointer).unwrap_err().to_string();
+        assert_eq!(error("a"), "invalid JSON pointer syntax: \"a\"");
+        assert_eq!(error("/x"), "missing key \"x\" at the root");
+        assert_eq!(
+            error("/a~1b/c~0d/01"),
+            "invalid array index \"01\" at /a~1b/c~0d"
+        );
+        assert_eq!(
+            error("/a~1b/c~0d/2"),
+            "index 2 out of bounds for array of length 2 at /a~1b/c~0d"
+        );
+        assert_eq!(
+            error("/list/0/x"),
+            "expected object, found boolean at /list/0"
+        );
+        assert_eq!(
+            bound.pointer("/a~1b/c~0d/0/3").unwrap_err().reason(),
+            &PointerErrorReason::TypeMismatch {
+                expected: ValueKind::Array,
+                found: ValueKind::Number,
+            }
+        );
+    }
+
+    #[test]
+    fn pointer_set() {
+        let interners = Jinterners::default();
+        let value = json!({"a": [1, {"b": null}], "c": true});
+        let ivalue = interners.intern_ref(&value);
+        let x = interners.intern(json!("x"));
+        let set = |pointer| {
+            interners
+                .pointer_set(&ivalue, pointer, x)
+                .map(|value| interners.lookup(&value))
+        };
+
+        assert_eq!(set(""), Ok(json!("x")));
+        assert_eq!(set("/c"), Ok(json!({"a": [1, {"b": null}], "c": "x"})));
+        assert_eq!(
+            set("/d"),
+            Ok(json!({"a": [1, {"b": null}], "c": true, "d": "x"}))
+        );
+        assert_eq!(set("/a/0"), Ok(json!({"a": ["x", {"b": null}], "c": true})));
+        assert_eq!(
+            set("/a/-"),
+            Ok(json!({"a": [1, {"b": null}, "x"], "c": true}))
+        );
+        assert_eq!(set("/a/1/b"), Ok(json!({"a": [1, {"b": "x"}], "c": true})));
+        assert_eq!(interners.lookup(&ivalue), value);
+
+        let error = |pointer| set(pointer).unwrap_err().to_string();
+        assert_eq!(error("a"), "invalid JSON pointer syntax: \"a\"");
+        assert_eq!(error("/x/y"), "missing key \"x\" at the root");
+        assert_eq!(error("/a/01"), "invalid array index \"01\" at /a");
+        assert_eq!(
+            error("/a/2"),
+            "index 2 out of bounds for array of length 2 at /a"
+        );
+        assert_eq!(error("/c/0"), "expected array, found boolean at /c");
+        assert_eq!(error("/a/1/b/c"), "expected object, found null at /a/1/b");
+    }
+}
@@ -0,0 +1,116 @@
+use crate::detail::deserialize_slice;
+use crate::{Bound, Error, IValue, InternedSeed, Jinterners};
+use bytes::{BufMut, BytesMut};
+use postgres_types::{FromSql, IsNull, ToSql, Type, to_sql_checked};
+use std::fmt::Write;
+
+/// Version of the binary JSONB format, which is the JSON text prefixed by
+/// this byte.
+const JSONB_VERSION: u8 = 1;
+
+/// The JSON text of a Postgres `JSON` or `JSONB` column, borrowed from a
+/// query result.
+///
+/// This type implements [`FromSql`], so that the text can then be interned
+/// into an application-provided arena with [`intern()`](Self::intern),
+/// without materializing a [`serde_json::Value`]. In the other direction,
+/// [`Bound`] values implement [`ToSql`].
+///
+/// ```
+/// use jinterner::{Jinterners, RawJson};
+/// use postgres_types::{FromSql, Type};
+/// use serde_json::json;
+///
+/// let interners = Jinterners::default();
+/// // Typically obtained with `row.get::<_, RawJson>(0)`.
+/// let raw = RawJson::from_sql(&Type::JSONB, b"\x01{\"name\": \"John\"}").unwrap();
+/// let ivalue = raw.intern(&interners).unwrap();
+/// assert_eq!(interners.lookup(&ivalue), json!({"name": "John"}));
+///
+/// // Values can be passed back as query parameters.
+/// let param: &(dyn postgres_types::ToSql + Sync) = &interners.bind(ivalue);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RawJson<'a>(&'a [u8]);
+
+impl<'a> RawJson<'a> {
+    /// Returns the JSON text, as bytes.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Parses the JSON text and interns it into the given arena.
+    pub fn intern(&self, interners: &Jinterners) -> Result<IValue, Error> {
+        Ok(deserialize_slice(InternedSeed::new(interners), self.0)?)
+    }
+}
+
+impl<'a> FromSql<'a> for RawJson<'a> {
+    fn from_sql(
+        ty: &Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        if *ty == Type::JSONB {
+            match raw.split_first() {
+                Some((&JSONB_VERSION, text)) => Ok(RawJson(text)),
+                _ => Err("unsupported JSONB encoding version".into()),
+            }
+        } else {
+            Ok(RawJson(raw))
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(*ty, Type::JSON | Type::JSONB)
+    }
+}
+
+impl ToSql for Bound<'_> {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        if *ty == Type::JSONB {
+            out.put_u8(JSONB_VERSION);
+        }
+        write!(out, "{self}")?;
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(*ty, Type::JSON | Type::JSONB)
+    }
+
+    to_sql_checked!();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn jsonb() {
+        let interners = Jinterners::default();
+        let raw = RawJson::from_sql(&Type::JSONB, b"\x01{\"a\": [1, null]}").unwrap();
+        let ivalue = raw.intern(&interners).unwrap();
+        assert_eq!(interners.lookup(&ivalue), json!({"a": [1, null]}));
+        assert!(RawJson::from_sql(&Type::JSONB, b"\x02{}").is_err());
+        assert!(
+            RawJson::from_sql(&Type::JSON, b"{} 1")
+                .unwrap()
+                .intern(&interners)
+                .is_err()
+        );
+
+        let mut out = BytesMut::new();
+        let bound = interners.bind(ivalue);
+        bound.to_sql_checked(&Type::JSONB, &mut out).unwrap();
+        assert_eq!(&out[..], b"\x01{\"a\":[1,null]}");
+        out.clear();
+        bound.to_sql_checked(&Type::JSON, &mut out).unwrap();
+        assert_eq!(&out[..], b"{\"a\":[1,null]}");
+        assert!(bound.to_sql_checked(&Type::TEXT, &mut out).is_err());
+    }
+}
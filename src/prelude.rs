@@ -0,0 +1,12 @@
+//! Re-exports of the most commonly used items of this crate.
+//!
+//! ```
+//! use jinterner::prelude::*;
+//! ```
+
+#[cfg(feature = "metrics")]
+pub use crate::MetricsRecorder;
+pub use crate::{
+    ArrayRef, Bound, IValue, InternHook, InternedStrKey, Jinterners, MapRef, Mapping, ValueRef,
+    interned_keys,
+};
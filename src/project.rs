@@ -0,0 +1,189 @@
+use crate::detail::{Float64, IValueImpl};
+use crate::{IValue, Jinterners, KeyPath};
+use ordered_float::OrderedFloat;
+
+/// The values of one field extracted from many documents, with a common type.
+///
+/// Each column has one entry per document, which is [`None`] if the document
+/// doesn't contain the field or if it's `null`.
+///
+/// This enum is created by the [`project()`](Jinterners::project) method on
+/// [`Jinterners`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProjectedColumn<'a> {
+    /// All values are booleans.
+    Bool(Vec<Option<bool>>),
+    /// All values are integers that fit in an [`i64`].
+    Int(Vec<Option<i64>>),
+    /// All values are numbers, and at least one of them isn't an [`i64`].
+    ///
+    /// Integers are converted to the nearest float.
+    Float(Vec<Option<f64>>),
+    /// All values are strings.
+    String(Vec<Option<&'a str>>),
+    /// Values have different types, are arrays or objects, or are all missing.
+    Mixed(Vec<Option<IValue>>),
+}
+
+impl ProjectedColumn<'_> {
+    /// Returns the number of entries in this column, i.e. the number of
+    /// projected documents.
+    pub fn len(&self) -> usize {
+        match self {
+            ProjectedColumn::Bool(x) => x.len(),
+            ProjectedColumn::Int(x) => x.len(),
+            ProjectedColumn::Float(x) => x.len(),
+            ProjectedColumn::String(x) => x.len(),
+            ProjectedColumn::Mixed(x) => x.len(),
+        }
+    }
+
+    /// Checks whether this column has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The common type of the values of a column.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Empty,
+    Bool,
+    Int,
+    Float,
+    String,
+    Mixed,
+}
+
+impl ColumnType {
+    fn of(value: &IValueImpl) -> Self {
+        match value {
+            IValueImpl::Null => ColumnType::Empty,
+            IValueImpl::Bool(_) => ColumnType::Bool,
+            IValueImpl::U64(x) if i64::try_from(*x).is_err() => ColumnType::Float,
+            IValueImpl::U64(_) | IValueImpl::I64(_) => ColumnType::Int,
+            IValueImpl::F64(_) => ColumnType::Float,
+            IValueImpl::String(_) => ColumnType::String,
+            IValueImpl::Array(_) | IValueImpl::Object(_) => ColumnType::Mixed,
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnType::Empty, x) | (x, ColumnType::Empty) => x,
+            (ColumnType::Int | ColumnType::Float, ColumnType::Int | ColumnType::Float) => {
+                ColumnType::Float
+            }
+            _ => ColumnType::Mixed,
+        }
+    }
+}
+
+impl Jinterners {
+    /// Extracts the field at the given dot-separated path of object keys (for
+    /// example `user.id`) from each of the given documents, see [`KeyPath`].
+    ///
+    /// The keys are resolved once, so that each document only requires a
+    /// binary search per key, without hashing or comparing strings.
+    ///
+    /// ```
+    /// use jinterner::{Jinterners, ProjectedColumn};
+    /// use serde_json::json;
+    ///
+    /// let interners = Jinterners::default();
+    /// let docs = [
+    ///     interners.intern(json!({"ms": 12, "status": "ok"})),
+    ///     interners.intern(json!({"ms": 7.5, "status": "error"})),
+    ///     interners.intern(json!({"status": "ok"})),
+    /// ];
+    /// assert_eq!(
+    ///     interners.project(&docs, "ms"),
+    ///     ProjectedColumn::Float(vec![Some(12.0), Some(7.5), None])
+    /// );
+    /// assert_eq!(
+    ///     interners.project(&docs, "status"),
+    ///     ProjectedColumn::String(vec![Some("ok"), Some("error"), Some("ok")])
+    /// );
+    /// ```
+    pub fn project(&self, docs: &[IValue], key_path: &str) -> ProjectedColumn<'_> {
+        self.project_path(docs, &KeyPath::new(self, key_path))
+    }
+
+    /// Same as [`project()`](Self::project), with a path resolved beforehand.
+    pub fn project_path(&self, docs: &[IValue], path: &KeyPath) -> ProjectedColumn<'_> {
+        let values: Vec<Option<IValue>> = docs
+            .iter()
+            .map(|doc| path.get(self, *doc).filter(|v| v.0 != IValueImpl::Null))
+            .collect();
+        let column_type = values
+            .iter()
+            .flatten()
+            .fold(ColumnType::Empty, |t, v| t.merge(ColumnType::of(&v.0)));
+
+        fn convert<T>(
+            values: &[Option<IValue>],
+            f: impl Fn(IValueImpl) -> Option<T>,
+        ) -> Vec<Option<T>> {
+            values.iter().map(|v| v.and_then(|v| f(v.0))).collect()
+        }
+        match column_type {
+            ColumnType::Bool => ProjectedColumn::Bool(convert(&values, |v| match v {
+                IValueImpl::Bool(x) => Some(x),
+                _ => None,
+            })),
+            ColumnType::Int => ProjectedColumn::Int(convert(&values, |v| match v {
+                IValueImpl::U64(x) => i64::try_from(x).ok(),
+                IValueImpl::I64(x) => Some(x),
+                _ => None,
+            })),
+            ColumnType::Float => ProjectedColumn::Float(convert(&values, |v| match v {
+                IValueImpl::U64(x) => Some(x as f64),
+                IValueImpl::I64(x) => Some(x as f64),
+                IValueImpl::F64(Float64(OrderedFloat(x))) => Some(x),
+                _ => None,
+            })),
+            ColumnType::String => ProjectedColumn::String(convert(&values, |v| match v {
+                IValueImpl::String(s) => Some(self.string.lookup(s)),
+                _ => None,
+            })),
+            ColumnType::Empty | ColumnType::Mixed => ProjectedColumn::Mixed(values),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn project() {
+        let interners = Jinterners::default();
+        let docs = interners.intern_many(vec![
+            json!({"a": {"b": 1, "c": true, "d": u64::MAX}}),
+            json!({"a": {"b": -2, "c": null, "d": "x"}}),
+            json!({"a": 3}),
+            json!({"a": {"b": 3, "c": false, "d": 1}}),
+        ]);
+
+        assert_eq!(
+            interners.project(&docs, "a.b"),
+            ProjectedColumn::Int(vec![Some(1), Some(-2), None, Some(3)])
+        );
+        assert_eq!(
+            interners.project(&docs, "a.c"),
+            ProjectedColumn::Bool(vec![Some(true), None, None, Some(false)])
+        );
+        let ProjectedColumn::Mixed(mixed) = interners.project(&docs, "a.d") else {
+            panic!("expected a mixed column");
+        };
+        assert_eq!(mixed[1], Some(interners.intern_str("x")));
+        assert_eq!(
+            interners.project(&docs, "a.missing"),
+            ProjectedColumn::Mixed(vec![None; 4])
+        );
+        assert_eq!(interners.project(&docs, "a.b").len(), 4);
+        assert!(interners.project(&[], "a").is_empty());
+    }
+}
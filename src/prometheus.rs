@@ -0,0 +1,178 @@
+use crate::Jinterners;
+use crate::analysis::ReferenceCounts;
+#[cfg(feature = "metrics")]
+use crate::{ArenaKind, InternCounters};
+use std::fmt::{self, Display, Write};
+
+const ARENAS: [&str; 3] = ["string", "array", "object"];
+
+impl Jinterners {
+    /// Writes statistics about this arena in the Prometheus text exposition
+    /// format.
+    ///
+    /// The following gauges are reported, with an `arena` label:
+    /// - `jinterner_entries`: number of interned entries,
+    /// - `jinterner_payload_bytes`: number of bytes used by the contents of the
+    ///   interned entries,
+    /// - `jinterner_heap_bytes`: number of bytes used by the arena (only with
+    ///   the `get-size2` feature),
+    /// - `jinterner_references`: number of references to entries from other
+    ///   entries,
+    /// - `jinterner_dedup_ratio`: average number of references per entry.
+    ///
+    /// This scans the whole arena, so it shouldn't be called too frequently on
+    /// large arenas.
+    pub fn write_prometheus(&self, w: &mut impl Write) -> fmt::Result {
+        let report = self.storage_report();
+        let arenas = [&report.strings, &report.arrays, &report.objects];
+        let counts = ReferenceCounts::new(self);
+        let references = [&counts.strings, &counts.arrays, &counts.objects]
+            .map(|counts| counts.iter().sum::<usize>());
+
+        write_family(
+            w,
+            "jinterner_entries",
+            "gauge",
+            "Number of interned entries.",
+            arenas.map(|a| a.entries),
+        )?;
+        write_family(
+            w,
+            "jinterner_payload_bytes",
+            "gauge",
+            "Number of bytes used by the contents of the interned entries.",
+            arenas.map(|a| a.payload_bytes),
+        )?;
+        #[cfg(feature = "get-size2")]
+        write_family(
+            w,
+            "jinterner_heap_bytes",
+            "gauge",
+            "Number of bytes used by the arena.",
+            arenas.map(|a| a.heap_bytes.unwrap_or_default()),
+        )?;
+        write_family(
+            w,
+            "jinterner_references",
+            "gauge",
+            "Number of references to entries from other entries.",
+            references,
+        )?;
+        write_family(
+            w,
+            "jinterner_dedup_ratio",
+            "gauge",
+            "Average number of references per entry.",
+            [0, 1, 2].map(|i| references[i] as f64 / arenas[i].entries.max(1) as f64),
+        )
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl InternCounters {
+    /// Writes these counters in the Prometheus text exposition format.
+    ///
+    /// The same counters as
+    /// [`MetricsCrateRecorder`](crate::MetricsCrateRecorder) are reported,
+    /// as well as a `jinterner_intern_hit_ratio` gauge.
+    pub fn write_prometheus(&self, w: &mut impl Write) -> fmt::Result {
+        let arenas = [ArenaKind::String, ArenaKind::Array, ArenaKind::Object];
+        let hits = arenas.map(|a| self.hits(a));
+        let misses = arenas.map(|a| self.misses(a));
+
+        write_family(
+            w,
+            "jinterner_intern_hits_total",
+            "counter",
+            "Number of interned entries that were already present.",
+            hits,
+        )?;
+        write_family(
+            w,
+            "jinterner_intern_misses_total",
+            "counter",
+            "Number of interned entries that were appended.",
+            misses,
+        )?;
+        write_family(
+            w,
+            "jinterner_appended_bytes_total",
+            "counter",
+            "Number of bytes appended to the arena.",
+            arenas.map(|a| self.appended_bytes(a)),
+        )?;
+        write_family(
+            w,
+            "jinterner_intern_hit_ratio",
+            "gauge",
+            "Share of interned entries that were already present.",
+            [0, 1, 2].map(|i| hits[i] as f64 / (hits[i] + misses[i]).max(1) as f64),
+        )?;
+        write_header(
+            w,
+            "jinterner_lookups_total",
+            "counter",
+            "Number of lookups.",
+        )?;
+        writeln!(w, "jinterner_lookups_total {}", self.lookups())
+    }
+}
+
+fn write_header(w: &mut impl Write, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(w, "# HELP {name} {help}")?;
+    writeln!(w, "# TYPE {name} {kind}")
+}
+
+fn write_family(
+    w: &mut impl Write,
+    name: &str,
+    kind: &str,
+    help: &str,
+    values: [impl Display; 3],
+) -> fmt::Result {
+    write_header(w, name, kind, help)?;
+    for (arena, value) in ARENAS.iter().zip(values) {
+        writeln!(w, "{name}{{arena=\"{arena}\"}} {value}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn write_prometheus() {
+        let interners = Jinterners::default();
+        interners.intern(json!([{"id": 1}, {"id": 1}, ["id"]]));
+
+        let mut text = String::new();
+        interners.write_prometheus(&mut text).unwrap();
+        assert!(text.contains(
+            "# HELP jinterner_entries Number of interned entries.\n\
+             # TYPE jinterner_entries gauge\n\
+             jinterner_entries{arena=\"string\"} 1\n\
+             jinterner_entries{arena=\"array\"} 2\n\
+             jinterner_entries{arena=\"object\"} 1\n"
+        ));
+        assert!(text.contains("jinterner_references{arena=\"string\"} 2\n"));
+        assert!(text.contains("jinterner_dedup_ratio{arena=\"object\"} 2\n"));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn write_prometheus_counters() {
+        let counters = std::sync::Arc::new(InternCounters::default());
+        let mut interners = Jinterners::default();
+        interners.set_metrics_recorder(Some(counters.clone()));
+        interners.intern(json!(["a", "a", "b", "a"]));
+
+        let mut text = String::new();
+        counters.write_prometheus(&mut text).unwrap();
+        assert!(text.contains("jinterner_intern_hits_total{arena=\"string\"} 2\n"));
+        assert!(text.contains("jinterner_intern_misses_total{arena=\"string\"} 2\n"));
+        assert!(text.contains("jinterner_intern_hit_ratio{arena=\"string\"} 0.5\n"));
+        assert!(text.ends_with("jinterner_lookups_total 0\n"));
+    }
+}
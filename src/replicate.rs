@@ -0,0 +1,307 @@
+//! A replication protocol streaming the [`Increment`]s of an arena over an
+//! asynchronous byte stream.
+//!
+//! Each message is made of its length as a little-endian `u64`, followed by
+//! its JSON serialization. A connection optionally starts with a handshake,
+//! where the consumer sends a [`SyncRequest`] and the producer answers with a
+//! [`SyncResponse`]. Then each frame contains the sequence number of the
+//! frame, starting at zero for each connection, and an increment.
+
+use crate::stream::{read_message, write_message};
+use crate::{Error, Increment, Jinterners, SyncRequest, SyncResponse, Watermark};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// A frame of the replication protocol.
+#[derive(Serialize, Deserialize)]
+struct Frame {
+    sequence: u64,
+    increment: Increment,
+}
+
+/// The sending side of the replication of a [`Jinterners`] arena, which
+/// streams the entries interned into the arena to a [`ReplicationConsumer`].
+///
+/// A producer starts from the watermark of the consumer, so that a consumer
+/// that reconnects resumes where it left off instead of receiving the whole
+/// arena again.
+///
+/// ```
+/// use jinterner::{Jinterners, ReplicationConsumer, ReplicationProducer};
+/// use serde_json::json;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let primary = Jinterners::default();
+/// let follower = Jinterners::default();
+/// let (mut writer, mut reader) = tokio::io::duplex(4096);
+///
+/// let mut producer = ReplicationProducer::new(follower.watermark());
+/// let mut consumer = ReplicationConsumer::default();
+///
+/// let ivalue = primary.intern(json!({"name": "John"}));
+/// assert!(producer.send(&primary, &mut writer).await?);
+/// consumer.receive(&follower, &mut reader).await?;
+/// assert_eq!(follower.lookup(&ivalue), json!({"name": "John"}));
+///
+/// // Nothing is sent if nothing was interned.
+/// assert!(!producer.send(&primary, &mut writer).await?);
+/// drop(writer);
+/// assert_eq!(consumer.receive(&follower, &mut reader).await?, None);
+/// # Ok::<(), jinterner::Error>(())
+/// # }).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ReplicationProducer {
+    sequence: u64,
+    watermark: Watermark,
+}
+
+impl ReplicationProducer {
+    /// Creates a producer that sends the entries after the given watermark,
+    /// typically the [`watermark()`](Jinterners::watermark) of the consumer
+    /// arena when it connects.
+    pub fn new(from: Watermark) -> Self {
+        Self {
+            sequence: 0,
+            watermark: from,
+        }
+    }
+
+    /// Answers the handshake of a consumer that calls
+    /// [`ReplicationConsumer::handshake()`], and returns a producer that
+    /// sends the entries interned afterwards.
+    ///
+    /// The consumer only receives the entries that it's missing, or all the
+    /// entries of the arena if it diverged from the arena.
+    pub async fn handshake(
+        interners: &Jinterners,
+        reader: &mut (impl AsyncRead + Unpin),
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<Self, Error> {
+        let request: SyncRequest = read_message(reader).await?.ok_or(Error::Replication {
+            reason: "the handshake is missing",
+        })?;
+        let response = interners.sync_response(&request);
+        write_message(writer, &response).await?;
+        Ok(Self::new(response.increment().to()))
+    }
+
+    /// Returns the watermark up to which entries were sent.
+    pub fn watermark(&self) -> Watermark {
+        self.watermark
+    }
+
+    /// Sends a frame with the entries interned into the given arena since the
+    /// last frame, and returns whether there were any such entries. Nothing is
+    /// written otherwise.
+    pub async fn send(
+        &mut self,
+        interners: &Jinterners,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<bool, Error> {
+        let increment = interners.increment_since(self.watermark)?;
+        if increment.is_empty() {
+            return Ok(false);
+        }
+        let watermark = increment.to();
+        let frame = Frame {
+            sequence: self.sequence,
+            increment,
+        };
+        write_message(writer, &frame).await?;
+        self.sequence += 1;
+        self.watermark = watermark;
+        Ok(true)
+    }
+
+    /// Sends the entries interned into the given arena, checking for new
+    /// entries at the given interval.
+    ///
+    /// This only returns when sending a frame fails, for example because the
+    /// consumer disconnected.
+    pub async fn run(
+        mut self,
+        interners: &Jinterners,
+        mut writer: impl AsyncWrite + Unpin,
+        interval: Duration,
+    ) -> Result<(), Error> {
+        loop {
+            self.send(interners, &mut writer).await?;
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+/// The receiving side of the replication of a [`Jinterners`] arena, which
+/// applies the frames sent by a [`ReplicationProducer`] to a mirror of the
+/// arena.
+///
+/// Values shouldn't be interned into the mirror by other means, see
+/// [`apply_increment()`](Jinterners::apply_increment).
+#[derive(Debug, Default)]
+pub struct ReplicationConsumer {
+    sequence: u64,
+}
+
+impl ReplicationConsumer {
+    /// Synchronizes the given mirror with the arena of a producer that calls
+    /// [`ReplicationProducer::handshake()`], and returns a consumer to receive
+    /// the entries interned afterwards.
+    ///
+    /// See [`apply_sync()`](Jinterners::apply_sync) for how the mirror is
+    /// updated.
+    pub async fn handshake(
+        interners: &mut Jinterners,
+        reader: &mut (impl AsyncRead + Unpin),
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<Self, Error> {
+        write_message(writer, &interners.sync_request()).await?;
+        let response: SyncResponse = read_message(reader).await?.ok_or(Error::Replication {
+            reason: "the handshake is missing",
+        })?;
+        interners.apply_sync(&response)?;
+        Ok(Self::default())
+    }
+
+    /// Receives a frame and applies it to the given arena, returning the new
+    /// watermark of the arena, or [`None`] if the stream ended.
+    ///
+    /// Returns [`Error::Replication`] if frames are missing or don't apply to
+    /// the arena.
+    pub async fn receive(
+        &mut self,
+        interners: &Jinterners,
+        reader: &mut (impl AsyncRead + Unpin),
+    ) -> Result<Option<Watermark>, Error> {
+        let Some(frame) = read_message::<Frame>(reader).await? else {
+            return Ok(None);
+        };
+        if frame.sequence != self.sequence {
+            return Err(Error::Replication {
+                reason: "a frame is missing",
+            });
+        }
+        let watermark = interners.apply_increment(&frame.increment)?;
+        self.sequence += 1;
+        Ok(Some(watermark))
+    }
+
+    /// Applies all the frames of the given stream to the given arena, and
+    /// returns the final watermark of the arena once the stream ends.
+    pub async fn run(
+        mut self,
+        interners: &Jinterners,
+        mut reader: impl AsyncRead + Unpin,
+    ) -> Result<Watermark, Error> {
+        while self.receive(interners, &mut reader).await?.is_some() {}
+        Ok(interners.watermark())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn replicate() {
+        let primary = Jinterners::default();
+        let follower = Jinterners::default();
+        let values = [json!({"a": [1, "b"]}), json!(["b", {"c": {}}]), json!("d")];
+
+        // The first connection is interrupted after the first value.
+        let (mut writer, reader) = tokio::io::duplex(64);
+        let mut producer = ReplicationProducer::new(follower.watermark());
+        let (watermark, ()) = tokio::join!(
+            ReplicationConsumer::default().run(&follower, reader),
+            async {
+                primary.intern_ref(&values[0]);
+                assert!(producer.send(&primary, &mut writer).await.unwrap());
+                drop(writer);
+            }
+        );
+        assert_eq!(watermark.unwrap(), producer.watermark());
+        assert_eq!(producer.watermark(), primary.watermark());
+
+        // The consumer resumes from its watermark.
+        let ivalues: Vec<_> = values[1..].iter().map(|v| primary.intern_ref(v)).collect();
+        let expected = primary.watermark();
+        let (writer, mut reader) = tokio::io::duplex(64);
+        let producer = ReplicationProducer::new(follower.watermark());
+        let (result, watermark) = tokio::join!(
+            producer.run(&primary, writer, Duration::from_millis(1)),
+            async {
+                let mut consumer = ReplicationConsumer::default();
+                let watermark = consumer.receive(&follower, &mut reader).await;
+                // The producer notices the disconnection on its next frame.
+                drop(reader);
+                primary.intern(json!("e"));
+                watermark
+            }
+        );
+        assert!(matches!(result, Err(Error::Io(_))));
+        assert_eq!(watermark.unwrap(), Some(expected));
+        for (ivalue, value) in ivalues.iter().zip(&values[1..]) {
+            assert_eq!(follower.lookup(ivalue), *value);
+        }
+    }
+
+    #[tokio::test]
+    async fn handshake() {
+        let primary = Jinterners::default();
+        let mut follower = Jinterners::default();
+        for diverged in [false, true] {
+            primary.intern(json!({"diverged": diverged}));
+            if diverged {
+                follower.intern(json!("local"));
+            }
+            let (consumer_side, producer_side) = tokio::io::duplex(4096);
+            let (mut consumer_reader, mut consumer_writer) = tokio::io::split(consumer_side);
+            let (mut producer_reader, mut producer_writer) = tokio::io::split(producer_side);
+            let (consumer, producer) = tokio::join!(
+                ReplicationConsumer::handshake(
+                    &mut follower,
+                    &mut consumer_reader,
+                    &mut consumer_writer
+                ),
+                ReplicationProducer::handshake(
+                    &primary,
+                    &mut producer_reader,
+                    &mut producer_writer
+                ),
+            );
+            let (mut consumer, mut producer) = (consumer.unwrap(), producer.unwrap());
+            assert_eq!(follower, primary);
+
+            let ivalue = primary.intern(json!(["after", diverged]));
+            assert!(producer.send(&primary, &mut producer_writer).await.unwrap());
+            consumer
+                .receive(&follower, &mut consumer_reader)
+                .await
+                .unwrap();
+            assert_eq!(follower.lookup(&ivalue), json!(["after", diverged]));
+        }
+        assert!(follower.find(&json!("local")).is_none());
+    }
+
+    #[tokio::test]
+    async fn missing_frame() {
+        let primary = Jinterners::default();
+        let (mut writer, mut reader) = tokio::io::duplex(4096);
+        let mut producer = ReplicationProducer::new(Watermark::default());
+        primary.intern(json!("a"));
+        producer.send(&primary, &mut writer).await.unwrap();
+        primary.intern(json!("b"));
+        producer.send(&primary, &mut writer).await.unwrap();
+
+        // The consumer only sees the second frame.
+        let mut skipped = vec![0; reader.read_u64_le().await.unwrap() as usize];
+        reader.read_exact(&mut skipped).await.unwrap();
+        let result = ReplicationConsumer::default()
+            .receive(&Jinterners::default(), &mut reader)
+            .await;
+        assert!(matches!(result, Err(Error::Replication { .. })));
+    }
+}
@@ -0,0 +1,375 @@
+use crate::translate::Translator;
+use crate::{IValue, InternedStrKey, Jinterners};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Number of entries listed in [`ArenaReport::largest_entries`].
+const LARGEST_ENTRIES: usize = 10;
+
+/// One of the underlying arenas of a [`Jinterners`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ArenaKind {
+    /// The arena of strings (object keys and string values).
+    String,
+    /// The arena of arrays.
+    Array,
+    /// The arena of objects.
+    Object,
+}
+
+impl ArenaKind {
+    /// Returns a short lowercase name for this arena, suitable as a metric
+    /// label.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArenaKind::String => "string",
+            ArenaKind::Array => "array",
+            ArenaKind::Object => "object",
+        }
+    }
+}
+
+/// A summary of the storage used by a [`Jinterners`] arena.
+///
+/// This struct is created by the
+/// [`storage_report()`](Jinterners::storage_report) method on [`Jinterners`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StorageReport {
+    /// Report for the string arena.
+    pub strings: ArenaReport,
+    /// Report for the array arena.
+    pub arrays: ArenaReport,
+    /// Report for the object arena.
+    pub objects: ArenaReport,
+}
+
+/// A summary of the storage used by one of the underlying arenas of a
+/// [`Jinterners`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ArenaReport {
+    /// Number of interned entries.
+    pub entries: usize,
+    /// Total length of the interned entries, i.e. the number of bytes for
+    /// strings, or the number of elements for arrays and objects.
+    pub total_len: usize,
+    /// Number of bytes used by the contents of the interned entries.
+    pub payload_bytes: usize,
+    /// Number of bytes used by the arena, including bookkeeping and spare
+    /// capacity.
+    ///
+    /// This is only available with the `get-size2` feature.
+    pub heap_bytes: Option<usize>,
+    /// Share of [`heap_bytes`](Self::heap_bytes) used by the contents of the
+    /// interned entries, between 0 and 1.
+    ///
+    /// This is only available with the `get-size2` feature.
+    pub fill_factor: Option<f64>,
+    /// The largest entries in this arena, sorted by decreasing length.
+    pub largest_entries: Vec<EntryReport>,
+}
+
+/// Description of an entry in one of the underlying arenas of a
+/// [`Jinterners`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EntryReport {
+    /// Index of the entry in its arena.
+    pub index: u32,
+    /// Length of the entry, i.e. the number of bytes for a string, or the
+    /// number of elements for an array or object.
+    pub len: usize,
+}
+
+impl Jinterners {
+    /// Returns a summary of the storage used by this arena.
+    ///
+    /// Contrary to the `print_summary_*()` functions, the result can be
+    /// inspected programmatically (or serialized with the `serde` feature).
+    pub fn storage_report(&self) -> StorageReport {
+        StorageReport {
+            strings: ArenaReport::new(
+                self.string.iter().map(str::len),
+                1,
+                #[cfg(feature = "get-size2")]
+                self.get_size_strings(),
+            ),
+            arrays: ArenaReport::new(
+                self.iarray.iter().map(<[_]>::len),
+                size_of::<IValue>(),
+                #[cfg(feature = "get-size2")]
+                self.get_size_arrays(),
+            ),
+            objects: ArenaReport::new(
+                self.iobject.iter().map(<[_]>::len),
+                size_of::<(InternedStrKey, IValue)>(),
+                #[cfg(feature = "get-size2")]
+                self.get_size_objects(),
+            ),
+        }
+    }
+}
+
+impl ArenaReport {
+    fn new(
+        lens: impl Iterator<Item = usize>,
+        item_size: usize,
+        #[cfg(feature = "get-size2")] heap_bytes: usize,
+    ) -> Self {
+        let mut entries = 0;
+        let mut total_len = 0;
+        let largest_entries = largest_entries(lens.enumerate().inspect(|(_, len)| {
+            entries += 1;
+            total_len += len;
+        }));
+
+        #[cfg(not(feature = "get-size2"))]
+        let heap_bytes = None;
+        #[cfg(feature = "get-size2")]
+        let heap_bytes = Some(heap_bytes);
+
+        let payload_bytes = total_len * item_size;
+        Self {
+            entries,
+            total_len,
+            payload_bytes,
+            heap_bytes,
+            fill_factor: heap_bytes
+                .map(|heap_bytes| payload_bytes as f64 / heap_bytes.max(1) as f64),
+            largest_entries,
+        }
+    }
+}
+
+/// A comparison of the contents of two [`Jinterners`] arenas.
+///
+/// This struct is created by the [`diff_report()`](Jinterners::diff_report)
+/// method on [`Jinterners`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DiffReport {
+    /// Comparison of the string arenas.
+    pub strings: ArenaDiff,
+    /// Comparison of the array arenas.
+    pub arrays: ArenaDiff,
+    /// Comparison of the object arenas.
+    pub objects: ArenaDiff,
+}
+
+/// A comparison of one of the underlying arenas of two [`Jinterners`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ArenaDiff {
+    /// Number of entries that exist in both arenas.
+    pub common: usize,
+    /// Entries that only exist in the arena that
+    /// [`diff_report()`](Jinterners::diff_report) was called on.
+    pub only_self: ArenaDiffSide,
+    /// Entries that only exist in the other arena.
+    pub only_other: ArenaDiffSide,
+}
+
+/// The entries that only exist on one side of an [`ArenaDiff`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ArenaDiffSide {
+    /// Number of entries.
+    pub entries: usize,
+    /// Number of bytes used by the contents of the entries.
+    pub payload_bytes: usize,
+    /// The largest entries, sorted by decreasing length.
+    pub largest_entries: Vec<EntryReport>,
+}
+
+impl Jinterners {
+    /// Compares the contents of this arena with another arena.
+    ///
+    /// Entries are compared by content rather than by index, i.e. an array or
+    /// object exists in both arenas if its elements recursively do, even if
+    /// the arenas interned them in a different order.
+    ///
+    /// This is useful to find out why two arenas that are supposed to contain
+    /// the same values have different sizes.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// let left = Jinterners::default();
+    /// left.intern(json!({"name": "John", "tags": ["a", "b"]}));
+    /// let right = Jinterners::default();
+    /// right.intern(json!(["b", "a"]));
+    /// right.intern(json!({"name": "John", "tags": ["a", "b"]}));
+    ///
+    /// let report = left.diff_report(&right);
+    /// assert_eq!(report.strings.common, 5);
+    /// assert_eq!(report.arrays.common, 1);
+    /// assert_eq!(report.arrays.only_self.entries, 0);
+    /// assert_eq!(report.arrays.only_other.entries, 1);
+    /// assert_eq!(report.arrays.only_other.payload_bytes, 2 * size_of::<jinterner::IValue>());
+    /// ```
+    pub fn diff_report(&self, other: &Jinterners) -> DiffReport {
+        let (strings_self, arrays_self, objects_self) = missing(self, other);
+        let (strings_other, arrays_other, objects_other) = missing(other, self);
+        DiffReport {
+            strings: ArenaDiff::new(
+                self.string.iter().map(str::len),
+                &strings_self,
+                other.string.iter().map(str::len),
+                &strings_other,
+                1,
+            ),
+            arrays: ArenaDiff::new(
+                self.iarray.iter().map(<[_]>::len),
+                &arrays_self,
+                other.iarray.iter().map(<[_]>::len),
+                &arrays_other,
+                size_of::<IValue>(),
+            ),
+            objects: ArenaDiff::new(
+                self.iobject.iter().map(<[_]>::len),
+                &objects_self,
+                other.iobject.iter().map(<[_]>::len),
+                &objects_other,
+                size_of::<(InternedStrKey, IValue)>(),
+            ),
+        }
+    }
+}
+
+impl ArenaDiff {
+    fn new(
+        lens_self: impl Iterator<Item = usize>,
+        missing_self: &[bool],
+        lens_other: impl Iterator<Item = usize>,
+        missing_other: &[bool],
+        item_size: usize,
+    ) -> Self {
+        Self {
+            common: missing_self.iter().filter(|missing| !**missing).count(),
+            only_self: ArenaDiffSide::new(lens_self, missing_self, item_size),
+            only_other: ArenaDiffSide::new(lens_other, missing_other, item_size),
+        }
+    }
+}
+
+impl ArenaDiffSide {
+    fn new(lens: impl Iterator<Item = usize>, missing: &[bool], item_size: usize) -> Self {
+        let mut entries = 0;
+        let mut total_len = 0;
+        let lens = lens
+            .enumerate()
+            .filter(|(index, _)| missing[*index])
+            .inspect(|(_, len)| {
+                entries += 1;
+                total_len += len;
+            });
+        let largest_entries = largest_entries(lens);
+        Self {
+            entries,
+            payload_bytes: total_len * item_size,
+            largest_entries,
+        }
+    }
+}
+
+/// Returns whether each string, array and object of one arena is missing
+/// from another arena.
+fn missing(from: &Jinterners, to: &Jinterners) -> (Vec<bool>, Vec<bool>, Vec<bool>) {
+    let [strings, arrays, objects] = Translator::find(from, to)
+        .translate_all()
+        .map(|ids| ids.iter().map(Option::is_none).collect());
+    (strings, arrays, objects)
+}
+
+/// Returns the [`LARGEST_ENTRIES`] largest of the given entries, sorted by
+/// decreasing length.
+fn largest_entries(lens: impl Iterator<Item = (usize, usize)>) -> Vec<EntryReport> {
+    let mut largest = BinaryHeap::with_capacity(LARGEST_ENTRIES + 1);
+    for (index, len) in lens {
+        largest.push(Reverse((len, Reverse(index as u32))));
+        if largest.len() > LARGEST_ENTRIES {
+            largest.pop();
+        }
+    }
+    largest
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((len, Reverse(index)))| EntryReport { index, len })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn storage_report() {
+        let interners = Jinterners::default();
+        interners.intern(json!({"name": "John", "tags": ["a", "bc", ["a"]]}));
+
+        let report = interners.storage_report();
+        assert_eq!(report.strings.entries, 5);
+        assert_eq!(report.strings.total_len, 15);
+        assert_eq!(report.strings.payload_bytes, 15);
+        assert_eq!(report.arrays.entries, 2);
+        assert_eq!(report.arrays.total_len, 4);
+        assert_eq!(report.objects.entries, 1);
+        assert_eq!(
+            report.strings.largest_entries[..2],
+            [
+                EntryReport { index: 0, len: 4 },
+                EntryReport { index: 1, len: 4 },
+            ]
+        );
+        assert_eq!(
+            report.arrays.largest_entries,
+            [
+                EntryReport { index: 1, len: 3 },
+                EntryReport { index: 0, len: 1 },
+            ]
+        );
+        #[cfg(feature = "get-size2")]
+        assert!(report.strings.fill_factor.unwrap() <= 1.0);
+    }
+
+    #[test]
+    fn diff_report() {
+        let left = Jinterners::default();
+        left.intern(json!({"name": "John", "tags": ["a", "bc", ["a"]]}));
+        left.intern(json!("only left"));
+        let right = Jinterners::default();
+        // Interned in a different order, so indices differ.
+        right.intern(json!(["a"]));
+        right.intern(json!({"tags": ["a", "bc", ["a"]], "name": "John"}));
+        right.intern(json!({"name": "Jane"}));
+
+        let report = left.diff_report(&right);
+        assert_eq!(report.strings.common, 5);
+        assert_eq!(report.strings.only_self.entries, 1);
+        assert_eq!(report.strings.only_self.payload_bytes, 9);
+        assert_eq!(
+            report.strings.only_self.largest_entries,
+            [EntryReport { index: 5, len: 9 }]
+        );
+        assert_eq!(report.strings.only_other.entries, 1);
+        assert_eq!(report.arrays.common, 2);
+        assert_eq!(report.arrays.only_self.entries, 0);
+        assert_eq!(report.arrays.only_other.entries, 0);
+        assert_eq!(report.objects.common, 1);
+        assert_eq!(report.objects.only_self.entries, 0);
+        assert_eq!(
+            report.objects.only_other.largest_entries,
+            [EntryReport { index: 1, len: 1 }]
+        );
+
+        let report = left.diff_report(&left);
+        assert_eq!(report.objects.common, 1);
+        assert_eq!(report.strings.only_other.entries, 0);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{IValue, Jinterners, Mapping};
+#[cfg(feature = "retain")]
+use crate::{JinternersMaintainer, MaintenanceOutcome};
+use std::collections::BTreeMap;
+
+/// Handle to a value registered in a [`Roots`] registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RootId(u64);
+
+/// A registry of the values of a [`Jinterners`] arena that are in use.
+///
+/// Rebuilding an arena (for example with [`Jinterners::optimize()`]) returns a
+/// [`Mapping`] that must be applied to every value kept by the application.
+/// Registering these values here instead allows to rebuild the arena and
+/// rewrite all of them in one call, and to compact the arena down to the
+/// registered values.
+///
+/// Values are accessed through the [`RootId`] returned when registering them,
+/// which stays valid across rebuilds.
+///
+/// ```
+/// use jinterner::{Jinterners, Roots};
+/// use serde_json::json;
+///
+/// let jinterners = Jinterners::default();
+/// let mut roots = Roots::new();
+/// let id = roots.register(jinterners.intern(json!({"b": ["y", "x"], "a": 1})));
+///
+/// let jinterners = roots.optimize(&jinterners, None).unwrap();
+/// assert_eq!(
+///     jinterners.lookup(&roots.get(id).unwrap()),
+///     json!({"b": ["y", "x"], "a": 1})
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Roots {
+    roots: BTreeMap<RootId, IValue>,
+    next_id: u64,
+}
+
+impl Roots {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of registered values.
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Checks whether no value is registered.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Registers the given value, and returns a handle to it.
+    ///
+    /// The same value can be registered several times, in which case it's
+    /// kept alive until all the handles are unregistered.
+    pub fn register(&mut self, value: IValue) -> RootId {
+        let id = RootId(self.next_id);
+        self.next_id += 1;
+        self.roots.insert(id, value);
+        id
+    }
+
+    /// Unregisters the given handle, and returns the current value if it was
+    /// registered.
+    pub fn unregister(&mut self, id: RootId) -> Option<IValue> {
+        self.roots.remove(&id)
+    }
+
+    /// Returns the current value of the given handle, or [`None`] if it isn't
+    /// registered.
+    pub fn get(&self, id: RootId) -> Option<IValue> {
+        self.roots.get(&id).copied()
+    }
+
+    /// Iterates over the registered values, in registration order.
+    pub fn values(&self) -> impl ExactSizeIterator<Item = IValue> + '_ {
+        self.roots.values().copied()
+    }
+
+    /// Converts all the registered values with the given mapping.
+    pub fn remap(&mut self, mapping: &Mapping) {
+        for value in self.roots.values_mut() {
+            *value = mapping.map(*value);
+        }
+    }
+
+    /// Optimizes the given arena, see [`Jinterners::optimize()`], and converts
+    /// all the registered values to the resulting arena.
+    ///
+    /// Returns [`None`] if the arena wasn't rebuilt, in which case the values
+    /// are left unchanged.
+    pub fn optimize(
+        &mut self,
+        jinterners: &Jinterners,
+        limit: Option<usize>,
+    ) -> Option<Jinterners> {
+        let (jinterners, mapping) = jinterners.optimize(limit)?;
+        self.remap(&mapping);
+        Some(jinterners)
+    }
+
+    /// Returns an arena containing only the registered values of the given
+    /// arena, see [`Jinterners::retain_values()`], and converts all the
+    /// registered values to it.
+    ///
+    /// Returns [`None`] if everything was retained, in which case the values
+    /// are left unchanged.
+    #[cfg(feature = "retain")]
+    pub fn compact(&mut self, jinterners: &Jinterners) -> Option<Jinterners> {
+        let (jinterners, mapping) = jinterners.retain_values(self.values())?;
+        self.remap(&mapping);
+        Some(jinterners)
+    }
+
+    /// Runs the given maintenance policy on the given arena with the
+    /// registered values as roots, see [`JinternersMaintainer::run()`], and
+    /// converts all the registered values with the resulting mapping.
+    #[cfg(feature = "retain")]
+    pub fn maintain(
+        &mut self,
+        maintainer: &JinternersMaintainer,
+        jinterners: &Jinterners,
+    ) -> Option<MaintenanceOutcome> {
+        let roots: Vec<IValue> = self.values().collect();
+        let outcome = maintainer.run(jinterners, &roots)?;
+        self.remap(&outcome.mapping);
+        Some(outcome)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn register() {
+        let jinterners = Jinterners::default();
+        let mut roots = Roots::new();
+        let value = jinterners.intern(json!(["a"]));
+        let a = roots.register(value);
+        let b = roots.register(value);
+        assert_ne!(a, b);
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots.unregister(a), Some(value));
+        assert_eq!(roots.unregister(a), None);
+        assert_eq!(roots.get(b), Some(value));
+        assert_eq!(roots.values().collect::<Vec<_>>(), [value]);
+    }
+
+    #[cfg(feature = "retain")]
+    #[test]
+    fn compact() {
+        let jinterners = Jinterners::default();
+        let mut roots = Roots::new();
+        let keep = json!({"name": "John", "tags": ["b", "a"]});
+        let id = roots.register(jinterners.intern_ref(&keep));
+        let dropped = roots.register(jinterners.intern(json!({"name": "Mary"})));
+        roots.unregister(dropped);
+
+        let jinterners = roots.compact(&jinterners).unwrap();
+        assert_eq!(jinterners.lookup(&roots.get(id).unwrap()), keep);
+        assert_eq!(jinterners.find_key("Mary"), None);
+        assert!(roots.compact(&jinterners).is_none());
+
+        let maintainer = JinternersMaintainer::new().disorder_threshold(0.0);
+        let outcome = roots.maintain(&maintainer, &jinterners).unwrap();
+        assert_eq!(outcome.jinterners.lookup(&roots.get(id).unwrap()), keep);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::increment::check_value;
+use crate::{Bound, IValue, Jinterners, ValueRef};
+use rusqlite::functions::{Context, FunctionFlags};
+use rusqlite::types::{self, FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput};
+use rusqlite::{Connection, Row};
+use std::sync::Arc;
+
+/// Size of an [`IValue`] handle stored as a blob: a tag byte followed by a
+/// little-endian `u64` payload.
+const BLOB_LEN: usize = 9;
+
+impl Jinterners {
+    /// Interns the given SQLite value, read from a JSON column, into this
+    /// arena.
+    ///
+    /// Text is parsed as JSON, SQL `NULL` is interned as null and numbers are
+    /// interned as they are. Blobs are rejected, as they aren't JSON text.
+    pub fn intern_sql(&self, value: types::ValueRef<'_>) -> FromSqlResult<IValue> {
+        match value {
+            types::ValueRef::Null => Ok(self.intern(serde_json::Value::Null)),
+            types::ValueRef::Integer(x) => Ok(self.intern(x.into())),
+            types::ValueRef::Real(x) => Ok(self.intern(x.into())),
+            types::ValueRef::Text(text) => {
+                let text = std::str::from_utf8(text).map_err(FromSqlError::other)?;
+                self.parse(text).map_err(FromSqlError::other)
+            }
+            types::ValueRef::Blob(_) => Err(FromSqlError::InvalidType),
+        }
+    }
+
+    /// Interns the JSON column at the given index of a query result into this
+    /// arena. See [`intern_sql()`](Self::intern_sql).
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use rusqlite::Connection;
+    /// use serde_json::json;
+    ///
+    /// let conn = Connection::open_in_memory()?;
+    /// conn.execute_batch(r#"CREATE TABLE docs (body TEXT); INSERT INTO docs VALUES ('{"a": 1}');"#)?;
+    ///
+    /// let interners = Jinterners::default();
+    /// let ivalue = conn.query_row("SELECT body FROM docs", [], |row| {
+    ///     interners.intern_column(row, 0)
+    /// })?;
+    /// assert_eq!(interners.lookup(&ivalue), json!({"a": 1}));
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn intern_column(&self, row: &Row<'_>, idx: usize) -> rusqlite::Result<IValue> {
+        let value = row.get_ref(idx)?;
+        self.intern_sql(value).map_err(|e| match e {
+            FromSqlError::InvalidType => rusqlite::Error::InvalidColumnType(
+                idx,
+                row.as_ref()
+                    .column_name(idx)
+                    .map_or_else(|_| idx.to_string(), str::to_owned),
+                value.data_type(),
+            ),
+            e => rusqlite::Error::FromSqlConversionFailure(idx, value.data_type(), Box::new(e)),
+        })
+    }
+}
+
+/// Registers a scalar SQL function with the given name, which extracts a
+/// value from an interned blob by a JSON pointer.
+///
+/// The function takes two arguments: an [`IValue`] stored as a blob (see the
+/// [`ToSql`] implementation of [`IValue`]) and a JSON pointer. Similarly to
+/// SQLite's `json_extract()`, it returns SQL `NULL` if the pointer doesn't
+/// match any value, scalars as SQL values and arrays or objects as JSON text.
+///
+/// The blobs must have been interned in the given arena, otherwise an
+/// arbitrary value will be returned or the function will fail. Blobs
+/// referencing entries outside of the arena make the function fail.
+///
+/// ```
+/// use jinterner::Jinterners;
+/// use rusqlite::Connection;
+/// use serde_json::json;
+/// use std::sync::Arc;
+///
+/// let interners = Arc::new(Jinterners::default());
+/// let conn = Connection::open_in_memory()?;
+/// jinterner::register_sqlite_extract(&conn, "jextract", interners.clone())?;
+///
+/// conn.execute("CREATE TABLE docs (body BLOB)", [])?;
+/// let ivalue = interners.intern(json!({"user": {"name": "John", "tags": ["a"]}}));
+/// conn.execute("INSERT INTO docs VALUES (?1)", [ivalue])?;
+///
+/// let (name, tags): (String, String) = conn.query_row(
+///     "SELECT jextract(body, '/user/name'), jextract(body, '/user/tags') FROM docs",
+///     [],
+///     |row| Ok((row.get(0)?, row.get(1)?)),
+/// )?;
+/// assert_eq!(name, "John");
+/// assert_eq!(tags, r#"["a"]"#);
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+pub fn register_sqlite_extract(
+    conn: &Connection,
+    name: &str,
+    interners: Arc<Jinterners>,
+) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        name,
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx: &Context<'_>| {
+            let Some(value) = ctx.get::<Option<IValue>>(0)? else {
+                return Ok(types::Value::Null);
+            };
+            check_value(&value, interners.watermark())
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?;
+            let pointer = ctx
+                .get_raw(1)
+                .as_str()
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?;
+            Ok(match interners.pointer(&value, pointer) {
+                Ok(value) => to_sql_value(interners.bind(value)),
+                Err(_) => types::Value::Null,
+            })
+        },
+    )
+}
+
+/// Converts an interned value to a SQL value, as returned by SQLite's
+/// `json_extract()`.
+fn to_sql_value(bound: Bound<'_>) -> types::Value {
+    match bound.lookup_ref() {
+        ValueRef::Null => types::Value::Null,
+        ValueRef::Bool(x) => types::Value::Integer(x.into()),
+        ValueRef::U64(x) => match i64::try_from(x) {
+            Ok(x) => types::Value::Integer(x),
+            Err(_) => types::Value::Real(x as f64),
+        },
+        ValueRef::I64(x) => types::Value::Integer(x),
+        ValueRef::F64(x) => types::Value::Real(x),
+        ValueRef::String(x) => types::Value::Text(x.to_owned()),
+        ValueRef::Array(_) | ValueRef::Object(_) => types::Value::Text(bound.to_string()),
+    }
+}
+
+/// Stores the handle as a compact blob, independently of the arena.
+///
+/// The arena itself must be persisted separately, as the handle can only be
+/// looked up in the arena it was interned in.
+impl ToSql for IValue {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let (tag, payload) = self.to_raw_parts();
+        let mut blob = Vec::with_capacity(BLOB_LEN);
+        blob.push(tag as u8);
+        blob.extend_from_slice(&payload.to_le_bytes());
+        Ok(ToSqlOutput::from(blob))
+    }
+}
+
+impl FromSql for IValue {
+    fn column_result(value: types::ValueRef<'_>) -> FromSqlResult<Self> {
+        let blob = value.as_blob()?;
+        let Ok([tag, payload @ ..]) = <[u8; BLOB_LEN]>::try_from(blob) else {
+            return Err(FromSqlError::InvalidBlobSize {
+                expected_size: BLOB_LEN,
+                blob_size: blob.len(),
+            });
+        };
+        IValue::from_raw_parts(tag.into(), u64::from_le_bytes(payload))
+            .ok_or_else(|| FromSqlError::other(InvalidHandle))
+    }
+}
+
+/// Stores the value as JSON text.
+impl ToSql for Bound<'_> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+#[derive(Debug)]
+struct InvalidHandle;
+
+impl std::fmt::Display for InvalidHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid interned value handle")
+    }
+}
+
+impl std::error::Error for InvalidHandle {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn columns() {
+        let interners = Jinterners::default();
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE docs (body, handle BLOB)", [])
+            .unwrap();
+        let ivalue = interners.intern(json!({"a": [1, null], "b": u64::MAX}));
+        conn.execute(
+            "INSERT INTO docs VALUES (?1, ?2), (42, NULL), (x'00', NULL)",
+            (interners.bind(ivalue), ivalue),
+        )
+        .unwrap();
+
+        let mut stmt = conn.prepare("SELECT body, handle FROM docs").unwrap();
+        let mut rows = stmt.query([]).unwrap();
+        let row = rows.next().unwrap().unwrap();
+        assert_eq!(interners.intern_column(row, 0).unwrap(), ivalue);
+        assert_eq!(row.get::<_, IValue>(1).unwrap(), ivalue);
+        let row = rows.next().unwrap().unwrap();
+        assert_eq!(
+            interners.lookup(&interners.intern_column(row, 0).unwrap()),
+            json!(42)
+        );
+        assert!(row.get::<_, Option<IValue>>(1).unwrap().is_none());
+        let row = rows.next().unwrap().unwrap();
+        assert!(matches!(
+            interners.intern_column(row, 0),
+            Err(rusqlite::Error::InvalidColumnType(0, _, types::Type::Blob))
+        ));
+        assert!(row.get::<_, IValue>(0).is_err());
+    }
+
+    #[test]
+    fn extract() {
+        let interners = Arc::new(Jinterners::default());
+        let conn = Connection::open_in_memory().unwrap();
+        register_sqlite_extract(&conn, "jextract", interners.clone()).unwrap();
+        let ivalue = interners.intern(json!({
+            "a": [true, {"b": null}],
+            "c": 1.5,
+            "d": u64::MAX,
+            "e": -1,
+        }));
+
+        let extract = |pointer: &str| -> types::Value {
+            conn.query_row("SELECT jextract(?1, ?2)", (ivalue, pointer), |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        assert_eq!(extract("/a/0"), types::Value::Integer(1));
+        assert_eq!(extract("/a/1"), types::Value::Text(r#"{"b":null}"#.into()));
+        assert_eq!(extract("/a/1/b"), types::Value::Null);
+        assert_eq!(extract("/c"), types::Value::Real(1.5));
+        assert_eq!(extract("/d"), types::Value::Real(u64::MAX as f64));
+        assert_eq!(extract("/e"), types::Value::Integer(-1));
+        assert_eq!(extract("/missing"), types::Value::Null);
+        assert_eq!(extract("a"), types::Value::Null);
+
+        let null: types::Value = conn
+            .query_row("SELECT jextract(NULL, '/a')", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(null, types::Value::Null);
+        assert!(
+            conn.query_row("SELECT jextract(x'ff', '/a')", [], |row| {
+                row.get::<_, types::Value>(0)
+            })
+            .is_err()
+        );
+
+        // A handle outside of the arena is an error rather than a panic.
+        let error = conn
+            .query_row("SELECT jextract(x'06e703000000000000', '/0')", [], |row| {
+                row.get::<_, types::Value>(0)
+            })
+            .unwrap_err();
+        assert_eq!(error.to_string(), "invalid id 999 in the array arena");
+    }
+}
@@ -0,0 +1,226 @@
+//! Serialization of [`IValue`] fields against the [`Jinterners`] arena in
+//! scope.
+//!
+//! The [`Serialize`] and [`Deserialize`](serde::Deserialize) implementations of
+//! [`IValue`] operate on the raw interned handle, which is only meaningful
+//! together with a serialized arena. This module instead (de)serializes the
+//! JSON value that an [`IValue`] represents, resolving it in (or interning it
+//! into) the arena set with [`Jinterners::scope()`]. It's meant to be used with
+//! the `#[serde(with = "...")]` attribute.
+//!
+//! ```
+//! use jinterner::{IValue, Jinterners};
+//! use serde::{Deserialize, Serialize};
+//! use serde_json::json;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Event {
+//!     id: u64,
+//!     #[serde(with = "jinterner::scoped")]
+//!     meta: IValue,
+//! }
+//!
+//! let interners = Jinterners::default();
+//! let text = r#"{"id":1,"meta":{"tags":["a","b"]}}"#;
+//! let event: Event = interners.scope(|| serde_json::from_str(text)).unwrap();
+//! assert_eq!(interners.lookup(&event.meta), json!({"tags": ["a", "b"]}));
+//!
+//! let output = interners.scope(|| serde_json::to_string(&event)).unwrap();
+//! assert_eq!(output, text);
+//! ```
+
+use crate::detail::Content;
+use crate::{IValue, InternedSeed, Jinterners};
+use serde::de::{DeserializeSeed, Error as _};
+use serde::ser::Error as _;
+use serde::{Deserializer, Serialize, Serializer};
+use std::cell::Cell;
+use std::ptr;
+
+thread_local! {
+    /// The arena set by the innermost call to [`Jinterners::scope()`] on this
+    /// thread, or null.
+    static CURRENT: Cell<*const Jinterners> = const { Cell::new(ptr::null()) };
+}
+
+const NO_SCOPE: &str = "no Jinterners in scope, see Jinterners::scope()";
+
+impl Jinterners {
+    /// Runs the given function with this arena in scope on the current thread.
+    ///
+    /// Within the function, [`IValue`] fields annotated with
+    /// `#[serde(with = "jinterner::scoped")]` are serialized by resolving them
+    /// in this arena, and deserialized by interning them into this arena. See
+    /// the [`scoped`](crate::scoped) module.
+    ///
+    /// Scopes can be nested, the innermost arena is used.
+    pub fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        /// Restores the previous arena, even if the function panics.
+        struct Restore(*const Jinterners);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT.set(self.0);
+            }
+        }
+
+        let _restore = Restore(CURRENT.replace(self));
+        f()
+    }
+}
+
+/// Calls the given function with the arena in scope on the current thread, if
+/// any.
+fn with_current<R>(f: impl FnOnce(Option<&Jinterners>) -> R) -> R {
+    let current = CURRENT.get();
+    // SAFETY: A non-null pointer was set by `Jinterners::scope()`, which borrows
+    // the arena until the pointer is restored to its previous value. The
+    // reference passed to the function cannot escape it.
+    let interners = unsafe { current.as_ref() };
+    f(interners)
+}
+
+/// Serializes the JSON value represented by the given [`IValue`], resolving it
+/// in the arena in scope.
+///
+/// This fails if no arena is in scope.
+pub fn serialize<S>(value: &IValue, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    with_current(|interners| match interners {
+        Some(interners) => Content { value, interners }.serialize(serializer),
+        None => Err(S::Error::custom(NO_SCOPE)),
+    })
+}
+
+/// Deserializes a JSON value and interns it into the arena in scope.
+///
+/// This fails if no arena is in scope.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<IValue, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    with_current(|interners| match interners {
+        Some(interners) => InternedSeed::new(interners).deserialize(deserializer),
+        None => Err(D::Error::custom(NO_SCOPE)),
+    })
+}
+
+/// A [`serde_with`] adapter (de)serializing [`IValue`]s against the arena in
+/// scope, in the same way as the [`serialize()`] and [`deserialize()`]
+/// functions of this module.
+///
+/// Contrary to `#[serde(with = "jinterner::scoped")]`, this adapter can be
+/// nested in other adapters, for example `Option<Interned>` or
+/// `Vec<Interned>`.
+///
+/// ```
+/// use jinterner::scoped::Interned;
+/// use jinterner::{IValue, Jinterners};
+/// use serde::{Deserialize, Serialize};
+/// use serde_with::As;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Event {
+///     #[serde(with = "As::<Vec<Interned>>")]
+///     items: Vec<IValue>,
+/// }
+///
+/// let interners = Jinterners::default();
+/// let text = r#"{"items":[{"id":1},{"id":1}]}"#;
+/// let event: Event = interners.scope(|| serde_json::from_str(text)).unwrap();
+/// assert_eq!(event.items[0], event.items[1]);
+/// ```
+#[cfg(feature = "serde_with")]
+#[derive(Clone, Copy, Debug)]
+pub struct Interned;
+
+#[cfg(feature = "serde_with")]
+impl serde_with::SerializeAs<IValue> for Interned {
+    fn serialize_as<S>(source: &IValue, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize(source, serializer)
+    }
+}
+
+#[cfg(feature = "serde_with")]
+impl<'de> serde_with::DeserializeAs<'de, IValue> for Interned {
+    fn deserialize_as<D>(deserializer: D) -> Result<IValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Event {
+        id: u64,
+        #[serde(with = "crate::scoped")]
+        meta: IValue,
+    }
+
+    #[test]
+    fn scoped() {
+        let interners = Jinterners::default();
+        let other = Jinterners::default();
+        let text = r#"{"id":1,"meta":{"name":"John","tags":["a"]}}"#;
+
+        let event: Event = interners.scope(|| serde_json::from_str(text)).unwrap();
+        assert_eq!(event.id, 1);
+        assert_eq!(
+            interners.lookup(&event.meta),
+            json!({"name": "John", "tags": ["a"]})
+        );
+
+        // The innermost scope is used, and the outer scope is restored after.
+        let nested: Event = interners.scope(|| {
+            let nested = other.scope(|| serde_json::from_str(text)).unwrap();
+            assert_eq!(serde_json::to_string(&event).unwrap(), text);
+            nested
+        });
+        assert_eq!(
+            other.lookup(&nested.meta),
+            json!({"name": "John", "tags": ["a"]})
+        );
+
+        let error = serde_json::to_string(&event).unwrap_err();
+        assert!(error.to_string().contains("no Jinterners in scope"));
+        let error = serde_json::from_str::<Event>(text).unwrap_err();
+        assert!(error.to_string().contains("no Jinterners in scope"));
+    }
+
+    #[cfg(feature = "serde_with")]
+    #[test]
+    fn serde_with() {
+        use serde_with::As;
+
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Batch {
+            #[serde(with = "As::<Option<Interned>>")]
+            first: Option<IValue>,
+            #[serde(with = "As::<Vec<Interned>>")]
+            items: Vec<IValue>,
+        }
+
+        let interners = Jinterners::default();
+        let text = r#"{"first":null,"items":[{"id":1},[true],{"id":1}]}"#;
+        let batch: Batch = interners.scope(|| serde_json::from_str(text)).unwrap();
+        assert_eq!(batch.first, None);
+        assert_eq!(batch.items.len(), 3);
+        assert_eq!(batch.items[0], batch.items[2]);
+        assert_eq!(interners.lookup(&batch.items[1]), json!([true]));
+
+        let output = interners.scope(|| serde_json::to_string(&batch)).unwrap();
+        assert_eq!(output, text);
+    }
+}
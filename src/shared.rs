@@ -0,0 +1,337 @@
+use crate::{Jinterners, Mapping};
+use arc_swap::{ArcSwap, Guard};
+use std::collections::BTreeSet;
+use std::ops::Deref;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+
+/// A shared handle to a [`Jinterners`] arena, which can be atomically replaced
+/// by a new version (e.g. an optimized one) while readers are still using the
+/// previous version.
+///
+/// Readers obtain a cheap [`SharedGuard`] with [`load()`](Self::load), which
+/// provides a consistent view of one [`Generation`] for as long as it's held.
+/// A replacement is published together with the [`Mapping`] that converts
+/// [`IValue`](crate::IValue)s from the previous generation.
+///
+/// Each generation is numbered by an epoch, and remains alive as long as a
+/// guard or pointer to it is held. Once a replacement is published, a
+/// compaction process can wait with [`wait_released()`](Self::wait_released)
+/// until all readers of the previous epochs are done, before releasing any
+/// resource associated with these epochs.
+///
+/// Note that values interned in a generation after its replacement was computed
+/// aren't part of the replacement. Interning should therefore be paused (or
+/// redirected to the new generation) while a replacement is being built.
+pub struct SharedJinterners {
+    current: ArcSwap<Generation>,
+    epochs: Arc<Epochs>,
+    /// Serializes the publication of new generations, so that epochs increase
+    /// in publication order.
+    publish: Mutex<()>,
+}
+
+/// One version of the [`Jinterners`] arena published in a [`SharedJinterners`]
+/// handle.
+pub struct Generation {
+    jinterners: Jinterners,
+    mapping: Option<Mapping>,
+    epoch: u64,
+    epochs: Arc<Epochs>,
+}
+
+/// The epochs of the generations of a [`SharedJinterners`] that are still
+/// alive.
+#[derive(Default)]
+struct Epochs {
+    live: Mutex<BTreeSet<u64>>,
+    released: Condvar,
+}
+
+impl Generation {
+    fn new(
+        jinterners: Jinterners,
+        mapping: Option<Mapping>,
+        epoch: u64,
+        epochs: Arc<Epochs>,
+    ) -> Self {
+        epochs
+            .live
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(epoch);
+        Self {
+            jinterners,
+            mapping,
+            epoch,
+            epochs,
+        }
+    }
+
+    /// Returns the epoch of this generation. The first generation has epoch
+    /// zero, and each replacement increments it.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the arena of this generation.
+    pub fn jinterners(&self) -> &Jinterners {
+        &self.jinterners
+    }
+
+    /// Returns the mapping to convert values from the previous generation to
+    /// this one, or [`None`] if this is the first generation.
+    pub fn mapping(&self) -> Option<&Mapping> {
+        self.mapping.as_ref()
+    }
+}
+
+impl Deref for Generation {
+    type Target = Jinterners;
+
+    fn deref(&self) -> &Self::Target {
+        &self.jinterners
+    }
+}
+
+impl Drop for Generation {
+    fn drop(&mut self) {
+        self.epochs
+            .live
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.epoch);
+        self.epochs.released.notify_all();
+    }
+}
+
+/// A guard providing access to the current [`Generation`] of a
+/// [`SharedJinterners`] handle.
+///
+/// This struct is created by the [`load()`](SharedJinterners::load) method on
+/// [`SharedJinterners`].
+pub struct SharedGuard {
+    guard: Guard<Arc<Generation>>,
+}
+
+impl SharedGuard {
+    /// Upgrades this guard into a full reference-counted pointer to the
+    /// generation, which can be kept for longer.
+    pub fn into_inner(self) -> Arc<Generation> {
+        Guard::into_inner(self.guard)
+    }
+}
+
+impl Deref for SharedGuard {
+    type Target = Generation;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl SharedJinterners {
+    /// Creates a new shared handle, with the given arena as first generation.
+    pub fn new(jinterners: Jinterners) -> Self {
+        let epochs = Arc::new(Epochs::default());
+        Self {
+            current: ArcSwap::from_pointee(Generation::new(jinterners, None, 0, epochs.clone())),
+            epochs,
+            publish: Mutex::new(()),
+        }
+    }
+
+    /// Returns a guard to the current generation.
+    pub fn load(&self) -> SharedGuard {
+        SharedGuard {
+            guard: self.current.load(),
+        }
+    }
+
+    /// Returns a reference-counted pointer to the current generation.
+    pub fn load_full(&self) -> Arc<Generation> {
+        self.current.load_full()
+    }
+
+    /// Publishes the given arena as the new generation, together with the
+    /// mapping to convert values from the current generation.
+    ///
+    /// Returns the previous generation.
+    pub fn swap(&self, jinterners: Jinterners, mapping: Mapping) -> Arc<Generation> {
+        let _publish = self.publish.lock().unwrap_or_else(PoisonError::into_inner);
+        let epoch = self.current.load().epoch + 1;
+        self.current.swap(Arc::new(Generation::new(
+            jinterners,
+            Some(mapping),
+            epoch,
+            self.epochs.clone(),
+        )))
+    }
+
+    /// Computes a replacement of the current generation with the given
+    /// function, and publishes it unless the function returns [`None`].
+    ///
+    /// If another replacement was published concurrently, the function is
+    /// called again on the newer generation.
+    ///
+    /// Returns the new generation, or [`None`] if nothing was replaced.
+    pub fn replace_with(
+        &self,
+        mut f: impl FnMut(&Jinterners) -> Option<(Jinterners, Mapping)>,
+    ) -> Option<Arc<Generation>> {
+        loop {
+            let current = self.current.load_full();
+            let (jinterners, mapping) = f(&current)?;
+            let _publish = self.publish.lock().unwrap_or_else(PoisonError::into_inner);
+            if Arc::ptr_eq(&self.current.load(), &current) {
+                let next = Arc::new(Generation::new(
+                    jinterners,
+                    Some(mapping),
+                    current.epoch + 1,
+                    self.epochs.clone(),
+                ));
+                self.current.store(next.clone());
+                return Some(next);
+            }
+        }
+    }
+
+    /// Replaces the current generation by an optimized version of it, see
+    /// [`Jinterners::optimize()`].
+    ///
+    /// Returns the new generation, or [`None`] if nothing was replaced.
+    pub fn optimize(&self, limit: Option<usize>) -> Option<Arc<Generation>> {
+        self.replace_with(|jinterners| jinterners.optimize(limit))
+    }
+
+    /// Returns the epoch of the oldest generation that is still alive, i.e.
+    /// still pinned by a guard or pointer if it isn't the current one.
+    pub fn oldest_live_epoch(&self) -> u64 {
+        let live = self
+            .epochs
+            .live
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // The current generation is always alive.
+        *live.first().unwrap()
+    }
+
+    /// Returns whether all the generations up to the given epoch have been
+    /// dropped.
+    pub fn is_released(&self, epoch: u64) -> bool {
+        self.oldest_live_epoch() > epoch
+    }
+
+    /// Blocks until all the generations up to the given epoch have been
+    /// dropped, i.e. until all the guards and pointers to them have been
+    /// released.
+    ///
+    /// The calling thread must not hold any of these generations itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the given epoch isn't older than the current generation,
+    /// which is never released while this handle is alive.
+    ///
+    /// ```
+    /// use jinterner::SharedJinterners;
+    /// use serde_json::json;
+    ///
+    /// let shared = SharedJinterners::default();
+    /// let ivalue = shared.load().intern(json!({"b": "x", "a": "y"}));
+    ///
+    /// let reader = shared.load_full();
+    /// let new = shared.optimize(None).unwrap();
+    /// assert_eq!(new.epoch(), 1);
+    /// assert!(!shared.is_released(0));
+    ///
+    /// let thread = std::thread::spawn(move || reader.lookup(&ivalue));
+    /// shared.wait_released(0);
+    /// assert_eq!(thread.join().unwrap(), json!({"b": "x", "a": "y"}));
+    /// assert_eq!(shared.oldest_live_epoch(), 1);
+    /// ```
+    pub fn wait_released(&self, epoch: u64) {
+        assert!(
+            epoch < self.current.load().epoch,
+            "the current generation is never released"
+        );
+        let live = self
+            .epochs
+            .live
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let _live = self
+            .epochs
+            .released
+            .wait_while(live, |live| live.first().is_some_and(|&e| e <= epoch))
+            .unwrap_or_else(PoisonError::into_inner);
+    }
+}
+
+impl Default for SharedJinterners {
+    fn default() -> Self {
+        Self::new(Jinterners::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn optimize_while_reading() {
+        let shared = SharedJinterners::default();
+
+        let value = json!({"b": ["x", "y"], "a": {"c": "z"}});
+        let ivalue = shared.load().intern_ref(&value);
+
+        let old = shared.load();
+        let new = shared.optimize(None).unwrap();
+
+        // Readers of the previous generation are unaffected.
+        assert_eq!(old.lookup(&ivalue), value);
+
+        let mapped = new.mapping().unwrap().map(ivalue);
+        assert_eq!(shared.load().lookup(&mapped), value);
+    }
+
+    #[test]
+    fn epochs() {
+        let shared = SharedJinterners::default();
+        let ivalue = shared.load().intern(json!({"b": "x", "a": "y"}));
+        assert_eq!(shared.load().epoch(), 0);
+
+        let first = shared.load_full();
+        let second = shared.optimize(None).unwrap();
+        second.intern(json!(["z", "c"]));
+        let (jinterners, mapping) = second.optimize(None).unwrap();
+        let previous = shared.swap(jinterners, mapping);
+        assert!(Arc::ptr_eq(&previous, &second));
+        drop(previous);
+        assert_eq!(second.epoch(), 1);
+        assert_eq!(shared.load().epoch(), 2);
+
+        // Older generations stay alive while they're pinned.
+        assert_eq!(shared.oldest_live_epoch(), 0);
+        drop(first);
+        assert_eq!(shared.oldest_live_epoch(), 1);
+        assert!(shared.is_released(0));
+        assert!(!shared.is_released(1));
+
+        let reader = std::thread::spawn(move || {
+            let mapped = second.mapping().unwrap().map(ivalue);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            second.lookup(&mapped)
+        });
+        shared.wait_released(1);
+        assert!(shared.is_released(1));
+        assert_eq!(shared.oldest_live_epoch(), 2);
+        assert_eq!(reader.join().unwrap(), json!({"b": "x", "a": "y"}));
+    }
+
+    #[test]
+    #[should_panic(expected = "the current generation is never released")]
+    fn wait_released_current() {
+        SharedJinterners::default().wait_released(0);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{IValue, Mapping};
+use std::collections::HashMap;
+
+/// Application metadata attached to the entries of a
+/// [`Jinterners`](crate::Jinterners) arena.
+///
+/// Entries are identified by the [`IValue`] of a string, array or object,
+/// which encodes both the arena kind and the index of the entry. Rebuilding
+/// the arena (for example with
+/// [`Jinterners::optimize()`](crate::Jinterners::optimize)) changes these
+/// indices: applying the resulting [`Mapping`] with [`remap()`](Self::remap)
+/// keeps the metadata attached to the same entries, and drops the metadata of
+/// removed entries.
+///
+/// ```
+/// use jinterner::{Jinterners, SideTable};
+/// use serde_json::json;
+///
+/// let jinterners = Jinterners::default();
+/// jinterners.intern(json!(["b"]));
+/// let a = jinterners.intern(json!(["a"]));
+///
+/// let mut provenance = SideTable::new();
+/// provenance.insert(a, "import.json");
+///
+/// let (jinterners, mapping) = jinterners.optimize(None).unwrap();
+/// provenance.remap(&mapping);
+/// let a = jinterners.find(&json!(["a"])).unwrap();
+/// assert_eq!(provenance.get(&a), Some(&"import.json"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SideTable<T> {
+    entries: HashMap<IValue, T>,
+}
+
+impl<T> Default for SideTable<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<T> SideTable<T> {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of entries with metadata.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks whether no entry has metadata.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Attaches metadata to the given entry, and returns the previous metadata
+    /// of this entry if any.
+    ///
+    /// Scalar values other than strings aren't entries of the arena, but can
+    /// still be used as keys: they are left unchanged by
+    /// [`remap()`](Self::remap).
+    pub fn insert(&mut self, value: IValue, metadata: T) -> Option<T> {
+        self.entries.insert(value, metadata)
+    }
+
+    /// Returns the metadata of the given entry.
+    pub fn get(&self, value: &IValue) -> Option<&T> {
+        self.entries.get(value)
+    }
+
+    /// Returns a mutable reference to the metadata of the given entry.
+    pub fn get_mut(&mut self, value: &IValue) -> Option<&mut T> {
+        self.entries.get_mut(value)
+    }
+
+    /// Detaches and returns the metadata of the given entry.
+    pub fn remove(&mut self, value: &IValue) -> Option<T> {
+        self.entries.remove(value)
+    }
+
+    /// Iterates over the entries and their metadata, in arbitrary order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (IValue, &T)> {
+        self.entries
+            .iter()
+            .map(|(value, metadata)| (*value, metadata))
+    }
+
+    /// Converts all the entries with the given mapping, dropping the metadata
+    /// of entries that were removed from the destination arena.
+    ///
+    /// Returns the number of dropped entries.
+    pub fn remap(&mut self, mapping: &Mapping) -> usize {
+        let before = self.entries.len();
+        self.entries = std::mem::take(&mut self.entries)
+            .into_iter()
+            .filter_map(|(value, metadata)| Some((mapping.try_map(value)?, metadata)))
+            .collect();
+        before - self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Jinterners;
+    use serde_json::json;
+
+    #[test]
+    fn remap() {
+        let jinterners = Jinterners::default();
+        let values = [json!(["z"]), json!({"b": 1}), json!("y"), json!(["a"])];
+        let ivalues = jinterners.intern_many_ref(&values);
+        let mut table = SideTable::new();
+        for (i, ivalue) in ivalues.iter().enumerate() {
+            assert_eq!(table.insert(*ivalue, i), None);
+        }
+        let number = jinterners.intern(json!(42));
+        table.insert(number, 42);
+        assert_eq!(table.insert(ivalues[0], 0), Some(0));
+        *table.get_mut(&ivalues[2]).unwrap() += 10;
+
+        let (optimized, mapping) = jinterners.optimize(None).unwrap();
+        assert_eq!(table.remap(&mapping), 0);
+        assert_eq!(table.len(), 5);
+        for (i, value) in values.iter().enumerate() {
+            let ivalue = optimized.find(value).unwrap();
+            let expected = if i == 2 { 12 } else { i };
+            assert_eq!(table.get(&ivalue), Some(&expected));
+        }
+        assert_eq!(table.get(&number), Some(&42));
+        assert_eq!(table.remove(&number), Some(42));
+        assert_eq!(table.iter().count(), 4);
+    }
+
+    #[cfg(feature = "retain")]
+    #[test]
+    fn remap_retain() {
+        let jinterners = Jinterners::default();
+        let keep = jinterners.intern(json!({"name": "John"}));
+        let drop = jinterners.intern(json!(["dropped"]));
+        let mut table = SideTable::new();
+        table.insert(keep, "kept");
+        table.insert(drop, "dropped");
+
+        let (retained, mapping) = jinterners.retain_values([keep].into_iter()).unwrap();
+        assert_eq!(table.remap(&mapping), 1);
+        let keep = retained.find(&json!({"name": "John"})).unwrap();
+        assert_eq!(table.get(&keep), Some(&"kept"));
+        assert_eq!(table.len(), 1);
+    }
+}
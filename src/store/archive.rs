@@ -0,0 +1,515 @@
+//! A random-access archive of the documents of a [`JDocStore`].
+//!
+//! The archive is laid out as follows, all integers being little-endian
+//! `u64`s:
+//!
+//! - the 7-byte magic [`MAGIC`] followed by the [`FORMAT_VERSION`] byte,
+//! - a header made of the offset and length of the serialized [`Jinterners`],
+//!   the offset and number of records, and the number of strings of the
+//!   [`Dictionary`] that the arena starts with (zero if none),
+//! - the [`Jinterners`] arena, serialized as JSON,
+//! - the JSON text of each document,
+//! - the record table, sorted by [`DocId`], each record being made of the
+//!   document identifier, its root value (as a tag and a payload), and the
+//!   offset and length of its JSON text.
+//!
+//! A reader can therefore fetch any document by binary search in the record
+//! table, either as text without loading the arena, or as an [`IValue`] rooted
+//! in the arena.
+//!
+//! Version 1 of the format didn't have the dictionary length in its header.
+//! Such archives are still read, as archives without dictionary.
+
+use super::{Dictionary, DocId, JDocStore};
+use crate::increment::check_value;
+use crate::{Error, IValue, Jinterners};
+use std::io::{self, Write};
+
+/// Version of the archive format written by
+/// [`JDocStore::write_archive()`].
+///
+/// It's bumped whenever the layout of archives changes. [`Archive::open()`]
+/// reads archives of this version and of all previous versions, so that
+/// archives written by older versions of this crate remain readable, and can
+/// be upgraded by loading and writing them again.
+pub const FORMAT_VERSION: u8 = 2;
+
+/// Magic bytes at the start of an archive, followed by the format version.
+const MAGIC: &[u8; 7] = b"JINTARC";
+/// Size of the magic, version and header.
+const HEADER_LEN: usize = header_len(FORMAT_VERSION);
+/// Size of a record.
+const RECORD_LEN: usize = 5 * 8;
+
+impl JDocStore {
+    /// Writes all the documents of this store as an [`Archive`].
+    ///
+    /// Indexes aren't part of the archive.
+    pub fn write_archive(&self, w: &mut impl Write) -> io::Result<()> {
+        self.write_archive_with(w, 0)
+    }
+
+    /// Writes all the documents of this store as an [`Archive`] whose arena
+    /// starts with the given number of dictionary strings.
+    pub(super) fn write_archive_with(
+        &self,
+        w: &mut impl Write,
+        dictionary_len: usize,
+    ) -> io::Result<()> {
+        let interners = serde_json::to_vec(&self.interners)?;
+
+        let mut texts = String::new();
+        let mut records = Vec::with_capacity(self.docs.len() * RECORD_LEN);
+        let texts_start = (HEADER_LEN + interners.len()) as u64;
+        for (id, value) in &self.docs {
+            let start = texts.len();
+            value
+                .write_json(&self.interners, &mut texts)
+                .expect("writing to a String never fails");
+            let (tag, payload) = value.to_raw_parts();
+            for x in [
+                id.0,
+                tag,
+                payload,
+                texts_start + start as u64,
+                (texts.len() - start) as u64,
+            ] {
+                records.extend_from_slice(&x.to_le_bytes());
+            }
+        }
+
+        w.write_all(MAGIC)?;
+        w.write_all(&[FORMAT_VERSION])?;
+        for x in [
+            HEADER_LEN as u64,
+            interners.len() as u64,
+            texts_start + texts.len() as u64,
+            self.docs.len() as u64,
+            dictionary_len as u64,
+        ] {
+            w.write_all(&x.to_le_bytes())?;
+        }
+        w.write_all(&interners)?;
+        w.write_all(texts.as_bytes())?;
+        w.write_all(&records)
+    }
+}
+
+/// A read-only view of an archive written by
+/// [`JDocStore::write_archive()`].
+///
+/// The archive bytes can be any buffer, for example a memory-mapped file.
+/// Opening the archive only checks its header, documents are then fetched
+/// individually by identifier.
+///
+/// With a memory-mapped file, the text of documents that aren't accessed stays
+/// on disk, and is paged in by the operating system on access. This is the
+/// way to keep the footprint of archival services proportional to their hot
+/// documents: a [`Jinterners`] arena, including the one returned by
+/// [`load_interners()`](Self::load_interners), is always fully resident in
+/// memory.
+///
+/// ```
+/// use jinterner::{Archive, JDocStore};
+/// use serde_json::json;
+///
+/// let mut store = JDocStore::new();
+/// let john = store.insert(json!({"name": "John"}));
+/// let mary = store.insert(json!({"name": "Mary"}));
+///
+/// let mut bytes = Vec::new();
+/// store.write_archive(&mut bytes).unwrap();
+///
+/// let archive = Archive::open(bytes).unwrap();
+/// assert_eq!(archive.text(mary), Some(r#"{"name":"Mary"}"#));
+///
+/// let interners = archive.load_interners().unwrap();
+/// let john = archive.get(&interners, john).unwrap().unwrap();
+/// assert_eq!(interners.lookup(&john), json!({"name": "John"}));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Archive<B> {
+    bytes: B,
+    interners: (usize, usize),
+    records: usize,
+    count: usize,
+    dictionary_len: usize,
+    version: u8,
+}
+
+/// A record of the archive table.
+struct Record {
+    value: IValue,
+    text: (usize, usize),
+}
+
+impl<B: AsRef<[u8]>> Archive<B> {
+    /// Opens an archive contained in the given bytes.
+    ///
+    /// Archives written in a previous [`FORMAT_VERSION`] are upgraded on the
+    /// fly, and are written in the current version by
+    /// [`load_store()`](Self::load_store) followed by
+    /// [`JDocStore::write_archive()`].
+    pub fn open(bytes: B) -> Result<Self, Error> {
+        let data = bytes.as_ref();
+        if data.len() < 8 || &data[..7] != MAGIC {
+            return Err(invalid("missing archive header"));
+        }
+        let version = data[7];
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(invalid("unsupported format version"));
+        }
+        if data.len() < header_len(version) {
+            return Err(invalid("missing archive header"));
+        }
+        let header = |i: usize| read_u64(data, 8 + 8 * i);
+        let interners = range(data, header(0), header(1)).ok_or(invalid("invalid arena range"))?;
+        let count = usize::try_from(header(3)).map_err(|_| invalid("invalid record table"))?;
+        let records = count
+            .checked_mul(RECORD_LEN)
+            .and_then(|len| range(data, header(2), len as u64))
+            .ok_or(invalid("invalid record table"))?
+            .0;
+        // Records are looked up by binary search, so their identifiers must
+        // be strictly increasing.
+        let ids = (0..count).map(|i| read_u64(data, records + i * RECORD_LEN));
+        if ids.clone().zip(ids.skip(1)).any(|(a, b)| a >= b) {
+            return Err(invalid("invalid record table"));
+        }
+        let dictionary_len = match version {
+            // Version 1 archives never have a dictionary.
+            1 => 0,
+            _ => usize::try_from(header(4)).map_err(|_| invalid("invalid dictionary length"))?,
+        };
+        Ok(Self {
+            bytes,
+            interners,
+            records,
+            count,
+            dictionary_len,
+            version,
+        })
+    }
+
+    /// Returns the [`FORMAT_VERSION`] in which this archive was written.
+    pub fn format_version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the number of documents in this archive.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Checks whether this archive contains no document.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Iterates over the identifiers of the documents in this archive, in
+    /// increasing order.
+    pub fn ids(&self) -> impl ExactSizeIterator<Item = DocId> + '_ {
+        (0..self.count).map(|i| self.record_id(i))
+    }
+
+    /// Returns the serialized [`Jinterners`] arena, as JSON.
+    pub fn interners_bytes(&self) -> &[u8] {
+        let (start, len) = self.interners;
+        &self.bytes.as_ref()[start..start + len]
+    }
+
+    /// Deserializes the [`Jinterners`] arena in which the values returned by
+    /// [`get()`](Self::get) are rooted.
+    pub fn load_interners(&self) -> Result<Jinterners, Error> {
+        let interners: Jinterners = serde_json::from_slice(self.interners_bytes())?;
+        interners.validate()?;
+        Ok(interners)
+    }
+
+    /// Loads all the documents of this archive into a new [`JDocStore`], with
+    /// the same identifiers.
+    ///
+    /// Documents inserted afterwards get identifiers after the last one of
+    /// the archive, so an archive containing the maximal identifier can't be
+    /// loaded.
+    pub fn load_store(&self) -> Result<JDocStore, Error> {
+        let mut store = JDocStore::with_interners(self.load_interners()?);
+        for i in 0..self.count {
+            let value = self.root(i, &store.interners)?;
+            let id = self.record_id(i);
+            store.insert_at(id, value);
+            store.next_id =
+                id.0.checked_add(1)
+                    .ok_or(invalid("invalid document id"))?
+                    .max(store.next_id);
+        }
+        Ok(store)
+    }
+
+    /// Returns the number of [`Dictionary`] strings that the arena starts
+    /// with, or zero if this archive wasn't written as a segment.
+    pub fn dictionary_len(&self) -> usize {
+        self.dictionary_len
+    }
+
+    /// Same as [`load_interners()`](Self::load_interners), but also checks
+    /// that the arena starts with the given dictionary, so that the key ids
+    /// of the dictionary are valid in it.
+    pub fn load_interners_with(&self, dictionary: &Dictionary) -> Result<Jinterners, Error> {
+        if self.dictionary_len != dictionary.len() {
+            return Err(invalid("dictionary mismatch"));
+        }
+        let interners = self.load_interners()?;
+        if !dictionary.is_prefix_of(&interners) {
+            return Err(invalid("dictionary mismatch"));
+        }
+        Ok(interners)
+    }
+
+    /// Checks whether the given document is in this archive.
+    pub fn contains(&self, id: DocId) -> bool {
+        self.find(id).is_some()
+    }
+
+    /// Returns the root value of the given document in the given arena, or
+    /// [`None`] if it isn't in this archive.
+    ///
+    /// The arena must be the one returned by
+    /// [`load_interners()`](Self::load_interners). Fails if the root value
+    /// isn't in it.
+    pub fn get(&self, interners: &Jinterners, id: DocId) -> Result<Option<IValue>, Error> {
+        self.find(id)
+            .map(|index| self.root(index, interners))
+            .transpose()
+    }
+
+    /// Returns the JSON text of the given document, or [`None`] if it isn't in
+    /// this archive.
+    ///
+    /// This doesn't require loading the arena.
+    pub fn text(&self, id: DocId) -> Option<&str> {
+        let (start, len) = self.record(self.find(id)?)?.text;
+        std::str::from_utf8(&self.bytes.as_ref()[start..start + len]).ok()
+    }
+
+    /// Returns the index of the given document in the record table.
+    fn find(&self, id: DocId) -> Option<usize> {
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.record_id(mid).cmp(&id) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    /// Reads the root value of the given record, checking that it's in the
+    /// given arena.
+    fn root(&self, index: usize, interners: &Jinterners) -> Result<IValue, Error> {
+        let value = self.record(index).ok_or(invalid("invalid record"))?.value;
+        check_value(&value, interners.watermark()).map_err(|_| invalid("invalid root value"))?;
+        Ok(value)
+    }
+
+    fn record_id(&self, index: usize) -> DocId {
+        DocId(read_u64(
+            self.bytes.as_ref(),
+            self.records + index * RECORD_LEN,
+        ))
+    }
+
+    /// Reads the given record, or returns [`None`] if it's malformed.
+    fn record(&self, index: usize) -> Option<Record> {
+        let data = self.bytes.as_ref();
+        let field = |i: usize| read_u64(data, self.records + index * RECORD_LEN + 8 * i);
+        Some(Record {
+            value: IValue::from_raw_parts(field(1), field(2))?,
+            text: range(data, field(3), field(4))?,
+        })
+    }
+}
+
+/// Returns the size of the magic, version and header in the given format
+/// version.
+const fn header_len(version: u8) -> usize {
+    match version {
+        1 => 8 + 4 * 8,
+        _ => 8 + 5 * 8,
+    }
+}
+
+fn invalid(reason: &'static str) -> Error {
+    Error::Archive { reason }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// Converts the given offset and length to a range within the data, or returns
+/// [`None`] if it's out of bounds.
+fn range(data: &[u8], start: u64, len: u64) -> Option<(usize, usize)> {
+    let start = usize::try_from(start).ok()?;
+    let len = usize::try_from(len).ok()?;
+    (start.checked_add(len)? <= data.len()).then_some((start, len))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn archive() {
+        let mut store = JDocStore::new();
+        let documents = [
+            json!({"name": "John", "tags": ["a", "b"], "age": 42}),
+            json!("hello"),
+            json!(-1.5),
+            json!(null),
+            json!([true, {"name": "Mary"}]),
+        ];
+        let ids: Vec<DocId> = documents.iter().map(|d| store.insert_ref(d)).collect();
+        store.remove(ids[3]);
+
+        let mut bytes = Vec::new();
+        store.write_archive(&mut bytes).unwrap();
+        let archive = Archive::open(&bytes[..]).unwrap();
+        assert_eq!(archive.len(), 4);
+        assert_eq!(
+            archive.ids().collect::<Vec<_>>(),
+            [ids[0], ids[1], ids[2], ids[4]]
+        );
+        assert!(!archive.contains(ids[3]));
+        assert_eq!(archive.text(ids[3]), None);
+
+        let interners = archive.load_interners().unwrap();
+        for (id, document) in ids.iter().zip(&documents) {
+            if *id == ids[3] {
+                continue;
+            }
+            assert_eq!(
+                interners.lookup(&archive.get(&interners, *id).unwrap().unwrap()),
+                *document
+            );
+            assert_eq!(archive.text(*id), Some(document.to_string().as_str()));
+        }
+
+        let mut loaded = archive.load_store().unwrap();
+        assert_eq!(loaded.len(), 4);
+        assert_eq!(loaded.lookup(ids[4]), Some(documents[4].clone()));
+        assert_eq!(loaded.lookup(ids[3]), None);
+        assert_eq!(loaded.insert(json!(1)), store.insert(json!(1)));
+    }
+
+    /// Rewrites an archive without dictionary in the version 1 layout.
+    fn downgrade(bytes: &[u8]) -> Vec<u8> {
+        let shift = |x: u64| x - 8;
+        let field = |offset: usize| read_u64(bytes, offset);
+        let mut v1 = b"JINTARC\x01".to_vec();
+        for x in [shift(field(8)), field(16), shift(field(24)), field(32)] {
+            v1.extend_from_slice(&x.to_le_bytes());
+        }
+        let records = field(24) as usize;
+        v1.extend_from_slice(&bytes[HEADER_LEN..records]);
+        for record in bytes[records..].chunks(RECORD_LEN) {
+            for (i, x) in record.chunks(8).enumerate() {
+                let x = u64::from_le_bytes(x.try_into().unwrap());
+                v1.extend_from_slice(&if i == 3 { shift(x) } else { x }.to_le_bytes());
+            }
+        }
+        v1
+    }
+
+    #[test]
+    fn previous_version() {
+        let mut store = JDocStore::new();
+        let john = store.insert(json!({"name": "John", "tags": ["a"]}));
+        let mary = store.insert(json!({"name": "Mary"}));
+        let mut bytes = Vec::new();
+        store.write_archive(&mut bytes).unwrap();
+        assert_eq!(Archive::open(&bytes[..]).unwrap().format_version(), 2);
+
+        let v1 = downgrade(&bytes);
+        let archive = Archive::open(&v1[..]).unwrap();
+        assert_eq!(archive.format_version(), 1);
+        assert_eq!(archive.dictionary_len(), 0);
+        assert_eq!(archive.text(mary), Some(r#"{"name":"Mary"}"#));
+        let interners = archive.load_interners().unwrap();
+        assert_eq!(
+            interners.lookup(&archive.get(&interners, john).unwrap().unwrap()),
+            json!({"name": "John", "tags": ["a"]})
+        );
+
+        // Loading and writing again upgrades to the current version.
+        let mut upgraded = Vec::new();
+        archive
+            .load_store()
+            .unwrap()
+            .write_archive(&mut upgraded)
+            .unwrap();
+        assert_eq!(upgraded, bytes);
+    }
+
+    #[test]
+    fn invalid_archive() {
+        let error = Archive::open(&b"JINTARC"[..]).unwrap_err();
+        assert_eq!(error.to_string(), "invalid archive: missing archive header");
+        let error = Archive::open(&b"JINTARC\xff"[..]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid archive: unsupported format version"
+        );
+
+        let mut store = JDocStore::new();
+        store.insert(json!([1]));
+        let mut bytes = Vec::new();
+        store.write_archive(&mut bytes).unwrap();
+        let mut truncated = bytes.clone();
+        truncated.truncate(bytes.len() - 1);
+        let error = Archive::open(&truncated[..]).unwrap_err();
+        assert_eq!(error.to_string(), "invalid archive: invalid record table");
+
+        // A root value beyond the arena is rejected when it's read.
+        let root = bytes.len() - RECORD_LEN + 16;
+        bytes[root..root + 8].copy_from_slice(&1u64.to_le_bytes());
+        let archive = Archive::open(&bytes[..]).unwrap();
+        let interners = archive.load_interners().unwrap();
+        let id = archive.ids().next().unwrap();
+        let error = archive.get(&interners, id).unwrap_err();
+        assert_eq!(error.to_string(), "invalid archive: invalid root value");
+        let error = archive.load_store().unwrap_err();
+        assert_eq!(error.to_string(), "invalid archive: invalid root value");
+    }
+
+    #[test]
+    fn invalid_record_ids() {
+        let mut store = JDocStore::new();
+        store.insert(json!("a"));
+        store.insert(json!("b"));
+        let mut bytes = Vec::new();
+        store.write_archive(&mut bytes).unwrap();
+        let records = read_u64(&bytes, 24) as usize;
+        let set_id = |bytes: &mut Vec<u8>, index: usize, id: u64| {
+            let offset = records + index * RECORD_LEN;
+            bytes[offset..offset + 8].copy_from_slice(&id.to_le_bytes());
+        };
+
+        // Unsorted identifiers.
+        let mut unsorted = bytes.clone();
+        set_id(&mut unsorted, 0, 5);
+        let error = Archive::open(&unsorted[..]).unwrap_err();
+        assert_eq!(error.to_string(), "invalid archive: invalid record table");
+
+        // The maximal identifier leaves no identifier for new documents.
+        let mut max = bytes.clone();
+        set_id(&mut max, 1, u64::MAX);
+        let archive = Archive::open(&max[..]).unwrap();
+        assert_eq!(archive.text(DocId(u64::MAX)), Some(r#""b""#));
+        let error = archive.load_store().unwrap_err();
+        assert_eq!(error.to_string(), "invalid archive: invalid document id");
+    }
+}
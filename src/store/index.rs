@@ -0,0 +1,225 @@
+use super::DocId;
+use crate::{Accessor, Error, IValue, Jinterners, Mapping, ValueRef};
+use ordered_float::OrderedFloat;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::{Bound, RangeBounds};
+
+/// A path to a field within a document, written in the syntax of
+/// [`Accessor::compile()`].
+///
+/// The path is resolved in the arena of the store, and resolved again when
+/// this arena is replaced or, if some of its keys weren't interned yet, when a
+/// document is indexed.
+#[derive(Clone, Debug)]
+pub(super) struct FieldPath {
+    path: String,
+    accessor: Accessor,
+}
+
+impl FieldPath {
+    /// Resolves the given path in the given arena, returning
+    /// [`Error::Accessor`] if it's malformed.
+    pub(super) fn new(interners: &Jinterners, path: &str) -> Result<Self, Error> {
+        Ok(Self {
+            accessor: Accessor::compile(interners, path)?,
+            path: path.to_owned(),
+        })
+    }
+
+    /// Resolves this path again in the given arena.
+    fn resolve(&mut self, interners: &Jinterners) {
+        self.accessor = Accessor::compile(interners, &self.path).expect("the path is valid");
+    }
+
+    /// Returns the value at this path in the given document, or [`None`] if
+    /// the document doesn't contain this field.
+    fn extract(&mut self, interners: &Jinterners, document: IValue) -> Option<IValue> {
+        if !self.accessor.is_resolved() {
+            self.resolve(interners);
+        }
+        self.accessor.get(interners, document)
+    }
+}
+
+/// An index mapping the values of a field to the documents containing them.
+///
+/// As equal values are interned to the same [`IValue`], comparing values only
+/// requires comparing handles.
+#[derive(Clone, Debug)]
+pub(super) struct EqIndex {
+    path: FieldPath,
+    entries: HashMap<IValue, BTreeSet<DocId>>,
+}
+
+impl EqIndex {
+    pub(super) fn new(path: FieldPath) -> Self {
+        Self {
+            path,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Removes all the documents from this index, whose arena is replaced by
+    /// the given one.
+    #[cfg(feature = "serde")]
+    pub(super) fn clear(&mut self, interners: &Jinterners) {
+        self.path.resolve(interners);
+        self.entries.clear();
+    }
+
+    pub(super) fn insert(&mut self, interners: &Jinterners, id: DocId, document: IValue) {
+        if let Some(value) = self.path.extract(interners, document) {
+            self.entries.entry(value).or_default().insert(id);
+        }
+    }
+
+    pub(super) fn remove(&mut self, interners: &Jinterners, id: DocId, document: IValue) {
+        if let Some(value) = self.path.extract(interners, document)
+            && let Some(ids) = self.entries.get_mut(&value)
+        {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.entries.remove(&value);
+            }
+        }
+    }
+
+    /// Returns the documents whose field is equal to the given value.
+    pub(super) fn get(&self, value: &IValue) -> impl Iterator<Item = DocId> + '_ {
+        self.entries.get(value).into_iter().flatten().copied()
+    }
+
+    /// Converts the indexed values to the given new arena.
+    pub(super) fn remap(&mut self, interners: &Jinterners, mapping: &Mapping) {
+        self.path.resolve(interners);
+        self.entries = std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(|(value, ids)| (mapping.map(value), ids))
+            .collect();
+    }
+}
+
+/// A JSON number, ordered by numeric value regardless of its representation.
+#[derive(Clone, Copy, Debug)]
+enum NumberKey {
+    Int(i128),
+    Float(OrderedFloat<f64>),
+}
+
+impl Ord for NumberKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (*self, *other) {
+            (NumberKey::Int(a), NumberKey::Int(b)) => a.cmp(&b),
+            (NumberKey::Float(a), NumberKey::Float(b)) => a.cmp(&b),
+            (NumberKey::Int(a), NumberKey::Float(b)) => cmp_float_int(b, a).reverse(),
+            (NumberKey::Float(a), NumberKey::Int(b)) => cmp_float_int(a, b),
+        }
+    }
+}
+
+/// Compares a float with an integer, without losing precision.
+fn cmp_float_int(float: OrderedFloat<f64>, int: i128) -> Ordering {
+    match float.cmp(&OrderedFloat(int as f64)) {
+        // The float is integral and in the range where the conversion is
+        // exact, so compare as integers.
+        Ordering::Equal => (float.0 as i128).cmp(&int),
+        ordering => ordering,
+    }
+}
+
+impl PartialOrd for NumberKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for NumberKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for NumberKey {}
+
+/// An index of the documents by the numeric value of a field, allowing range
+/// queries.
+#[derive(Clone, Debug)]
+pub(super) struct RangeIndex {
+    path: FieldPath,
+    entries: BTreeMap<NumberKey, BTreeSet<DocId>>,
+}
+
+impl RangeIndex {
+    pub(super) fn new(path: FieldPath) -> Self {
+        Self {
+            path,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    fn key(&mut self, interners: &Jinterners, document: IValue) -> Option<NumberKey> {
+        let value = self.path.extract(interners, document)?;
+        Some(match interners.lookup_ref(&value) {
+            ValueRef::U64(x) => NumberKey::Int(x.into()),
+            ValueRef::I64(x) => NumberKey::Int(x.into()),
+            ValueRef::F64(x) => NumberKey::Float(OrderedFloat(x)),
+            _ => return None,
+        })
+    }
+
+    /// Removes all the documents from this index, whose arena is replaced by
+    /// the given one.
+    #[cfg(feature = "serde")]
+    pub(super) fn clear(&mut self, interners: &Jinterners) {
+        self.path.resolve(interners);
+        self.entries.clear();
+    }
+
+    /// Switches to the given new arena. The indexed numbers don't depend on
+    /// the arena.
+    pub(super) fn remap(&mut self, interners: &Jinterners) {
+        self.path.resolve(interners);
+    }
+
+    pub(super) fn insert(&mut self, interners: &Jinterners, id: DocId, document: IValue) {
+        if let Some(key) = self.key(interners, document) {
+            self.entries.entry(key).or_default().insert(id);
+        }
+    }
+
+    pub(super) fn remove(&mut self, interners: &Jinterners, id: DocId, document: IValue) {
+        if let Some(key) = self.key(interners, document)
+            && let Some(ids) = self.entries.get_mut(&key)
+        {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    /// Returns the documents whose field is in the given range, in increasing
+    /// identifier order.
+    pub(super) fn get(&self, range: impl RangeBounds<f64>) -> Vec<DocId> {
+        let convert = |bound: Bound<&f64>| bound.map(|x| NumberKey::Float(OrderedFloat(*x)));
+        let range = (convert(range.start_bound()), convert(range.end_bound()));
+        // Empty ranges make BTreeMap::range() panic.
+        let empty = match range {
+            (Bound::Included(a), Bound::Included(b)) => a > b,
+            (Bound::Included(a) | Bound::Excluded(a), Bound::Excluded(b))
+            | (Bound::Excluded(a), Bound::Included(b)) => a >= b,
+            _ => false,
+        };
+        if empty {
+            return Vec::new();
+        }
+        let ids: BTreeSet<DocId> = self
+            .entries
+            .range(range)
+            .flat_map(|(_, ids)| ids)
+            .copied()
+            .collect();
+        ids.into_iter().collect()
+    }
+}
@@ -0,0 +1,545 @@
+#[cfg(feature = "serde")]
+mod archive;
+mod index;
+#[cfg(feature = "serde")]
+mod segment;
+#[cfg(feature = "serde")]
+mod spill;
+mod version;
+
+#[cfg(feature = "retain")]
+use crate::JinternersMaintainer;
+use crate::{Error, IValue, Jinterners, Mapping};
+#[cfg(feature = "serde")]
+pub use archive::{Archive, FORMAT_VERSION};
+use index::{EqIndex, FieldPath, RangeIndex};
+#[cfg(feature = "serde")]
+pub use segment::{Dictionary, Segments};
+use serde_json::Value;
+#[cfg(feature = "serde")]
+pub use spill::SpillingIngest;
+use std::collections::{BTreeMap, btree_map};
+use std::ops::RangeBounds;
+pub use version::{VersionId, VersionedStore};
+
+/// Identifier of a document in a [`JDocStore`].
+///
+/// Identifiers are allocated in increasing order and never reused, even after
+/// the document is removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DocId(u64);
+
+impl DocId {
+    /// Returns the numeric value of this identifier.
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+/// A collection of JSON documents interned in a [`Jinterners`] arena.
+///
+/// Contrary to a bare arena, the store knows which values are in use, so that
+/// the arena can be optimized or compacted without the caller having to track
+/// and remap the roots: documents are always addressed by a stable [`DocId`].
+///
+/// ```
+/// use jinterner::JDocStore;
+/// use serde_json::json;
+///
+/// let mut store = JDocStore::new();
+/// let john = store.insert(json!({"name": "John", "tags": ["a", "b"]}));
+/// let mary = store.insert(json!({"name": "Mary", "tags": ["b", "a"]}));
+/// store.remove(john);
+///
+/// store.optimize(None);
+/// assert_eq!(store.lookup(mary), Some(json!({"name": "Mary", "tags": ["b", "a"]})));
+/// assert_eq!(store.lookup(john), None);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct JDocStore {
+    interners: Jinterners,
+    docs: BTreeMap<DocId, IValue>,
+    next_id: u64,
+    eq_indexes: BTreeMap<String, EqIndex>,
+    range_indexes: BTreeMap<String, RangeIndex>,
+}
+
+impl JDocStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty store interning documents into the given arena, for
+    /// example to configure it beforehand.
+    ///
+    /// Values that were already interned in the arena are only kept until the
+    /// next [`compact()`](Self::compact).
+    pub fn with_interners(interners: Jinterners) -> Self {
+        Self {
+            interners,
+            docs: BTreeMap::new(),
+            next_id: 0,
+            eq_indexes: BTreeMap::new(),
+            range_indexes: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the underlying arena.
+    ///
+    /// [`IValue`]s returned by this store are rooted in this arena, until the
+    /// next operation that rebuilds it.
+    pub fn interners(&self) -> &Jinterners {
+        &self.interners
+    }
+
+    /// Returns the number of documents in this store.
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// Checks whether this store contains no document.
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Interns the given document and returns its identifier.
+    pub fn insert(&mut self, document: Value) -> DocId {
+        let value = self.interners.intern_mut(document);
+        self.insert_interned(value)
+    }
+
+    /// Interns the given document and returns its identifier.
+    pub fn insert_ref(&mut self, document: &Value) -> DocId {
+        let value = self.interners.intern_ref_mut(document);
+        self.insert_interned(value)
+    }
+
+    /// Adds a value already interned in [`interners()`](Self::interners) as a
+    /// new document.
+    ///
+    /// The caller is responsible for ensuring that the value was interned in
+    /// this store's arena, otherwise an arbitrary value will be returned or a
+    /// panic will happen when using it.
+    pub fn insert_interned(&mut self, value: IValue) -> DocId {
+        let id = DocId(self.next_id);
+        self.next_id += 1;
+        self.insert_at(id, value);
+        id
+    }
+
+    fn insert_at(&mut self, id: DocId, value: IValue) {
+        self.docs.insert(id, value);
+        for index in self.eq_indexes.values_mut() {
+            index.insert(&self.interners, id, value);
+        }
+        for index in self.range_indexes.values_mut() {
+            index.insert(&self.interners, id, value);
+        }
+    }
+
+    /// Checks whether the given document is in this store.
+    pub fn contains(&self, id: DocId) -> bool {
+        self.docs.contains_key(&id)
+    }
+
+    /// Returns the interned value of the given document, or [`None`] if it
+    /// isn't in this store.
+    pub fn get(&self, id: DocId) -> Option<IValue> {
+        self.docs.get(&id).copied()
+    }
+
+    /// Retrieves the given document, or [`None`] if it isn't in this store.
+    pub fn lookup(&self, id: DocId) -> Option<Value> {
+        self.docs.get(&id).map(|value| self.interners.lookup(value))
+    }
+
+    /// Interns the given document and replaces the given one with it, and
+    /// returns the previous interned value, or [`None`] (without changing
+    /// anything) if the document isn't in this store.
+    ///
+    /// The previous value stays in the arena until the next
+    /// [`compact()`](Self::compact).
+    pub fn update(&mut self, id: DocId, document: Value) -> Option<IValue> {
+        if !self.contains(id) {
+            return None;
+        }
+        let value = self.interners.intern_mut(document);
+        self.update_interned(id, value)
+    }
+
+    /// Replaces the given document with a value already interned in
+    /// [`interners()`](Self::interners), and returns the previous interned
+    /// value, or [`None`] (without changing anything) if the document isn't in
+    /// this store.
+    ///
+    /// The same caveats as for [`insert_interned()`](Self::insert_interned)
+    /// apply.
+    pub fn update_interned(&mut self, id: DocId, value: IValue) -> Option<IValue> {
+        let previous = self.remove(id)?;
+        self.insert_at(id, value);
+        Some(previous)
+    }
+
+    /// Removes the given document, and returns its interned value if it was in
+    /// this store.
+    ///
+    /// The value stays in the arena until the next
+    /// [`compact()`](Self::compact).
+    pub fn remove(&mut self, id: DocId) -> Option<IValue> {
+        let value = self.docs.remove(&id)?;
+        for index in self.eq_indexes.values_mut() {
+            index.remove(&self.interners, id, value);
+        }
+        for index in self.range_indexes.values_mut() {
+            index.remove(&self.interners, id, value);
+        }
+        Some(value)
+    }
+
+    /// Iterates over the documents of this store, in increasing identifier
+    /// order.
+    pub fn iter(&self) -> DocIter<'_> {
+        DocIter {
+            iter: self.docs.iter(),
+        }
+    }
+
+    /// Optimizes the underlying arena, see [`Jinterners::optimize()`], and
+    /// remaps all documents accordingly.
+    ///
+    /// Returns [`true`] if the arena was rebuilt.
+    pub fn optimize(&mut self, limit: Option<usize>) -> bool {
+        match self.interners.optimize(limit) {
+            Some((interners, mapping)) => {
+                self.replace(interners, &mapping);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops the values of the underlying arena that aren't reachable from any
+    /// document, and remaps all documents accordingly.
+    ///
+    /// Returns [`true`] if the arena was rebuilt.
+    #[cfg(feature = "retain")]
+    pub fn compact(&mut self) -> bool {
+        match self.interners.retain_values(self.docs.values().copied()) {
+            Some((interners, mapping)) => {
+                self.replace(interners, &mapping);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Runs the given maintenance policy on the underlying arena, with all
+    /// documents as roots, see [`JinternersMaintainer::run()`].
+    ///
+    /// Returns [`true`] if the arena was rebuilt.
+    #[cfg(feature = "retain")]
+    pub fn maintain(&mut self, maintainer: &JinternersMaintainer) -> bool {
+        let roots: Vec<IValue> = self.docs.values().copied().collect();
+        match maintainer.run(&self.interners, &roots) {
+            Some(outcome) => {
+                self.replace(outcome.jinterners, &outcome.mapping);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn replace(&mut self, interners: Jinterners, mapping: &Mapping) {
+        self.interners = interners;
+        for value in self.docs.values_mut() {
+            *value = mapping.map(*value);
+        }
+        for index in self.eq_indexes.values_mut() {
+            index.remap(&self.interners, mapping);
+        }
+        for index in self.range_indexes.values_mut() {
+            index.remap(&self.interners);
+        }
+    }
+}
+
+/// Secondary indexes.
+impl JDocStore {
+    /// Adds an equality index on the given field, allowing to query documents
+    /// by value with [`find_eq()`](Self::find_eq).
+    ///
+    /// The field is given in the syntax of
+    /// [`Accessor::compile()`](crate::Accessor::compile), for example
+    /// `user.tags[0]`. Documents that don't contain the field aren't indexed.
+    ///
+    /// Existing documents are indexed immediately, and the index is then
+    /// maintained as documents are inserted and removed. Returns [`false`] if
+    /// the field was already indexed, or [`Error::Accessor`] if the path is
+    /// malformed.
+    ///
+    /// ```
+    /// use jinterner::JDocStore;
+    /// use serde_json::json;
+    ///
+    /// let mut store = JDocStore::new();
+    /// let a = store.insert(json!({"user": {"id": 1}, "text": "hello"}));
+    /// let b = store.insert(json!({"user": {"id": 2}, "text": "world"}));
+    /// let c = store.insert(json!({"user": {"id": 1}, "text": "again"}));
+    ///
+    /// store.index("user.id")?;
+    /// assert_eq!(store.find_eq("user.id", &json!(1)), Some(vec![a, c]));
+    /// assert_eq!(store.find_eq("user.id", &json!(3)), Some(vec![]));
+    /// // This field isn't indexed.
+    /// assert_eq!(store.find_eq("text", &json!("hello")), None);
+    /// # Ok::<(), jinterner::Error>(())
+    /// ```
+    pub fn index(&mut self, path: &str) -> Result<bool, Error> {
+        if self.eq_indexes.contains_key(path) {
+            return Ok(false);
+        }
+        let mut index = EqIndex::new(FieldPath::new(&self.interners, path)?);
+        for (id, value) in &self.docs {
+            index.insert(&self.interners, *id, *value);
+        }
+        self.eq_indexes.insert(path.to_owned(), index);
+        Ok(true)
+    }
+
+    /// Removes the equality index on the given field. Returns [`false`] if the
+    /// field wasn't indexed.
+    pub fn drop_index(&mut self, path: &str) -> bool {
+        self.eq_indexes.remove(path).is_some()
+    }
+
+    /// Returns the documents whose given field is equal to the given value, in
+    /// increasing identifier order.
+    ///
+    /// Returns [`None`] if the field isn't indexed, see
+    /// [`index()`](Self::index).
+    pub fn find_eq(&self, path: &str, value: &Value) -> Option<Vec<DocId>> {
+        let index = self.eq_indexes.get(path)?;
+        // A value that was never interned can't be in any document.
+        Some(match self.interners.find(value) {
+            Some(value) => index.get(&value).collect(),
+            None => Vec::new(),
+        })
+    }
+
+    /// Same as [`find_eq()`](Self::find_eq), for a value interned in
+    /// [`interners()`](Self::interners).
+    pub fn find_eq_interned(&self, path: &str, value: IValue) -> Option<Vec<DocId>> {
+        Some(self.eq_indexes.get(path)?.get(&value).collect())
+    }
+
+    /// Adds a range index on the given numeric field, allowing to query
+    /// documents by range with [`find_range()`](Self::find_range).
+    ///
+    /// The field is given like for [`index()`](Self::index). Documents where
+    /// the field is missing or isn't a number aren't indexed. Returns [`false`]
+    /// if the field already had a range index, or [`Error::Accessor`] if the
+    /// path is malformed.
+    ///
+    /// ```
+    /// use jinterner::JDocStore;
+    /// use serde_json::json;
+    ///
+    /// let mut store = JDocStore::new();
+    /// store.range_index("ts")?;
+    /// let a = store.insert(json!({"ts": 1000, "event": "start"}));
+    /// let b = store.insert(json!({"ts": 1500.5, "event": "tick"}));
+    /// let c = store.insert(json!({"ts": 2000, "event": "stop"}));
+    ///
+    /// assert_eq!(store.find_range("ts", 1000.0..=1500.5), Some(vec![a, b]));
+    /// assert_eq!(store.find_range("ts", 1200.0..), Some(vec![b, c]));
+    /// assert_eq!(store.find_range("event", ..), None);
+    /// # Ok::<(), jinterner::Error>(())
+    /// ```
+    pub fn range_index(&mut self, path: &str) -> Result<bool, Error> {
+        if self.range_indexes.contains_key(path) {
+            return Ok(false);
+        }
+        let mut index = RangeIndex::new(FieldPath::new(&self.interners, path)?);
+        for (id, value) in &self.docs {
+            index.insert(&self.interners, *id, *value);
+        }
+        self.range_indexes.insert(path.to_owned(), index);
+        Ok(true)
+    }
+
+    /// Removes the range index on the given field. Returns [`false`] if the
+    /// field didn't have a range index.
+    pub fn drop_range_index(&mut self, path: &str) -> bool {
+        self.range_indexes.remove(path).is_some()
+    }
+
+    /// Returns the documents whose given numeric field is within the given
+    /// range, in increasing identifier order.
+    ///
+    /// Integers and floats are compared by numeric value, without loss of
+    /// precision. Returns [`None`] if the field doesn't have a range index,
+    /// see [`range_index()`](Self::range_index).
+    pub fn find_range(&self, path: &str, range: impl RangeBounds<f64>) -> Option<Vec<DocId>> {
+        Some(self.range_indexes.get(path)?.get(range))
+    }
+}
+
+/// Iterator over the documents of a [`JDocStore`].
+///
+/// This struct is created by the [`iter()`](JDocStore::iter) method on
+/// [`JDocStore`].
+pub struct DocIter<'a> {
+    iter: btree_map::Iter<'a, DocId, IValue>,
+}
+
+impl Iterator for DocIter<'_> {
+    type Item = (DocId, IValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(id, value)| (*id, *value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl ExactSizeIterator for DocIter<'_> {}
+
+impl<'a> IntoIterator for &'a JDocStore {
+    type Item = (DocId, IValue);
+    type IntoIter = DocIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn insert_remove() {
+        let mut store = JDocStore::new();
+        let a = store.insert(json!({"a": [1, 2]}));
+        let b = store.insert_ref(&json!("b"));
+        assert_ne!(a, b);
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.lookup(a), Some(json!({"a": [1, 2]})));
+
+        assert!(store.remove(a).is_some());
+        assert!(store.remove(a).is_none());
+        assert!(!store.contains(a));
+        let c = store.insert(json!({"a": [1, 2]}));
+        assert!(c > b);
+
+        let docs: Vec<_> = store
+            .iter()
+            .map(|(id, value)| (id, store.interners().lookup(&value)))
+            .collect();
+        assert_eq!(docs, [(b, json!("b")), (c, json!({"a": [1, 2]}))]);
+    }
+
+    #[test]
+    fn optimize_remaps_documents() {
+        let mut store = JDocStore::new();
+        let values = [
+            json!({"z": ["y", "x"]}),
+            json!(["b", {"a": "c"}]),
+            json!({"z": ["x"]}),
+        ];
+        let ids: Vec<DocId> = values.iter().map(|v| store.insert_ref(v)).collect();
+
+        assert!(store.optimize(None));
+        for (id, value) in ids.iter().zip(&values) {
+            assert_eq!(store.lookup(*id).as_ref(), Some(value));
+        }
+
+        #[cfg(feature = "retain")]
+        {
+            store.remove(ids[1]);
+            assert!(store.compact());
+            assert!(!store.compact());
+            assert_eq!(store.lookup(ids[0]).as_ref(), Some(&values[0]));
+            assert_eq!(store.lookup(ids[2]).as_ref(), Some(&values[2]));
+            assert_eq!(store.interners().find_key("a"), None);
+        }
+    }
+
+    #[test]
+    fn eq_index() {
+        let mut store = JDocStore::new();
+        let a = store.insert(json!({"user": {"id": 1, "tags": ["x", "y"]}}));
+        let b = store.insert(json!({"user": {"id": "1", "tags": ["y"]}}));
+        assert!(store.index("user.id").unwrap());
+        assert!(!store.index("user.id").unwrap());
+        assert!(store.index("user.tags[0]").unwrap());
+        assert!(store.index("user.tags[x]").is_err());
+        let c = store.insert(json!({"user": {"id": 1}}));
+
+        assert_eq!(store.find_eq("user.id", &json!(1)), Some(vec![a, c]));
+        assert_eq!(store.find_eq("user.id", &json!("1")), Some(vec![b]));
+        assert_eq!(store.find_eq("user.tags[0]", &json!("y")), Some(vec![b]));
+        assert_eq!(store.find_eq("user", &json!(1)), None);
+
+        store.remove(a);
+        assert_eq!(store.find_eq("user.id", &json!(1)), Some(vec![c]));
+
+        // The keys of an index don't need to be interned yet.
+        assert!(store.index("late[1]").unwrap());
+        let d = store.insert(json!({"late": [0, "x"]}));
+        assert_eq!(store.find_eq("late[1]", &json!("x")), Some(vec![d]));
+
+        // Indexes follow the documents when the arena is rebuilt.
+        store.insert(json!(["unrelated", "values"]));
+        assert!(store.optimize(None));
+        assert_eq!(store.find_eq("user.id", &json!("1")), Some(vec![b]));
+        assert_eq!(store.find_eq("user.tags[0]", &json!("y")), Some(vec![b]));
+        let e = store.insert(json!({"late": [1, "x"], "user": {"id": "1"}}));
+        assert_eq!(store.find_eq("late[1]", &json!("x")), Some(vec![d, e]));
+        assert_eq!(store.find_eq("user.id", &json!("1")), Some(vec![b, e]));
+
+        assert!(store.drop_index("user.id"));
+        assert_eq!(store.find_eq("user.id", &json!(1)), None);
+    }
+
+    #[test]
+    fn range_index() {
+        let mut store = JDocStore::new();
+        let big = (1u64 << 53) + 1;
+        let a = store.insert(json!({"n": -1}));
+        let b = store.insert(json!({"n": 0.5}));
+        let c = store.insert(json!({"n": big}));
+        let d = store.insert(json!({"n": 1u64 << 53}));
+        store.insert(json!({"n": "1"}));
+        store.insert(json!({}));
+        assert!(store.range_index("n").unwrap());
+        let e = store.insert(json!({"n": 1.0}));
+
+        assert_eq!(store.find_range("n", ..), Some(vec![a, b, c, d, e]));
+        assert_eq!(store.find_range("n", -1.0..1.0), Some(vec![a, b]));
+        assert_eq!(store.find_range("n", 0.5..=1.0), Some(vec![b, e]));
+        assert_eq!(store.find_range("n", ..-1.0), Some(vec![]));
+        assert_eq!(store.find_range("n", 2.0..1.0), Some(vec![]));
+        // The bound is 2^53, which excludes 2^53 + 1.
+        assert_eq!(
+            store.find_range("n", ..=(1u64 << 53) as f64),
+            Some(vec![a, b, d, e])
+        );
+        assert_eq!(
+            store.find_range("n", (1u64 << 53) as f64..),
+            Some(vec![c, d])
+        );
+
+        store.remove(b);
+        assert!(store.optimize(None));
+        assert_eq!(store.find_range("n", 0.0..2.0), Some(vec![e]));
+        // Documents inserted after the arena is rebuilt are indexed too.
+        let f = store.insert(json!({"n": 1.5}));
+        assert_eq!(store.find_range("n", 0.0..2.0), Some(vec![e, f]));
+        assert!(store.drop_range_index("n"));
+        assert_eq!(store.find_range("n", ..), None);
+    }
+}
@@ -0,0 +1,310 @@
+use super::{Archive, DocId, JDocStore};
+use crate::{Error, IValue, Jinterners};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+/// A list of strings interned first in the arena of every segment of a
+/// [`Segments`] collection.
+///
+/// As interning is append-only, these strings (typically the object keys) get
+/// the same ids in all the segments, so that values computed from the
+/// dictionary, such as a [`KeyPath`](crate::KeyPath), can be used across
+/// segments.
+///
+/// The dictionary is serialized as a plain list of strings.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Dictionary {
+    strings: Vec<String>,
+}
+
+impl Dictionary {
+    /// Creates a dictionary of the given strings, in order, ignoring
+    /// duplicates.
+    pub fn new(strings: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        let mut interners = Jinterners::default();
+        for string in strings {
+            interners.string.intern_mut(string.as_ref());
+        }
+        Self::from_interners(&interners)
+    }
+
+    /// Creates a dictionary of all the strings of the given arena, for
+    /// example one into which representative documents were interned.
+    pub fn from_interners(interners: &Jinterners) -> Self {
+        Self {
+            strings: interners.string.iter().map(str::to_owned).collect(),
+        }
+    }
+
+    /// Returns the number of strings in this dictionary.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Checks whether this dictionary contains no string.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Iterates over the strings of this dictionary, in id order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.strings.iter().map(String::as_str)
+    }
+
+    /// Creates an arena containing the strings of this dictionary.
+    pub fn interners(&self) -> Jinterners {
+        let mut interners = Jinterners::default();
+        self.seed(&mut interners);
+        interners
+    }
+
+    /// Creates an empty store whose arena contains the strings of this
+    /// dictionary, ready to be sealed with [`JDocStore::seal()`].
+    pub fn store(&self) -> JDocStore {
+        JDocStore::with_interners(self.interners())
+    }
+
+    /// Interns the strings of this dictionary in the given empty arena.
+    fn seed(&self, interners: &mut Jinterners) {
+        for string in &self.strings {
+            interners.string.intern_mut(string);
+            interners.string_filter.insert(string);
+        }
+    }
+
+    /// Checks whether the given arena starts with the strings of this
+    /// dictionary.
+    pub(super) fn is_prefix_of(&self, interners: &Jinterners) -> bool {
+        interners.string.strings() >= self.len()
+            && interners
+                .string
+                .iter()
+                .zip(self.iter())
+                .all(|(a, b)| a == b)
+    }
+}
+
+impl JDocStore {
+    /// Writes all the documents of this store as a segment [`Archive`]
+    /// sharing the given dictionary, then removes them from this store.
+    ///
+    /// The store is left with an arena only containing the dictionary, and
+    /// keeps its indexes (now empty) as well as its identifier sequence, so
+    /// that the documents of successive segments have distinct identifiers.
+    ///
+    /// Fails without removing anything if the arena of this store doesn't
+    /// start with the dictionary, for example after it was optimized.
+    pub fn seal(&mut self, dictionary: &Dictionary, w: &mut impl Write) -> io::Result<()> {
+        if !dictionary.is_prefix_of(&self.interners) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the arena doesn't start with the dictionary",
+            ));
+        }
+        self.write_archive_with(w, dictionary.len())?;
+
+        let mut interners = self.interners.with_settings_of();
+        dictionary.seed(&mut interners);
+        self.interners = interners;
+        self.docs.clear();
+        for index in self.eq_indexes.values_mut() {
+            index.clear(&self.interners);
+        }
+        for index in self.range_indexes.values_mut() {
+            index.clear(&self.interners);
+        }
+        Ok(())
+    }
+}
+
+/// A collection of segment [`Archive`]s sharing a [`Dictionary`], written by
+/// [`JDocStore::seal()`].
+///
+/// Any subset of the segments can be opened, documents are then looked up by
+/// identifier in all of them.
+///
+/// ```
+/// use jinterner::{Archive, Dictionary, KeyPath, Segments};
+/// use serde_json::json;
+///
+/// let dictionary = Dictionary::new(["name", "age"]);
+/// let mut store = dictionary.store();
+/// let mut files = Vec::new();
+/// for name in ["John", "Mary"] {
+///     store.insert(json!({"name": name, "age": 42}));
+///     let mut file = Vec::new();
+///     store.seal(&dictionary, &mut file).unwrap();
+///     files.push(file);
+/// }
+///
+/// let mut segments = Segments::new(dictionary);
+/// segments.push(Archive::open(&files[1]).unwrap()).unwrap();
+/// let mary = segments.ids().next().unwrap();
+/// let segment = segments.segment_of(mary).unwrap();
+/// let interners = segments.load_interners(segment).unwrap();
+/// let mary = segments.get(&interners, mary).unwrap().unwrap();
+///
+/// // Key ids are the same in all segments.
+/// let path = KeyPath::new(&segments.dictionary().interners(), "name");
+/// let name = path.get(&interners, mary).unwrap();
+/// assert_eq!(interners.lookup(&name), json!("Mary"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Segments<B> {
+    dictionary: Dictionary,
+    segments: Vec<Archive<B>>,
+}
+
+impl<B: AsRef<[u8]>> Segments<B> {
+    /// Creates an empty collection of segments sharing the given dictionary.
+    pub fn new(dictionary: Dictionary) -> Self {
+        Self {
+            dictionary,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Returns the dictionary shared by the segments.
+    pub fn dictionary(&self) -> &Dictionary {
+        &self.dictionary
+    }
+
+    /// Returns the segments, in the order they were added.
+    pub fn segments(&self) -> &[Archive<B>] {
+        &self.segments
+    }
+
+    /// Adds a segment to this collection.
+    ///
+    /// Fails if the segment wasn't written with a dictionary of the same
+    /// length. The dictionary strings themselves are only checked when
+    /// loading the arena of the segment.
+    pub fn push(&mut self, segment: Archive<B>) -> Result<(), Error> {
+        if segment.dictionary_len() != self.dictionary.len() {
+            return Err(Error::Archive {
+                reason: "dictionary mismatch",
+            });
+        }
+        self.segments.push(segment);
+        Ok(())
+    }
+
+    /// Returns the total number of documents in the segments.
+    pub fn len(&self) -> usize {
+        self.segments.iter().map(Archive::len).sum()
+    }
+
+    /// Checks whether the segments contain no document.
+    pub fn is_empty(&self) -> bool {
+        self.segments.iter().all(Archive::is_empty)
+    }
+
+    /// Iterates over the identifiers of the documents, segment by segment.
+    pub fn ids(&self) -> impl Iterator<Item = DocId> + '_ {
+        self.segments.iter().flat_map(Archive::ids)
+    }
+
+    /// Returns the index of the segment containing the given document, or
+    /// [`None`] if it isn't in any segment.
+    pub fn segment_of(&self, id: DocId) -> Option<usize> {
+        self.segments
+            .iter()
+            .position(|segment| segment.contains(id))
+    }
+
+    /// Returns the root value of the given document in the given arena, or
+    /// [`None`] if it isn't in any segment.
+    ///
+    /// The arena must be the one of the segment containing the document,
+    /// returned by [`load_interners()`](Self::load_interners) for the index
+    /// returned by [`segment_of()`](Self::segment_of). Fails if the root
+    /// value isn't in it.
+    pub fn get(&self, interners: &Jinterners, id: DocId) -> Result<Option<IValue>, Error> {
+        match self.segment_of(id) {
+            None => Ok(None),
+            Some(segment) => self.segments[segment].get(interners, id),
+        }
+    }
+
+    /// Returns the JSON text of the given document, or [`None`] if it isn't in
+    /// any segment.
+    pub fn text(&self, id: DocId) -> Option<&str> {
+        self.segments[self.segment_of(id)?].text(id)
+    }
+
+    /// Deserializes the arena of the given segment, checking that it starts
+    /// with the dictionary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the segment index is out of bounds.
+    pub fn load_interners(&self, segment: usize) -> Result<Jinterners, Error> {
+        self.segments[segment].load_interners_with(&self.dictionary)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::KeyPath;
+    use serde_json::json;
+
+    #[test]
+    fn segments() {
+        let dictionary = Dictionary::new(["name", "tags", "name"]);
+        assert_eq!(dictionary.iter().collect::<Vec<_>>(), ["name", "tags"]);
+
+        let mut store = dictionary.store();
+        store.index("name").unwrap();
+        let john = store.insert(json!({"name": "John", "tags": ["a"]}));
+        let mut first = Vec::new();
+        store.seal(&dictionary, &mut first).unwrap();
+        assert!(store.is_empty());
+        assert_eq!(store.find_eq("name", &json!("John")), Some(vec![]));
+
+        let mary = store.insert(json!({"tags": ["b"], "name": "Mary"}));
+        assert_eq!(store.find_eq("name", &json!("Mary")), Some(vec![mary]));
+        let mut second = Vec::new();
+        store.seal(&dictionary, &mut second).unwrap();
+
+        let mut segments = Segments::new(dictionary.clone());
+        segments.push(Archive::open(&first[..]).unwrap()).unwrap();
+        segments.push(Archive::open(&second[..]).unwrap()).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments.ids().collect::<Vec<_>>(), [john, mary]);
+        assert_eq!(segments.text(mary), Some(r#"{"name":"Mary","tags":["b"]}"#));
+
+        let path = KeyPath::new(&dictionary.interners(), "tags");
+        for (id, tags) in [(john, json!(["a"])), (mary, json!(["b"]))] {
+            let segment = segments.segment_of(id).unwrap();
+            let interners = segments.load_interners(segment).unwrap();
+            let value = segments.get(&interners, id).unwrap().unwrap();
+            assert_eq!(
+                interners.lookup(&path.get(&interners, value).unwrap()),
+                tags
+            );
+        }
+
+        let mut other = Segments::new(Dictionary::new(["name"]));
+        let error = other.push(Archive::open(&first[..]).unwrap()).unwrap_err();
+        assert_eq!(error.to_string(), "invalid archive: dictionary mismatch");
+        let error = Archive::open(&first[..])
+            .unwrap()
+            .load_interners_with(&Dictionary::new(["tags", "name"]))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "invalid archive: dictionary mismatch");
+    }
+
+    #[test]
+    fn seal_optimized() {
+        let dictionary = Dictionary::new(["b", "a"]);
+        let mut store = dictionary.store();
+        store.insert(json!({"a": 1, "b": 2}));
+        store.optimize(None);
+        let mut file = Vec::new();
+        assert!(store.seal(&dictionary, &mut file).is_err());
+        assert_eq!(store.len(), 1);
+        assert!(file.is_empty());
+    }
+}
@@ -0,0 +1,306 @@
+//! Ingestion of documents into a [`JDocStore`] with a bounded amount of
+//! intermediate memory.
+
+use super::{DocId, JDocStore};
+use crate::translate::{InternInto, Translator};
+use crate::{IValue, InternedStrKey, Jinterners};
+use serde_json::Value;
+use std::collections::hash_map::RandomState;
+use std::fs::{File, OpenOptions};
+use std::hash::BuildHasher;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counter to give distinct file names to the spills of concurrent ingestions.
+static NEXT_INGEST: AtomicU64 = AtomicU64::new(0);
+
+/// A batch ingestion of documents that spills its arena to temporary files
+/// once it exceeds a memory threshold, and merges the spills into a
+/// [`JDocStore`] at the end.
+///
+/// While ingesting, only the documents interned since the last spill are kept
+/// in memory, so that a one-shot job can intern a corpus much larger than the
+/// available memory, as long as the final deduplicated arena fits. Each spill
+/// is an arena serialized as JSON, together with the roots of its documents.
+///
+/// Temporary files are removed once merged, or when the ingestion is dropped.
+///
+/// ```
+/// use jinterner::SpillingIngest;
+/// use serde_json::json;
+///
+/// let mut ingest = SpillingIngest::new(std::env::temp_dir(), 1 << 10);
+/// for i in 0..100 {
+///     ingest.insert(json!({"id": i, "tags": ["a", "b"]}))?;
+/// }
+/// assert!(ingest.spills() > 0);
+///
+/// let store = ingest.finish()?;
+/// assert_eq!(store.len(), 100);
+///
+/// let mut snapshot = Vec::new();
+/// store.write_archive(&mut snapshot)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct SpillingIngest {
+    dir: PathBuf,
+    prefix: String,
+    threshold_bytes: usize,
+    /// Empty arena with the settings of the final store.
+    settings: Jinterners,
+    chunk: Jinterners,
+    roots: Vec<IValue>,
+    spills: Vec<PathBuf>,
+    len: u64,
+}
+
+impl SpillingIngest {
+    /// Creates an ingestion that spills its arena to the given directory
+    /// whenever its contents exceed the given number of bytes.
+    pub fn new(dir: impl Into<PathBuf>, threshold_bytes: usize) -> Self {
+        Self::with_interners(dir, threshold_bytes, Jinterners::default())
+    }
+
+    /// Same as [`new()`](Self::new), but interns documents with the settings
+    /// of the given arena (for example its
+    /// [`FloatPolicy`](crate::FloatPolicy)).
+    ///
+    /// Values already interned in the arena are discarded.
+    pub fn with_interners(
+        dir: impl Into<PathBuf>,
+        threshold_bytes: usize,
+        interners: Jinterners,
+    ) -> Self {
+        Self {
+            dir: dir.into(),
+            prefix: format!(
+                "jinterner-spill-{}-{}-{:016x}",
+                std::process::id(),
+                NEXT_INGEST.fetch_add(1, Ordering::Relaxed),
+                // Randomly keyed, so that names are hard to predict.
+                RandomState::new().hash_one(std::process::id())
+            ),
+            threshold_bytes,
+            chunk: chunk_like(&interners),
+            settings: interners.with_settings_of(),
+            roots: Vec::new(),
+            spills: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Returns the number of ingested documents.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Checks whether no document was ingested.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of temporary files written so far.
+    pub fn spills(&self) -> usize {
+        self.spills.len()
+    }
+
+    /// Interns the given document and returns the identifier that it will
+    /// have in the store returned by [`finish()`](Self::finish).
+    pub fn insert(&mut self, document: Value) -> io::Result<DocId> {
+        let value = self.chunk.intern_mut(document);
+        self.push(value)
+    }
+
+    /// Interns the given document and returns the identifier that it will
+    /// have in the store returned by [`finish()`](Self::finish).
+    pub fn insert_ref(&mut self, document: &Value) -> io::Result<DocId> {
+        let value = self.chunk.intern_ref_mut(document);
+        self.push(value)
+    }
+
+    fn push(&mut self, value: IValue) -> io::Result<DocId> {
+        let id = DocId(self.len);
+        self.len += 1;
+        self.roots.push(value);
+        if payload_bytes(&self.chunk) > self.threshold_bytes {
+            self.spill()?;
+        }
+        Ok(id)
+    }
+
+    /// Writes the current arena and its roots to a new temporary file, and
+    /// starts a new arena.
+    fn spill(&mut self) -> io::Result<()> {
+        let path = self
+            .dir
+            .join(format!("{}-{}.json", self.prefix, self.spills.len()));
+        // Never follow or truncate an existing file or symbolic link at this
+        // path.
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        // Register the file before writing it, so that it's removed even if
+        // writing fails.
+        self.spills.push(path.clone());
+        let mut w = BufWriter::new(file);
+        serde_json::to_writer(&mut w, &(&self.chunk, &self.roots))?;
+        w.flush()?;
+        self.chunk = chunk_like(&self.chunk);
+        self.roots.clear();
+        Ok(())
+    }
+
+    /// Merges all the ingested documents into a new [`JDocStore`], and
+    /// optimizes its arena.
+    ///
+    /// The temporary files are read back one at a time, and removed once
+    /// merged.
+    pub fn finish(mut self) -> io::Result<JDocStore> {
+        let mut store = JDocStore::with_interners(self.settings.with_settings_of());
+        for path in std::mem::take(&mut self.spills) {
+            let result = merge_spill(&mut store, &path);
+            let _ = std::fs::remove_file(&path);
+            result?;
+        }
+        merge(&mut store, &self.chunk, &self.roots);
+        store.optimize(None);
+        Ok(store)
+    }
+}
+
+impl Drop for SpillingIngest {
+    fn drop(&mut self) {
+        for path in &self.spills {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn merge_spill(store: &mut JDocStore, path: &PathBuf) -> io::Result<()> {
+    let (chunk, roots): (Jinterners, Vec<IValue>) =
+        serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    chunk
+        .validate()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    merge(store, &chunk, &roots);
+    Ok(())
+}
+
+/// Re-interns the given roots of the given arena into the store, as new
+/// documents.
+fn merge(store: &mut JDocStore, chunk: &Jinterners, roots: &[IValue]) {
+    let strings = chunk
+        .string
+        .iter()
+        .map(|s| Some(store.interners.intern_string_mut(s)))
+        .collect();
+    let mut translator = Translator::new(chunk, strings, InternInto(&mut store.interners));
+    let roots: Vec<IValue> = roots
+        .iter()
+        .map(|root| {
+            translator
+                .value(*root)
+                .expect("interned values always translate")
+        })
+        .collect();
+    for root in roots {
+        store.insert_interned(root);
+    }
+}
+
+/// Returns a new empty arena to intern a chunk of documents, with the same
+/// settings as the given one but without its watchers and
+/// [`InternHook`](crate::InternHook), which are applied once when merging the
+/// chunk into the final store.
+fn chunk_like(interners: &Jinterners) -> Jinterners {
+    Jinterners {
+        watchers: Default::default(),
+        intern_hook: Default::default(),
+        ..interners.with_settings_of()
+    }
+}
+
+/// Returns the number of bytes used by the contents of the given arena.
+fn payload_bytes(interners: &Jinterners) -> usize {
+    interners.string.bytes()
+        + interners.iarray.items() * size_of::<IValue>()
+        + interners.iobject.items() * size_of::<(InternedStrKey, IValue)>()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn spill_and_merge() {
+        let dir = std::env::temp_dir();
+        let documents: Vec<Value> = (0..200)
+            .map(|i| json!({"id": i % 50, "tags": ["a", "b"], "nested": {"x": [i % 7]}}))
+            .collect();
+
+        let mut ingest = SpillingIngest::new(&dir, 512);
+        for (i, document) in documents.iter().enumerate() {
+            assert_eq!(ingest.insert_ref(document).unwrap(), DocId(i as u64));
+        }
+        assert_eq!(ingest.len(), 200);
+        assert!(ingest.spills() > 1);
+        let spills = ingest.spills.clone();
+        assert!(spills.iter().all(|path| path.exists()));
+
+        let store = ingest.finish().unwrap();
+        assert!(spills.iter().all(|path| !path.exists()));
+        assert_eq!(store.len(), 200);
+        for (i, document) in documents.iter().enumerate() {
+            assert_eq!(store.lookup(DocId(i as u64)).as_ref(), Some(document));
+        }
+        // Documents are deduplicated across spills.
+        let mut direct = JDocStore::new();
+        for document in &documents {
+            direct.insert_ref(document);
+        }
+        direct.optimize(None);
+        assert_eq!(store.interners(), direct.interners());
+
+        // Dropping an unfinished ingestion removes its files.
+        let mut ingest = SpillingIngest::new(&dir, 0);
+        ingest.insert(json!(["spilled"])).unwrap();
+        let spills = ingest.spills.clone();
+        assert_eq!(spills.len(), 1);
+        drop(ingest);
+        assert!(!spills[0].exists());
+    }
+
+    #[test]
+    fn spill_with_hook() {
+        use crate::InternHook;
+        use std::sync::Arc;
+
+        /// A hook that isn't idempotent.
+        struct Exclaim;
+
+        impl InternHook for Exclaim {
+            fn rewrite_string(&self, value: &str) -> Option<String> {
+                Some(format!("{value}!"))
+            }
+        }
+
+        let mut interners = Jinterners::default();
+        interners.set_intern_hook(Some(Arc::new(Exclaim)));
+        let mut ingest = SpillingIngest::with_interners(std::env::temp_dir(), 64, interners);
+        for i in 0..10 {
+            ingest.insert(json!({"id": i, "name": "John"})).unwrap();
+        }
+        assert!(ingest.spills() > 0);
+        let store = ingest.finish().unwrap();
+        for i in 0..10 {
+            assert_eq!(
+                store.lookup(DocId(i)),
+                Some(json!({"id!": i, "name!": "John!"}))
+            );
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use super::{DocId, DocIter, JDocStore};
+#[cfg(feature = "retain")]
+use crate::JinternersMaintainer;
+use crate::{ChangeKind, IValue, Jinterners, Mapping, ValueChange};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Identifier of a committed version of a [`VersionedStore`].
+///
+/// Identifiers are allocated in increasing order and never reused, even after
+/// the version is dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VersionId(u64);
+
+impl VersionId {
+    /// Returns the numeric value of this identifier.
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+/// The roots of the documents of a version, shared between consecutive
+/// versions when nothing changed.
+type VersionRoots = Arc<BTreeMap<DocId, IValue>>;
+
+/// A [`JDocStore`] whose successive states can be committed as versions, which
+/// remain addressable afterwards.
+///
+/// All versions share the same append-only arena, so that a version only
+/// records the root value of each of its documents: unchanged documents, and
+/// the unchanged parts of modified documents, aren't duplicated.
+///
+/// Dropping a version releases its roots, the values only reachable from it
+/// are then reclaimed by the next [`compact()`](Self::compact).
+///
+/// ```
+/// use jinterner::VersionedStore;
+/// use serde_json::json;
+///
+/// let mut store = VersionedStore::new();
+/// let config = store.insert(json!({"retries": 3, "hosts": ["a", "b"]}));
+/// let v1 = store.commit();
+///
+/// store.update(config, json!({"retries": 5, "hosts": ["a", "b"]}));
+/// let v2 = store.commit();
+///
+/// assert_eq!(
+///     store.lookup_at(v1, config),
+///     Some(json!({"retries": 3, "hosts": ["a", "b"]}))
+/// );
+/// assert_eq!(
+///     store.lookup_at(v2, config),
+///     Some(json!({"retries": 5, "hosts": ["a", "b"]}))
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct VersionedStore {
+    current: JDocStore,
+    versions: BTreeMap<VersionId, VersionRoots>,
+    next_version: u64,
+}
+
+impl VersionedStore {
+    /// Creates an empty store, without any version.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty store interning documents into the given arena, for
+    /// example to configure it beforehand.
+    pub fn with_interners(interners: Jinterners) -> Self {
+        Self {
+            current: JDocStore::with_interners(interners),
+            versions: BTreeMap::new(),
+            next_version: 0,
+        }
+    }
+
+    /// Returns the underlying arena, shared by all the versions.
+    pub fn interners(&self) -> &Jinterners {
+        self.current.interners()
+    }
+
+    /// Returns the current, uncommitted, state of the documents.
+    ///
+    /// This gives access to the read-only operations of [`JDocStore`], such
+    /// as index queries.
+    pub fn current(&self) -> &JDocStore {
+        &self.current
+    }
+
+    /// Interns the given document in the current state, and returns its
+    /// identifier.
+    pub fn insert(&mut self, document: Value) -> DocId {
+        self.current.insert(document)
+    }
+
+    /// Interns the given document in the current state, and returns its
+    /// identifier.
+    pub fn insert_ref(&mut self, document: &Value) -> DocId {
+        self.current.insert_ref(document)
+    }
+
+    /// Replaces the given document in the current state, see
+    /// [`JDocStore::update()`].
+    pub fn update(&mut self, id: DocId, document: Value) -> Option<IValue> {
+        self.current.update(id, document)
+    }
+
+    /// Removes the given document from the current state, see
+    /// [`JDocStore::remove()`].
+    pub fn remove(&mut self, id: DocId) -> Option<IValue> {
+        self.current.remove(id)
+    }
+
+    /// Returns the interned value of the given document in the current state.
+    pub fn get(&self, id: DocId) -> Option<IValue> {
+        self.current.get(id)
+    }
+
+    /// Retrieves the given document in the current state.
+    pub fn lookup(&self, id: DocId) -> Option<Value> {
+        self.current.lookup(id)
+    }
+
+    /// Records the current state as a new version, and returns its identifier.
+    ///
+    /// If nothing changed since the last commit, the new version shares its
+    /// roots with the previous one.
+    pub fn commit(&mut self) -> VersionId {
+        let id = VersionId(self.next_version);
+        self.next_version += 1;
+        let roots = match self.versions.last_key_value() {
+            Some((_, roots)) if **roots == self.current.docs => roots.clone(),
+            _ => Arc::new(self.current.docs.clone()),
+        };
+        self.versions.insert(id, roots);
+        id
+    }
+
+    /// Iterates over the available versions, from oldest to newest.
+    pub fn versions(&self) -> impl ExactSizeIterator<Item = VersionId> + '_ {
+        self.versions.keys().copied()
+    }
+
+    /// Checks whether the given version is available.
+    pub fn contains_version(&self, version: VersionId) -> bool {
+        self.versions.contains_key(&version)
+    }
+
+    /// Returns the interned value of the given document in the given version,
+    /// or [`None`] if the version isn't available or didn't contain the
+    /// document.
+    pub fn get_at(&self, version: VersionId, id: DocId) -> Option<IValue> {
+        self.versions.get(&version)?.get(&id).copied()
+    }
+
+    /// Retrieves the given document in the given version, or [`None`] if the
+    /// version isn't available or didn't contain the document.
+    pub fn lookup_at(&self, version: VersionId, id: DocId) -> Option<Value> {
+        let value = self.get_at(version, id)?;
+        Some(self.interners().lookup(&value))
+    }
+
+    /// Iterates over the documents of the given version, in increasing
+    /// identifier order, or returns [`None`] if the version isn't available.
+    pub fn iter_at(&self, version: VersionId) -> Option<DocIter<'_>> {
+        Some(DocIter {
+            iter: self.versions.get(&version)?.iter(),
+        })
+    }
+
+    /// Lists the documents that changed between the given versions, in
+    /// increasing identifier order, or returns [`None`] if a version isn't
+    /// available.
+    ///
+    /// As documents are interned, a document is
+    /// [modified](ChangeKind::Modified) exactly when its root value
+    /// changed, which is checked without visiting its contents.
+    ///
+    /// ```
+    /// use jinterner::{ChangeKind, VersionedStore};
+    /// use serde_json::json;
+    ///
+    /// let mut store = VersionedStore::new();
+    /// let a = store.insert(json!({"retries": 3}));
+    /// let b = store.insert(json!({"hosts": ["a"]}));
+    /// let v1 = store.commit();
+    ///
+    /// store.update(a, json!({"retries": 5}));
+    /// store.update(b, json!({"hosts": ["a"]}));
+    /// let c = store.insert(json!(null));
+    /// let v2 = store.commit();
+    ///
+    /// assert_eq!(
+    ///     store.diff(v1, v2),
+    ///     Some(vec![(a, ChangeKind::Modified), (c, ChangeKind::Added)])
+    /// );
+    ///
+    /// let changes = store.diff_document(v1, v2, a).unwrap();
+    /// assert_eq!(changes[0].pointer, "/retries");
+    /// ```
+    pub fn diff(&self, from: VersionId, to: VersionId) -> Option<Vec<(DocId, ChangeKind)>> {
+        let (from, to) = (self.versions.get(&from)?, self.versions.get(&to)?);
+        let mut changes = Vec::new();
+        if Arc::ptr_eq(from, to) {
+            return Some(changes);
+        }
+        let (mut a, mut b) = (from.iter().peekable(), to.iter().peekable());
+        loop {
+            let change = match (a.peek(), b.peek()) {
+                (None, None) => break,
+                (Some((id, _)), None) => (**id, ChangeKind::Removed),
+                (None, Some((id, _))) => (**id, ChangeKind::Added),
+                (Some((i, x)), Some((j, y))) => match i.cmp(j) {
+                    Ordering::Less => (**i, ChangeKind::Removed),
+                    Ordering::Greater => (**j, ChangeKind::Added),
+                    Ordering::Equal => {
+                        let (id, modified) = (**i, x != y);
+                        a.next();
+                        b.next();
+                        if modified {
+                            changes.push((id, ChangeKind::Modified));
+                        }
+                        continue;
+                    }
+                },
+            };
+            match change.1 {
+                ChangeKind::Removed => a.next(),
+                _ => b.next(),
+            };
+            changes.push(change);
+        }
+        Some(changes)
+    }
+
+    /// Computes the changes of the given document between the given versions,
+    /// see [`Jinterners::diff()`], or returns [`None`] if a version isn't
+    /// available.
+    ///
+    /// A document that only exists in one of the versions is reported as a
+    /// single change at the root.
+    pub fn diff_document(
+        &self,
+        from: VersionId,
+        to: VersionId,
+        id: DocId,
+    ) -> Option<Vec<ValueChange>> {
+        let (from, to) = (self.versions.get(&from)?, self.versions.get(&to)?);
+        let (from, to) = (from.get(&id).copied(), to.get(&id).copied());
+        Some(match (from, to) {
+            (Some(from), Some(to)) => self.interners().diff(&from, &to),
+            (None, None) => Vec::new(),
+            _ => vec![ValueChange {
+                pointer: String::new(),
+                kind: if from.is_none() {
+                    ChangeKind::Added
+                } else {
+                    ChangeKind::Removed
+                },
+                from,
+                to,
+            }],
+        })
+    }
+
+    /// Drops the given version, and returns [`true`] if it was available.
+    ///
+    /// Values that are only reachable from this version stay in the arena
+    /// until the next [`compact()`](Self::compact).
+    pub fn drop_version(&mut self, version: VersionId) -> bool {
+        self.versions.remove(&version).is_some()
+    }
+
+    /// Drops all the versions older than the given one, and returns how many
+    /// were dropped.
+    pub fn drop_versions_before(&mut self, version: VersionId) -> usize {
+        let kept = self.versions.split_off(&version);
+        std::mem::replace(&mut self.versions, kept).len()
+    }
+
+    /// Optimizes the underlying arena, see [`Jinterners::optimize()`], and
+    /// remaps the current state and all the versions accordingly.
+    ///
+    /// Returns [`true`] if the arena was rebuilt.
+    pub fn optimize(&mut self, limit: Option<usize>) -> bool {
+        match self.interners().optimize(limit) {
+            Some((interners, mapping)) => {
+                self.replace(interners, &mapping);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops the values of the underlying arena that aren't reachable from the
+    /// current state nor from any available version, and remaps them
+    /// accordingly.
+    ///
+    /// Returns [`true`] if the arena was rebuilt.
+    #[cfg(feature = "retain")]
+    pub fn compact(&mut self) -> bool {
+        match self.interners().retain_values(self.roots()) {
+            Some((interners, mapping)) => {
+                self.replace(interners, &mapping);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Runs the given maintenance policy on the underlying arena, with the
+    /// current state and all available versions as roots, see
+    /// [`JinternersMaintainer::run()`].
+    ///
+    /// Returns [`true`] if the arena was rebuilt.
+    #[cfg(feature = "retain")]
+    pub fn maintain(&mut self, maintainer: &JinternersMaintainer) -> bool {
+        let roots: Vec<IValue> = self.roots().collect();
+        match maintainer.run(self.interners(), &roots) {
+            Some(outcome) => {
+                self.replace(outcome.jinterners, &outcome.mapping);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Iterates over the roots of the current state and of all the versions,
+    /// with duplicates.
+    #[cfg(feature = "retain")]
+    fn roots(&self) -> impl Iterator<Item = IValue> + '_ {
+        self.current
+            .docs
+            .values()
+            .chain(self.versions.values().flat_map(|roots| roots.values()))
+            .copied()
+    }
+
+    fn replace(&mut self, interners: Jinterners, mapping: &Mapping) {
+        self.current.replace(interners, mapping);
+        // Consecutive versions sharing their roots keep sharing them once
+        // remapped.
+        let mut last: Option<(VersionRoots, VersionRoots)> = None;
+        for roots in self.versions.values_mut() {
+            let remapped = match &last {
+                Some((original, remapped)) if Arc::ptr_eq(original, roots) => Arc::clone(remapped),
+                _ => Arc::new(
+                    roots
+                        .iter()
+                        .map(|(id, value)| (*id, mapping.map(*value)))
+                        .collect(),
+                ),
+            };
+            last = Some((std::mem::replace(roots, Arc::clone(&remapped)), remapped));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn versions() {
+        let mut store = VersionedStore::new();
+        let a = store.insert(json!({"z": 1}));
+        let b = store.insert(json!({"b": [1, 2]}));
+        let v0 = store.commit();
+
+        store.remove(a);
+        store.update(b, json!({"b": [1, 2, 3]}));
+        assert_eq!(store.update(a, json!(null)), None);
+        let v1 = store.commit();
+        let v2 = store.commit();
+        assert_eq!(store.versions().collect::<Vec<_>>(), [v0, v1, v2]);
+
+        assert_eq!(store.lookup_at(v0, a), Some(json!({"z": 1})));
+        assert_eq!(store.lookup_at(v1, a), None);
+        assert_eq!(store.lookup_at(v0, b), Some(json!({"b": [1, 2]})));
+        assert_eq!(store.lookup_at(v2, b), Some(json!({"b": [1, 2, 3]})));
+        assert_eq!(store.iter_at(v0).unwrap().len(), 2);
+        assert!(store.iter_at(VersionId(42)).is_none());
+
+        assert!(store.optimize(None));
+        assert_eq!(store.lookup_at(v0, a), Some(json!({"z": 1})));
+        assert_eq!(store.lookup_at(v2, b), Some(json!({"b": [1, 2, 3]})));
+        assert!(Arc::ptr_eq(&store.versions[&v1], &store.versions[&v2]));
+
+        assert_eq!(
+            store.diff(v0, v2),
+            Some(vec![(a, ChangeKind::Removed), (b, ChangeKind::Modified)])
+        );
+        assert_eq!(
+            store.diff(v2, v0),
+            Some(vec![(a, ChangeKind::Added), (b, ChangeKind::Modified)])
+        );
+        assert_eq!(store.diff(v1, v2), Some(vec![]));
+        let changes = store.diff_document(v0, v2, b).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].pointer, "/b/2");
+        assert_eq!(changes[0].kind, ChangeKind::Added);
+        assert_eq!(
+            store.diff_document(v0, v1, a).unwrap()[0].kind,
+            ChangeKind::Removed
+        );
+        assert_eq!(store.diff(v0, VersionId(42)), None);
+
+        assert_eq!(store.drop_versions_before(v1), 1);
+        assert!(!store.contains_version(v0));
+        assert!(!store.drop_version(v0));
+    }
+
+    #[cfg(feature = "retain")]
+    #[test]
+    fn compact() {
+        let mut store = VersionedStore::new();
+        let a = store.insert(json!({"name": "old"}));
+        let v0 = store.commit();
+        store.update(a, json!({"name": "new"}));
+        let v1 = store.commit();
+
+        assert!(!store.compact());
+        assert_eq!(store.lookup_at(v0, a), Some(json!({"name": "old"})));
+        assert!(store.interners().find(&json!("old")).is_some());
+
+        store.drop_version(v0);
+        assert!(store.compact());
+        assert!(store.interners().find(&json!("old")).is_none());
+        assert_eq!(store.lookup_at(v1, a), Some(json!({"name": "new"})));
+        assert_eq!(store.lookup(a), Some(json!({"name": "new"})));
+    }
+}
@@ -0,0 +1,579 @@
+//! Asynchronous snapshots and deltas of an arena, streamed over
+//! [`AsyncWrite`] and [`AsyncRead`] byte streams.
+//!
+//! A stream is made of length-prefixed messages, each being its length as a
+//! little-endian `u64` followed by its JSON serialization. A snapshot is
+//! written as a delta from the empty arena. A delta starts with a header
+//! containing its start and end [`Watermark`]s, followed by chunks of
+//! strings, then of arrays, then of objects, and ends with the [`Checksums`]
+//! of the arena at the end watermark. Each chunk holds at most about
+//! [`CHUNK_BYTES`] of serialized entries, and messages larger than
+//! [`MAX_MESSAGE_BYTES`] are rejected, which bounds the memory used to buffer
+//! the stream.
+
+use crate::increment::{CYCLE, check_array, check_capacity, check_object};
+use crate::{
+    ArenaKind, Checksums, Error, IValue, Integrity, InternedStrKey, Jinterners, Watermark,
+};
+use blazinterner::{InternedSlice, InternedStr};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Approximate maximal size of the serialized entries of a chunk.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Maximal size of a serialized message of a delta. Larger messages are
+/// rejected, as well as entries too large to fit in a message.
+const MAX_MESSAGE_BYTES: usize = 16 * CHUNK_BYTES;
+
+/// Writes a length-prefixed message.
+pub(crate) async fn write_message(
+    writer: &mut (impl AsyncWrite + Unpin),
+    message: &impl Serialize,
+) -> Result<(), Error> {
+    let message = serde_json::to_vec(message).expect("serializing a message never fails");
+    write_bytes(writer, &message).await
+}
+
+/// Writes the given serialized message, prefixed by its length.
+async fn write_bytes(writer: &mut (impl AsyncWrite + Unpin), message: &[u8]) -> Result<(), Error> {
+    writer.write_u64_le(message.len() as u64).await?;
+    writer.write_all(message).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Reads a length-prefixed message, or returns [`None`] if the stream ended.
+pub(crate) async fn read_message<T: DeserializeOwned>(
+    reader: &mut (impl AsyncRead + Unpin),
+) -> Result<Option<T>, Error> {
+    read_message_within(reader, u64::MAX).await
+}
+
+/// Reads a length-prefixed message of at most `max_len` bytes, or returns
+/// [`None`] if the stream ended.
+async fn read_message_within<T: DeserializeOwned>(
+    reader: &mut (impl AsyncRead + Unpin),
+    max_len: u64,
+) -> Result<Option<T>, Error> {
+    let len = match reader.read_u64_le().await {
+        Ok(len) => len,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if len > max_len {
+        return Err(Error::Replication {
+            reason: "a message exceeds the maximal size",
+        });
+    }
+    let mut message = Vec::new();
+    reader.take(len).read_to_end(&mut message).await?;
+    if message.len() as u64 != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(Some(serde_json::from_slice(&message)?))
+}
+
+/// Returns the length of the JSON serialization of the given value.
+fn json_len<T: ?Sized + Serialize>(value: &T) -> usize {
+    struct Counter(usize);
+
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    serde_json::to_writer(&mut counter, value).expect("serializing an entry never fails");
+    counter.0
+}
+
+/// Reads a message that must be present.
+async fn expect_message<T: DeserializeOwned>(
+    reader: &mut (impl AsyncRead + Unpin),
+) -> Result<T, Error> {
+    read_message_within(reader, MAX_MESSAGE_BYTES as u64)
+        .await?
+        .ok_or(Error::Io(io::ErrorKind::UnexpectedEof.into()))
+}
+
+/// The header of a delta.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    from: Watermark,
+    to: Watermark,
+}
+
+/// A message following the header of a delta, as read.
+#[derive(Deserialize)]
+enum Chunk {
+    Strings(Vec<Box<str>>),
+    Arrays(Vec<Box<[IValue]>>),
+    Objects(Vec<Box<[(InternedStrKey, IValue)]>>),
+    End(Checksums),
+}
+
+/// A message following the header of a delta, as written, which borrows the
+/// entries of the arena.
+#[derive(Serialize)]
+enum ChunkRef<'a> {
+    Strings(Vec<&'a str>),
+    Arrays(Vec<&'a [IValue]>),
+    Objects(Vec<&'a [(InternedStrKey, IValue)]>),
+    End(Checksums),
+}
+
+/// Writes the given entries in chunks of about [`CHUNK_BYTES`].
+async fn write_chunks<'a, T: ?Sized + Serialize + 'a>(
+    writer: &mut (impl AsyncWrite + Unpin),
+    entries: impl Iterator<Item = &'a T>,
+    chunk: impl Fn(Vec<&'a T>) -> ChunkRef<'a>,
+) -> Result<(), Error> {
+    let mut pending = Vec::new();
+    let mut bytes = 0;
+    for entry in entries {
+        // Account for the separating comma.
+        let len = json_len(entry) + 1;
+        if bytes + len > CHUNK_BYTES && !pending.is_empty() {
+            write_chunk(writer, &chunk(std::mem::take(&mut pending))).await?;
+            bytes = 0;
+        }
+        bytes += len;
+        pending.push(entry);
+    }
+    if !pending.is_empty() {
+        write_chunk(writer, &chunk(pending)).await?;
+    }
+    Ok(())
+}
+
+/// Writes a chunk, which only exceeds [`MAX_MESSAGE_BYTES`] if it consists
+/// of a single entry too large to be streamed.
+async fn write_chunk(
+    writer: &mut (impl AsyncWrite + Unpin),
+    chunk: &ChunkRef<'_>,
+) -> Result<(), Error> {
+    let message = serde_json::to_vec(chunk).expect("serializing a message never fails");
+    if message.len() > MAX_MESSAGE_BYTES {
+        return Err(Error::Replication {
+            reason: "an entry is too large to be streamed",
+        });
+    }
+    write_bytes(writer, &message).await
+}
+
+impl Jinterners {
+    /// Writes all the entries of this arena to the given stream, without
+    /// blocking the thread, and returns the [`Integrity`] of the written
+    /// snapshot.
+    ///
+    /// Entries are serialized in chunks, so that only a bounded part of the
+    /// snapshot is buffered at a time. Values may be concurrently interned
+    /// into this arena: the snapshot contains the entries below the
+    /// [`watermark()`](Self::watermark) of the arena when this is called.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.intern(json!({"name": "John"}));
+    ///
+    /// let mut bytes = Vec::new();
+    /// interners.write_snapshot_async(&mut bytes).await?;
+    /// let loaded = Jinterners::read_snapshot_async(&mut bytes.as_slice()).await?;
+    /// assert_eq!(loaded.lookup(&ivalue), json!({"name": "John"}));
+    /// # Ok::<(), jinterner::Error>(())
+    /// # }).unwrap();
+    /// ```
+    pub async fn write_snapshot_async(
+        &self,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<Integrity, Error> {
+        self.write_delta_async(Watermark::default(), writer).await
+    }
+
+    /// Reads a snapshot written by
+    /// [`write_snapshot_async()`](Self::write_snapshot_async) into a new
+    /// arena, without blocking the thread.
+    ///
+    /// Returns [`Error::Integrity`] if the entries don't match the checksums
+    /// of the snapshot, and [`Error::Validation`] if the snapshot is
+    /// inconsistent.
+    pub async fn read_snapshot_async(
+        reader: &mut (impl AsyncRead + Unpin),
+    ) -> Result<Jinterners, Error> {
+        let interners = Jinterners::default();
+        interners.read_delta_async(reader).await?;
+        interners.validate()?;
+        Ok(interners)
+    }
+
+    /// Writes the entries of this arena after the given watermark to the
+    /// given stream, without blocking the thread, and returns the
+    /// [`Integrity`] of the arena at the end of the delta.
+    ///
+    /// This is the streaming counterpart of
+    /// [`increment_since()`](Self::increment_since), which doesn't
+    /// materialize the whole increment. Returns [`Error::Replication`] if the
+    /// given watermark is beyond this arena, or if the serialization of an
+    /// entry exceeds 1 MiB, in which case the stream is left incomplete.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let primary = Jinterners::default();
+    /// let mirror = Jinterners::default();
+    /// primary.intern(json!({"name": "John"}));
+    ///
+    /// let mut bytes = Vec::new();
+    /// let integrity = primary.write_delta_async(mirror.watermark(), &mut bytes).await?;
+    /// assert_eq!(mirror.read_delta_async(&mut bytes.as_slice()).await?, integrity.watermark);
+    /// assert_eq!(mirror, primary);
+    /// # Ok::<(), jinterner::Error>(())
+    /// # }).unwrap();
+    /// ```
+    pub async fn write_delta_async(
+        &self,
+        from: Watermark,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<Integrity, Error> {
+        let to = self.watermark();
+        if from.strings > to.strings || from.arrays > to.arrays || from.objects > to.objects {
+            return Err(Error::Replication {
+                reason: "the watermark is beyond the arena",
+            });
+        }
+        let checksums = self
+            .checksums(to)
+            .expect("the watermark of an arena is within the arena");
+
+        write_message(writer, &Header { from, to }).await?;
+        write_chunks(
+            writer,
+            (from.strings..to.strings).map(|i| self.string.lookup(InternedStr::from_id(i as u32))),
+            ChunkRef::Strings,
+        )
+        .await?;
+        write_chunks(
+            writer,
+            (from.arrays..to.arrays).map(|i| self.iarray.lookup(InternedSlice::from_id(i as u32))),
+            ChunkRef::Arrays,
+        )
+        .await?;
+        write_chunks(
+            writer,
+            (from.objects..to.objects)
+                .map(|i| self.iobject.lookup(InternedSlice::from_id(i as u32))),
+            ChunkRef::Objects,
+        )
+        .await?;
+        write_message(writer, &ChunkRef::End(checksums)).await?;
+        Ok(Integrity {
+            watermark: to,
+            checksums,
+        })
+    }
+
+    /// Appends the entries of a delta written by
+    /// [`write_delta_async()`](Self::write_delta_async) to this arena, without
+    /// blocking the thread, and returns the new watermark of this arena.
+    ///
+    /// This is the streaming counterpart of
+    /// [`apply_increment()`](Self::apply_increment), with the same errors.
+    /// However, entries are appended as they are read, so errors are only
+    /// detected once the corresponding chunk is read, or once the whole
+    /// delta is read for [`Error::Integrity`] and for entries containing
+    /// themselves. The arena then needs to be resynchronized. Messages larger
+    /// than 1 MiB are rejected with [`Error::Replication`].
+    pub async fn read_delta_async(
+        &self,
+        reader: &mut (impl AsyncRead + Unpin),
+    ) -> Result<Watermark, Error> {
+        let Header { from, to } = expect_message(reader).await?;
+        if self.string.strings() != from.strings
+            || self.iarray.slices() != from.arrays
+            || self.iobject.slices() != from.objects
+        {
+            return Err(Error::Replication {
+                reason: "the arena isn't at the start of the increment",
+            });
+        }
+        if from.strings > to.strings || from.arrays > to.arrays || from.objects > to.objects {
+            return Err(Error::Replication {
+                reason: "the delta ends before it starts",
+            });
+        }
+        let diverged = || Error::Replication {
+            reason: "the arena diverged from the increment",
+        };
+        let overflow = || Error::Replication {
+            reason: "the delta contains more entries than announced",
+        };
+
+        let checksums = loop {
+            match expect_message(reader).await? {
+                Chunk::Strings(strings) => {
+                    for string in strings {
+                        if self.string.strings() >= to.strings {
+                            return Err(overflow());
+                        }
+                        let id = self.string.strings();
+                        let bytes = self.string.bytes() + string.len();
+                        check_capacity(ArenaKind::String, id + 1, bytes)?;
+                        if self.string.intern(&string).id() as usize != id {
+                            return Err(diverged());
+                        }
+                        self.string_filter.insert(&string);
+                    }
+                }
+                Chunk::Arrays(arrays) => {
+                    for array in arrays {
+                        if self.iarray.slices() >= to.arrays {
+                            return Err(overflow());
+                        }
+                        let id = self.iarray.slices();
+                        check_array(&array, to)?;
+                        let items = self.iarray.items() + array.len();
+                        check_capacity(ArenaKind::Array, id + 1, items)?;
+                        if self.iarray.intern_copy(&array).id() as usize != id {
+                            return Err(diverged());
+                        }
+                    }
+                }
+                Chunk::Objects(objects) => {
+                    for object in objects {
+                        if self.iobject.slices() >= to.objects {
+                            return Err(overflow());
+                        }
+                        let id = self.iobject.slices();
+                        check_object(&object, to)?;
+                        let items = self.iobject.items() + object.len();
+                        check_capacity(ArenaKind::Object, id + 1, items)?;
+                        if self.iobject.intern_copy(&object).id() as usize != id {
+                            return Err(diverged());
+                        }
+                    }
+                }
+                Chunk::End(checksums) => break checksums,
+            }
+        };
+        // Entries may reference entries appended after them, as in optimized
+        // arenas, so cycles are only ruled out once the whole delta is read.
+        let mut cycle = false;
+        self.find_cycles_since(from, |_, _| cycle = true);
+        if cycle {
+            return Err(CYCLE);
+        }
+        self.checksums(to)
+            .ok_or(Error::Replication {
+                reason: "the delta contains fewer entries than announced",
+            })?
+            .check(&checksums)?;
+        Ok(to)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::detail::IValueImpl;
+    use crate::integrity::Fnv;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn snapshot() {
+        let interners = Jinterners::default();
+        // Enough entries to span several chunks.
+        let values: Vec<_> = (0..2000)
+            .map(|i| json!({"id": i, "name": format!("{i:0>40}"), "tags": [i % 7, {"x": i % 3}]}))
+            .collect();
+        let ivalues = interners.intern_many_ref(&values);
+
+        let (mut writer, mut reader) = tokio::io::duplex(4096);
+        let (integrity, loaded) = tokio::join!(
+            interners.write_snapshot_async(&mut writer),
+            Jinterners::read_snapshot_async(&mut reader)
+        );
+        let loaded = loaded.unwrap();
+        assert_eq!(integrity.unwrap(), loaded.integrity());
+        assert_eq!(loaded, interners);
+        for (ivalue, value) in ivalues.iter().zip(&values) {
+            assert_eq!(loaded.lookup(ivalue), *value);
+        }
+    }
+
+    #[tokio::test]
+    async fn delta() {
+        let primary = Jinterners::default();
+        let mirror = Jinterners::default();
+        for value in [json!({"a": [1, "x"]}), json!([{"b": null}, "x", "y"])] {
+            primary.intern(value);
+            let mut bytes = Vec::new();
+            primary
+                .write_delta_async(mirror.watermark(), &mut bytes)
+                .await
+                .unwrap();
+            mirror
+                .read_delta_async(&mut bytes.as_slice())
+                .await
+                .unwrap();
+            assert_eq!(mirror, primary);
+        }
+
+        // A truncated delta is an error.
+        primary.intern(json!("z"));
+        let mut bytes = Vec::new();
+        primary
+            .write_delta_async(Watermark::default(), &mut bytes)
+            .await
+            .unwrap();
+        bytes.truncate(bytes.len() - 1);
+        assert!(matches!(
+            Jinterners::default()
+                .read_delta_async(&mut bytes.as_slice())
+                .await,
+            Err(Error::Io(_))
+        ));
+
+        // So is a delta that doesn't match its checksums.
+        let mut bytes = Vec::new();
+        write_message(
+            &mut bytes,
+            &Header {
+                from: Watermark::default(),
+                to: Watermark {
+                    strings: 1,
+                    ..Default::default()
+                },
+            },
+        )
+        .await
+        .unwrap();
+        write_message(&mut bytes, &ChunkRef::Strings(vec!["z"]))
+            .await
+            .unwrap();
+        write_message(&mut bytes, &ChunkRef::End(Checksums::default()))
+            .await
+            .unwrap();
+        assert!(matches!(
+            Jinterners::default()
+                .read_delta_async(&mut bytes.as_slice())
+                .await,
+            Err(Error::Integrity { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn optimized_delta() {
+        let interners = Jinterners::default();
+        interners.intern(json!([[1], [[2]], {"a": [3]}]));
+        interners.intern(json!({"b": [[1], {"c": {"d": [4]}}]}));
+        interners.intern(json!([[[2]], [[2]], [1]]));
+        let (primary, _) = interners.optimize(None).unwrap();
+
+        let mut bytes = Vec::new();
+        primary
+            .write_delta_async(Watermark::default(), &mut bytes)
+            .await
+            .unwrap();
+        let mirror = Jinterners::default();
+        assert_eq!(
+            mirror
+                .read_delta_async(&mut bytes.as_slice())
+                .await
+                .unwrap(),
+            primary.watermark()
+        );
+        assert_eq!(mirror, primary);
+        assert_eq!(mirror.validate(), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn invalid_delta() {
+        let write_delta = async |to: Watermark, chunks: &[ChunkRef<'_>]| {
+            let mut bytes = Vec::new();
+            let from = Watermark::default();
+            write_message(&mut bytes, &Header { from, to })
+                .await
+                .unwrap();
+            for chunk in chunks {
+                write_message(&mut bytes, chunk).await.unwrap();
+            }
+            bytes
+        };
+
+        // An array and an object containing each other, with matching
+        // checksums.
+        let array = [IValue(IValueImpl::Object(InternedSlice::from_id(0)))];
+        let key = InternedStrKey(InternedStr::from_id(0));
+        let object = [(key, IValue(IValueImpl::Array(InternedSlice::from_id(0))))];
+        let empty = Checksums::default();
+        let mut fnv = [empty.strings, empty.arrays, empty.objects].map(Fnv::resume);
+        fnv[0].write_str("k");
+        fnv[1].write_array(&array);
+        fnv[2].write_object(&object);
+        let [strings, arrays, objects] = fnv.map(|fnv| fnv.finish());
+        let to = Watermark {
+            strings: 1,
+            arrays: 1,
+            objects: 1,
+        };
+        let bytes = write_delta(
+            to,
+            &[
+                ChunkRef::Strings(vec!["k"]),
+                ChunkRef::Arrays(vec![&array]),
+                ChunkRef::Objects(vec![&object]),
+                ChunkRef::End(Checksums {
+                    strings,
+                    arrays,
+                    objects,
+                }),
+            ],
+        )
+        .await;
+        assert!(matches!(
+            Jinterners::default()
+                .read_delta_async(&mut bytes.as_slice())
+                .await,
+            Err(Error::Replication {
+                reason: "an entry contains itself"
+            })
+        ));
+
+        // A message announcing more than the maximal size is rejected before
+        // being buffered.
+        let mut bytes = write_delta(to, &[]).await;
+        bytes.extend_from_slice(&(MAX_MESSAGE_BYTES as u64 + 1).to_le_bytes());
+        assert!(matches!(
+            Jinterners::default()
+                .read_delta_async(&mut bytes.as_slice())
+                .await,
+            Err(Error::Replication {
+                reason: "a message exceeds the maximal size"
+            })
+        ));
+
+        // An entry too large to fit in a message isn't written.
+        let interners = Jinterners::default();
+        interners.intern(json!("x".repeat(MAX_MESSAGE_BYTES)));
+        assert!(matches!(
+            interners.write_snapshot_async(&mut Vec::new()).await,
+            Err(Error::Replication {
+                reason: "an entry is too large to be streamed"
+            })
+        ));
+    }
+}
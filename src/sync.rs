@@ -0,0 +1,211 @@
+use crate::{Checksums, Error, Increment, Integrity, Jinterners, Watermark};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The state of a mirror of an arena, sent when it (re)connects to the arena
+/// that it mirrors, created by [`sync_request()`](Jinterners::sync_request).
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SyncRequest {
+    /// The watermark of the mirror.
+    pub watermark: Watermark,
+    /// The checksums of the mirror at its watermark.
+    pub checksums: Checksums,
+}
+
+/// The entries to send to a mirror to bring it up to date, created by
+/// [`sync_response()`](Jinterners::sync_response).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SyncResponse {
+    /// The entries that the mirror is missing.
+    Increment(Increment),
+    /// All the entries of the arena, which replace the entries of the mirror
+    /// because it diverged.
+    Snapshot(Increment),
+}
+
+impl SyncResponse {
+    /// Returns the increment of this response.
+    pub fn increment(&self) -> &Increment {
+        match self {
+            SyncResponse::Increment(increment) | SyncResponse::Snapshot(increment) => increment,
+        }
+    }
+}
+
+impl Jinterners {
+    /// Returns the state of this arena to send to the arena that it mirrors,
+    /// which answers with [`sync_response()`](Self::sync_response).
+    pub fn sync_request(&self) -> SyncRequest {
+        let Integrity {
+            watermark,
+            checksums,
+        } = self.integrity();
+        SyncRequest {
+            watermark,
+            checksums,
+        }
+    }
+
+    /// Returns the entries that the mirror that sent the given request is
+    /// missing, or all the entries of this arena if the mirror diverged from
+    /// this arena, for example because this arena was rebuilt.
+    ///
+    /// This allows a mirror to reconnect without transferring the whole arena
+    /// every time.
+    ///
+    /// ```
+    /// use jinterner::{Jinterners, SyncResponse};
+    /// use serde_json::json;
+    ///
+    /// let primary = Jinterners::default();
+    /// let mut mirror = Jinterners::default();
+    /// primary.intern(json!({"name": "John"}));
+    /// let response = primary.sync_response(&mirror.sync_request());
+    /// mirror.apply_sync(&response)?;
+    ///
+    /// // Only the new entries are sent.
+    /// let ivalue = primary.intern(json!({"name": "Jane"}));
+    /// let response = primary.sync_response(&mirror.sync_request());
+    /// assert!(matches!(response, SyncResponse::Increment(_)));
+    /// assert_eq!(response.increment().strings(), 1);
+    /// mirror.apply_sync(&response)?;
+    /// assert_eq!(mirror.lookup(&ivalue), json!({"name": "Jane"}));
+    /// # Ok::<(), jinterner::Error>(())
+    /// ```
+    pub fn sync_response(&self, request: &SyncRequest) -> SyncResponse {
+        if self.checksums(request.watermark) == Some(request.checksums)
+            && let Ok(increment) = self.increment_since(request.watermark)
+        {
+            return SyncResponse::Increment(increment);
+        }
+        SyncResponse::Snapshot(
+            self.increment_since(Watermark::default())
+                .expect("the empty watermark is within any arena"),
+        )
+    }
+
+    /// Applies the given response to this mirror, returning the new watermark
+    /// of this arena.
+    ///
+    /// A [snapshot](SyncResponse::Snapshot) replaces all the entries of this
+    /// arena. Values interned into this arena beforehand must then be
+    /// discarded. See [`apply_increment()`](Self::apply_increment) for the
+    /// errors, which leave this arena unmodified, including for snapshots.
+    pub fn apply_sync(&mut self, response: &SyncResponse) -> Result<Watermark, Error> {
+        match response {
+            SyncResponse::Increment(increment) => self.apply_increment(increment),
+            SyncResponse::Snapshot(increment) => {
+                // Entries are appended to empty arenas, which only replace the
+                // arenas of this mirror once the snapshot is fully applied.
+                let snapshot = Jinterners::default();
+                let watermark = snapshot.apply_increment(increment)?;
+                let Jinterners {
+                    string,
+                    iarray,
+                    iobject,
+                    integrity,
+                    ..
+                } = snapshot;
+                // The string filter may keep the bits of the removed strings,
+                // which only causes false positives.
+                string.iter().for_each(|s| self.string_filter.insert(s));
+                self.string = string;
+                self.iarray = iarray;
+                self.iobject = iobject;
+                self.integrity = integrity;
+                self.lookup_cache = self.lookup_cache.emptied();
+                Ok(watermark)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sync() {
+        let primary = Jinterners::default();
+        let mut mirror = Jinterners::default();
+        assert_eq!(
+            primary.checksums(Watermark::default()),
+            mirror.checksums(Watermark::default())
+        );
+
+        let a = primary.intern(json!({"a": [1, "x"]}));
+        let response = primary.sync_response(&mirror.sync_request());
+        assert!(matches!(response, SyncResponse::Increment(_)));
+        mirror.apply_sync(&response).unwrap();
+        assert_eq!(mirror.sync_request(), primary.sync_request());
+        assert!(
+            primary
+                .sync_response(&mirror.sync_request())
+                .increment()
+                .is_empty()
+        );
+
+        // A mirror that diverged receives a snapshot.
+        mirror.intern(json!("y"));
+        let b = primary.intern(json!(["x", {"b": null}]));
+        let response = primary.sync_response(&mirror.sync_request());
+        assert!(matches!(response, SyncResponse::Snapshot(_)));
+        assert_eq!(mirror.apply_sync(&response).unwrap(), primary.watermark());
+        assert_eq!(mirror, primary);
+        assert_eq!(mirror.lookup(&a), json!({"a": [1, "x"]}));
+        assert_eq!(mirror.lookup(&b), json!(["x", {"b": null}]));
+        assert!(mirror.find(&json!("y")).is_none());
+
+        // An invalid snapshot leaves the mirror untouched.
+        let invalid = SyncResponse::Snapshot(primary.increment_since(primary.watermark()).unwrap());
+        assert!(matches!(
+            mirror.apply_sync(&invalid),
+            Err(Error::Replication { .. })
+        ));
+        assert_eq!(mirror, primary);
+        assert_eq!(mirror.lookup(&b), json!(["x", {"b": null}]));
+
+        // So does a mirror ahead of the arena.
+        let response = Jinterners::default().sync_response(&mirror.sync_request());
+        assert!(matches!(response, SyncResponse::Snapshot(_)));
+        mirror.apply_sync(&response).unwrap();
+        assert_eq!(mirror, Jinterners::default());
+        assert_eq!(
+            primary.checksums(Watermark {
+                strings: 100,
+                ..Default::default()
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn sync_optimized() {
+        let interners = Jinterners::default();
+        interners.intern(json!([[1], [[2]], {"a": [3]}]));
+        interners.intern(json!({"b": [[1], {"c": {"d": [4]}}]}));
+        interners.intern(json!([[[2]], [[2]], [1]]));
+        let (primary, mapping) = interners.optimize(None).unwrap();
+
+        // The optimized arena references later entries, and is sent to a
+        // diverged mirror as a snapshot.
+        let mut mirror = Jinterners::default();
+        mirror.intern(json!("y"));
+        let response = primary.sync_response(&mirror.sync_request());
+        assert!(matches!(response, SyncResponse::Snapshot(_)));
+        assert_eq!(mirror.apply_sync(&response).unwrap(), primary.watermark());
+        assert_eq!(mirror, primary);
+        let ivalue = mapping.map(
+            interners
+                .find(&json!({"b": [[1], {"c": {"d": [4]}}]}))
+                .unwrap(),
+        );
+        assert_eq!(
+            mirror.lookup(&ivalue),
+            json!({"b": [[1], {"c": {"d": [4]}}]})
+        );
+    }
+}
@@ -0,0 +1,532 @@
+//! Preservation of the concrete syntax of JSON documents.
+
+use crate::pointer::push_token;
+use crate::{Error, IValue, Jinterners, ValueRef};
+use serde::de::Error as _;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+/// Maximum nesting depth accepted by
+/// [`parse_preserving()`](Jinterners::parse_preserving), the same as
+/// [`serde_json`]'s.
+const MAX_DEPTH: usize = 128;
+
+/// The formatting of a JSON document, recorded alongside its interned value.
+///
+/// Interned objects are stored with their keys sorted by interned id, and
+/// without any whitespace or comments, so that equal values are deduplicated
+/// regardless of how they were written. A layout is a side table recording
+/// the rest of the source text of one document: whitespace, comments,
+/// trailing commas, the order of object keys and the original spelling of
+/// strings and numbers. [`render()`](Self::render) re-emits the document from
+/// its interned value in its original form.
+///
+/// The layout refers to values by their JSON pointer in the document rather
+/// than by their interned ids, so it remains valid after rebuilding the arena
+/// and applies to modified versions of the document: unchanged parts are
+/// rendered as in the source, and new parts as compact JSON.
+///
+/// This struct is created by the
+/// [`parse_preserving()`](Jinterners::parse_preserving) method on
+/// [`Jinterners`].
+///
+/// ```
+/// use jinterner::Jinterners;
+///
+/// let interners = Jinterners::default();
+/// let text = r#"{
+///     // Listening port.
+///     "port": 8080,
+///     "hosts": ["a", "b",], /* Trailing comma. */
+/// }"#;
+/// let (config, layout) = interners.parse_preserving(text)?;
+/// assert_eq!(layout.render(&interners, config), text);
+///
+/// // Edit the document and render it again.
+/// let mut value = interners.lookup(&config);
+/// value["port"] = 9090.into();
+/// let edited = interners.intern(value);
+/// assert_eq!(
+///     layout.render(&interners, edited),
+///     r#"{
+///     // Listening port.
+///     "port": 9090,
+///     "hosts": ["a", "b",], /* Trailing comma. */
+/// }"#
+/// );
+/// # Ok::<(), jinterner::Error>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceLayout {
+    nodes: HashMap<String, NodeLayout>,
+}
+
+/// The formatting of a single value of a document.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct NodeLayout {
+    /// Whitespace and comments before the value, or before the key of an
+    /// object member.
+    before: String,
+    /// Whitespace and comments after the value.
+    after: String,
+    /// Source text of the key of an object member, and of the separator up to
+    /// the value.
+    key: Option<(String, String)>,
+    /// Source text of a scalar, and the value that it was parsed to.
+    scalar: Option<(String, Value)>,
+    /// Whitespace and comments before the closing bracket of an array or
+    /// object.
+    close: String,
+    /// Whether the last element of an array or object is followed by a comma.
+    trailing_comma: bool,
+    /// Keys of an object, in source order.
+    keys: Vec<String>,
+}
+
+impl Jinterners {
+    /// Parses the given JSON text and interns it into this arena, together
+    /// with a [`SourceLayout`] recording its formatting.
+    ///
+    /// Besides standard JSON, comments (`//` and `/* */`) and trailing commas
+    /// are accepted, i.e. the JSONC dialect used by many configuration files.
+    /// As with [`parse()`](Self::parse), duplicate keys keep the last value.
+    pub fn parse_preserving(&self, text: &str) -> Result<(IValue, SourceLayout), Error> {
+        let mut parser = Parser {
+            text,
+            pos: 0,
+            nodes: HashMap::new(),
+        };
+        let before = parser.trivia()?;
+        let value = parser.value(&mut String::new(), 0)?;
+        let after = parser.trivia()?;
+        if parser.pos != text.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        let root = parser.node("");
+        root.before = before;
+        root.after = after;
+        let layout = SourceLayout {
+            nodes: parser.nodes,
+        };
+        Ok((self.intern(value), layout))
+    }
+}
+
+impl SourceLayout {
+    /// Writes the given interned value as JSON text, with the formatting
+    /// recorded in this layout.
+    pub fn render(&self, interners: &Jinterners, value: IValue) -> String {
+        let mut output = String::new();
+        let root = self.nodes.get("");
+        output.push_str(root.map_or("", |root| &root.before));
+        // Writing to a String cannot fail.
+        self.write_value(interners, value, &mut String::new(), &mut output)
+            .unwrap();
+        output.push_str(root.map_or("", |root| &root.after));
+        output
+    }
+
+    fn write_value(
+        &self,
+        interners: &Jinterners,
+        value: IValue,
+        pointer: &mut String,
+        w: &mut impl Write,
+    ) -> fmt::Result {
+        let node = self.nodes.get(pointer.as_str());
+        match (interners.lookup_ref(&value), node) {
+            (ValueRef::Array(array), Some(node)) => {
+                w.write_char('[')?;
+                for (i, item) in array.iter().enumerate() {
+                    let last = i + 1 == array.len();
+                    self.write_member(interners, &i.to_string(), None, *item, pointer, w)?;
+                    if !last || node.trailing_comma {
+                        w.write_char(',')?;
+                    }
+                }
+                w.write_str(&node.close)?;
+                w.write_char(']')
+            }
+            (ValueRef::Object(map), Some(node)) => {
+                // Keys of the source come first in source order, followed by
+                // new keys in arena order.
+                let order: HashMap<&str, usize> = node
+                    .keys
+                    .iter()
+                    .enumerate()
+                    .map(|(i, k)| (k.as_str(), i))
+                    .collect();
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by_key(|(k, _)| order.get(k).copied().unwrap_or(usize::MAX));
+
+                w.write_char('{')?;
+                for (i, (k, v)) in entries.iter().enumerate() {
+                    let last = i + 1 == entries.len();
+                    self.write_member(interners, k, Some(*k), **v, pointer, w)?;
+                    if !last || node.trailing_comma {
+                        w.write_char(',')?;
+                    }
+                }
+                w.write_str(&node.close)?;
+                w.write_char('}')
+            }
+            _ => match node.and_then(|node| node.scalar.as_ref()) {
+                Some((raw, parsed)) if interners.lookup(&value) == *parsed => w.write_str(raw),
+                _ => value.write_json(interners, w),
+            },
+        }
+    }
+
+    /// Writes an element of an array or object, with its surrounding
+    /// whitespace and comments.
+    fn write_member(
+        &self,
+        interners: &Jinterners,
+        token: &str,
+        key: Option<&str>,
+        value: IValue,
+        pointer: &mut String,
+        w: &mut impl Write,
+    ) -> fmt::Result {
+        let len = pointer.len();
+        push_token(pointer, token);
+        let node = self.nodes.get(pointer.as_str());
+        w.write_str(node.map_or("", |node| &node.before))?;
+        if let Some(key) = key {
+            match node.and_then(|node| node.key.as_ref()) {
+                Some((raw, separator)) => {
+                    w.write_str(raw)?;
+                    w.write_str(separator)?;
+                }
+                None => {
+                    crate::detail::write_json_str(w, key)?;
+                    w.write_char(':')?;
+                }
+            }
+        }
+        self.write_value(interners, value, pointer, w)?;
+        w.write_str(node.map_or("", |node| &node.after))?;
+        pointer.truncate(len);
+        Ok(())
+    }
+}
+
+/// A parser of JSON text that records the formatting of each value.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    nodes: HashMap<String, NodeLayout>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn node(&mut self, pointer: &str) -> &mut NodeLayout {
+        self.nodes.entry(pointer.to_owned()).or_default()
+    }
+
+    fn error(&self, reason: &str) -> Error {
+        self.error_at(self.pos, reason)
+    }
+
+    fn error_at(&self, pos: usize, reason: &str) -> Error {
+        let prefix = &self.text[..pos];
+        let line = prefix.matches('\n').count() + 1;
+        let column = prefix[prefix.rfind('\n').map_or(0, |i| i + 1)..]
+            .chars()
+            .count()
+            + 1;
+        Error::Parse(serde_json::Error::custom(format!(
+            "{reason} at line {line} column {column}"
+        )))
+    }
+
+    /// Consumes whitespace and comments.
+    fn trivia(&mut self) -> Result<String, Error> {
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.pos += 1,
+                Some(b'/') => {
+                    let rest = &self.text[self.pos..];
+                    if rest.starts_with("//") {
+                        self.pos += rest.find('\n').unwrap_or(rest.len());
+                    } else if let Some(comment) = rest.strip_prefix("/*") {
+                        match comment.find("*/") {
+                            Some(end) => self.pos += end + 4,
+                            None => return Err(self.error("unterminated comment")),
+                        }
+                    } else {
+                        return Err(self.error("invalid comment"));
+                    }
+                }
+                _ => return Ok(self.text[start..self.pos].to_owned()),
+            }
+        }
+    }
+
+    fn value(&mut self, pointer: &mut String, depth: usize) -> Result<Value, Error> {
+        match self.peek() {
+            Some(b'[' | b'{') if depth == MAX_DEPTH => Err(Error::DepthLimit { limit: MAX_DEPTH }),
+            Some(b'[') => self.array(pointer, depth + 1),
+            Some(b'{') => self.object(pointer, depth + 1),
+            _ => {
+                let start = self.pos;
+                let token = self.scalar_token()?;
+                let value: Value = serde_json::from_str(token)
+                    .map_err(|_| self.error_at(start, "invalid value"))?;
+                self.node(pointer).scalar = Some((token.to_owned(), value.clone()));
+                Ok(value)
+            }
+        }
+    }
+
+    /// Consumes a string, number or literal token.
+    fn scalar_token(&mut self) -> Result<&'a str, Error> {
+        let start = self.pos;
+        let bytes = self.text.as_bytes();
+        if self.peek() == Some(b'"') {
+            let mut i = start + 1;
+            loop {
+                match bytes.get(i) {
+                    Some(b'"') => break,
+                    Some(b'\\') => i += 2,
+                    Some(_) => i += 1,
+                    None => return Err(self.error("unterminated string")),
+                }
+            }
+            self.pos = i + 1;
+        } else {
+            while let Some(c) = self.peek() {
+                if c.is_ascii_whitespace() || b",:[]{}\"/".contains(&c) {
+                    break;
+                }
+                self.pos += 1;
+            }
+            if self.pos == start {
+                return Err(self.error("expected value"));
+            }
+        }
+        Ok(&self.text[start..self.pos])
+    }
+
+    fn array(&mut self, pointer: &mut String, depth: usize) -> Result<Value, Error> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        let mut trailing_comma = false;
+        let close = loop {
+            let before = self.trivia()?;
+            if self.peek() == Some(b']') {
+                break before;
+            }
+            let len = pointer.len();
+            push_token(pointer, &items.len().to_string());
+            items.push(self.value(pointer, depth)?);
+            let after = self.trivia()?;
+            let node = self.node(pointer);
+            node.before = before;
+            node.after = after;
+            pointer.truncate(len);
+
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    trailing_comma = true;
+                }
+                Some(b']') => {
+                    trailing_comma = false;
+                    break String::new();
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        };
+        self.pos += 1;
+        let node = self.node(pointer);
+        node.close = close;
+        node.trailing_comma = trailing_comma;
+        Ok(Value::Array(items))
+    }
+
+    fn object(&mut self, pointer: &mut String, depth: usize) -> Result<Value, Error> {
+        self.pos += 1;
+        let mut map = Map::new();
+        let mut keys = Vec::new();
+        let mut trailing_comma = false;
+        let close = loop {
+            let before = self.trivia()?;
+            match self.peek() {
+                Some(b'}') => break before,
+                Some(b'"') => (),
+                _ => return Err(self.error("expected key")),
+            }
+            let start = self.pos;
+            let raw_key = self.scalar_token()?;
+            let key: String =
+                serde_json::from_str(raw_key).map_err(|_| self.error_at(start, "invalid key"))?;
+            let separator_start = self.pos;
+            self.trivia()?;
+            if self.peek() != Some(b':') {
+                return Err(self.error("expected `:`"));
+            }
+            self.pos += 1;
+            self.trivia()?;
+            let separator = self.text[separator_start..self.pos].to_owned();
+
+            let len = pointer.len();
+            push_token(pointer, &key);
+            if map.contains_key(&key) {
+                // Only the last duplicate is kept.
+                keys.retain(|k| *k != key);
+                let prefix = format!("{pointer}/");
+                self.nodes
+                    .retain(|p, _| *p != *pointer && !p.starts_with(&prefix));
+            }
+            let value = self.value(pointer, depth)?;
+            let after = self.trivia()?;
+            let node = self.node(pointer);
+            node.before = before;
+            node.after = after;
+            node.key = Some((raw_key.to_owned(), separator));
+            pointer.truncate(len);
+            keys.push(key.clone());
+            map.insert(key, value);
+
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    trailing_comma = true;
+                }
+                Some(b'}') => {
+                    trailing_comma = false;
+                    break String::new();
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        };
+        self.pos += 1;
+        let node = self.node(pointer);
+        node.close = close;
+        node.trailing_comma = trailing_comma;
+        node.keys = keys;
+        Ok(Value::Object(map))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn round_trip() {
+        let interners = Jinterners::default();
+        let texts = [
+            "null",
+            "  42  ",
+            r#"{"b": 1, "a": [1.0, 1e3, -0, "é\n"]}"#,
+            "[\n  1,\n  // Comment.\n  2,\n]\n",
+            r#"{ /* empty */ }"#,
+            "[ ]",
+            r#"// Header.
+{
+  "z": {"y": [ {}, [], "x" ], /* inline */ "w": true},
+  "a"  :  false , // After.
+  "m": "é",
+}
+// Footer."#,
+        ];
+        for text in texts {
+            let (value, layout) = interners.parse_preserving(text).unwrap();
+            assert_eq!(layout.render(&interners, value), text);
+        }
+
+        let (value, _) = interners
+            .parse_preserving(r#"{"b": 1, "a": [1.0, 1e3, "é"]}"#)
+            .unwrap();
+        assert_eq!(
+            interners.lookup(&value),
+            json!({"a": [1.0, 1000.0, "é"], "b": 1})
+        );
+    }
+
+    #[test]
+    fn edit() {
+        let interners = Jinterners::default();
+        let text = "{\n  \"b\": [1, 2], // B.\n  \"a\": 1.0,\n  \"d\": null,\n}";
+        let (value, layout) = interners.parse_preserving(text).unwrap();
+
+        let mut edited = interners.lookup(&value);
+        edited["b"].as_array_mut().unwrap().push(json!(3));
+        edited["c"] = json!({"new": true});
+        edited.as_object_mut().unwrap().remove("d");
+        let edited = interners.intern(edited);
+        assert_eq!(
+            layout.render(&interners, edited),
+            "{\n  \"b\": [1, 2,3], // B.\n  \"a\": 1.0,\"c\":{\"new\":true},\n}"
+        );
+
+        // The layout doesn't depend on interned ids.
+        let (optimized, mapping) = interners.optimize(None).unwrap();
+        assert_eq!(layout.render(&optimized, mapping.map(value)), text);
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let interners = Jinterners::default();
+        let (value, layout) = interners
+            .parse_preserving(r#"{"a": {"x": 1}, "b": 2, "a": [3]}"#)
+            .unwrap();
+        assert_eq!(interners.lookup(&value), json!({"a": [3], "b": 2}));
+        assert_eq!(layout.render(&interners, value), r#"{ "b": 2, "a": [3]}"#);
+    }
+
+    #[test]
+    fn errors() {
+        let interners = Jinterners::default();
+        let error = |text| interners.parse_preserving(text).unwrap_err().to_string();
+        assert_eq!(error(""), "parse error: expected value at line 1 column 1");
+        assert_eq!(
+            error("[1,,]"),
+            "parse error: expected value at line 1 column 4"
+        );
+        assert_eq!(
+            error("[1 2]"),
+            "parse error: expected `,` or `]` at line 1 column 4"
+        );
+        assert_eq!(
+            error("{\n  a: 1}"),
+            "parse error: expected key at line 2 column 3"
+        );
+        assert_eq!(
+            error(r#"{"a" 1}"#),
+            "parse error: expected `:` at line 1 column 6"
+        );
+        assert_eq!(
+            error("[tru]"),
+            "parse error: invalid value at line 1 column 2"
+        );
+        assert_eq!(
+            error("/* open"),
+            "parse error: unterminated comment at line 1 column 1"
+        );
+        assert_eq!(
+            error("\"open"),
+            "parse error: unterminated string at line 1 column 1"
+        );
+        assert_eq!(
+            error("1 / 2"),
+            "parse error: invalid comment at line 1 column 3"
+        );
+        assert_eq!(
+            error("[] []"),
+            "parse error: trailing characters at line 1 column 4"
+        );
+        assert_eq!(
+            error(&"[".repeat(200)),
+            "nesting depth exceeds the limit of 128"
+        );
+    }
+}
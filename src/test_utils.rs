@@ -0,0 +1,159 @@
+//! Fixtures and assertions to test code embedding a [`Jinterners`] arena.
+//!
+//! The corpus generators are deterministic for a given seed, and produce
+//! values with the kind of repetition that interning is meant for.
+//!
+//! ```
+//! use jinterner::Jinterners;
+//! use jinterner::test_utils::{assert_mapping_consistent, assert_round_trip, telemetry_corpus};
+//!
+//! let interners = Jinterners::default();
+//! let values = telemetry_corpus(100, 42);
+//! let roots = assert_round_trip(&interners, &values);
+//!
+//! let (optimized, mapping) = interners.optimize(None).unwrap();
+//! assert_mapping_consistent(&interners, &optimized, &mapping, &roots);
+//! ```
+
+use crate::rng::Rng;
+use crate::{IValue, Jinterners, Mapping};
+use serde_json::{Value, json};
+
+/// Generates telemetry-like events: flat records sharing the same keys, with
+/// a few distinct values for most fields.
+pub fn telemetry_corpus(count: usize, seed: u64) -> Vec<Value> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .map(|i| {
+            let level = rng.choose(&["debug", "info", "info", "info", "warn", "error"]);
+            let service = rng.choose(&["api", "auth", "billing", "search"]);
+            let status = [200, 200, 201, 404, 500][rng.below(5) as usize];
+            let mut event = json!({
+                "timestamp": 1_700_000_000_000u64 + i as u64 * 250 + rng.below(250),
+                "level": level,
+                "service": service,
+                "host": format!("{service}-{}", rng.below(4)),
+                "message": format!("request handled in {} ms", rng.below(500)),
+                "attributes": {
+                    "http.method": rng.choose(&["GET", "GET", "POST", "DELETE"]),
+                    "http.status": status,
+                    "region": rng.choose(&["eu-west-1", "us-east-1"]),
+                },
+            });
+            if level == "error" {
+                event["error"] = json!({
+                    "kind": rng.choose(&["timeout", "unavailable"]),
+                    "retryable": rng.below(2) == 0,
+                });
+            }
+            event
+        })
+        .collect()
+}
+
+/// Generates configuration-like documents: nested objects that are mostly
+/// identical, with a few overridden settings.
+pub fn config_corpus(count: usize, seed: u64) -> Vec<Value> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .map(|i| {
+            let port = [8080, 8080, 8443][rng.below(3) as usize];
+            let pool_size = [10, 10, 20, 50][rng.below(4) as usize];
+            let features = &["search", "export", "sso", "audit"][..rng.below(5) as usize];
+            json!({
+                "name": format!("instance-{i}"),
+                "version": 3,
+                "server": {
+                    "listen": "0.0.0.0",
+                    "port": port,
+                    "tls": {"enabled": rng.below(2) == 0, "min_version": "1.2"},
+                },
+                "database": {
+                    "url": "postgres://db.internal:5432/app",
+                    "pool": {"min": 1, "max": pool_size},
+                    "timeout_ms": 5000,
+                },
+                "features": features,
+                "ratio": 0.25 * rng.below(5) as f64,
+            })
+        })
+        .collect()
+}
+
+/// Generates a value of the given nesting depth, alternating arrays and
+/// objects.
+pub fn nested_value(depth: usize) -> Value {
+    (0..depth).fold(json!("leaf"), |value, i| {
+        if i % 2 == 0 {
+            json!([i, value])
+        } else {
+            json!({"depth": i, "child": value})
+        }
+    })
+}
+
+/// Interns the given values and asserts that they are retrieved unchanged, and
+/// that the arena passes [`validate()`](Jinterners::validate).
+///
+/// Returns the interned values.
+#[track_caller]
+pub fn assert_round_trip(interners: &Jinterners, values: &[Value]) -> Vec<IValue> {
+    let roots = interners.intern_many_ref(values);
+    for (i, (value, root)) in values.iter().zip(&roots).enumerate() {
+        assert_eq!(interners.lookup(root), *value, "value {i} changed");
+        assert_eq!(
+            interners.intern_ref(value),
+            *root,
+            "value {i} not deduplicated"
+        );
+    }
+    if let Err(report) = interners.validate() {
+        panic!("invalid arena: {report}");
+    }
+    roots
+}
+
+/// Asserts that the given values rooted in the `source` arena represent the
+/// same JSON values once converted by the `mapping` into the `destination`
+/// arena, for example after [`optimize()`](Jinterners::optimize).
+#[track_caller]
+pub fn assert_mapping_consistent(
+    source: &Jinterners,
+    destination: &Jinterners,
+    mapping: &Mapping,
+    roots: &[IValue],
+) {
+    if let Err(report) = destination.validate() {
+        panic!("invalid destination arena: {report}");
+    }
+    for (i, root) in roots.iter().enumerate() {
+        assert!(
+            root.eq_content(source, &mapping.map(*root), destination),
+            "value {i} isn't consistent across the mapping"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn corpora() {
+        assert_eq!(telemetry_corpus(10, 1), telemetry_corpus(10, 1));
+        assert_ne!(telemetry_corpus(10, 1), telemetry_corpus(10, 2));
+
+        let interners = Jinterners::default();
+        let mut values = telemetry_corpus(200, 1);
+        values.extend(config_corpus(50, 1));
+        values.extend((0..20).map(nested_value));
+        let roots = assert_round_trip(&interners, &values);
+
+        // Repeated keys and values are deduplicated.
+        let report = interners.storage_report();
+        assert!(report.strings.entries < 500, "{}", report.strings.entries);
+
+        let (optimized, mapping) = interners.optimize(None).unwrap();
+        assert_mapping_consistent(&interners, &optimized, &mapping, &roots);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::detail::IValueImpl;
+use crate::{IValue, InternedStrKey, Jinterners};
+use blazinterner::{InternedSlice, InternedStr};
+
+type InternedObject = InternedSlice<(InternedStrKey, IValue)>;
+
+/// The destination of the arrays and objects translated by a [`Translator`].
+pub(crate) trait Target {
+    /// Returns the given translated array in the destination arena, or
+    /// [`None`] if it isn't there.
+    fn array(&mut self, items: &[IValue]) -> Option<InternedSlice<IValue>>;
+
+    /// Returns the given translated object entries, sorted by key, in the
+    /// destination arena, or [`None`] if they aren't there.
+    fn object(&mut self, entries: &[(InternedStrKey, IValue)]) -> Option<InternedObject>;
+}
+
+/// Finds the translated entries in an arena, without modifying it.
+pub(crate) struct FindIn<'a>(pub(crate) &'a Jinterners);
+
+impl Target for FindIn<'_> {
+    fn array(&mut self, items: &[IValue]) -> Option<InternedSlice<IValue>> {
+        self.0.iarray.find(items)
+    }
+
+    fn object(&mut self, entries: &[(InternedStrKey, IValue)]) -> Option<InternedObject> {
+        self.0.iobject.find(entries)
+    }
+}
+
+/// Copies the translated entries into an arena, bypassing its hooks and
+/// metrics, which is suitable for arenas only populated with copies of
+/// existing values.
+#[cfg(feature = "rayon")]
+pub(crate) struct CopyInto<'a>(pub(crate) &'a Jinterners);
+
+#[cfg(feature = "rayon")]
+impl Target for CopyInto<'_> {
+    fn array(&mut self, items: &[IValue]) -> Option<InternedSlice<IValue>> {
+        Some(self.0.iarray.intern_copy(items))
+    }
+
+    fn object(&mut self, entries: &[(InternedStrKey, IValue)]) -> Option<InternedObject> {
+        Some(self.0.iobject.intern_copy(entries))
+    }
+}
+
+/// Interns the translated entries into an arena, as any new value.
+#[cfg(feature = "serde")]
+pub(crate) struct InternInto<'a>(pub(crate) &'a mut Jinterners);
+
+#[cfg(feature = "serde")]
+impl Target for InternInto<'_> {
+    fn array(&mut self, items: &[IValue]) -> Option<InternedSlice<IValue>> {
+        Some(self.0.intern_array_slice_mut(items))
+    }
+
+    fn object(&mut self, entries: &[(InternedStrKey, IValue)]) -> Option<InternedObject> {
+        Some(self.0.intern_object_slice_mut(entries))
+    }
+}
+
+/// Memoized translation of the values of an arena into another one, given
+/// the translation of their strings.
+pub(crate) struct Translator<'a, T> {
+    from: &'a Jinterners,
+    target: T,
+    strings: Vec<Option<InternedStr>>,
+    arrays: Vec<Option<Option<InternedSlice<IValue>>>>,
+    objects: Vec<Option<Option<InternedObject>>>,
+}
+
+impl<'a> Translator<'a, FindIn<'a>> {
+    /// Creates a translator finding the values of one arena in another arena.
+    pub(crate) fn find(from: &'a Jinterners, to: &'a Jinterners) -> Self {
+        let strings = from.string.iter().map(|s| to.string.find(s)).collect();
+        Self::new(from, strings, FindIn(to))
+    }
+}
+
+impl<'a, T: Target> Translator<'a, T> {
+    /// Creates a translator of the values of the given arena, whose strings
+    /// translate to the given strings, if any.
+    pub(crate) fn new(from: &'a Jinterners, strings: Vec<Option<InternedStr>>, target: T) -> Self {
+        Self {
+            from,
+            target,
+            strings,
+            arrays: vec![None; from.iarray.slices()],
+            objects: vec![None; from.iobject.slices()],
+        }
+    }
+
+    /// Returns the index of each string, array and object in the destination
+    /// arena, if it could be translated.
+    pub(crate) fn translate_all(mut self) -> [Vec<Option<u32>>; 3] {
+        let arrays = (0..self.arrays.len() as u32)
+            .map(|id| self.array(InternedSlice::from_id(id)).map(|a| a.id()))
+            .collect();
+        let objects = (0..self.objects.len() as u32)
+            .map(|id| self.object(InternedSlice::from_id(id)).map(|o| o.id()))
+            .collect();
+        let strings = self.strings.iter().map(|s| s.map(|s| s.id())).collect();
+        [strings, arrays, objects]
+    }
+
+    /// Translates the given value, or returns [`None`] if it contains an entry
+    /// that can't be translated.
+    pub(crate) fn value(&mut self, value: IValue) -> Option<IValue> {
+        Some(IValue(match value.0 {
+            IValueImpl::String(s) => IValueImpl::String(self.strings[s.id() as usize]?),
+            IValueImpl::Array(a) => IValueImpl::Array(self.array(a)?),
+            IValueImpl::Object(o) => IValueImpl::Object(self.object(o)?),
+            scalar => scalar,
+        }))
+    }
+
+    fn array(&mut self, array: InternedSlice<IValue>) -> Option<InternedSlice<IValue>> {
+        let index = array.id() as usize;
+        if let Some(result) = self.arrays[index] {
+            return result;
+        }
+        let from = self.from;
+        let result = from
+            .iarray
+            .lookup(array)
+            .iter()
+            .map(|item| self.value(*item))
+            .collect::<Option<Vec<_>>>()
+            .and_then(|items| self.target.array(&items));
+        self.arrays[index] = Some(result);
+        result
+    }
+
+    fn object(&mut self, object: InternedObject) -> Option<InternedObject> {
+        let index = object.id() as usize;
+        if let Some(result) = self.objects[index] {
+            return result;
+        }
+        let from = self.from;
+        let result = from
+            .iobject
+            .lookup(object)
+            .iter()
+            .map(|(k, v)| {
+                Some((
+                    InternedStrKey(self.strings[k.0.id() as usize]?),
+                    self.value(*v)?,
+                ))
+            })
+            .collect::<Option<Vec<_>>>()
+            .and_then(|mut entries| {
+                // Entries are sorted by key index, which differs across arenas.
+                entries.sort_unstable_by_key(|(k, _)| *k);
+                self.target.object(&entries)
+            });
+        self.objects[index] = Some(result);
+        result
+    }
+}
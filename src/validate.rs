@@ -0,0 +1,391 @@
+use crate::detail::{Float64, IValueImpl};
+use crate::{ArenaKind, IValue, Jinterners, Watermark};
+use blazinterner::InternedSlice;
+use ordered_float::OrderedFloat;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+/// The inconsistencies found in a [`Jinterners`] arena.
+///
+/// This struct is returned by the [`validate()`](Jinterners::validate) method
+/// on [`Jinterners`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ValidationReport {
+    /// The issues found, in arena order.
+    pub issues: Vec<ValidationIssue>,
+}
+
+/// An inconsistency found in an entry of a [`Jinterners`] arena.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ValidationIssue {
+    /// An entry contains a handle past the end of its target arena.
+    InvalidId {
+        /// Arena of the entry containing the handle.
+        arena: ArenaKind,
+        /// Index of the entry containing the handle.
+        index: u32,
+        /// Arena that the handle refers to.
+        target: ArenaKind,
+        /// Identifier of the handle.
+        id: u32,
+    },
+    /// An object's keys aren't strictly increasing.
+    UnsortedKeys {
+        /// Index of the object.
+        index: u32,
+    },
+    /// A string isn't valid UTF-8.
+    InvalidUtf8 {
+        /// Index of the string.
+        index: u32,
+    },
+    /// An entry contains a number that isn't in canonical form, i.e. a
+    /// non-finite float or a non-negative number stored as signed.
+    InvalidNumber {
+        /// Arena of the entry containing the number.
+        arena: ArenaKind,
+        /// Index of the entry containing the number.
+        index: u32,
+    },
+    /// An entry contains itself, directly or transitively.
+    Cycle {
+        /// Arena of the entry.
+        arena: ArenaKind,
+        /// Index of the entry.
+        index: u32,
+    },
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::InvalidId {
+                arena,
+                index,
+                target,
+                id,
+            } => write!(
+                f,
+                "{} {index} refers to {} {id} out of bounds",
+                arena.name(),
+                target.name()
+            ),
+            ValidationIssue::UnsortedKeys { index } => {
+                write!(f, "object {index} has unsorted keys")
+            }
+            ValidationIssue::InvalidUtf8 { index } => {
+                write!(f, "string {index} isn't valid UTF-8")
+            }
+            ValidationIssue::InvalidNumber { arena, index } => {
+                write!(
+                    f,
+                    "{} {index} contains a non-canonical number",
+                    arena.name()
+                )
+            }
+            ValidationIssue::Cycle { arena, index } => {
+                write!(f, "{} {index} contains itself", arena.name())
+            }
+        }
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.issues.as_slice() {
+            [] => f.write_str("no issues"),
+            [issue] => issue.fmt(f),
+            [issue, rest @ ..] => write!(f, "{issue} (and {} more issues)", rest.len()),
+        }
+    }
+}
+
+impl std::error::Error for ValidationReport {}
+
+impl Jinterners {
+    /// Checks the internal invariants of this arena.
+    ///
+    /// This verifies that all handles stored in arrays and objects are in
+    /// range, that object keys are sorted, that strings are valid UTF-8, that
+    /// numbers are in canonical form and that no entry contains itself.
+    ///
+    /// Arenas built by interning always pass this check, including with
+    /// `IValue::from_value()` and the other serialization methods. It's meant
+    /// to be run after deserializing an arena from less-trusted storage or
+    /// from an older version of this crate, as violated invariants may
+    /// cause arbitrary results or panics when looking up values.
+    pub fn validate(&self) -> Result<(), ValidationReport> {
+        let mut issues = Vec::new();
+
+        for (index, bytes) in self.string.iter_bytes().enumerate() {
+            if std::str::from_utf8(bytes).is_err() {
+                issues.push(ValidationIssue::InvalidUtf8 {
+                    index: index as u32,
+                });
+            }
+        }
+
+        let strings = self.string.strings();
+        for (index, array) in self.iarray.iter().enumerate() {
+            for value in array {
+                self.validate_value(value, ArenaKind::Array, index as u32, &mut issues);
+            }
+        }
+        for (index, object) in self.iobject.iter().enumerate() {
+            let index = index as u32;
+            if !object.is_sorted_by(|a, b| a.0 < b.0) {
+                issues.push(ValidationIssue::UnsortedKeys { index });
+            }
+            for (key, value) in object {
+                let id = key.0.id();
+                if id as usize >= strings {
+                    issues.push(ValidationIssue::InvalidId {
+                        arena: ArenaKind::Object,
+                        index,
+                        target: ArenaKind::String,
+                        id,
+                    });
+                }
+                self.validate_value(value, ArenaKind::Object, index, &mut issues);
+            }
+        }
+
+        // Only look for cycles among valid handles.
+        if issues
+            .iter()
+            .all(|issue| !matches!(issue, ValidationIssue::InvalidId { .. }))
+        {
+            self.find_cycles(&mut issues);
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationReport { issues })
+        }
+    }
+
+    fn validate_value(
+        &self,
+        value: &IValue,
+        arena: ArenaKind,
+        index: u32,
+        issues: &mut Vec<ValidationIssue>,
+    ) {
+        let (target, id, len) = match value.0 {
+            IValueImpl::Null | IValueImpl::Bool(_) | IValueImpl::U64(_) => return,
+            IValueImpl::I64(x) => {
+                if x >= 0 {
+                    issues.push(ValidationIssue::InvalidNumber { arena, index });
+                }
+                return;
+            }
+            IValueImpl::F64(Float64(OrderedFloat(x))) => {
+                if !x.is_finite() {
+                    issues.push(ValidationIssue::InvalidNumber { arena, index });
+                }
+                return;
+            }
+            IValueImpl::String(s) => (ArenaKind::String, s.id(), self.string.strings()),
+            IValueImpl::Array(a) => (ArenaKind::Array, a.id(), self.iarray.slices()),
+            IValueImpl::Object(o) => (ArenaKind::Object, o.id(), self.iobject.slices()),
+        };
+        if id as usize >= len {
+            issues.push(ValidationIssue::InvalidId {
+                arena,
+                index,
+                target,
+                id,
+            });
+        }
+    }
+
+    /// Reports the arrays and objects that contain themselves.
+    fn find_cycles(&self, issues: &mut Vec<ValidationIssue>) {
+        self.find_cycles_since(Watermark::default(), |arena, index| {
+            issues.push(ValidationIssue::Cycle { arena, index })
+        });
+    }
+
+    /// Calls `found` on the arrays and objects above the given watermark that
+    /// contain themselves, assuming that the entries below the watermark
+    /// don't.
+    pub(crate) fn find_cycles_since(&self, from: Watermark, found: impl FnMut(ArenaKind, u32)) {
+        find_cycles(
+            [self.iarray.slices(), self.iobject.slices()],
+            [from.arrays, from.objects],
+            |(arena, index)| -> Box<dyn Iterator<Item = IValue> + '_> {
+                let id = index as u32;
+                if arena == 0 {
+                    Box::new(
+                        self.iarray
+                            .lookup(InternedSlice::from_id(id))
+                            .iter()
+                            .copied(),
+                    )
+                } else {
+                    let object = self.iobject.lookup(InternedSlice::from_id(id));
+                    Box::new(object.iter().map(|(_, v)| *v))
+                }
+            },
+            found,
+        );
+    }
+}
+
+/// Calls `found` on the arrays and objects that contain themselves, with an
+/// iterative depth-first search.
+///
+/// Nodes are identified by their arena, which is 0 for arrays and 1 for
+/// objects, and their index in this arena. There are `counts` nodes in each
+/// arena, and `children` returns the values contained in a node. The nodes
+/// below `skip` are assumed to be acyclic and aren't visited. Handles must be
+/// within `counts`.
+pub(crate) fn find_cycles<'a>(
+    counts: [usize; 2],
+    skip: [usize; 2],
+    children: impl Fn((usize, usize)) -> Box<dyn Iterator<Item = IValue> + 'a>,
+    mut found: impl FnMut(ArenaKind, u32),
+) {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum State {
+        Unvisited,
+        InProgress,
+        Done,
+    }
+
+    let mut states = [0, 1].map(|arena| vec![State::Unvisited; counts[arena] - skip[arena]]);
+    let node = |value: IValue| {
+        let (arena, id) = match value.0 {
+            IValueImpl::Array(a) => (0, a.id() as usize),
+            IValueImpl::Object(o) => (1, o.id() as usize),
+            _ => return None,
+        };
+        (id >= skip[arena]).then_some((arena, id))
+    };
+
+    for root in (skip[0]..counts[0])
+        .map(|i| (0, i))
+        .chain((skip[1]..counts[1]).map(|i| (1, i)))
+    {
+        let state = |(arena, index): (usize, usize)| (arena, index - skip[arena]);
+        let (arena, index) = state(root);
+        if states[arena][index] != State::Unvisited {
+            continue;
+        }
+        states[arena][index] = State::InProgress;
+        let mut stack = vec![(root, children(root))];
+        while let Some((current, iter)) = stack.last_mut() {
+            match iter.find_map(node) {
+                Some(child) => {
+                    let (arena, index) = state(child);
+                    match states[arena][index] {
+                        State::Unvisited => {
+                            states[arena][index] = State::InProgress;
+                            stack.push((child, children(child)));
+                        }
+                        State::InProgress => {
+                            found([ArenaKind::Array, ArenaKind::Object][arena], child.1 as u32)
+                        }
+                        State::Done => (),
+                    }
+                }
+                None => {
+                    let (arena, index) = state(*current);
+                    states[arena][index] = State::Done;
+                    stack.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn validate_interned() {
+        let interners = Jinterners::default();
+        interners.intern(json!({"b": [1, -1, 1.5, "x"], "a": {"c": [[]]}}));
+        interners.intern(json!([{"a": null}, true]));
+        assert_eq!(interners.validate(), Ok(()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn validate_from_value() {
+        use crate::FloatPolicy;
+
+        #[derive(serde::Serialize)]
+        struct Record {
+            signed: i32,
+            negative: i64,
+            floats: [f64; 4],
+        }
+
+        let record = Record {
+            signed: 1,
+            negative: -1,
+            floats: [1.5, f64::NAN, f64::INFINITY, -0.0],
+        };
+        let expected = json!({"signed": 1, "negative": -1, "floats": [1.5, null, null, -0.0]});
+        for policy in [FloatPolicy::Preserve, FloatPolicy::Canonicalize] {
+            let mut interners = Jinterners::default();
+            interners.set_float_policy(policy);
+            let ivalue = IValue::from_value(&record, &interners).unwrap();
+            assert_eq!(interners.validate(), Ok(()));
+            assert_eq!(ivalue, interners.intern_ref(&expected));
+
+            let ivalue = IValue::from_value_mut(&record, &mut interners).unwrap();
+            assert_eq!(interners.validate(), Ok(()));
+            assert_eq!(ivalue, interners.intern_ref(&expected));
+        }
+    }
+
+    #[test]
+    fn validate_invalid() {
+        let mut interners = Jinterners::default();
+        let array = |id| IValue(IValueImpl::Array(InternedSlice::from_id(id)));
+        // Array 0 contains itself, array 1 refers to a missing array.
+        interners.iarray.intern_mut(&[array(0)][..]);
+        interners
+            .iarray
+            .intern_mut(&[array(5), IValue(IValueImpl::I64(1))][..]);
+
+        let report = interners.validate().unwrap_err();
+        assert_eq!(
+            report.issues,
+            [
+                ValidationIssue::InvalidId {
+                    arena: ArenaKind::Array,
+                    index: 1,
+                    target: ArenaKind::Array,
+                    id: 5,
+                },
+                ValidationIssue::InvalidNumber {
+                    arena: ArenaKind::Array,
+                    index: 1,
+                },
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "array 1 refers to array 5 out of bounds (and 1 more issues)"
+        );
+
+        let mut interners = Jinterners::default();
+        interners.iarray.intern_mut(&[array(0)][..]);
+        assert_eq!(
+            interners.validate().unwrap_err().issues,
+            [ValidationIssue::Cycle {
+                arena: ArenaKind::Array,
+                index: 0,
+            }]
+        );
+    }
+}
@@ -0,0 +1,646 @@
+//! A read-only view of a [`Jinterners`] arena over a borrowed byte buffer.
+//!
+//! The buffer is laid out as follows, all integers being little-endian
+//! `u64`s:
+//!
+//! - the 7-byte magic [`MAGIC`] followed by the [`VERSION`] byte,
+//! - a header made of the number of strings, arrays and objects, the total
+//!   number of array items and object entries, and the total length of the
+//!   strings,
+//! - the end offset of each string, array and object within the items of its
+//!   arena,
+//! - the array items, each being a value encoded as a tag and a payload,
+//! - the object entries, each being the index of its key string followed by a
+//!   value encoded as a tag and a payload,
+//! - the concatenated strings.
+
+use crate::detail::IValueImpl;
+use crate::validate::find_cycles;
+use crate::{Error, IValue, Jinterners};
+use serde_json::{Map, Value};
+use std::io::{self, Write};
+
+/// Magic bytes at the start of a view buffer, followed by the format version.
+const MAGIC: &[u8; 7] = b"JINVIEW";
+/// Version of the view format.
+const VERSION: u8 = 1;
+/// Size of the magic, version and header.
+const HEADER_LEN: usize = 8 + 6 * 8;
+/// Size of an array item.
+const ITEM_LEN: usize = 2 * 8;
+/// Size of an object entry.
+const ENTRY_LEN: usize = 3 * 8;
+
+impl Jinterners {
+    /// Writes this arena in the format read by [`JinternersView`].
+    ///
+    /// Values interned in this arena can then be looked up in the view, with
+    /// the same [`IValue`] handles.
+    pub fn write_view(&self, w: &mut impl Write) -> io::Result<()> {
+        let items: usize = self.iarray.iter().map(<[_]>::len).sum();
+        let entries: usize = self.iobject.iter().map(<[_]>::len).sum();
+        let string_bytes: usize = self.string.iter().map(str::len).sum();
+
+        let mut buf = Vec::with_capacity(
+            HEADER_LEN
+                + 8 * (self.string.strings() + self.iarray.slices() + self.iobject.slices())
+                + items * ITEM_LEN
+                + entries * ENTRY_LEN
+                + string_bytes,
+        );
+        let mut put = |x: u64| buf.extend_from_slice(&x.to_le_bytes());
+        for x in [
+            self.string.strings(),
+            self.iarray.slices(),
+            self.iobject.slices(),
+            items,
+            entries,
+            string_bytes,
+        ] {
+            put(x as u64);
+        }
+        running_ends(self.string.iter().map(str::len)).for_each(&mut put);
+        running_ends(self.iarray.iter().map(<[_]>::len)).for_each(&mut put);
+        running_ends(self.iobject.iter().map(<[_]>::len)).for_each(&mut put);
+        for item in self.iarray.iter().flatten() {
+            let (tag, payload) = item.to_raw_parts();
+            put(tag);
+            put(payload);
+        }
+        for (key, value) in self.iobject.iter().flatten() {
+            let (tag, payload) = value.to_raw_parts();
+            put(key.0.id().into());
+            put(tag);
+            put(payload);
+        }
+
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        w.write_all(&buf)?;
+        for s in self.string.iter() {
+            w.write_all(s.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+fn running_ends(lens: impl Iterator<Item = usize>) -> impl Iterator<Item = u64> {
+    lens.scan(0, |end, len| {
+        *end += len as u64;
+        Some(*end)
+    })
+}
+
+/// A read-only view of a [`Jinterners`] arena, borrowed from a byte buffer
+/// written by [`Jinterners::write_view()`].
+///
+/// The buffer is owned by the caller, for example after fetching it from a
+/// cache service, and is never copied: strings are returned as slices of it,
+/// and arrays and objects are decoded on access. Opening the view validates
+/// the whole buffer once, in linear time, including that no value contains
+/// itself, so that lookups of valid handles never fail.
+///
+/// ```
+/// use jinterner::{Jinterners, JinternersView, ViewValueRef};
+/// use serde_json::json;
+///
+/// let interners = Jinterners::default();
+/// let ivalue = interners.intern(json!({"name": "John", "tags": ["a", "b"]}));
+///
+/// let mut bytes = Vec::new();
+/// interners.write_view(&mut bytes).unwrap();
+///
+/// let view = JinternersView::new(&bytes).unwrap();
+/// assert_eq!(view.lookup(&ivalue), json!({"name": "John", "tags": ["a", "b"]}));
+/// let ViewValueRef::String(tag) = view.pointer(&ivalue, "/tags/1").unwrap() else {
+///     unreachable!()
+/// };
+/// assert_eq!(tag, "b");
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct JinternersView<'a> {
+    bytes: &'a [u8],
+    strings: Section,
+    arrays: Section,
+    objects: Section,
+    items: usize,
+    entries: usize,
+    string_bytes: usize,
+}
+
+/// A table of end offsets within the buffer.
+#[derive(Clone, Copy, Debug)]
+struct Section {
+    offset: usize,
+    count: usize,
+}
+
+impl<'a> JinternersView<'a> {
+    /// Opens a view of the arena contained in the given bytes.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        if bytes.len() < HEADER_LEN || &bytes[..7] != MAGIC {
+            return Err(invalid("missing view header"));
+        }
+        if bytes[7] != VERSION {
+            return Err(invalid("unsupported format version"));
+        }
+        let header = |i: usize| {
+            usize::try_from(read_u64(bytes, 8 + 8 * i)).map_err(|_| invalid("invalid header"))
+        };
+        let (strings, arrays, objects) = (header(0)?, header(1)?, header(2)?);
+        let (items, entries, string_bytes) = (header(3)?, header(4)?, header(5)?);
+
+        let mut offset = HEADER_LEN;
+        let mut section = |count: usize, len: usize| {
+            let start = offset;
+            offset = count
+                .checked_mul(len)
+                .and_then(|len| offset.checked_add(len))
+                .ok_or(invalid("invalid header"))?;
+            Ok::<_, Error>(start)
+        };
+        let view = Self {
+            bytes,
+            strings: Section {
+                offset: section(strings, 8)?,
+                count: strings,
+            },
+            arrays: Section {
+                offset: section(arrays, 8)?,
+                count: arrays,
+            },
+            objects: Section {
+                offset: section(objects, 8)?,
+                count: objects,
+            },
+            items: section(items, ITEM_LEN)?,
+            entries: section(entries, ENTRY_LEN)?,
+            string_bytes: section(string_bytes, 1)?,
+        };
+        if offset != bytes.len() {
+            return Err(invalid("invalid length"));
+        }
+        view.validate(items, entries, string_bytes)?;
+        Ok(view)
+    }
+
+    /// Checks that all the offsets, strings and values of the buffer are
+    /// valid.
+    fn validate(&self, items: usize, entries: usize, string_bytes: usize) -> Result<(), Error> {
+        for (section, total) in [
+            (self.strings, string_bytes),
+            (self.arrays, items),
+            (self.objects, entries),
+        ] {
+            let mut start = 0;
+            for i in 0..section.count {
+                let end = read_u64(self.bytes, section.offset + 8 * i);
+                if end < start || end > total as u64 {
+                    return Err(invalid("invalid offsets"));
+                }
+                start = end;
+            }
+            if start != total as u64 {
+                return Err(invalid("invalid offsets"));
+            }
+        }
+        for i in 0..self.strings.count {
+            let (start, end) = self.range(self.strings, i);
+            std::str::from_utf8(&self.bytes[self.string_bytes + start..self.string_bytes + end])
+                .map_err(|_| invalid("invalid UTF-8 string"))?;
+        }
+        let check = |offset: usize| {
+            let value = IValue::from_raw_parts(
+                read_u64(self.bytes, offset),
+                read_u64(self.bytes, offset + 8),
+            )
+            .ok_or(invalid("invalid value"))?;
+            let in_range = match value.0 {
+                IValueImpl::String(s) => (s.id() as usize) < self.strings.count,
+                IValueImpl::Array(a) => (a.id() as usize) < self.arrays.count,
+                IValueImpl::Object(o) => (o.id() as usize) < self.objects.count,
+                _ => true,
+            };
+            in_range.then_some(()).ok_or(invalid("invalid value"))
+        };
+        for i in 0..self.arrays.count {
+            let (start, end) = self.range(self.arrays, i);
+            for j in start..end {
+                check(self.items + j * ITEM_LEN)?;
+            }
+        }
+        for i in 0..self.objects.count {
+            let (start, end) = self.range(self.objects, i);
+            for j in start..end {
+                let offset = self.entries + j * ENTRY_LEN;
+                if read_u64(self.bytes, offset) >= self.strings.count as u64 {
+                    return Err(invalid("invalid key"));
+                }
+                check(offset + 8)?;
+            }
+        }
+
+        // Entries may reference entries after them, as in optimized arenas,
+        // as long as no value contains itself.
+        let view = *self;
+        let mut cycle = false;
+        find_cycles(
+            [self.arrays.count, self.objects.count],
+            [0, 0],
+            |(arena, index)| -> Box<dyn Iterator<Item = IValue> + 'a> {
+                let (section, offset, len) = match arena {
+                    0 => (view.arrays, view.items, ITEM_LEN),
+                    _ => (view.objects, view.entries + 8, ENTRY_LEN),
+                };
+                let (start, end) = view.range(section, index);
+                Box::new((start..end).map(move |j| view.raw_value(offset + j * len)))
+            },
+            |_, _| cycle = true,
+        );
+        if cycle {
+            return Err(invalid("cyclic value"));
+        }
+        Ok(())
+    }
+
+    /// Returns the number of strings in the viewed arena.
+    pub fn strings(&self) -> usize {
+        self.strings.count
+    }
+
+    /// Returns the number of arrays in the viewed arena.
+    pub fn arrays(&self) -> usize {
+        self.arrays.count
+    }
+
+    /// Returns the number of objects in the viewed arena.
+    pub fn objects(&self) -> usize {
+        self.objects.count
+    }
+
+    /// Retrieves the given interned value from the viewed arena, without
+    /// allocating.
+    ///
+    /// The caller is responsible for ensuring that the viewed arena was used
+    /// to intern this value, otherwise an arbitrary value will be returned or
+    /// a panic will happen.
+    pub fn lookup_ref(&self, value: &IValue) -> ViewValueRef<'a> {
+        match value.0 {
+            IValueImpl::Null => ViewValueRef::Null,
+            IValueImpl::Bool(x) => ViewValueRef::Bool(x),
+            IValueImpl::U64(x) => ViewValueRef::U64(x),
+            IValueImpl::I64(x) => ViewValueRef::I64(x),
+            IValueImpl::F64(x) => ViewValueRef::F64(x.0.0),
+            IValueImpl::String(s) => ViewValueRef::String(self.string(s.id() as usize)),
+            IValueImpl::Array(a) => {
+                let (start, end) = self.range(self.arrays, a.id() as usize);
+                ViewValueRef::Array(ViewArrayRef {
+                    view: *self,
+                    start,
+                    len: end - start,
+                })
+            }
+            IValueImpl::Object(o) => {
+                let (start, end) = self.range(self.objects, o.id() as usize);
+                ViewValueRef::Object(ViewMapRef {
+                    view: *self,
+                    start,
+                    len: end - start,
+                })
+            }
+        }
+    }
+
+    /// Retrieves the given interned value from the viewed arena.
+    ///
+    /// Contrary to [`lookup_ref()`](Self::lookup_ref), this allocates the
+    /// resulting deep copy of the value.
+    pub fn lookup(&self, value: &IValue) -> Value {
+        self.lookup_ref(value).to_value()
+    }
+
+    /// Looks up a value by a JSON pointer within the given interned value, or
+    /// returns [`None`] if the pointer doesn't match any value.
+    pub fn pointer(&self, value: &IValue, pointer: &str) -> Option<ViewValueRef<'a>> {
+        self.lookup_ref(value).pointer(pointer)
+    }
+
+    /// Returns the start and end of the given entry in the given section.
+    fn range(&self, section: Section, index: usize) -> (usize, usize) {
+        assert!(index < section.count, "invalid id {index}");
+        let start = match index {
+            0 => 0,
+            _ => read_u64(self.bytes, section.offset + 8 * (index - 1)) as usize,
+        };
+        let end = read_u64(self.bytes, section.offset + 8 * index) as usize;
+        (start, end)
+    }
+
+    fn string(&self, index: usize) -> &'a str {
+        let (start, end) = self.range(self.strings, index);
+        let bytes = &self.bytes[self.string_bytes + start..self.string_bytes + end];
+        // SAFETY: Strings are validated as UTF-8 when opening the view, and
+        // the buffer is immutable.
+        unsafe { std::str::from_utf8_unchecked(bytes) }
+    }
+
+    /// Decodes the value at the given offset.
+    fn raw_value(&self, offset: usize) -> IValue {
+        IValue::from_raw_parts(
+            read_u64(self.bytes, offset),
+            read_u64(self.bytes, offset + 8),
+        )
+        .expect("values are validated when opening the view")
+    }
+
+    fn value(&self, offset: usize) -> ViewValueRef<'a> {
+        self.lookup_ref(&self.raw_value(offset))
+    }
+}
+
+/// A value borrowed from a [`JinternersView`].
+#[derive(Clone, Copy, Debug)]
+pub enum ViewValueRef<'a> {
+    /// JSON null value.
+    Null,
+    /// JSON boolean value.
+    Bool(bool),
+    /// JSON number that fits in a [`u64`].
+    U64(u64),
+    /// JSON number that fits in a [`i64`].
+    I64(i64),
+    /// JSON number that fits in a [`f64`].
+    F64(f64),
+    /// JSON string.
+    String(&'a str),
+    /// JSON array.
+    Array(ViewArrayRef<'a>),
+    /// JSON object.
+    Object(ViewMapRef<'a>),
+}
+
+impl<'a> ViewValueRef<'a> {
+    /// Converts this value into a [`serde_json::Value`], allocating a deep
+    /// copy of it.
+    pub fn to_value(&self) -> Value {
+        match *self {
+            ViewValueRef::Null => Value::Null,
+            ViewValueRef::Bool(x) => Value::Bool(x),
+            ViewValueRef::U64(x) => x.into(),
+            ViewValueRef::I64(x) => x.into(),
+            ViewValueRef::F64(x) => x.into(),
+            ViewValueRef::String(x) => Value::String(x.to_owned()),
+            ViewValueRef::Array(array) => {
+                Value::Array(array.iter().map(|x| x.to_value()).collect())
+            }
+            ViewValueRef::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.to_owned(), v.to_value()))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+
+    /// Looks up a value by a JSON pointer within this value, or returns
+    /// [`None`] if the pointer doesn't match any value.
+    pub fn pointer(self, pointer: &str) -> Option<Self> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        let tokens = pointer.strip_prefix('/')?;
+        let mut current = self;
+        for token in tokens.split('/') {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            current = match current {
+                ViewValueRef::Object(map) => map.get(&token)?,
+                ViewValueRef::Array(array) => {
+                    if token.len() > 1 && token.starts_with('0') {
+                        return None;
+                    }
+                    array.get(token.parse().ok()?)?
+                }
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+}
+
+/// An array borrowed from a [`JinternersView`].
+#[derive(Clone, Copy, Debug)]
+pub struct ViewArrayRef<'a> {
+    view: JinternersView<'a>,
+    start: usize,
+    len: usize,
+}
+
+impl<'a> ViewArrayRef<'a> {
+    /// Returns the number of items in this array.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether this array is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the item at the given index, or [`None`] if it's out of
+    /// bounds.
+    pub fn get(&self, index: usize) -> Option<ViewValueRef<'a>> {
+        (index < self.len).then(|| {
+            self.view
+                .value(self.view.items + (self.start + index) * ITEM_LEN)
+        })
+    }
+
+    /// Iterates over the items of this array.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = ViewValueRef<'a>> + use<'a> {
+        let array = *self;
+        (0..self.len).map(move |i| array.get(i).unwrap())
+    }
+}
+
+/// An object borrowed from a [`JinternersView`].
+#[derive(Clone, Copy, Debug)]
+pub struct ViewMapRef<'a> {
+    view: JinternersView<'a>,
+    start: usize,
+    len: usize,
+}
+
+impl<'a> ViewMapRef<'a> {
+    /// Returns the number of entries in this object.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether this object is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the value of the given key, or [`None`] if it's missing.
+    ///
+    /// This is a linear scan over the entries, as they are ordered by key
+    /// index rather than by key string.
+    pub fn get(&self, key: &str) -> Option<ViewValueRef<'a>> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Iterates over the entries of this object, in the order of the
+    /// interned arena.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&'a str, ViewValueRef<'a>)> + use<'a> {
+        let map = *self;
+        (0..self.len).map(move |i| {
+            let offset = map.view.entries + (map.start + i) * ENTRY_LEN;
+            let key = read_u64(map.view.bytes, offset) as usize;
+            (map.view.string(key), map.view.value(offset + 8))
+        })
+    }
+}
+
+fn invalid(reason: &'static str) -> Error {
+    Error::View { reason }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::InternedStrKey;
+    use blazinterner::InternedSlice;
+    use serde_json::json;
+
+    #[test]
+    fn view() {
+        let interners = Jinterners::default();
+        let documents = [
+            json!({"name": "John", "tags": ["a", "b"], "age": 42}),
+            json!([true, null, -1, 1.5, {"a~b/c": []}, {}]),
+            json!("hello"),
+        ];
+        let ivalues = interners.intern_many_ref(&documents);
+        let mut bytes = Vec::new();
+        interners.write_view(&mut bytes).unwrap();
+
+        let view = JinternersView::new(&bytes).unwrap();
+        assert_eq!(view.strings(), interners.string.strings());
+        assert_eq!(view.arrays(), interners.iarray.slices());
+        assert_eq!(view.objects(), interners.iobject.slices());
+        for (ivalue, document) in ivalues.iter().zip(&documents) {
+            assert_eq!(view.lookup(ivalue), *document);
+            assert_eq!(view.lookup(ivalue), interners.lookup(ivalue));
+        }
+
+        let pointer = |i: usize, p: &str| view.pointer(&ivalues[i], p).map(|v| v.to_value());
+        assert_eq!(pointer(0, "/tags/1"), Some(json!("b")));
+        assert_eq!(pointer(0, "/age"), Some(json!(42)));
+        assert_eq!(pointer(0, ""), Some(documents[0].clone()));
+        assert_eq!(pointer(0, "/missing"), None);
+        assert_eq!(pointer(0, "/tags/01"), None);
+        assert_eq!(pointer(0, "/tags/2"), None);
+        assert_eq!(pointer(1, "/4/a~0b~1c"), Some(json!([])));
+        assert_eq!(pointer(2, "/0"), None);
+        assert_eq!(pointer(2, "a"), None);
+
+        let ViewValueRef::Object(map) = view.lookup_ref(&ivalues[0]) else {
+            panic!("expected an object");
+        };
+        assert_eq!(map.len(), 3);
+        assert!(matches!(
+            map.get("name"),
+            Some(ViewValueRef::String("John"))
+        ));
+        let ViewValueRef::Array(array) = view.lookup_ref(&ivalues[1]) else {
+            panic!("expected an array");
+        };
+        assert_eq!(array.iter().len(), 6);
+        assert!(matches!(array.get(3), Some(ViewValueRef::F64(1.5))));
+    }
+
+    #[test]
+    fn optimized_view() {
+        let interners = Jinterners::default();
+        let documents = [
+            json!([[1], [[2]], {"a": [3]}]),
+            json!({"b": [[1], {"c": {"d": [4]}}]}),
+            json!([[[2]], [[2]], [1]]),
+        ];
+        let ivalues = interners.intern_many_ref(&documents);
+        let (optimized, mapping) = interners.optimize(None).unwrap();
+        let mut bytes = Vec::new();
+        optimized.write_view(&mut bytes).unwrap();
+
+        let view = JinternersView::new(&bytes).unwrap();
+        for (ivalue, document) in ivalues.iter().zip(&documents) {
+            assert_eq!(view.lookup(&mapping.map(*ivalue)), *document);
+        }
+    }
+
+    #[test]
+    fn invalid_view() {
+        let interners = Jinterners::default();
+        interners.intern(json!({"key": ["value"]}));
+        let mut bytes = Vec::new();
+        interners.write_view(&mut bytes).unwrap();
+
+        let error = |bytes: &[u8]| JinternersView::new(bytes).unwrap_err().to_string();
+        assert_eq!(error(b"JINVIEW"), "invalid view: missing view header");
+        assert_eq!(
+            error(&bytes[..bytes.len() - 1]),
+            "invalid view: invalid length"
+        );
+
+        let mut corrupt = bytes.clone();
+        corrupt[7] = 2;
+        assert_eq!(error(&corrupt), "invalid view: unsupported format version");
+
+        // Replace the tag of the array item by an invalid one.
+        let mut corrupt = bytes.clone();
+        corrupt[HEADER_LEN + 4 * 8] = 42;
+        assert_eq!(error(&corrupt), "invalid view: invalid value");
+
+        // Make the last string invalid UTF-8.
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() = 0xff;
+        assert_eq!(error(&corrupt), "invalid view: invalid UTF-8 string");
+    }
+
+    #[test]
+    fn cyclic_view() {
+        let array = |id| IValue(IValueImpl::Array(InternedSlice::from_id(id)));
+        let object = |id| IValue(IValueImpl::Object(InternedSlice::from_id(id)));
+        let error = |interners: &Jinterners| {
+            let mut bytes = Vec::new();
+            interners.write_view(&mut bytes).unwrap();
+            JinternersView::new(&bytes).unwrap_err().to_string()
+        };
+
+        // Array 1 contains itself.
+        let mut interners = Jinterners::default();
+        interners.iarray.intern_mut(&[][..]);
+        interners.iarray.intern_mut(&[array(1)][..]);
+        assert_eq!(error(&interners), "invalid view: cyclic value");
+
+        // Array 0 contains an array beyond the arena.
+        let mut interners = Jinterners::default();
+        interners.iarray.intern_mut(&[array(1)][..]);
+        assert_eq!(error(&interners), "invalid view: invalid value");
+
+        // Array 0 and object 0 contain each other.
+        let mut interners = Jinterners::default();
+        let key = interners.intern_str_mut("k");
+        let IValueImpl::String(key) = key.0 else {
+            unreachable!()
+        };
+        interners.iarray.intern_mut(&[object(0)][..]);
+        interners
+            .iobject
+            .intern_mut(&[(InternedStrKey(key), array(0))][..]);
+        assert_eq!(error(&interners), "invalid view: cyclic value");
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{IValue, Jinterners, ValueKind};
+use js_sys::Object;
+use serde::Serialize;
+use serde_wasm_bindgen::{Deserializer, Error, Serializer};
+use wasm_bindgen::{JsCast, JsValue};
+
+impl Jinterners {
+    /// Interns the given JavaScript value into this arena, without
+    /// materializing an intermediate [`serde_json::Value`].
+    ///
+    /// Values that have no JSON representation, such as functions or symbols,
+    /// are rejected.
+    ///
+    /// ```no_run
+    /// use jinterner::Jinterners;
+    /// use wasm_bindgen::JsValue;
+    ///
+    /// # fn run(input: JsValue) -> Result<JsValue, serde_wasm_bindgen::Error> {
+    /// let interners = Jinterners::default();
+    /// let ivalue = interners.intern_js(&input)?;
+    /// let output = interners.to_js(&ivalue)?;
+    /// # Ok(output)
+    /// # }
+    /// ```
+    pub fn intern_js(&self, value: &JsValue) -> Result<IValue, Error> {
+        IValue::from_deserializer(Deserializer::from(value.clone()), self)
+    }
+
+    /// Converts the given interned value to a JavaScript value.
+    ///
+    /// Objects are converted to plain JavaScript objects (rather than `Map`s)
+    /// and integers to numbers when they fit in the safe integer range, or to
+    /// `BigInt`s otherwise.
+    pub fn to_js(&self, value: &IValue) -> Result<JsValue, Error> {
+        self.bind(*value)
+            .serialize(&Serializer::json_compatible().serialize_large_number_types_as_bigints(true))
+    }
+
+    /// Converts the given interned value to a JavaScript object, or returns
+    /// [`None`] if it isn't an object.
+    pub fn to_js_object(&self, value: &IValue) -> Result<Option<Object>, Error> {
+        if !matches!(value.kind(), ValueKind::Object) {
+            return Ok(None);
+        }
+        Ok(self.to_js(value)?.dyn_into().ok())
+    }
+}
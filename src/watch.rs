@@ -0,0 +1,173 @@
+use crate::detail::IValueImpl;
+use crate::{IValue, InternedStrKey, Jinterners};
+use blazinterner::InternedSlice;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Identifier of a watcher registered with
+/// [`watch_key()`](Jinterners::watch_key), to remove it with
+/// [`unwatch()`](Jinterners::unwatch).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WatchId(u64);
+
+/// A callback invoked with the arena, the interned object and the value of the
+/// watched key.
+type Callback = dyn Fn(&Jinterners, IValue, IValue) + Send + Sync;
+
+/// The key watchers registered on a [`Jinterners`].
+///
+/// The keys are stored as strings rather than interned, so that watchers
+/// remain valid in arenas derived from this one, where strings may be
+/// renumbered.
+///
+/// This is ignored when comparing, measuring or serializing arenas.
+#[derive(Clone, Default)]
+pub(crate) struct Watchers {
+    next_id: u64,
+    watchers: Vec<(WatchId, Box<str>, Arc<Callback>)>,
+}
+
+impl Watchers {
+    /// Invokes the watchers whose key is present in the given object, which
+    /// was just interned with the given entries.
+    pub(crate) fn notify(
+        &self,
+        jinterners: &Jinterners,
+        object: InternedSlice<(InternedStrKey, IValue)>,
+        entries: &[(InternedStrKey, IValue)],
+    ) {
+        for (_, key, callback) in &self.watchers {
+            let Some(key) = jinterners.find_string(key) else {
+                continue;
+            };
+            if let Ok(i) = entries.binary_search_by_key(&InternedStrKey(key), |(k, _)| *k) {
+                callback(jinterners, IValue(IValueImpl::Object(object)), entries[i].1);
+            }
+        }
+    }
+}
+
+impl Debug for Watchers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.watchers.iter().map(|(id, key, _)| (id, key)))
+            .finish()
+    }
+}
+
+impl PartialEq for Watchers {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Watchers {}
+
+impl Jinterners {
+    /// Registers a callback invoked whenever an object containing the given
+    /// key is interned into this arena, at any depth of an interned value.
+    ///
+    /// The callback receives this arena, the interned object and the value of
+    /// the watched key. It runs in the ingest path on the interning thread,
+    /// each time such an object is interned, even if it was already present in
+    /// the arena. This allows alerting on or enriching a stream of values
+    /// without scanning them a second time.
+    ///
+    /// Watchers are preserved in arenas derived from this one (for example
+    /// with [`optimize()`](Self::optimize)), but aren't serialized. Entries
+    /// copied into a derived arena aren't reported again.
+    ///
+    /// ```
+    /// use jinterner::Jinterners;
+    /// use serde_json::json;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let mut interners = Jinterners::default();
+    /// let alerts = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = alerts.clone();
+    /// let id = interners.watch_key("error", move |interners, _object, value| {
+    ///     sink.lock().unwrap().push(interners.lookup(&value));
+    /// });
+    ///
+    /// interners.intern(json!({"status": "ok"}));
+    /// interners.intern(json!({"jobs": [{"id": 1, "error": "timeout"}]}));
+    /// assert_eq!(*alerts.lock().unwrap(), [json!("timeout")]);
+    ///
+    /// assert!(interners.unwatch(id));
+    /// interners.intern(json!({"error": "disk full"}));
+    /// assert_eq!(alerts.lock().unwrap().len(), 1);
+    /// ```
+    pub fn watch_key(
+        &mut self,
+        key: &str,
+        callback: impl Fn(&Jinterners, IValue, IValue) + Send + Sync + 'static,
+    ) -> WatchId {
+        let watchers = &mut self.watchers;
+        let id = WatchId(watchers.next_id);
+        watchers.next_id += 1;
+        watchers.watchers.push((id, key.into(), Arc::new(callback)));
+        id
+    }
+
+    /// Removes the watcher with the given identifier, returning whether it was
+    /// registered.
+    pub fn unwatch(&mut self, id: WatchId) -> bool {
+        let watchers = &mut self.watchers.watchers;
+        let len = watchers.len();
+        watchers.retain(|(i, _, _)| *i != id);
+        watchers.len() != len
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[test]
+    fn watch_key() {
+        let mut interners = Jinterners::default();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        for key in ["a", "b"] {
+            let events = events.clone();
+            interners.watch_key(key, move |interners, object, value| {
+                events.lock().unwrap().push((
+                    key,
+                    interners.lookup(&object),
+                    interners.lookup(&value),
+                ));
+            });
+        }
+
+        let value = json!([{"a": 1, "c": {"b": [2]}}, {"c": 3}]);
+        let ivalue = interners.intern_ref(&value);
+        assert_eq!(
+            events.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            [
+                ("b", json!({"b": [2]}), json!([2])),
+                ("a", json!({"a": 1, "c": {"b": [2]}}), json!(1)),
+            ]
+        );
+
+        // Watchers fire again for values already interned, including through
+        // a derived arena.
+        assert_eq!(interners.intern_mut(value.clone()), ivalue);
+        assert_eq!(events.lock().unwrap().drain(..).count(), 2);
+        let optimized = interners.optimize(None).unwrap().0;
+        assert!(events.lock().unwrap().is_empty());
+        optimized.intern(json!({"b": null}));
+        assert_eq!(
+            events.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            [("b", json!({"b": null}), json!(null))]
+        );
+
+        // Watchers don't affect equality.
+        let mut other = interners.clone();
+        assert!(other.unwatch(WatchId(0)));
+        assert!(!other.unwatch(WatchId(0)));
+        assert_eq!(interners, other);
+        other.intern_ref(&value);
+        assert_eq!(events.lock().unwrap().drain(..).count(), 1);
+    }
+}
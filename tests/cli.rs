@@ -0,0 +1,57 @@
+#![cfg(feature = "cli")]
+
+use std::process::{Command, Output};
+
+/// Runs the command-line tool with the given arguments.
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_jinterner"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    assert!(output.status.success(), "{output:?}");
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn round_trip() {
+    let dir = std::env::temp_dir().join(format!("jinterner-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let paths: Vec<String> = ["input.ndjson", "archive", "converted"]
+        .iter()
+        .map(|name| dir.join(name).to_str().unwrap().to_owned())
+        .collect();
+    let [input, archive, converted] = [&paths[0], &paths[1], &paths[2]].map(String::as_str);
+
+    let documents = [
+        r#"{"name":"John","tags":["a","b"]}"#,
+        r#"[1,-2,3.5,null]"#,
+        r#"{"name":"Mary","tags":["b"]}"#,
+    ];
+    std::fs::write(input, documents.join("\n") + "\n\n").unwrap();
+
+    stdout(&run(&["intern", input, archive]));
+    stdout(&run(&["convert", archive, converted]));
+    assert_eq!(
+        std::fs::read(archive).unwrap(),
+        std::fs::read(converted).unwrap()
+    );
+
+    let output = run(&["stats", converted]);
+    assert!(stdout(&output).contains("documents: 3\n"));
+    let output = run(&["dump", converted]);
+    assert_eq!(stdout(&output), documents.join("\n") + "\n");
+    let output = run(&["dump", converted, "2", "0"]);
+    assert_eq!(stdout(&output), [documents[2], documents[0], ""].join("\n"));
+
+    let output = run(&["dump", converted, "3"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        std::str::from_utf8(&output.stderr).unwrap(),
+        "error: no such document: 3\n"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}